@@ -1,7 +1,41 @@
 extern crate cli;
 
+// completes the commands of the example
+struct Commands;
+
+impl cli::completion::Completer for Commands {
+    fn complete(&self, line: &str, position: usize) -> (usize, Vec<String>) {
+        let start = line[..position].rfind(' ').map_or(0, |space| space + 1);
+        let word = &line[start..position];
+        let candidates = ["quit", "query", "echo"]
+            .iter()
+            .filter(|command| command.starts_with(word))
+            .map(|command| command.to_string())
+            .collect();
+        (start, candidates)
+    }
+}
+
+// colors the commands of the example green, and what isn't one red
+impl cli::highlight::Highlighter for Commands {
+    fn highlight(&self, line: &str) -> Vec<(std::ops::Range<usize>, cli::highlight::Style)> {
+        let end = line.find(' ').unwrap_or(line.len());
+        let color = match ["quit", "query", "echo"].contains(&&line[..end]) {
+            true => cli::highlight::Color::Green,
+            false => cli::highlight::Color::Red,
+        };
+        vec![(0..end, cli::highlight::Style::color(color))]
+    }
+
+    fn hint(&self, line: &str, history: &[String]) -> Option<String> {
+        cli::highlight::history_hint(line, history)
+    }
+}
+
 fn main() -> Result<(), &'static str> {
     let mut p = cli::Prompt::new("midas> ")?;
+    p.set_completer(Box::new(Commands));
+    p.set_highlighter(Box::new(Commands));
     loop {
         let input = p.read_input();
         match &input[..] {
@@ -6,12 +6,24 @@ pub const CLEAR_LINE_FROM_CURSOR: &'static str = "\x1b[0K";
 
 pub const CLEAR_LINE: &'static str = "\x1b[2K";
 
-// currently unused
-pub const SET_BOLD: &'static str = "\x1b[1m";
-pub const SET_DIM: &'static str = "\x1b[2m";
-pub const COLOR_BLACK: (&'static str, &'static str) = ("30", "40");
+pub const BELL: &str = "\x07";
+
+pub const SET_REVERSE: &str = "\x1b[7m";
+pub const RESET_STYLE: &str = "\x1b[0m";
+
+pub const SET_BOLD: &str = "\x1b[1m";
+pub const SET_DIM: &str = "\x1b[2m";
+
+// the foreground and background codes of each color
 pub const COLOR_RED: (&str, &str) = ("31", "41");
 pub const COLOR_GREEN: (&str, &str) = ("32", "42");
+pub const COLOR_YELLOW: (&str, &str) = ("33", "43");
+pub const COLOR_BLUE: (&str, &str) = ("34", "44");
+pub const COLOR_MAGENTA: (&str, &str) = ("35", "45");
+pub const COLOR_CYAN: (&str, &str) = ("36", "46");
+
+// currently unused
+pub const COLOR_BLACK: (&str, &str) = ("30", "40");
 pub const COLOR_WHITE: (&str, &str) = ("37", "37");
 pub const RESET: &str = "0";
 pub const LAST_LINE: &str = "\x1b[99999B";
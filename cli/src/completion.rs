@@ -0,0 +1,104 @@
+//! Completion of the word at the cursor, when Tab is pressed. What the candidates are is up to the program using the
+//! prompt; it knows what the words of it's input mean.
+use crate::unicode;
+
+/// Supplies the candidates for completing what's being typed
+pub trait Completer {
+    /// The candidates for the word that ends at byte `position` of `line`, and the byte that word starts at. A
+    /// candidate replaces the whole word. When there's only one, a space is put after it, unless it ends with a `/`
+    /// or a `:`, that more is written after; a directory, or the file of a location.
+    fn complete(&self, line: &str, position: usize) -> (usize, Vec<String>);
+}
+
+/// The longest prefix that all of `candidates` start with
+pub fn common_prefix(candidates: &[String]) -> &str {
+    let Some((first, rest)) = candidates.split_first() else {
+        return "";
+    };
+    let mut length = first.len();
+    for candidate in rest {
+        length = first
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(length.min(candidate.len()), |((index, _), _)| {
+                index.min(length)
+            });
+    }
+    &first[..length]
+}
+
+/// Lays `candidates` out in columns, as many as fit in `width`, going down each column first like readline does
+pub fn columns(candidates: &[String], width: usize) -> Vec<String> {
+    let column_width = candidates
+        .iter()
+        .map(|candidate| unicode::width(candidate))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let columns = (width / column_width).max(1);
+    let rows = candidates.len().div_ceil(columns);
+    (0..rows)
+        .map(|row| {
+            let line: String = candidates
+                .iter()
+                .skip(row)
+                .step_by(rows)
+                .map(|candidate| {
+                    format!(
+                        "{}{}",
+                        candidate,
+                        " ".repeat(column_width - unicode::width(candidate))
+                    )
+                })
+                .collect();
+            line.trim_end().to_owned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn common_prefix_of_candidates() {
+        assert_eq!(common_prefix(&[]), "");
+        assert_eq!(common_prefix(&strings(&["break"])), "break");
+        assert_eq!(common_prefix(&strings(&["break", "backtrace"])), "b");
+        assert_eq!(common_prefix(&strings(&["delete", "del"])), "del");
+        assert_eq!(common_prefix(&strings(&["step", "next"])), "");
+        // é and è start with the same byte; the prefix doesn't end in the middle of them
+        assert_eq!(common_prefix(&strings(&["héllo", "hèllo"])), "h");
+        assert_eq!(common_prefix(&strings(&["中文", "中国", "中"])), "中");
+    }
+
+    #[test]
+    fn candidates_go_down_the_columns() {
+        let candidates = strings(&["a", "bb", "ccc", "dddd", "e"]);
+        // columns of 6, two of which fit
+        assert_eq!(
+            columns(&candidates, 12),
+            strings(&["a     dddd", "bb    e", "ccc"])
+        );
+        assert_eq!(
+            columns(&candidates, 80),
+            strings(&["a     bb    ccc   dddd  e"])
+        );
+        // one to a line when not even one column fits
+        assert_eq!(
+            columns(&candidates, 3),
+            strings(&["a", "bb", "ccc", "dddd", "e"])
+        );
+        // by the columns they take up, not their bytes
+        assert_eq!(
+            columns(&strings(&["中文", "ab"]), 80),
+            strings(&["中文  ab"])
+        );
+        assert!(columns(&[], 80).is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! Coloring the input as it's typed, and hinting at what could come after it. Like completion, what the words of the
+//! input are is up to the program using the prompt; it says which parts to draw how, and the prompt draws them.
+use std::ops::Range;
+
+use crate::ansicodes;
+
+/// The colors a part of the input can be drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+/// How a part of the input is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+}
+
+impl Style {
+    pub fn color(color: Color) -> Style {
+        Style {
+            color: Some(color),
+            bold: false,
+        }
+    }
+
+    pub fn bold(self) -> Style {
+        Style { bold: true, ..self }
+    }
+
+    // the escape sequences that draw in this style
+    pub(crate) fn ansi(&self) -> String {
+        let mut codes = String::new();
+        if self.bold {
+            codes.push_str(ansicodes::SET_BOLD);
+        }
+        if let Some(color) = self.color {
+            let (foreground, _) = match color {
+                Color::Red => ansicodes::COLOR_RED,
+                Color::Green => ansicodes::COLOR_GREEN,
+                Color::Yellow => ansicodes::COLOR_YELLOW,
+                Color::Blue => ansicodes::COLOR_BLUE,
+                Color::Magenta => ansicodes::COLOR_MAGENTA,
+                Color::Cyan => ansicodes::COLOR_CYAN,
+            };
+            codes.push_str(&format!("\x1b[{}m", foreground));
+        }
+        codes
+    }
+}
+
+/// Says how the input is drawn, every time it's redrawn
+pub trait Highlighter {
+    /// The parts of `line` to draw in a style, by the bytes they span; the rest is drawn as it is. Parts that overlap
+    /// the one before them are left out.
+    fn highlight(&self, line: &str) -> Vec<(Range<usize>, Style)>;
+
+    /// What might come after `line`, that's drawn dimmed after it while the cursor is at the end, and filled in by
+    /// Right or Ctrl-E. `history` is what was entered before, the newest last.
+    fn hint(&self, _line: &str, _history: &[String]) -> Option<String> {
+        None
+    }
+}
+
+/// The rest of the newest entry of `history` that starts with `line`
+pub fn history_hint(line: &str, history: &[String]) -> Option<String> {
+    if line.is_empty() {
+        return None;
+    }
+    history
+        .iter()
+        .rev()
+        .find_map(|entry| entry.strip_prefix(line).filter(|rest| !rest.is_empty()))
+        .map(|rest| rest.to_owned())
+}
+
+/// `line` with the escape sequences that draw `parts` of it in their styles
+pub(crate) fn styled(line: &str, mut parts: Vec<(Range<usize>, Style)>) -> String {
+    parts.sort_by_key(|(range, _)| range.start);
+    let mut styled = String::with_capacity(line.len());
+    let mut drawn = 0;
+    for (range, style) in parts {
+        let (Some(before), Some(part)) = (line.get(drawn..range.start), line.get(range.clone())) else {
+            continue;
+        };
+        styled.push_str(before);
+        styled.push_str(&style.ansi());
+        styled.push_str(part);
+        styled.push_str(ansicodes::RESET_STYLE);
+        drawn = range.end;
+    }
+    styled.push_str(&line[drawn..]);
+    styled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn parts_are_drawn_in_their_styles() {
+        let green = Style::color(Color::Green);
+        let bold = Style::default().bold();
+        assert_eq!(
+            styled("b main", vec![(2..6, bold), (0..1, green)]),
+            "\x1b[32mb\x1b[0m \x1b[1mmain\x1b[0m"
+        );
+        assert_eq!(Style::color(Color::Red).bold().ansi(), "\x1b[1m\x1b[31m");
+        assert_eq!(styled("run", vec![]), "run");
+    }
+
+    #[test]
+    fn parts_that_overlap_or_are_past_the_end_are_left_out() {
+        let green = Style::color(Color::Green);
+        // the part that starts first is drawn, the one overlapping it isn't
+        assert_eq!(
+            styled("break", vec![(2..5, green), (0..3, green)]),
+            "\x1b[32mbre\x1b[0mak"
+        );
+        assert_eq!(styled("run", vec![(1..10, green), (5..6, green)]), "run");
+        // nor are parts that start or end in the middle of a character
+        assert_eq!(styled("p é", vec![(3..4, green)]), "p é");
+    }
+
+    #[test]
+    fn hints_are_the_rest_of_the_newest_entry() {
+        let entries = history(&["break main", "bt", "break add", "run"]);
+        assert_eq!(history_hint("br", &entries).as_deref(), Some("eak add"));
+        assert_eq!(history_hint("break m", &entries).as_deref(), Some("ain"));
+        // there's nothing to hint when the line is an entry already, or nothing's typed
+        assert_eq!(history_hint("run", &entries), None);
+        assert_eq!(history_hint("", &entries), None);
+        assert_eq!(history_hint("step", &entries), None);
+        assert_eq!(history_hint("b", &[]), None);
+    }
+}
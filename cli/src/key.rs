@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KeyboardInput {
     Char(char),
     Left,
@@ -9,6 +10,7 @@ pub enum KeyboardInput {
     Home,
     End,
     Enter,
+    Tab,
     Escape,
     Delete,
     Backspace,
@@ -24,6 +26,7 @@ impl Into<ParsedKey> for KeyboardInput {
     }
 }
 #[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Modifier {
     Control,
     Shift,
@@ -32,11 +35,31 @@ pub enum Modifier {
 
 // For our intents and purposes, we only care about when 1 modifier is active. in other solutions, we would *obviously* care
 // if several modifiers are in effect.
-pub(crate) struct ParsedKey {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParsedKey {
     pub key: KeyboardInput,
     pub modifier: Option<Modifier>,
 }
 
+impl ParsedKey {
+    pub fn modified(key: KeyboardInput, modifier: Modifier) -> ParsedKey {
+        ParsedKey {
+            key,
+            modifier: Some(modifier),
+        }
+    }
+
+    /// Ctrl + `c`; a letter, or `_`
+    pub fn control(c: char) -> ParsedKey {
+        ParsedKey::modified(KeyboardInput::Char(c), Modifier::Control)
+    }
+
+    /// Alt + `c`, that terminals send as Escape and then `c`
+    pub fn alt(c: char) -> ParsedKey {
+        ParsedKey::modified(KeyboardInput::Char(c), Modifier::Alt)
+    }
+}
+
 pub(crate) fn read_key() -> ParsedKey {
     unsafe {
         use libc::read;
@@ -54,9 +77,18 @@ pub(crate) fn read_key() -> ParsedKey {
                 modifier: Some(Modifier::Control),
             },
             27 => {
-                if read(0, buf.as_mut_ptr().offset(1) as _, 2) == 0 {
+                if read(0, buf.as_mut_ptr().offset(1) as _, 1) <= 0 {
                     return KeyboardInput::Escape.into();
                 }
+                // anything but the start of a sequence is the key pressed with alt
+                match buf[1] {
+                    b'[' | b'O' => {}
+                    127 => return ParsedKey::modified(KeyboardInput::Backspace, Modifier::Alt),
+                    byte => return ParsedKey::alt(byte as char),
+                }
+                if read(0, buf.as_mut_ptr().offset(2) as _, 1) <= 0 {
+                    return ParsedKey::alt(buf[1] as char);
+                }
                 if buf[1] as char == '[' {
                     if buf[2] >= '0' as u8 && buf[2] <= '9' as u8 {
                         // extended escape seq
@@ -66,7 +98,9 @@ pub(crate) fn read_key() -> ParsedKey {
                         let k = buf[2] as char;
                         if buf[3] as char == '~' {
                             match k {
+                                '1' | '7' => return KeyboardInput::Home.into(),
                                 '3' => return KeyboardInput::Delete.into(),
+                                '4' | '8' => return KeyboardInput::End.into(),
                                 '5' => return KeyboardInput::PageUp.into(),
                                 '6' => return KeyboardInput::PageDown.into(),
                                 _ => {}
@@ -75,30 +109,22 @@ pub(crate) fn read_key() -> ParsedKey {
                             if read(0, buf.as_mut_ptr().offset(4) as _, 2) == -1 {
                                 panic!("failed to read from STDIN");
                             }
-                            if buf[4] as char == '5' {
-                                return match buf[5] as char {
-                                    'A' => ParsedKey {
-                                        key: KeyboardInput::Up,
-                                        modifier: Some(Modifier::Control),
-                                    },
-                                    'B' => ParsedKey {
-                                        key: KeyboardInput::Down,
-                                        modifier: Some(Modifier::Control),
-                                    },
-                                    'C' => ParsedKey {
-                                        key: KeyboardInput::Right,
-                                        modifier: Some(Modifier::Control),
-                                    },
-                                    'D' => ParsedKey {
-                                        key: KeyboardInput::Left,
-                                        modifier: Some(Modifier::Control),
-                                    },
-                                    _ => ParsedKey {
-                                        key: KeyboardInput::Err,
-                                        modifier: None,
-                                    },
-                                };
-                            }
+                            // 3 is alt, 5 is ctrl
+                            let modifier = match buf[4] as char {
+                                '3' => Modifier::Alt,
+                                '5' => Modifier::Control,
+                                _ => return KeyboardInput::Err.into(),
+                            };
+                            return match buf[5] as char {
+                                'A' => ParsedKey::modified(KeyboardInput::Up, modifier),
+                                'B' => ParsedKey::modified(KeyboardInput::Down, modifier),
+                                'C' => ParsedKey::modified(KeyboardInput::Right, modifier),
+                                'D' => ParsedKey::modified(KeyboardInput::Left, modifier),
+                                _ => ParsedKey {
+                                    key: KeyboardInput::Err,
+                                    modifier: None,
+                                },
+                            };
                         }
                     } else {
                         return match buf[2] as char {
@@ -120,8 +146,38 @@ pub(crate) fn read_key() -> ParsedKey {
                 }
                 return KeyboardInput::Err.into();
             }
+            9 => KeyboardInput::Tab.into(),
             127 => KeyboardInput::Backspace.into(),
             13 => KeyboardInput::Enter.into(),
+            // the other control characters are ctrl + the letter; 18 is ctrl + r
+            byte @ 1..=26 => ParsedKey {
+                key: KeyboardInput::Char((b'a' + byte - 1) as char),
+                modifier: Some(Modifier::Control),
+            },
+            31 => ParsedKey::control('_'),
+            // the first byte of a character in UTF-8 says how many bytes come after it
+            first @ 0xC0..=0xF7 => {
+                let length = first.leading_ones() as usize;
+                let mut read_so_far = 1;
+                while read_so_far < length {
+                    let bytes_read = read(
+                        0,
+                        buf.as_mut_ptr().add(read_so_far) as _,
+                        length - read_so_far,
+                    );
+                    if bytes_read <= 0 {
+                        return KeyboardInput::Err.into();
+                    }
+                    read_so_far += bytes_read as usize;
+                }
+                match std::str::from_utf8(&buf[..length])
+                    .ok()
+                    .and_then(|c| c.chars().next())
+                {
+                    Some(c) => KeyboardInput::Char(c).into(),
+                    None => KeyboardInput::Err.into(),
+                }
+            }
             _ => KeyboardInput::Char(buf[0] as char).into(),
         }
     }
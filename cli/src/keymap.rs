@@ -0,0 +1,159 @@
+//! What the keys do at the prompt. The keymap starts out with the bindings of emacs, the ones bash and gdb have too,
+//! and a program can bind keys to other actions, or unbind them, and set it on the prompt.
+use std::collections::HashMap;
+
+use crate::key::{KeyboardInput, Modifier, ParsedKey};
+
+/// What a key can be bound to do. They're named after the readline functions that do the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Writes the character typed; what the letters, digits and the rest are bound to when nothing else is
+    SelfInsert,
+    BackwardChar,
+    ForwardChar,
+    /// Moves to the start of the word before the cursor; words are letters, digits and underscores
+    BackwardWord,
+    /// Moves to the end of the word after the cursor
+    ForwardWord,
+    BeginningOfLine,
+    EndOfLine,
+    PreviousHistory,
+    NextHistory,
+    /// Searches the history backwards, as the query is typed
+    ReverseSearchHistory,
+    ForwardSearchHistory,
+    DeleteChar,
+    BackwardDeleteChar,
+    /// Kills from the cursor to the end of the line
+    KillLine,
+    /// Kills from the start of the line to the cursor
+    BackwardKillLine,
+    /// Kills to the end of the word after the cursor
+    KillWord,
+    /// Kills to the start of the word before the cursor
+    BackwardKillWord,
+    /// Kills back to the whitespace before the cursor; what Ctrl-W does in a terminal
+    UnixWordRubout,
+    /// Inserts the text that was killed last
+    Yank,
+    /// Right after a yank, replaces the text yanked with what was killed before it
+    YankPop,
+    /// Swaps the character before the cursor with the one at it, or the two before it at the end of the line
+    TransposeChars,
+    /// Takes back the last change to the line
+    Undo,
+    Complete,
+    AcceptLine,
+}
+
+impl Action {
+    /// Whether the action cuts text into the kill ring. Kills one after the other go together, to be yanked as one.
+    pub fn is_kill(self) -> bool {
+        matches!(
+            self,
+            Action::KillLine
+                | Action::BackwardKillLine
+                | Action::KillWord
+                | Action::BackwardKillWord
+                | Action::UnixWordRubout
+        )
+    }
+}
+
+/// The actions keys are bound to
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<ParsedKey, Action>,
+}
+
+impl Keymap {
+    /// The bindings of emacs, like readline has them; Ctrl-A and Ctrl-E go to the start and end of the line, Ctrl-K,
+    /// Ctrl-U and Ctrl-W kill, Ctrl-Y yanks and Alt-Y cycles through what was killed, Alt-B, Alt-F and Alt-D work on
+    /// words, Ctrl-T transposes and Ctrl-_ undoes.
+    pub fn emacs() -> Keymap {
+        use Action::*;
+        use KeyboardInput as Key;
+        let keys = [
+            (Key::Left.into(), BackwardChar),
+            (ParsedKey::control('b'), BackwardChar),
+            (Key::Right.into(), ForwardChar),
+            (ParsedKey::control('f'), ForwardChar),
+            (
+                ParsedKey::modified(Key::Left, Modifier::Control),
+                BackwardWord,
+            ),
+            (ParsedKey::modified(Key::Left, Modifier::Alt), BackwardWord),
+            (ParsedKey::alt('b'), BackwardWord),
+            (
+                ParsedKey::modified(Key::Right, Modifier::Control),
+                ForwardWord,
+            ),
+            (ParsedKey::modified(Key::Right, Modifier::Alt), ForwardWord),
+            (ParsedKey::alt('f'), ForwardWord),
+            (Key::Home.into(), BeginningOfLine),
+            (ParsedKey::control('a'), BeginningOfLine),
+            (Key::End.into(), EndOfLine),
+            (ParsedKey::control('e'), EndOfLine),
+            (Key::Up.into(), PreviousHistory),
+            (ParsedKey::control('p'), PreviousHistory),
+            (Key::Down.into(), NextHistory),
+            (ParsedKey::control('n'), NextHistory),
+            (ParsedKey::control('r'), ReverseSearchHistory),
+            (ParsedKey::control('s'), ForwardSearchHistory),
+            (Key::Delete.into(), DeleteChar),
+            (ParsedKey::control('d'), DeleteChar),
+            (Key::Backspace.into(), BackwardDeleteChar),
+            (ParsedKey::control('k'), KillLine),
+            (ParsedKey::control('u'), BackwardKillLine),
+            (ParsedKey::alt('d'), KillWord),
+            (
+                ParsedKey::modified(Key::Backspace, Modifier::Alt),
+                BackwardKillWord,
+            ),
+            (
+                ParsedKey::modified(Key::Backspace, Modifier::Control),
+                UnixWordRubout,
+            ),
+            (ParsedKey::control('w'), UnixWordRubout),
+            (ParsedKey::control('y'), Yank),
+            (ParsedKey::alt('y'), YankPop),
+            (ParsedKey::control('t'), TransposeChars),
+            (ParsedKey::control('_'), Undo),
+            (Key::Tab.into(), Complete),
+            (Key::Enter.into(), AcceptLine),
+        ];
+        Keymap {
+            bindings: keys.into_iter().collect(),
+        }
+    }
+
+    /// Makes `key` do `action`, instead of what it did before
+    pub fn bind(&mut self, key: ParsedKey, action: Action) {
+        self.bindings.insert(key, action);
+    }
+
+    /// Makes `key` do nothing; or write itself, if it's a character without a modifier
+    pub fn unbind(&mut self, key: &ParsedKey) {
+        self.bindings.remove(key);
+    }
+
+    /// What `key` is bound to do
+    pub fn action(&self, key: &ParsedKey) -> Option<Action> {
+        match self.bindings.get(key) {
+            Some(action) => Some(*action),
+            None => match key {
+                ParsedKey {
+                    key: KeyboardInput::Char(_),
+                    modifier: None,
+                } => Some(Action::SelfInsert),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::emacs()
+    }
+}
@@ -1,6 +1,10 @@
 extern crate libc;
 pub(crate) mod ansicodes;
 pub(crate) mod cfg;
-pub(crate) mod key;
+pub mod completion;
+pub mod highlight;
+pub mod key;
+pub mod keymap;
 pub mod prompt;
+pub(crate) mod unicode;
 pub use prompt::Prompt;
@@ -1,12 +1,23 @@
 use crate::ansicodes;
 use crate::cfg;
+use crate::completion::{self, Completer};
+use crate::highlight::{self, Highlighter};
 use crate::key;
+use crate::unicode;
 
-use crate::key::KeyboardInput;
+use crate::key::{KeyboardInput, Modifier};
+use crate::keymap::{Action, Keymap};
+use std::{ops::Range, path::PathBuf};
 
 struct PromptHistory {
     history: Vec<String>,
     history_selector: usize,
+    // the most entries that are kept; the oldest go first
+    limit: usize,
+    // where the history is saved, if it is
+    file: Option<PathBuf>,
+    // the lines in the file; entries are appended to it, and it's only written anew when it has more than the limit
+    saved: usize,
 }
 
 impl PromptHistory {
@@ -14,12 +25,86 @@ impl PromptHistory {
         PromptHistory {
             history: Vec::with_capacity(20),
             history_selector: 0,
+            limit: usize::MAX,
+            file: None,
+            saved: 0,
         }
     }
 
-    pub fn add(&mut self, item: String) {
+    /// Loads the history saved in `file`, that new entries are saved to from now on. A file that doesn't exist yet
+    /// is an empty history.
+    pub fn load(&mut self, file: PathBuf, limit: usize) -> std::io::Result<()> {
+        self.limit = limit;
+        let saved = match std::fs::read_to_string(&file) {
+            Ok(saved) => saved,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        self.file = Some(file);
+        self.saved = saved.lines().count();
+        let entered = std::mem::take(&mut self.history);
+        for item in saved.lines() {
+            self.push(item.to_owned());
+        }
+        // what was entered before the history was loaded is newer than what's in it
+        for item in entered {
+            self.add(item);
+        }
+        self.history_selector = self.history.len();
+        Ok(())
+    }
+
+    // adds `item` as the newest entry; if it's been entered before, that entry is moved here rather than kept twice
+    fn push(&mut self, item: String) {
+        if item.trim().is_empty() {
+            return;
+        }
+        self.history.retain(|entry| *entry != item);
         self.history.push(item);
+        let excess = self.history.len().saturating_sub(self.limit);
+        self.history.drain(..excess);
+    }
+
+    pub fn add(&mut self, item: String) {
+        let blank = item.trim().is_empty();
+        self.push(item.clone());
         self.history_selector = self.history.len();
+        if !blank {
+            // a history that can't be saved is still there for this session
+            let _ = self.save(&item);
+        }
+    }
+
+    // appends `item` to the history file. The entries entered again are left where they were in it, as loading it
+    // drops them, until the file has more lines than are kept; then it's written anew with just the history.
+    fn save(&mut self, item: &str) -> std::io::Result<()> {
+        use std::io::Write;
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if self.saved < self.limit {
+            self.saved += 1;
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)?;
+            return writeln!(file, "{}", item);
+        }
+        self.saved = self.history.len();
+        let mut saved = self.history.join("\n");
+        saved.push('\n');
+        std::fs::write(file, saved)
+    }
+
+    /// The newest entry at or before `from` that has `query` in it if `backward`, otherwise the oldest at or after
+    pub fn find(&self, query: &str, from: usize, backward: bool) -> Option<usize> {
+        let has_query = |index: &usize| self.history[*index].contains(query);
+        match backward {
+            true => (0..=from.min(self.history.len().checked_sub(1)?))
+                .rev()
+                .find(has_query),
+            false => (from..self.history.len()).find(has_query),
+        }
     }
 
     pub fn get_previous(&mut self) -> Option<&String> {
@@ -43,11 +128,138 @@ impl PromptHistory {
     }
 }
 
+// as many kills as readline keeps
+const KILL_RING_SIZE: usize = 10;
+
+// the text that's been killed, the newest last, to be yanked back
+struct KillRing {
+    kills: Vec<String>,
+    // how many kills back the last yank went
+    yanked: usize,
+}
+
+impl KillRing {
+    fn new() -> KillRing {
+        KillRing {
+            kills: Vec::with_capacity(KILL_RING_SIZE),
+            yanked: 0,
+        }
+    }
+
+    // keeps `text`; with the text killed last if `append`, after it if the kill was `forward`, before it otherwise
+    fn kill(&mut self, text: &str, forward: bool, append: bool) {
+        match self.kills.last_mut() {
+            Some(last) if append && forward => last.push_str(text),
+            Some(last) if append => last.insert_str(0, text),
+            _ if text.is_empty() => {}
+            _ => {
+                if self.kills.len() == KILL_RING_SIZE {
+                    self.kills.remove(0);
+                }
+                self.kills.push(text.to_owned());
+            }
+        }
+    }
+
+    fn yank(&mut self) -> Option<&str> {
+        self.yanked = 0;
+        self.kills.last().map(String::as_str)
+    }
+
+    // what was killed before what was yanked last, going around to the newest after the oldest
+    fn yank_pop(&mut self) -> Option<&str> {
+        if self.kills.is_empty() {
+            return None;
+        }
+        self.yanked = (self.yanked + 1) % self.kills.len();
+        Some(&self.kills[self.kills.len() - 1 - self.yanked])
+    }
+}
+
+// the input and cursor before each change, to undo it
+struct UndoList {
+    changes: Vec<(String, usize)>,
+}
+
+impl UndoList {
+    fn new() -> UndoList {
+        UndoList { changes: vec![] }
+    }
+
+    // keeps `before`, what the input was before `action` made it `after`, to go back to. A word typed is undone all
+    // at once, so a character typed right after another doesn't start a change of it's own, unless it's a space.
+    fn record(
+        &mut self,
+        before: (String, usize),
+        after: &str,
+        action: Action,
+        last: Option<Action>,
+        key: &KeyboardInput,
+    ) {
+        let typing =
+            action == Action::SelfInsert && last == Some(Action::SelfInsert) && *key != KeyboardInput::Char(' ');
+        if action != Action::Undo && after != before.0 && !typing {
+            self.changes.push(before);
+        }
+    }
+
+    fn undo(&mut self) -> Option<(String, usize)> {
+        self.changes.pop()
+    }
+}
+
+// swaps the grapheme before `cursor` with the one at it, or the two before it when it's at the end of `line`, and
+// returns where the cursor goes; after them. None if there aren't two to swap.
+fn transpose(line: &mut String, cursor: usize) -> Option<usize> {
+    let second = match cursor == line.len() {
+        true => unicode::previous_grapheme(line, cursor),
+        false => cursor,
+    };
+    if second == 0 {
+        return None;
+    }
+    let first = unicode::previous_grapheme(line, second);
+    let end = unicode::next_grapheme(line, second);
+    let swapped = format!("{}{}", &line[second..end], &line[first..second]);
+    line.replace_range(first..end, &swapped);
+    Some(end)
+}
+
+// the marks that combine with a letter are part of the word too
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || unicode::is_extending(c)
+}
+
+// the start of the word before `position`, or of the one it's in
+fn word_start(line: &str, position: usize) -> usize {
+    let before = line[..position].trim_end_matches(|c| !is_word_char(c));
+    before.trim_end_matches(is_word_char).len()
+}
+
+// the end of the word after `position`, or of the one it's in
+fn word_end(line: &str, position: usize) -> usize {
+    let after = line[position..].trim_start_matches(|c| !is_word_char(c));
+    line.len() - after.trim_start_matches(is_word_char).len()
+}
+
+// the start of the word before `position`, where words are everything between whitespace
+fn unix_word_start(line: &str, position: usize) -> usize {
+    line[..position]
+        .trim_end()
+        .trim_end_matches(|c: char| !c.is_whitespace())
+        .len()
+}
+
 pub struct Prompt {
     prompt: String,
     history: PromptHistory,
+    // the byte of the input the cursor is at; it's column is the width of what's before it
     cursor_column: usize,
     config: cfg::TerminalConfiguration,
+    completer: Option<Box<dyn Completer>>,
+    highlighter: Option<Box<dyn Highlighter>>,
+    keymap: Keymap,
+    kill_ring: KillRing,
 }
 
 // utility functions
@@ -70,13 +282,14 @@ pub(crate) fn move_cursor_left(steps: usize) {
     }
 }
 
-pub(crate) fn move_cursor_right(steps: usize) {
-    if steps != 0 {
-        let cmd = format!("\x1b[{}C", steps);
-        unsafe {
-            if libc::write(libc::STDOUT_FILENO, cmd.as_ptr() as _, cmd.len()) == -1 {
-                panic!("failed to write to stdout");
-            }
+// the number of columns of the terminal; 80 if it can't be told
+pub(crate) fn terminal_width() -> usize {
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size as *mut _) == -1 || size.ws_col == 0 {
+            80
+        } else {
+            size.ws_col as usize
         }
     }
 }
@@ -89,6 +302,10 @@ impl Prompt {
             history: PromptHistory::new(),
             cursor_column: 0,
             config,
+            completer: None,
+            highlighter: None,
+            keymap: Keymap::emacs(),
+            kill_ring: KillRing::new(),
         })
     }
 
@@ -111,152 +328,306 @@ impl Prompt {
         self.prompt = format!("\x1b[2K{}", prompt);
     }
 
-    pub fn read_input(&mut self) -> String {
-        write_string(ansicodes::CLEAR_LINE);
+    /// Sets what the keys do; the bindings of emacs, unless it's set
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// Sets what completes the input when Tab is pressed
+    pub fn set_completer(&mut self, completer: Box<dyn Completer>) {
+        self.completer = Some(completer);
+    }
+
+    /// Sets what colors the input, and hints at what could come after it
+    pub fn set_highlighter(&mut self, highlighter: Box<dyn Highlighter>) {
+        self.highlighter = Some(highlighter);
+    }
+
+    // what the highlighter hints at after `input`, while the cursor is at the end of it
+    fn hint(&self, input: &str) -> Option<String> {
+        if self.cursor_column != input.len() {
+            return None;
+        }
+        self.highlighter
+            .as_ref()?
+            .hint(input, &self.history.history)
+    }
+
+    // writes the prompt and `input` over the current line, with the hint after it, and puts the cursor back where it
+    // was
+    fn redraw(&self, input: &str) {
+        write_string("\r");
         write_string(&self.prompt);
+        match &self.highlighter {
+            Some(highlighter) => write_string(&highlight::styled(input, highlighter.highlight(input))),
+            None => write_string(input),
+        }
+        let hint = self.hint(input).unwrap_or_default();
+        if !hint.is_empty() {
+            write_string(ansicodes::SET_DIM);
+            write_string(&hint);
+            write_string(ansicodes::RESET_STYLE);
+        }
+        write_string(ansicodes::CLEAR_LINE_FROM_CURSOR);
+        move_cursor_left(unicode::width(&input[self.cursor_column..]) + unicode::width(&hint));
+    }
 
-        let update_cursor = |current_input: &str, cursor_idx| {
-            move_cursor_left(current_input.len().saturating_sub(cursor_idx));
+    // completes the word at the cursor; as far as all candidates agree, and when they already do, lists them if
+    // Tab was pressed twice. The input is redrawn after.
+    fn complete(&mut self, input: &mut String, list: bool) {
+        let Some(completer) = &self.completer else {
+            return;
         };
-        self.cursor_column = 0;
-        let mut res = String::with_capacity(100);
+        let (start, candidates) = completer.complete(input, self.cursor_column);
+        let start = start.min(self.cursor_column);
+        let completed = match &candidates[..] {
+            [] => {
+                write_string(ansicodes::BELL);
+                return;
+            }
+            // a unique candidate is the whole word, so the next one can be typed right away; unless it's a directory
+            [only] if !only.ends_with(['/', ':']) && !input[self.cursor_column..].starts_with(' ') => {
+                format!("{} ", only)
+            }
+            [only] => only.clone(),
+            _ => completion::common_prefix(&candidates).to_owned(),
+        };
+        if completed.len() > self.cursor_column - start {
+            input.replace_range(start..self.cursor_column, &completed);
+            self.cursor_column = start + completed.len();
+        } else if list {
+            write_string("\r\n");
+            for line in completion::columns(&candidates, terminal_width()) {
+                write_string(&line);
+                write_string("\r\n");
+            }
+        } else {
+            write_string(ansicodes::BELL);
+        }
+    }
+
+    /// Keeps the history in `file`; what's in it now is loaded, and every entry is saved to it as it's entered. At
+    /// most `limit` entries are kept, and an entry that's entered again replaces the one from before.
+    pub fn set_history_file<P: Into<PathBuf>>(&mut self, file: P, limit: usize) -> std::io::Result<()> {
+        self.history.load(file.into(), limit)
+    }
+
+    // shows the state of an incremental search; the query, and the entry found with the query highlighted in it
+    fn draw_search(&self, query: &str, found: Option<usize>, backward: bool, failed: bool) {
+        let entry = found.map_or("", |index| self.history.history[index].as_str());
+        write_string("\r");
+        write_string(ansicodes::CLEAR_LINE);
+        write_string(&format!(
+            "({}{}i-search)`{}': ",
+            if failed { "failed " } else { "" },
+            if backward { "reverse-" } else { "" },
+            query
+        ));
+        match entry.find(query).filter(|_| !query.is_empty()) {
+            Some(start) => {
+                let end = start + query.len();
+                write_string(&entry[..start]);
+                write_string(ansicodes::SET_REVERSE);
+                write_string(&entry[start..end]);
+                write_string(ansicodes::RESET_STYLE);
+                write_string(&entry[end..]);
+            }
+            None => write_string(entry),
+        }
+    }
+
+    // searches the history for entries with what's typed in them, as it's typed; back from the newest if
+    // `backward`, forward from the oldest otherwise. Ctrl-R and Ctrl-S go on to the next entry before or after the
+    // one found, Enter enters it, Ctrl-G gives up and puts back what was being written, and any other key makes the
+    // entry found the input, to be edited. Returns whether it was entered.
+    fn search(&mut self, input: &mut String, mut backward: bool) -> bool {
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+        let mut failed = false;
+        let newest = self.history.history.len().saturating_sub(1);
         loop {
+            self.draw_search(&query, found, backward, failed);
             let read_key = key::read_key();
-            match read_key.key {
-                KeyboardInput::Char(c) => {
-                    if self.cursor_column == res.len() {
-                        unsafe {
-                            if libc::write(libc::STDOUT_FILENO, &c as *const _ as _, 1) == -1 {
-                                panic!("failed to write to stdout");
-                            }
-                        }
-                        res.push(c);
-                        self.cursor_column += 1;
-                    } else {
-                        res.insert(self.cursor_column, c);
-                        write_string(&res[self.cursor_column..]);
-                        self.cursor_column += 1;
+            let control = matches!(read_key.modifier, Some(Modifier::Control));
+            // where to look from, and whether the entry found there counts
+            let from = match read_key.key {
+                KeyboardInput::Char('r') if control => {
+                    backward = true;
+                    found.map_or(Some(newest), |index| index.checked_sub(1))
+                }
+                KeyboardInput::Char('s') if control => {
+                    backward = false;
+                    found.map_or(Some(0), |index| Some(index + 1))
+                }
+                KeyboardInput::Char('g') if control => {
+                    self.redraw(input);
+                    return false;
+                }
+                KeyboardInput::Char(c) if !control => {
+                    query.push(c);
+                    Some(found.unwrap_or(if backward { newest } else { 0 }))
+                }
+                KeyboardInput::Backspace => {
+                    query.pop();
+                    // there's nothing to find, until something's typed again
+                    (!query.is_empty()).then_some(if backward { newest } else { 0 })
+                }
+                key => {
+                    if let Some(index) = found {
+                        *input = self.history.history[index].clone();
+                        self.history.history_selector = index;
                     }
-                    if self.cursor_column != res.len() {
-                        update_cursor(&res, self.cursor_column);
+                    self.cursor_column = input.len();
+                    if matches!(key, KeyboardInput::Enter) {
+                        return true;
                     }
+                    self.redraw(input);
+                    return false;
                 }
-                KeyboardInput::Left => {
-                    if let Some(_) = read_key.modifier {
-                        let p = res.len().saturating_sub(self.cursor_column);
-                        let steps_left = res
-                            .chars()
-                            .rev()
-                            .skip(p)
-                            .position(|c| c == ' ')
-                            .unwrap_or(self.cursor_column);
-                        self.cursor_column = self.cursor_column.saturating_sub(steps_left);
-                        move_cursor_left(steps_left);
-                    } else {
-                        move_cursor_left(1);
-                        self.cursor_column = self.cursor_column.saturating_sub(1);
-                    }
+            };
+            match from.and_then(|from| self.history.find(&query, from, backward)) {
+                _ if query.is_empty() && from.is_none() => {
+                    found = None;
+                    failed = false;
+                }
+                Some(index) => {
+                    found = Some(index);
+                    failed = false;
                 }
-                KeyboardInput::Right => {
-                    if self.cursor_column != res.len() {
-                        move_cursor_right(1);
+                None => {
+                    failed = true;
+                    write_string(ansicodes::BELL);
+                }
+            }
+        }
+    }
+
+    // cuts `range` out of the input into the kill ring; onto the text killed last, if `append`, as kills one after
+    // the other are yanked back as one
+    fn kill(&mut self, input: &mut String, range: Range<usize>, append: bool) {
+        let forward = range.start == self.cursor_column;
+        self.kill_ring.kill(&input[range.clone()], forward, append);
+        input.replace_range(range.clone(), "");
+        self.cursor_column = range.start;
+    }
+
+    pub fn read_input(&mut self) -> String {
+        write_string(ansicodes::CLEAR_LINE);
+        write_string(&self.prompt);
+        self.cursor_column = 0;
+        let mut res = String::with_capacity(100);
+        let mut undo = UndoList::new();
+        let mut previous: Option<Action> = None;
+        // where the text yanked last is, for yank-pop to replace
+        let mut yanked = 0..0;
+        loop {
+            let read_key = key::read_key();
+            let Some(action) = self.keymap.action(&read_key) else {
+                continue;
+            };
+            let before = (res.clone(), self.cursor_column);
+            let last = previous.replace(action);
+            match action {
+                Action::SelfInsert => {
+                    if let KeyboardInput::Char(c) = read_key.key {
+                        res.insert(self.cursor_column, c);
+                        self.cursor_column += c.len_utf8();
                     }
-                    self.cursor_column = self.cursor_column.saturating_add(1).min(res.len());
                 }
-                KeyboardInput::Up => {
-                    write_string(ansicodes::CLEAR_LINE);
-                    write_string(&self.prompt);
-                    if let Some(s) = self.history.get_previous() {
-                        res = s.clone();
-                    } else {
-                        res.clear();
+                Action::BackwardChar => self.cursor_column = unicode::previous_grapheme(&res, self.cursor_column),
+                // at the end of the line, they fill in the hint
+                Action::ForwardChar | Action::EndOfLine if self.cursor_column == res.len() => {
+                    if let Some(hint) = self.hint(&res) {
+                        res.push_str(&hint);
+                        self.cursor_column = res.len();
                     }
+                }
+                Action::ForwardChar => self.cursor_column = unicode::next_grapheme(&res, self.cursor_column),
+                Action::BackwardWord => self.cursor_column = word_start(&res, self.cursor_column),
+                Action::ForwardWord => self.cursor_column = word_end(&res, self.cursor_column),
+                Action::BeginningOfLine => self.cursor_column = 0,
+                Action::EndOfLine => self.cursor_column = res.len(),
+                Action::PreviousHistory => {
+                    res = self.history.get_previous().cloned().unwrap_or_default();
                     self.cursor_column = res.len();
-                    write_string(&res);
                 }
-                KeyboardInput::Down => {
-                    write_string(ansicodes::CLEAR_LINE);
-                    write_string(&self.prompt);
-                    if let Some(s) = self.history.get_next() {
-                        res = s.clone();
-                    } else {
-                        res.clear();
-                    }
+                Action::NextHistory => {
+                    res = self.history.get_next().cloned().unwrap_or_default();
                     self.cursor_column = res.len();
-                    write_string(&res);
                 }
-                KeyboardInput::PageUp => todo!("page up not yet implemented"),
-                KeyboardInput::PageDown => todo!("page down not yet implemented"),
-                KeyboardInput::Home => todo!("home not yet implemented"),
-                KeyboardInput::End => todo!("end not yet implemented"),
-                KeyboardInput::Enter => {
-                    if !res.is_empty() {
+                Action::ReverseSearchHistory | Action::ForwardSearchHistory => {
+                    if self.search(&mut res, action == Action::ReverseSearchHistory) {
                         write_string("\r\n");
                         self.history.add(res.clone());
                         return res;
                     }
                 }
-                KeyboardInput::Escape => todo!("escape not yet implemented"),
-                KeyboardInput::Delete => {
-                    if res.len() != 0 && self.cursor_column < res.len() {
-                        res.remove(self.cursor_column);
-                        write_string(ansicodes::CLEAR_LINE_FROM_CURSOR);
-                        if res.is_empty() {
-                            move_cursor_right(1);
-                        }
-                        write_string(&res[self.cursor_column..]);
-                        update_cursor(&res, self.cursor_column);
-                    }
+                Action::DeleteChar => {
+                    let end = unicode::next_grapheme(&res, self.cursor_column);
+                    res.replace_range(self.cursor_column..end, "");
                 }
-                KeyboardInput::Backspace => {
-                    if let Some(_) = read_key.modifier {
-                        if !res.is_empty() && self.cursor_column != 0 {
-                            let skip = res.len().saturating_sub(self.cursor_column);
-                            let mut remove_count = self.cursor_column;
-                            for (i, x) in res.chars().rev().skip(skip).enumerate() {
-                                if x == ' ' {
-                                    remove_count = i;
-                                    break;
-                                }
-                            }
-                            unsafe {
-                                std::ptr::copy(
-                                    res.as_mut_ptr().offset(self.cursor_column as _) as _,
-                                    res.as_mut_ptr()
-                                        .offset(self.cursor_column as isize - remove_count as isize)
-                                        as _,
-                                    res.len().saturating_sub(self.cursor_column),
-                                )
-                            }
-                            for _ in 0..remove_count {
-                                res.pop();
-                            }
-                            self.cursor_column = self.cursor_column.saturating_sub(remove_count);
-                            move_cursor_left(remove_count);
-                            write_string(ansicodes::CLEAR_LINE_FROM_CURSOR);
-                            write_string(&res[self.cursor_column..]);
-                            update_cursor(&res, self.cursor_column);
-                        }
-                    } else {
-                        if self.cursor_column == res.len() && !res.is_empty() {
-                            res.pop();
-                            self.cursor_column = self.cursor_column.saturating_sub(1);
-                            move_cursor_left(1);
+                Action::BackwardDeleteChar => {
+                    let start = unicode::previous_grapheme(&res, self.cursor_column);
+                    res.replace_range(start..self.cursor_column, "");
+                    self.cursor_column = start;
+                }
+                Action::KillLine
+                | Action::BackwardKillLine
+                | Action::KillWord
+                | Action::BackwardKillWord
+                | Action::UnixWordRubout => {
+                    let range = match action {
+                        Action::KillLine => self.cursor_column..res.len(),
+                        Action::BackwardKillLine => 0..self.cursor_column,
+                        Action::KillWord => self.cursor_column..word_end(&res, self.cursor_column),
+                        Action::BackwardKillWord => word_start(&res, self.cursor_column)..self.cursor_column,
+                        _ => unix_word_start(&res, self.cursor_column)..self.cursor_column,
+                    };
+                    self.kill(&mut res, range, last.is_some_and(Action::is_kill));
+                }
+                Action::Yank => match self.kill_ring.yank() {
+                    Some(text) => {
+                        res.insert_str(self.cursor_column, text);
+                        yanked = self.cursor_column..self.cursor_column + text.len();
+                        self.cursor_column = yanked.end;
+                    }
+                    None => write_string(ansicodes::BELL),
+                },
+                Action::YankPop => match self.kill_ring.yank_pop() {
+                    Some(text) if matches!(last, Some(Action::Yank | Action::YankPop)) => {
+                        res.replace_range(yanked.clone(), text);
+                        yanked = yanked.start..yanked.start + text.len();
+                        self.cursor_column = yanked.end;
+                    }
+                    _ => write_string(ansicodes::BELL),
+                },
+                Action::TransposeChars => match transpose(&mut res, self.cursor_column) {
+                    Some(cursor_column) => self.cursor_column = cursor_column,
+                    None => write_string(ansicodes::BELL),
+                },
+                Action::Undo => match undo.undo() {
+                    Some((input, cursor_column)) => {
+                        res = input;
+                        self.cursor_column = cursor_column;
+                    }
+                    None => write_string(ansicodes::BELL),
+                },
+                // a second Tab in a row lists the candidates
+                Action::Complete => self.complete(&mut res, last == Some(Action::Complete)),
+                Action::AcceptLine => {
+                    if !res.is_empty() {
+                        // the cursor is before the hint, if there is one
+                        if self.cursor_column == res.len() {
                             write_string(ansicodes::CLEAR_LINE_FROM_CURSOR);
-                        } else {
-                            if self.cursor_column > 0 {
-                                self.cursor_column -= 1;
-                                res.remove(self.cursor_column);
-                                move_cursor_left(1);
-                                write_string(ansicodes::CLEAR_LINE_FROM_CURSOR);
-                                write_string(&res[self.cursor_column..]);
-                                update_cursor(&res, self.cursor_column);
-                            }
                         }
+                        write_string("\r\n");
+                        self.history.add(res.clone());
+                        return res;
                     }
                 }
-                KeyboardInput::Err => todo!("err not yet implemented"),
             }
+            undo.record(before, &res, action, last, &read_key.key);
+            self.redraw(&res);
         }
     }
 
@@ -274,3 +645,200 @@ impl Drop for Prompt {
         self.close();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a history file of it's own for each test, that doesn't exist yet
+    fn history_file(name: &str) -> PathBuf {
+        let file = std::env::temp_dir().join(format!("history-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&file);
+        file
+    }
+
+    fn loaded(file: &std::path::Path, limit: usize) -> PromptHistory {
+        let mut history = PromptHistory::new();
+        history.load(file.to_path_buf(), limit).unwrap();
+        history
+    }
+
+    fn saved(file: &std::path::Path) -> Vec<String> {
+        std::fs::read_to_string(file)
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn history_keeps_the_newest_entries() {
+        let file = history_file("limit");
+        let mut history = loaded(&file, 3);
+        for item in ["run", "bt", "next", "step"] {
+            history.add(item.into());
+        }
+        assert_eq!(history.history, ["bt", "next", "step"]);
+        // it's written anew only once there's more in it than the limit
+        assert_eq!(saved(&file), ["bt", "next", "step"]);
+        assert_eq!(loaded(&file, 3).history, ["bt", "next", "step"]);
+        // a smaller limit keeps fewer of what was saved
+        assert_eq!(loaded(&file, 2).history, ["next", "step"]);
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn entries_entered_again_move_to_the_end() {
+        let file = history_file("duplicates");
+        let mut history = PromptHistory::new();
+        history.add("run".into());
+        let _ = history.load(file.clone(), 10);
+        for item in ["bt", "  ", "run", "bt"] {
+            history.add(item.into());
+        }
+        assert_eq!(history.history, ["run", "bt"]);
+        // they're appended, and what's there twice is dropped as it's loaded
+        assert_eq!(saved(&file), ["run", "bt", "run", "bt"]);
+        assert_eq!(loaded(&file, 10).history, ["run", "bt"]);
+        // the file is written anew once there's more in it than the limit
+        let mut history = loaded(&file, 4);
+        history.add("next".into());
+        assert_eq!(saved(&file), ["run", "bt", "next"]);
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn searches_stay_in_the_history() {
+        let mut history = PromptHistory::new();
+        assert_eq!(history.find("", 0, true), None);
+        assert_eq!(history.find("", 0, false), None);
+        for item in ["break main", "run", "bt", "break add"] {
+            history.add(item.into());
+        }
+        assert_eq!(history.find("break", 3, true), Some(3));
+        assert_eq!(history.find("break", 2, true), Some(0));
+        assert_eq!(history.find("break", 100, true), Some(3));
+        assert_eq!(history.find("break", 1, false), Some(3));
+        assert_eq!(history.find("break", 0, false), Some(0));
+        assert_eq!(history.find("break", 4, false), None);
+        assert_eq!(history.find("break", 100, false), None);
+        assert_eq!(history.find("continue", 3, true), None);
+    }
+
+    #[test]
+    fn kills_one_after_the_other_go_together() {
+        let mut ring = KillRing::new();
+        ring.kill("break", true, false);
+        ring.kill(" main", true, true);
+        assert_eq!(ring.yank(), Some("break main"));
+        // killing backward puts what's killed before what was killed last
+        ring.kill("bt", false, false);
+        ring.kill("run ", false, true);
+        assert_eq!(ring.yank(), Some("run bt"));
+        // nothing killed isn't kept
+        ring.kill("", true, false);
+        assert_eq!(ring.yank(), Some("run bt"));
+    }
+
+    #[test]
+    fn yank_pop_goes_around_the_ring() {
+        let mut ring = KillRing::new();
+        assert_eq!(ring.yank(), None);
+        assert_eq!(ring.yank_pop(), None);
+        for kill in ["one", "two", "three"] {
+            ring.kill(kill, true, false);
+        }
+        assert_eq!(ring.yank(), Some("three"));
+        assert_eq!(ring.yank_pop(), Some("two"));
+        assert_eq!(ring.yank_pop(), Some("one"));
+        assert_eq!(ring.yank_pop(), Some("three"));
+        // a yank starts from the newest again
+        assert_eq!(ring.yank_pop(), Some("two"));
+        assert_eq!(ring.yank(), Some("three"));
+        // the oldest go, once there are as many as are kept
+        for kill in 0..KILL_RING_SIZE {
+            ring.kill(&kill.to_string(), true, false);
+        }
+        assert_eq!(ring.kills.len(), KILL_RING_SIZE);
+        assert_eq!(ring.kills[0], "0");
+    }
+
+    #[test]
+    fn words_are_letters_digits_and_underscores() {
+        let line = "p my_var+1 /x";
+        assert_eq!(word_start(line, line.len()), line.len() - 1);
+        assert_eq!(word_start(line, 8), 2);
+        assert_eq!(word_start(line, 5), 2);
+        assert_eq!(word_start(line, 0), 0);
+        assert_eq!(word_end(line, 0), 1);
+        assert_eq!(word_end(line, 1), 8);
+        assert_eq!(word_end(line, 10), line.len());
+        // the combining accent is part of the word
+        assert_eq!(word_end("cafe\u{301} au lait", 0), 6);
+        // everything between the spaces is a word to Ctrl-W
+        assert_eq!(unix_word_start(line, 10), 2);
+        assert_eq!(unix_word_start(line, line.len()), 11);
+        assert_eq!(unix_word_start("break  ", 7), 0);
+    }
+
+    #[test]
+    fn transposing_at_the_end_of_the_line_swaps_the_last_two() {
+        let mut line = String::from("stpe");
+        assert_eq!(transpose(&mut line, 4), Some(4));
+        assert_eq!(line, "step");
+        // in the middle, the one before the cursor goes after the one at it
+        let mut line = String::from("breka");
+        assert_eq!(transpose(&mut line, 4), Some(5));
+        assert_eq!(line, "break");
+        // graphemes are swapped whole
+        let mut line = String::from("ae\u{301}");
+        assert_eq!(transpose(&mut line, 4), Some(4));
+        assert_eq!(line, "e\u{301}a");
+        // at the start, or with one character, there's nothing to swap
+        let mut line = String::from("x");
+        assert_eq!(transpose(&mut line, 0), None);
+        assert_eq!(transpose(&mut line, 1), None);
+        assert_eq!(transpose(&mut String::new(), 0), None);
+    }
+
+    #[test]
+    fn a_word_typed_is_undone_at_once() {
+        let mut undo = UndoList::new();
+        let mut line = String::new();
+        let mut last = None;
+        for c in "p ab".chars() {
+            let before = (line.clone(), line.len());
+            line.push(c);
+            undo.record(
+                before,
+                &line,
+                Action::SelfInsert,
+                last,
+                &KeyboardInput::Char(c),
+            );
+            last = Some(Action::SelfInsert);
+        }
+        // moving the cursor changes nothing to undo
+        undo.record(
+            (line.clone(), 4),
+            &line,
+            Action::BackwardChar,
+            last,
+            &KeyboardInput::Left,
+        );
+        let before = (line.clone(), line.len());
+        line.pop();
+        undo.record(
+            before,
+            &line,
+            Action::BackwardDeleteChar,
+            Some(Action::BackwardChar),
+            &KeyboardInput::Backspace,
+        );
+
+        assert_eq!(undo.undo(), Some(("p ab".to_owned(), 4)));
+        assert_eq!(undo.undo(), Some(("p".to_owned(), 1)));
+        assert_eq!(undo.undo(), Some((String::new(), 0)));
+        assert_eq!(undo.undo(), None);
+    }
+}
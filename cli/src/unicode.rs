@@ -0,0 +1,266 @@
+//! How many columns text takes up in a terminal, and where the characters as people see them start and end. The
+//! cursor has to move over what's drawn, not over bytes; an `é` can be 2 bytes, or an `e` and a combining accent,
+//! and a `中` is 3 bytes wide in UTF-8 and 2 columns wide on screen.
+//!
+//! The tables cover the blocks that come up at a prompt, the combining marks of the scripts written left to right and
+//! of Hebrew and Arabic, CJK and Hangul, and emoji, rather than all of Unicode.
+
+// characters drawn over the one before them, or not at all
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036F), // combining diacritical marks
+    (0x0483, 0x0489), // cyrillic
+    (0x0591, 0x05BD), // hebrew
+    (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5),
+    (0x05C7, 0x05C7),
+    (0x0610, 0x061A), // arabic
+    (0x064B, 0x065F),
+    (0x0670, 0x0670),
+    (0x06D6, 0x06DC),
+    (0x06DF, 0x06E4),
+    (0x06E7, 0x06E8),
+    (0x06EA, 0x06ED),
+    (0x0E31, 0x0E31), // thai
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x1AB0, 0x1AFF),   // combining diacritical marks extended
+    (0x1DC0, 0x1DFF),   // and supplement
+    (0x200B, 0x200F),   // zero width space, non-joiner, joiner and direction marks
+    (0x20D0, 0x20FF),   // combining marks for symbols
+    (0x302A, 0x302F),   // ideographic tone marks
+    (0x3099, 0x309A),   // kana voiced sound marks
+    (0xFE00, 0xFE0F),   // variation selectors
+    (0xFE20, 0xFE2F),   // combining half marks
+    (0xFEFF, 0xFEFF),   // zero width no-break space
+    (0x1F3FB, 0x1F3FF), // emoji skin tones
+    (0xE0020, 0xE007F), // tags
+    (0xE0100, 0xE01EF), // variation selectors supplement
+];
+
+// characters that take up two columns
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F), // hangul jamo
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x26FD, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274C),
+    (0x274E, 0x274E),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55),
+    (0x2E80, 0x303E), // cjk radicals, punctuation
+    (0x3041, 0x33FF), // kana, bopomofo, hangul compatibility jamo, cjk symbols
+    (0x3400, 0x4DBF), // cjk unified ideographs extension a
+    (0x4E00, 0x9FFF), // cjk unified ideographs
+    (0xA000, 0xA4CF), // yi
+    (0xA960, 0xA97F), // hangul jamo extended a
+    (0xAC00, 0xD7A3), // hangul syllables
+    (0xF900, 0xFAFF), // cjk compatibility ideographs
+    (0xFE10, 0xFE19), // vertical forms
+    (0xFE30, 0xFE6F), // cjk compatibility forms, small forms
+    (0xFF00, 0xFF60), // fullwidth forms
+    (0xFFE0, 0xFFE6),
+    (0x16FE0, 0x16FE4),
+    (0x17000, 0x18CFF), // tangut
+    (0x1B000, 0x1B2FF), // kana supplement
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F1E6, 0x1F1FF), // regional indicators, that make flags in pairs
+    (0x1F200, 0x1F2FF), // enclosed ideographic supplement
+    (0x1F300, 0x1F64F), // pictographs, emoticons
+    (0x1F680, 0x1F6FF), // transport and map symbols
+    (0x1F7E0, 0x1F7EB),
+    (0x1F90C, 0x1F9FF), // supplemental symbols and pictographs
+    (0x1FA70, 0x1FAFF),
+    (0x20000, 0x2FFFD), // cjk unified ideographs extension b and on
+    (0x30000, 0x3FFFD),
+];
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+// asks for the character before it to be drawn as an emoji, two columns wide
+const EMOJI_PRESENTATION: char = '\u{FE0F}';
+
+fn in_table(table: &[(u32, u32)], c: char) -> bool {
+    let c = c as u32;
+    table
+        .binary_search_by(|&(first, last)| {
+            if last < c {
+                std::cmp::Ordering::Less
+            } else if first > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Whether `c` goes with the character before it; a combining mark, a joiner or a variation selector
+pub fn is_extending(c: char) -> bool {
+    in_table(ZERO_WIDTH, c)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+// the columns `c` takes up on it's own; 0 for combining marks and control characters, 2 for wide ones
+fn char_width(c: char) -> usize {
+    if c.is_control() || is_extending(c) {
+        0
+    } else if in_table(WIDE, c) {
+        2
+    } else {
+        1
+    }
+}
+
+/// The columns `text` takes up. A grapheme is as wide as the character it starts with; what's combined or joined
+/// with it is drawn over it.
+pub fn width(text: &str) -> usize {
+    let mut width = 0;
+    let mut start = 0;
+    while start < text.len() {
+        let end = next_grapheme(text, start);
+        let grapheme = &text[start..end];
+        width += match grapheme.contains(EMOJI_PRESENTATION) {
+            true => 2,
+            false => grapheme.chars().next().map_or(0, char_width),
+        };
+        start = end;
+    }
+    width
+}
+
+/// The byte after the character as people see it, the grapheme, that starts at `position`; the character with the
+/// marks that combine with it, an emoji with what's joined to it, or the pair of regional indicators of a flag
+pub fn next_grapheme(line: &str, position: usize) -> usize {
+    let mut chars = line[position..].char_indices().peekable();
+    let Some((_, first)) = chars.next() else {
+        return position;
+    };
+    let mut joined = false;
+    while let Some(&(index, c)) = chars.peek() {
+        let after_joiner = std::mem::replace(&mut joined, c == ZERO_WIDTH_JOINER);
+        let extends = after_joiner
+            || is_extending(c)
+            || (is_regional_indicator(first) && is_regional_indicator(c) && index == first.len_utf8());
+        if !extends {
+            return position + index;
+        }
+        chars.next();
+    }
+    line.len()
+}
+
+/// The byte the grapheme before `position` starts at
+pub fn previous_grapheme(line: &str, position: usize) -> usize {
+    // graphemes are found going forward, from the start of the line; that's quadratic when going back over all of
+    // it, but the lines typed at a prompt are short enough for it not to matter
+    let mut start = 0;
+    while start < position {
+        let next = next_grapheme(line, start);
+        if next >= position {
+            return start;
+        }
+        start = next;
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the graphemes of `text`, going forward and going back
+    fn graphemes(text: &str) -> Vec<&str> {
+        let mut forward = vec![];
+        let mut start = 0;
+        while start < text.len() {
+            let end = next_grapheme(text, start);
+            forward.push(&text[start..end]);
+            start = end;
+        }
+        let mut backward = vec![];
+        let mut end = text.len();
+        while end > 0 {
+            let start = previous_grapheme(text, end);
+            backward.insert(0, &text[start..end]);
+            end = start;
+        }
+        assert_eq!(forward, backward);
+        forward
+    }
+
+    #[test]
+    fn cjk_takes_two_columns() {
+        assert_eq!(width("中文"), 4);
+        assert_eq!(width("p 中"), 4);
+        assert_eq!(width("한국어"), 6);
+        assert_eq!(graphemes("中文"), ["中", "文"]);
+    }
+
+    #[test]
+    fn combining_marks_go_with_the_letter_before_them() {
+        let decomposed = "e\u{301}";
+        assert_eq!(graphemes(decomposed), [decomposed]);
+        assert_eq!(width(decomposed), 1);
+        assert_eq!(width("caf\u{e9}"), width("cafe\u{301}"));
+        assert_eq!(graphemes("cafe\u{301}!"), ["c", "a", "f", decomposed, "!"]);
+    }
+
+    #[test]
+    fn emoji_joined_together_are_one() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(graphemes(family), [family]);
+        assert_eq!(width(family), 2);
+        let thumbs_up = "\u{1F44D}\u{1F3FD}";
+        assert_eq!(
+            graphemes(&format!("{}{}a", family, thumbs_up)),
+            [family, thumbs_up, "a"]
+        );
+        // a character that's drawn as an emoji when it's asked to be
+        assert_eq!(width("\u{2764}"), 1);
+        assert_eq!(width("\u{2764}\u{FE0F}"), 2);
+    }
+
+    #[test]
+    fn regional_indicators_pair_up_into_flags() {
+        let (germany, france) = ("\u{1F1E9}\u{1F1EA}", "\u{1F1EB}\u{1F1F7}");
+        let flags = format!("{}{}", germany, france);
+        assert_eq!(graphemes(&flags), [germany, france]);
+        assert_eq!(width(&flags), 4);
+        // an odd one out is on it's own
+        let odd = format!("{}\u{1F1EB}", germany);
+        assert_eq!(graphemes(&odd), [germany, "\u{1F1EB}"]);
+    }
+}
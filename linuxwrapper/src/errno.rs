@@ -1,28 +1,24 @@
-use std::sync::Once;
-
-static ERR_NO: Once = Once::new();
-static mut ERRNO: *mut i32 = std::ptr::null_mut() as _;
+/// Resets errno to 0. Needed for calls like PTRACE_PEEKDATA, where -1 is both a valid return value and the error value;
+/// the only way to tell them apart is to clear errno before the call and check it afterwards.
+pub fn clear_errno() {
+    unsafe {
+        *libc::__errno_location() = 0;
+    }
+}
 
-fn get_errno() -> &'static i32 {
-    ERR_NO.call_once(|| {
-        unsafe {
-            ERRNO = libc::__errno_location();
-            if ERRNO.is_null() {
-                panic!("failed to get errno location");
-            }
-        };
-    });
-    unsafe { &*ERRNO }
+pub fn errno_is_set() -> bool {
+    unsafe { *libc::__errno_location() != 0 }
 }
 
 pub fn get_errno_msg() -> String {
     unsafe {
-        let errno = get_errno();
-        let err_msg = libc::strerror(*errno);
-        let err = std::ffi::CString::from_raw(err_msg);
-        if err.as_bytes().is_empty() {
+        // errno is thread local, so it's location must be looked up by the calling thread
+        let errno = *libc::__errno_location();
+        // strerror returns a pointer to a static (or thread local) buffer owned by libc; we must not free it
+        let err = std::ffi::CStr::from_ptr(libc::strerror(errno));
+        if err.to_bytes().is_empty() {
             return "No errno message found".into();
         }
-        err.to_str().unwrap().to_string()
+        err.to_string_lossy().to_string()
     }
 }
@@ -37,14 +37,15 @@ pub fn unwrap_err_err(err: Result<String, String>) -> String {
     }
 }
 
-pub fn continue_execution(pid: pid_t) -> Result<(), String> {
+/// Continues `pid`, delivering `signal` to it if it isn't 0
+pub fn continue_execution(pid: pid_t, signal: i32) -> Result<(), String> {
     use libc::{ptrace, PTRACE_CONT};
     unsafe {
         if ptrace(
             PTRACE_CONT,
             pid,
             std::ptr::null() as *const libc::c_void,
-            std::ptr::null() as *const libc::c_void,
+            signal as usize as *const libc::c_void,
         ) == -1
         {
             return Err(errno::get_errno_msg());
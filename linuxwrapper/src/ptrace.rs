@@ -1,7 +1,7 @@
 use crate::{MidasSysResultDynamic, Pid};
-pub(in crate) use libc::ptrace;
+pub(crate) use libc::ptrace;
 // todo(simon): this should probably be removed in release
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct UserRegisters {
     pub r15: u64,
     pub r14: u64,
@@ -94,9 +94,113 @@ impl UserRegisters {
         }
     }
 
+    /// The registers as ptrace takes them
+    pub fn to_raw(&self) -> libc::user_regs_struct {
+        libc::user_regs_struct {
+            r15: self.r15,
+            r14: self.r14,
+            r13: self.r13,
+            r12: self.r12,
+            rbp: self.rbp,
+            rbx: self.rbx,
+            r11: self.r11,
+            r10: self.r10,
+            r9: self.r9,
+            r8: self.r8,
+            rax: self.rax,
+            rcx: self.rcx,
+            rdx: self.rdx,
+            rsi: self.rsi,
+            rdi: self.rdi,
+            orig_rax: self.orig_rax,
+            rip: self.rip,
+            cs: self.cs,
+            eflags: self.eflags,
+            rsp: self.rsp,
+            ss: self.ss,
+            fs_base: self.fs_base,
+            gs_base: self.gs_base,
+            ds: self.ds,
+            es: self.es,
+            fs: self.fs,
+            gs: self.gs,
+        }
+    }
+
     pub fn pc(&self) -> u64 {
         self.rip
     }
+
+    /// Look up a register by it's name, as written in assembly (without the %), or one of the aliases pc, sp and fp
+    pub fn get_by_name(&self, name: &str) -> Option<u64> {
+        let value = match name {
+            "r15" => self.r15,
+            "r14" => self.r14,
+            "r13" => self.r13,
+            "r12" => self.r12,
+            "rbp" | "fp" => self.rbp,
+            "rbx" => self.rbx,
+            "r11" => self.r11,
+            "r10" => self.r10,
+            "r9" => self.r9,
+            "r8" => self.r8,
+            "rax" => self.rax,
+            "rcx" => self.rcx,
+            "rdx" => self.rdx,
+            "rsi" => self.rsi,
+            "rdi" => self.rdi,
+            "orig_rax" => self.orig_rax,
+            "rip" | "pc" => self.rip,
+            "cs" => self.cs,
+            "eflags" => self.eflags,
+            "rsp" | "sp" => self.rsp,
+            "ss" => self.ss,
+            "fs_base" => self.fs_base,
+            "gs_base" => self.gs_base,
+            "ds" => self.ds,
+            "es" => self.es,
+            "fs" => self.fs,
+            "gs" => self.gs,
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Sets the register `name`, by the same names as `get_by_name`. Returns false if there's no such register.
+    pub fn set_by_name(&mut self, name: &str, value: u64) -> bool {
+        let register = match name {
+            "r15" => &mut self.r15,
+            "r14" => &mut self.r14,
+            "r13" => &mut self.r13,
+            "r12" => &mut self.r12,
+            "rbp" | "fp" => &mut self.rbp,
+            "rbx" => &mut self.rbx,
+            "r11" => &mut self.r11,
+            "r10" => &mut self.r10,
+            "r9" => &mut self.r9,
+            "r8" => &mut self.r8,
+            "rax" => &mut self.rax,
+            "rcx" => &mut self.rcx,
+            "rdx" => &mut self.rdx,
+            "rsi" => &mut self.rsi,
+            "rdi" => &mut self.rdi,
+            "orig_rax" => &mut self.orig_rax,
+            "rip" | "pc" => &mut self.rip,
+            "cs" => &mut self.cs,
+            "eflags" => &mut self.eflags,
+            "rsp" | "sp" => &mut self.rsp,
+            "ss" => &mut self.ss,
+            "fs_base" => &mut self.fs_base,
+            "gs_base" => &mut self.gs_base,
+            "ds" => &mut self.ds,
+            "es" => &mut self.es,
+            "fs" => &mut self.fs,
+            "gs" => &mut self.gs,
+            _ => return false,
+        };
+        *register = value;
+        true
+    }
 }
 
 pub fn init_user_regs() -> libc::user_regs_struct {
@@ -168,15 +272,86 @@ pub fn trace_me() -> crate::MidasSysResultDynamic<()> {
     }
 }
 
+/// Starts tracing process `pid`, which is sent a SIGSTOP; wait for it to stop before doing anything else with it
+pub fn attach(pid: Pid) -> crate::MidasSysResultDynamic<()> {
+    unsafe {
+        if ptrace(
+            libc::PTRACE_ATTACH,
+            *pid,
+            std::ptr::null::<libc::c_void>(),
+            std::ptr::null::<libc::c_void>(),
+        ) == -1
+        {
+            Err(crate::errno::get_errno_msg())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Starts tracing `pid` without stopping it, like PTRACE_ATTACH would with it's SIGSTOP. `pid` can be a thread of a
+/// process that's traced already; only the thread that's seized is.
+pub fn seize(pid: Pid) -> crate::MidasSysResultDynamic<()> {
+    unsafe {
+        if ptrace(
+            libc::PTRACE_SEIZE,
+            *pid,
+            std::ptr::null::<libc::c_void>(),
+            std::ptr::null::<libc::c_void>(),
+        ) == -1
+        {
+            Err(crate::errno::get_errno_msg())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Stops `pid`, that was seized; wait for it to stop before reading it's registers
+pub fn interrupt(pid: Pid) -> crate::MidasSysResultDynamic<()> {
+    unsafe {
+        if ptrace(
+            libc::PTRACE_INTERRUPT,
+            *pid,
+            std::ptr::null::<libc::c_void>(),
+            std::ptr::null::<libc::c_void>(),
+        ) == -1
+        {
+            Err(crate::errno::get_errno_msg())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Stops tracing `pid`, which runs on, and is sent `signal` if it isn't 0
+pub fn detach(pid: Pid, signal: i32) -> crate::MidasSysResultDynamic<()> {
+    unsafe {
+        if ptrace(
+            libc::PTRACE_DETACH,
+            *pid,
+            std::ptr::null::<libc::c_void>(),
+            signal as usize as *const libc::c_void,
+        ) == -1
+        {
+            Err(crate::errno::get_errno_msg())
+        } else {
+            Ok(())
+        }
+    }
+}
+
 pub fn peek_data(pid: Pid, addr: usize) -> crate::MidasSysResultDynamic<i64> {
     unsafe {
+        crate::errno::clear_errno();
         let quadword = libc::ptrace(
             libc::PTRACE_PEEKDATA,
             *pid,
             addr,
             std::ptr::null() as *const libc::c_void,
         );
-        if quadword == -1 {
+        // -1 is a perfectly valid word of memory; only errno can tell us if the peek failed
+        if quadword == -1 && crate::errno::errno_is_set() {
             Err(format!(
                 "failed to peek data at {} of [PID: {}]",
                 addr, *pid
@@ -238,3 +413,115 @@ pub fn set_pc(pid: Pid, address: usize) -> MidasSysResultDynamic<()> {
     }
     Ok(())
 }
+
+/// Sets all of the general purpose registers
+pub fn set_regs(pid: Pid, registers: &UserRegisters) -> MidasSysResultDynamic<()> {
+    let regs = registers.to_raw();
+    unsafe {
+        if libc::ptrace(
+            libc::PTRACE_SETREGS,
+            *pid,
+            std::ptr::null::<libc::c_void>(),
+            &regs,
+        ) == -1
+        {
+            return Err(crate::errno::get_errno_msg());
+        }
+    }
+    Ok(())
+}
+
+/// Reads the word at `offset` in the inferior's `struct user` (see sys/user.h)
+pub fn peek_user(pid: Pid, offset: usize) -> MidasSysResultDynamic<i64> {
+    unsafe {
+        crate::errno::clear_errno();
+        let word = libc::ptrace(
+            libc::PTRACE_PEEKUSER,
+            *pid,
+            offset,
+            std::ptr::null::<libc::c_void>(),
+        );
+        if word == -1 && crate::errno::errno_is_set() {
+            Err(format!(
+                "failed to peek user area at offset {} of [PID: {}]: {}",
+                offset,
+                *pid,
+                crate::errno::get_errno_msg()
+            ))
+        } else {
+            Ok(word)
+        }
+    }
+}
+
+/// Writes `data` to the word at `offset` in the inferior's `struct user`
+pub fn poke_user(pid: Pid, offset: usize, data: libc::c_long) -> MidasSysResultDynamic<()> {
+    unsafe {
+        if libc::ptrace(libc::PTRACE_POKEUSER, *pid, offset, data) == -1 {
+            Err(format!(
+                "failed to poke user area at offset {} of [PID: {}]: {}",
+                offset,
+                *pid,
+                crate::errno::get_errno_msg()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Offset of debug register DR`index` (0-7) in `struct user`
+fn debug_register_offset(index: usize) -> usize {
+    std::mem::offset_of!(libc::user, u_debugreg) + index * std::mem::size_of::<u64>()
+}
+
+pub fn get_debug_register(pid: Pid, index: usize) -> MidasSysResultDynamic<u64> {
+    peek_user(pid, debug_register_offset(index)).map(|value| value as u64)
+}
+
+pub fn set_debug_register(pid: Pid, index: usize, value: u64) -> MidasSysResultDynamic<()> {
+    poke_user(pid, debug_register_offset(index), value as libc::c_long)
+}
+
+/// Executes one instruction, delivering `signal` first if it isn't 0. Like continuing, the caller has to waitpid
+/// for the inferior to stop again
+pub fn single_step(pid: Pid, signal: i32) -> MidasSysResultDynamic<()> {
+    unsafe {
+        if libc::ptrace(
+            libc::PTRACE_SINGLESTEP,
+            *pid,
+            std::ptr::null::<libc::c_void>(),
+            signal as usize as *const libc::c_void,
+        ) == -1
+        {
+            Err(crate::errno::get_errno_msg())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reads the SSE registers xmm0-xmm15 (PTRACE_GETFPREGS). Floating point values are passed and returned in them.
+pub fn get_xmm_registers(pid: Pid) -> MidasSysResultDynamic<[u128; 16]> {
+    let mut fpregs: libc::user_fpregs_struct = unsafe { std::mem::zeroed() };
+    unsafe {
+        if libc::ptrace(
+            libc::PTRACE_GETFPREGS,
+            *pid,
+            std::ptr::null::<libc::c_void>(),
+            &mut fpregs as *mut _,
+        ) == -1
+        {
+            return Err(crate::errno::get_errno_msg());
+        }
+    }
+    let mut xmm = [0u128; 16];
+    for (index, register) in xmm.iter_mut().enumerate() {
+        let words = &fpregs.xmm_space[index * 4..index * 4 + 4];
+        *register = words
+            .iter()
+            .rev()
+            .fold(0u128, |value, word| (value << 32) | *word as u128);
+    }
+    Ok(xmm)
+}
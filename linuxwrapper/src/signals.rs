@@ -107,4 +107,65 @@ impl Signal {
             _ => Err(signum),
         }
     }
+
+    /// The name of the signal, as C knows it; SIGSEGV
+    pub fn name(&self) -> &'static str {
+        match self {
+            Signal::HangUp => "SIGHUP",
+            Signal::Interrupt => "SIGINT",
+            Signal::Quit => "SIGQUIT",
+            Signal::Ill => "SIGILL",
+            Signal::Trap => "SIGTRAP",
+            Signal::Abort => "SIGABRT",
+            Signal::BusError => "SIGBUS",
+            Signal::FloatingPointException => "SIGFPE",
+            Signal::Kill => "SIGKILL",
+            Signal::UserDefined1 => "SIGUSR1",
+            Signal::SegmentationFault => "SIGSEGV",
+            Signal::UserDefined2 => "SIGUSR2",
+            Signal::BrokenPipe => "SIGPIPE",
+            Signal::Alarm => "SIGALRM",
+            Signal::Termination => "SIGTERM",
+            Signal::StackFault => "SIGSTKFLT",
+            Signal::ChildStopped => "SIGCHLD",
+            Signal::Continued => "SIGCONT",
+            Signal::Stopped => "SIGSTOP",
+            Signal::SignalTerminalStop => "SIGTSTP",
+            Signal::TTYIn => "SIGTTIN",
+            Signal::TTYOut => "SIGTTOU",
+            Signal::UrgentOutOfBand => "SIGURG",
+            Signal::CPUTimeLimitExceeded => "SIGXCPU",
+            Signal::FileSizeExceeded => "SIGXFSZ",
+            Signal::VirtualTimeAlarm => "SIGVTALRM",
+            Signal::ProfilingTimerExpired => "SIGPROF",
+            Signal::WindowsChange => "SIGWINCH",
+            Signal::InputOutputPoll => "SIGIO",
+            Signal::PowerFailure => "SIGPWR",
+            Signal::BadSystemCallArgument => "SIGSYS",
+        }
+    }
+
+    /// What the signal means, as strsignal(3) describes it; Segmentation fault
+    pub fn description(&self) -> String {
+        unsafe {
+            let description = libc::strsignal(*self as i32);
+            if description.is_null() {
+                self.name().to_owned()
+            } else {
+                std::ffi::CStr::from_ptr(description)
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        }
+    }
+}
+
+/// Sends `signal` to process `pid`
+pub fn send(pid: crate::Pid, signal: Signal) -> crate::MidasSysResultDynamic<()> {
+    unsafe {
+        if libc::kill(*pid, signal as i32) == -1 {
+            return Err(crate::errno::get_errno_msg());
+        }
+    }
+    Ok(())
 }
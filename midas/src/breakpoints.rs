@@ -0,0 +1,537 @@
+//! Breakpoints as the user sees them; numbered, with the location they were requested at, the addresses that
+//! resolved to, and whether they're enabled. The target only knows about the traps and debug registers at
+//! addresses; this is what keeps track of which breakpoints they belong to, and whether a trap is a stop the user
+//! wants to see; conditions, ignore counts and temporary breakpoints are all decided on here. So is the breakpoint
+//! the dynamic linker is stopped at to keep track of the shared libraries, which the user never sees. Breakpoints on
+//! functions in libraries that aren't loaded yet are pending; they have no locations until the library is loaded,
+//! and go back to having none when it's unloaded.
+use std::collections::BTreeMap;
+
+use nixwrap::{MidasSysResultDynamic, WaitStatus};
+
+use crate::{
+    elf::ParsedELF,
+    expression::Expression,
+    hardware_breakpoint::{HWBreakpoint, WatchKind},
+    shared_library::{SharedLibraries, SharedLibrary},
+    software_breakpoint::BreakpointRequest,
+    target::Target,
+    types::Address,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAccess {
+    /// Stop when the value is written
+    Write,
+    /// Stop when the value is read. x86 can't trap on reads only, so this traps on writes too
+    Read,
+    /// Stop on any access
+    Access,
+}
+
+impl WatchAccess {
+    fn kind(&self) -> WatchKind {
+        match self {
+            WatchAccess::Write => WatchKind::Write,
+            WatchAccess::Read | WatchAccess::Access => WatchKind::ReadWrite,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointKind {
+    /// An int3 patched into the code
+    Software,
+    /// An execute breakpoint in a debug register
+    Hardware,
+    /// A debug register watching the `len` bytes that `expression` refers to
+    Watchpoint {
+        access: WatchAccess,
+        expression: String,
+        len: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct UserBreakpoint {
+    pub number: usize,
+    pub kind: BreakpointKind,
+    /// What the user asked for
+    pub spec: BreakpointRequest,
+    /// The addresses `spec` resolved to; none while it's pending
+    pub locations: Vec<Address>,
+    pub enabled: bool,
+    pub hit_count: usize,
+    /// Only stop when this expression is true (non zero)
+    pub condition: Option<String>,
+    /// The number of hits to continue past, before stopping again
+    pub ignore_count: usize,
+    /// Deleted the first time it stops the inferior
+    pub temporary: bool,
+    // the debug registers in use by a hardware breakpoint or watchpoint, while it's enabled
+    hardware: Vec<HWBreakpoint>,
+}
+
+/// Why `BreakpointTable::continue_execution` returned
+#[derive(Debug)]
+pub struct Stop {
+    pub status: WaitStatus,
+    /// The breakpoints that stopped the inferior, as they were when they did; temporary ones are gone from the table
+    /// by now
+    pub breakpoints: Vec<UserBreakpoint>,
+    /// Breakpoints whose condition couldn't be evaluated, with the error. They stop the inferior too.
+    pub condition_errors: Vec<(usize, String)>,
+}
+
+impl UserBreakpoint {
+    /// Whether the breakpoint waits for a library to be loaded, to have a location
+    pub fn is_pending(&self) -> bool {
+        self.locations.is_empty()
+    }
+}
+
+impl Stop {
+    /// Whether breakpoints stopped the inferior, or their conditions failing to evaluate did
+    pub fn by_breakpoint(&self) -> bool {
+        !self.breakpoints.is_empty() || !self.condition_errors.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct BreakpointTable {
+    breakpoints: BTreeMap<usize, UserBreakpoint>,
+    last_number: usize,
+    shared_libraries: SharedLibraries,
+}
+
+impl BreakpointTable {
+    /// Creates breakpoint `spec`, resolved to `locations`, and inserts it into the target. Numbers are handed out in
+    /// order, starting at 1, and never reused.
+    pub fn insert(
+        &mut self,
+        target: &mut dyn Target,
+        kind: BreakpointKind,
+        spec: BreakpointRequest,
+        locations: Vec<Address>,
+    ) -> MidasSysResultDynamic<&UserBreakpoint> {
+        if locations.is_empty() {
+            return Err("breakpoint has no locations".into());
+        }
+        let mut breakpoint = UserBreakpoint {
+            number: self.last_number + 1,
+            kind,
+            spec,
+            locations,
+            enabled: false,
+            hit_count: 0,
+            condition: None,
+            ignore_count: 0,
+            temporary: false,
+            hardware: vec![],
+        };
+        self.arm(target, &mut breakpoint)?;
+        self.last_number += 1;
+        Ok(self
+            .breakpoints
+            .entry(breakpoint.number)
+            .or_insert(breakpoint))
+    }
+
+    /// Creates breakpoint `spec` on a function that's not in the program or the libraries that are loaded, and
+    /// leaves it pending until a library that has the function is loaded.
+    pub fn insert_pending(
+        &mut self,
+        kind: BreakpointKind,
+        spec: BreakpointRequest,
+    ) -> MidasSysResultDynamic<&UserBreakpoint> {
+        if matches!(kind, BreakpointKind::Watchpoint { .. }) {
+            return Err("watchpoints can't be pending".into());
+        }
+        if !matches!(spec, BreakpointRequest::Function { .. }) {
+            return Err("only breakpoints on functions can be pending".into());
+        }
+        self.last_number += 1;
+        let breakpoint = UserBreakpoint {
+            number: self.last_number,
+            kind,
+            spec,
+            locations: vec![],
+            enabled: true,
+            hit_count: 0,
+            condition: None,
+            ignore_count: 0,
+            temporary: false,
+            hardware: vec![],
+        };
+        Ok(self
+            .breakpoints
+            .entry(breakpoint.number)
+            .or_insert(breakpoint))
+    }
+
+    pub fn delete(&mut self, target: &mut dyn Target, number: usize) -> MidasSysResultDynamic<UserBreakpoint> {
+        self.disable(target, number)?;
+        self.breakpoints
+            .remove(&number)
+            .ok_or(format!("No breakpoint number {}.", number))
+    }
+
+    pub fn enable(&mut self, target: &mut dyn Target, number: usize) -> MidasSysResultDynamic<()> {
+        let mut breakpoint = self
+            .breakpoints
+            .remove(&number)
+            .ok_or(format!("No breakpoint number {}.", number))?;
+        let result = if breakpoint.enabled {
+            Ok(())
+        } else {
+            self.arm(target, &mut breakpoint)
+        };
+        self.breakpoints.insert(number, breakpoint);
+        result
+    }
+
+    pub fn disable(&mut self, target: &mut dyn Target, number: usize) -> MidasSysResultDynamic<()> {
+        let mut breakpoint = self
+            .breakpoints
+            .remove(&number)
+            .ok_or(format!("No breakpoint number {}.", number))?;
+        let result = if breakpoint.enabled {
+            self.disarm(target, &mut breakpoint)
+        } else {
+            Ok(())
+        };
+        self.breakpoints.insert(number, breakpoint);
+        result
+    }
+
+    /// Makes breakpoint `number` stop only when `condition` evaluates to non zero, or always, if it's None
+    pub fn set_condition(&mut self, number: usize, condition: Option<&str>) -> MidasSysResultDynamic<()> {
+        let breakpoint = self.get_mut(number)?;
+        if let Some(condition) = condition {
+            Expression::parse(condition)?;
+        }
+        breakpoint.condition = condition.map(|condition| condition.trim().to_owned());
+        Ok(())
+    }
+
+    /// Makes breakpoint `number` continue past the next `count` hits
+    pub fn set_ignore_count(&mut self, number: usize, count: usize) -> MidasSysResultDynamic<()> {
+        self.get_mut(number)?.ignore_count = count;
+        Ok(())
+    }
+
+    /// Makes breakpoint `number` delete itself, the first time it stops the inferior
+    pub fn set_temporary(&mut self, number: usize) -> MidasSysResultDynamic<()> {
+        self.get_mut(number)?.temporary = true;
+        Ok(())
+    }
+
+    /// Starts keeping track of the shared libraries the dynamic linker loads into the inferior, which is the
+    /// program `elf`. The inferior is stopped where the dynamic linker changes them, and continued once they've
+    /// been read.
+    pub fn track_shared_libraries(&mut self, target: &mut dyn Target, elf: &ParsedELF) -> MidasSysResultDynamic<()> {
+        self.shared_libraries = SharedLibraries::new(elf);
+        match self.shared_libraries.event_address() {
+            Some(address) => target.set_breakpoint(BreakpointRequest::Address(address)),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads the shared libraries of the program `elf` once, for an inferior that can't be stopped when they change,
+    /// like the process of a core dump
+    pub fn read_shared_libraries(&mut self, target: &dyn Target, elf: &ParsedELF) -> MidasSysResultDynamic<()> {
+        self.shared_libraries = SharedLibraries::new(elf);
+        self.shared_libraries.update(target).map(|_| ())
+    }
+
+    /// The shared libraries loaded into the inferior, as of the last time it stopped
+    pub fn shared_libraries(&self) -> &SharedLibraries {
+        &self.shared_libraries
+    }
+
+    // the inferior stopped where the dynamic linker tells us about libraries; read them, move the trap if where it's
+    // told us next has changed, and update the breakpoints in the libraries that came and went
+    fn shared_library_event(&mut self, target: &mut dyn Target) -> MidasSysResultDynamic<()> {
+        let before = self.shared_libraries.event_address();
+        if let Some(unloaded) = self.shared_libraries.update(&*target)? {
+            self.forget_unloaded(target, &unloaded);
+            self.resolve_pending(target);
+        }
+        let after = self.shared_libraries.event_address();
+        if after != before {
+            if let Some(after) = after {
+                target.set_breakpoint(BreakpointRequest::Address(after))?;
+            }
+            if let Some(before) = before.filter(|before| !self.user_trap_in_use(*before)) {
+                target.remove_breakpoint(before)?;
+            }
+        }
+        Ok(())
+    }
+
+    // the code of the unloaded libraries is gone, and so are the traps in it; breakpoints on functions go back to
+    // pending, and ones on addresses are disabled, like gdb does
+    fn forget_unloaded(&mut self, target: &mut dyn Target, unloaded: &[SharedLibrary]) {
+        let in_unloaded = |address: &Address| {
+            unloaded.iter().any(|library| {
+                library
+                    .text
+                    .as_ref()
+                    .is_some_and(|text| text.contains(&address.value()))
+            })
+        };
+        for breakpoint in self.breakpoints.values_mut() {
+            if matches!(breakpoint.kind, BreakpointKind::Watchpoint { .. })
+                || !breakpoint.locations.iter().any(in_unloaded)
+            {
+                continue;
+            }
+            if breakpoint.enabled {
+                // the memory isn't there to restore the instructions in; it's enough that the target forgets the traps
+                for address in breakpoint
+                    .locations
+                    .iter()
+                    .filter(|address| in_unloaded(address))
+                {
+                    let _ = target.remove_breakpoint(*address);
+                }
+                for hw in breakpoint.hardware.drain(..) {
+                    let _ = target.remove_hardware_breakpoint(hw.slot);
+                }
+            }
+            match breakpoint.spec {
+                BreakpointRequest::Function { .. } => breakpoint.locations.retain(|address| !in_unloaded(address)),
+                _ => breakpoint.enabled = false,
+            }
+        }
+    }
+
+    // gives the pending breakpoints whose function a library has now, their location there, and inserts them. One
+    // that can't be inserted stays pending.
+    fn resolve_pending(&mut self, target: &mut dyn Target) {
+        let pending: Vec<usize> = self
+            .breakpoints
+            .values()
+            .filter(|breakpoint| breakpoint.is_pending())
+            .map(|breakpoint| breakpoint.number)
+            .collect();
+        for number in pending {
+            let mut breakpoint = match self.breakpoints.remove(&number) {
+                Some(breakpoint) => breakpoint,
+                None => continue,
+            };
+            let address = match &breakpoint.spec {
+                BreakpointRequest::Function { name, .. } => self
+                    .shared_libraries
+                    .iter()
+                    .find_map(|library| library.breakpoint_address(name)),
+                _ => None,
+            };
+            if let Some(address) = address {
+                breakpoint.locations = vec![Address(address)];
+                if breakpoint.enabled && self.arm(target, &mut breakpoint).is_err() {
+                    breakpoint.locations.clear();
+                }
+            }
+            self.breakpoints.insert(number, breakpoint);
+        }
+    }
+
+    fn get_mut(&mut self, number: usize) -> MidasSysResultDynamic<&mut UserBreakpoint> {
+        self.breakpoints
+            .get_mut(&number)
+            .ok_or(format!("No breakpoint number {}.", number))
+    }
+
+    pub fn get(&self, number: usize) -> Option<&UserBreakpoint> {
+        self.breakpoints.get(&number)
+    }
+
+    /// All breakpoints, in order of their number
+    pub fn iter(&self) -> impl Iterator<Item = &UserBreakpoint> {
+        self.breakpoints.values()
+    }
+
+    /// Counts a hit for every enabled breakpoint that's responsible for the inferior's current stop, and returns
+    /// their numbers. Conditions and ignore counts are not considered; see `continue_execution` for that.
+    pub fn record_hit(&mut self, target: &dyn Target) -> Vec<usize> {
+        let hit = self.responsible(target);
+        for number in hit.iter() {
+            if let Some(breakpoint) = self.breakpoints.get_mut(number) {
+                breakpoint.hit_count += 1;
+            }
+        }
+        hit
+    }
+
+    /// Continues the inferior until it stops for a reason the user wants to see. Traps of breakpoints whose
+    /// condition is false, or that have hits left to ignore, are continued past. `evaluate` evaluates a condition
+    /// against the stopped inferior. Temporary breakpoints that stop the inferior are deleted.
+    pub fn continue_execution(
+        &mut self,
+        target: &mut dyn Target,
+        evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+    ) -> MidasSysResultDynamic<Stop> {
+        loop {
+            let status = target.continue_execution()?;
+            if let Some(stop) = self.check_stop(target, status, evaluate)? {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// Decides what the inferior stopping with `status` means for the breakpoints; None if it's a breakpoint's trap
+    /// that's to be continued past, otherwise the stop. A stop that no breakpoint is responsible for has no
+    /// breakpoints or condition errors. Hits are counted and temporary breakpoints deleted, as in
+    /// `continue_execution`.
+    pub fn check_stop(
+        &mut self,
+        target: &mut dyn Target,
+        status: WaitStatus,
+        evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+    ) -> MidasSysResultDynamic<Option<Stop>> {
+        let library_event = target.stopped_at_breakpoint().is_some()
+            && target.stopped_at_breakpoint() == self.shared_libraries.event_address();
+        if library_event {
+            self.shared_library_event(target)?;
+        }
+        let responsible = self.responsible(&*target);
+        if responsible.is_empty() && library_event {
+            return Ok(None);
+        }
+        if responsible.is_empty() {
+            return Ok(Some(Stop {
+                status,
+                breakpoints: vec![],
+                condition_errors: vec![],
+            }));
+        }
+        let mut stopping = vec![];
+        let mut condition_errors = vec![];
+        for number in responsible {
+            let breakpoint = self.get_mut(number)?;
+            if let Some(condition) = &breakpoint.condition {
+                match Expression::parse(condition).and_then(|condition| evaluate(&*target, &condition)) {
+                    Ok(0) => continue,
+                    Ok(_) => {}
+                    Err(err) => condition_errors.push((number, err)),
+                }
+            }
+            // like gdb, a hit is only counted when the condition holds, ignored or not
+            breakpoint.hit_count += 1;
+            if breakpoint.ignore_count > 0 {
+                breakpoint.ignore_count -= 1;
+                continue;
+            }
+            stopping.push(breakpoint.clone());
+        }
+        if stopping.is_empty() && condition_errors.is_empty() {
+            return Ok(None);
+        }
+        for breakpoint in stopping.iter().filter(|bp| bp.temporary) {
+            self.delete(target, breakpoint.number)?;
+        }
+        Ok(Some(Stop {
+            status,
+            breakpoints: stopping,
+            condition_errors,
+        }))
+    }
+
+    // the enabled breakpoints that made the inferior stop where it is
+    fn responsible(&self, target: &dyn Target) -> Vec<usize> {
+        let hardware = target.stopped_at_hardware_breakpoint();
+        let software = target.stopped_at_breakpoint();
+        self.breakpoints
+            .values()
+            .filter(|bp| bp.enabled)
+            .filter(|breakpoint| match (&breakpoint.kind, hardware, software) {
+                (BreakpointKind::Software, _, Some(address)) => breakpoint.locations.contains(&address),
+                (BreakpointKind::Hardware, Some(hw), _) | (BreakpointKind::Watchpoint { .. }, Some(hw), _) => {
+                    breakpoint.hardware.contains(&hw)
+                }
+                _ => false,
+            })
+            .map(|breakpoint| breakpoint.number)
+            .collect()
+    }
+
+    // does any enabled software breakpoint in the table need the int3 at `address`, or do we, to keep track of the
+    // shared libraries
+    pub(crate) fn software_trap_in_use(&self, address: Address) -> bool {
+        self.user_trap_in_use(address) || self.shared_libraries.event_address() == Some(address)
+    }
+
+    fn user_trap_in_use(&self, address: Address) -> bool {
+        self.breakpoints
+            .values()
+            .any(|bp| bp.enabled && bp.kind == BreakpointKind::Software && bp.locations.contains(&address))
+    }
+
+    // inserts the traps or debug registers of `breakpoint` into the target. Must not be in `self.breakpoints` while
+    // doing so; that way, it's not mistaken for one of the other breakpoints sharing a trap.
+    fn arm(&self, target: &mut dyn Target, breakpoint: &mut UserBreakpoint) -> MidasSysResultDynamic<()> {
+        match &breakpoint.kind {
+            BreakpointKind::Software => {
+                for (index, address) in breakpoint.locations.iter().enumerate() {
+                    if let Err(err) = target.set_breakpoint(BreakpointRequest::Address(*address)) {
+                        // leave things as they were
+                        for inserted in &breakpoint.locations[..index] {
+                            if !self.software_trap_in_use(*inserted) {
+                                let _ = target.remove_breakpoint(*inserted);
+                            }
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+            kind => {
+                let (watch_kind, len) = match kind {
+                    BreakpointKind::Watchpoint { access, len, .. } => (access.kind(), *len),
+                    _ => (WatchKind::Execute, 1),
+                };
+                for address in breakpoint.locations.iter() {
+                    match target.set_hardware_breakpoint(*address, watch_kind, len) {
+                        Ok(hw) => breakpoint.hardware.push(hw),
+                        Err(err) => {
+                            for hw in breakpoint.hardware.drain(..) {
+                                let _ = target.remove_hardware_breakpoint(hw.slot);
+                            }
+                            return Err(err);
+                        }
+                    }
+                }
+            }
+        }
+        breakpoint.enabled = true;
+        Ok(())
+    }
+
+    fn disarm(&self, target: &mut dyn Target, breakpoint: &mut UserBreakpoint) -> MidasSysResultDynamic<()> {
+        breakpoint.enabled = false;
+        if breakpoint.kind == BreakpointKind::Software {
+            for address in breakpoint.locations.iter() {
+                if !self.software_trap_in_use(*address) {
+                    target.remove_breakpoint(*address)?;
+                }
+            }
+        } else {
+            for hw in breakpoint.hardware.drain(..) {
+                target.remove_hardware_breakpoint(hw.slot)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Breakpoints on a function go after it's prologue, where the frame has been set up and the parameters stored, so
+/// that they can be read. Functions without line information are broken on at their first instruction.
+pub fn skip_prologue(elf: &ParsedELF, address: Address) -> Address {
+    match (elf.function_containing(address.value()), elf.line_table()) {
+        (Some(function), Some(lines)) if function.pc_range.start == address.value() => lines
+            .address_after_prologue(function.pc_range)
+            .map(Address)
+            .unwrap_or(address),
+        _ => address,
+    }
+}
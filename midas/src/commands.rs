@@ -1,16 +1,61 @@
-use crate::software_breakpoint::BreakpointRequest;
+use nixwrap::signals::Signal;
 
-#[allow(dead_code)]
+use crate::{breakpoints::WatchAccess, software_breakpoint::BreakpointRequest};
+
+/// What a `Session` can be told to do. Frontends turn what their user typed or sent into these; how it went comes
+/// back as the result of `Session::handle`, and as events.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Command {
-    SetBreakpoint(BreakpointRequest),
-    Run(usize),
-    ListLines(usize),
-    RunAll,
-    Step { count: Option<usize> },
-    StepIn,
-    Next { count: Option<usize> },
+    /// A breakpoint at `location`, in a debug register if `hardware`, that's deleted after it first stops the
+    /// inferior if `temporary`, and stops it only when `condition` holds
+    SetBreakpoint {
+        location: BreakpointRequest,
+        hardware: bool,
+        temporary: bool,
+        condition: Option<String>,
+    },
+    /// A watchpoint on `expression`; a variable, `*address` or `-l address`
+    Watch {
+        expression: String,
+        access: WatchAccess,
+    },
+    Delete(Vec<usize>),
+    Enable(Vec<usize>),
+    Disable(Vec<usize>),
+    /// Sets the condition of a breakpoint, or removes it with None
+    Condition {
+        number: usize,
+        condition: Option<String>,
+    },
+    /// Makes a breakpoint not stop the inferior for it's next `count` hits
+    Ignore {
+        number: usize,
+        count: usize,
+    },
+    Continue,
+    /// Steps `count` lines, into the functions called
+    Step {
+        count: usize,
+    },
+    /// Steps `count` lines, over the functions called
+    Next {
+        count: usize,
+    },
+    StepInstruction {
+        count: usize,
+    },
+    NextInstruction {
+        count: usize,
+    },
+    /// Runs to the next line, or to a location, without stopping in loops or the functions called
+    Until(Option<BreakpointRequest>),
+    /// Runs to a location, in any frame
+    Advance(BreakpointRequest),
+    /// Runs until the function returns
     Finish,
-    Noop,
-    Info,
-    Unknown,
+    /// Has `signal` delivered to the inferior when it's next resumed, like gdb's `queue-signal`
+    QueueSignal(Signal),
+    Kill,
+    /// Takes our breakpoints out of the inferior and stops tracing it, so that it runs on without us
+    Detach,
 }
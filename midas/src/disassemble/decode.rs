@@ -0,0 +1,861 @@
+//! Decoding an instruction; the prefixes, the opcode, ModRM, SIB, displacement and immediates, in that order.
+use super::{
+    tables::{self, Context, Op},
+    DecodeError, Instruction, Memory, Operand, Register,
+};
+
+/// The most bytes an instruction can have
+const MAX_LENGTH: usize = 15;
+
+/// Decodes the instruction at the start of `bytes`, which is at `address`
+pub fn decode(bytes: &[u8], address: u64) -> Result<Instruction, DecodeError> {
+    let instruction = Decoder::new(bytes, address).instruction()?;
+    if instruction.mnemonic != "fwait" || instruction.length != 1 {
+        return Ok(instruction);
+    }
+    // fwait before an x87 control instruction that doesn't wait is the one that does; fnstsw is fstsw
+    match Decoder::new(&bytes[1..], address + 1).instruction() {
+        Ok(control)
+            if control.prefixes.is_empty() && control.mnemonic.starts_with("fn") && control.mnemonic != "fnop" =>
+        {
+            Ok(Instruction {
+                address,
+                length: control.length + 1,
+                mnemonic: control.mnemonic.replacen("fn", "f", 1),
+                att_mnemonic: control.att_mnemonic.replacen("fn", "f", 1),
+                ..control
+            })
+        }
+        _ => Ok(instruction),
+    }
+}
+
+// what VEX and EVEX encode besides the opcode
+#[derive(Debug, Default, Clone, Copy)]
+struct Vex {
+    evex: bool,
+    /// The extra source register, vvvv
+    register: u8,
+    /// VEX.L, or for EVEX, L'L
+    length: u8,
+    /// The EVEX opmask register
+    mask: u8,
+    zeroing: bool,
+    /// EVEX.b; broadcast for memory operands, rounding for registers
+    broadcast: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ModRm {
+    mode: u8,
+    reg: u8,
+    rm: u8,
+}
+
+// the r/m operand as encoded, before the opcode says what it is
+#[derive(Debug, Clone, Copy)]
+enum Rm {
+    /// The register number, extended by REX.B
+    Register(u8),
+    Memory {
+        base: Option<u8>,
+        rip: bool,
+        index: Option<(u8, u8)>,
+        displacement: Option<i64>,
+        /// An 8 bit displacement, which EVEX scales by the size of the memory operand
+        compressed: bool,
+    },
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    address: u64,
+    lock: bool,
+    /// The last of F2 and F3
+    repeat: Option<u8>,
+    /// The segment override, by it's number
+    segment: Option<u8>,
+    operand_size_prefixes: usize,
+    address_size_prefix: bool,
+    rex: Option<u8>,
+    vex: Option<Vex>,
+    /// The register extensions of REX, VEX or EVEX, already shifted to bit 3
+    w: bool,
+    r: u8,
+    x: u8,
+    b: u8,
+    /// EVEX R' and X, bit 4 of vector register numbers
+    r_high: u8,
+    x_high: u8,
+    /// The mandatory prefix VEX encodes; none, 66, F3 or F2
+    vex_prefix: usize,
+    modrm: Option<(ModRm, Rm)>,
+    opcode: u8,
+    operand_size: usize,
+    address_size: usize,
+    vector_length: usize,
+    /// For the AT&T size suffix; whether a general register says the operand size, and the size of general memory
+    sized_by_register: bool,
+    general_memory: Option<usize>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8], address: u64) -> Self {
+        Decoder {
+            bytes,
+            position: 0,
+            address,
+            lock: false,
+            repeat: None,
+            segment: None,
+            operand_size_prefixes: 0,
+            address_size_prefix: false,
+            rex: None,
+            vex: None,
+            w: false,
+            r: 0,
+            x: 0,
+            b: 0,
+            r_high: 0,
+            x_high: 0,
+            vex_prefix: 0,
+            modrm: None,
+            opcode: 0,
+            operand_size: 4,
+            address_size: 8,
+            vector_length: 16,
+            sized_by_register: false,
+            general_memory: None,
+        }
+    }
+
+    fn peek(&self) -> Result<u8, DecodeError> {
+        if self.position >= MAX_LENGTH {
+            return Err(DecodeError::Invalid);
+        }
+        self.bytes
+            .get(self.position)
+            .copied()
+            .ok_or(DecodeError::Truncated)
+    }
+
+    fn next(&mut self) -> Result<u8, DecodeError> {
+        let byte = self.peek()?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    // `size` bytes, little endian
+    fn unsigned(&mut self, size: usize) -> Result<u64, DecodeError> {
+        let mut value = 0;
+        for shift in 0..size {
+            value |= (self.next()? as u64) << (shift * 8);
+        }
+        Ok(value)
+    }
+
+    fn signed(&mut self, size: usize) -> Result<i64, DecodeError> {
+        let value = self.unsigned(size)?;
+        let unused = 64 - size * 8;
+        Ok(((value << unused) as i64) >> unused)
+    }
+
+    fn prefixes(&mut self) -> Result<(), DecodeError> {
+        loop {
+            let byte = self.peek()?;
+            match byte {
+                0xf0 => self.lock = true,
+                0xf2 | 0xf3 => self.repeat = Some(byte),
+                0x26 => self.segment = Some(0),
+                0x2e => self.segment = Some(1),
+                0x36 => self.segment = Some(2),
+                0x3e => self.segment = Some(3),
+                0x64 => self.segment = Some(4),
+                0x65 => self.segment = Some(5),
+                0x66 => self.operand_size_prefixes += 1,
+                0x67 => self.address_size_prefix = true,
+                0x40..=0x4f => {
+                    self.position += 1;
+                    self.rex = Some(byte);
+                    continue;
+                }
+                _ => return Ok(()),
+            }
+            // REX only counts right before the opcode
+            self.rex = None;
+            self.position += 1;
+        }
+    }
+
+    // C4, C5 and 62, which have been read; returns the opcode map
+    fn vex(&mut self, first: u8) -> Result<u8, DecodeError> {
+        if self.rex.is_some() || self.repeat.is_some() || self.lock || self.operand_size_prefixes > 0 {
+            return Err(DecodeError::Invalid);
+        }
+        let mut vex = Vex::default();
+        let map = match first {
+            0xc5 => {
+                let byte = self.next()?;
+                self.r = (!byte >> 4) & 8;
+                vex.register = (!byte >> 3) & 0xf;
+                vex.length = (byte >> 2) & 1;
+                self.vex_prefix = (byte & 3) as usize;
+                1
+            }
+            0xc4 => {
+                let first = self.next()?;
+                let second = self.next()?;
+                self.r = (!first >> 4) & 8;
+                self.x = (!first >> 3) & 8;
+                self.b = (!first >> 2) & 8;
+                self.w = second & 0x80 != 0;
+                vex.register = (!second >> 3) & 0xf;
+                vex.length = (second >> 2) & 1;
+                self.vex_prefix = (second & 3) as usize;
+                first & 0x1f
+            }
+            _ => {
+                let (p0, p1, p2) = (self.next()?, self.next()?, self.next()?);
+                // bits that are always 0 and 1
+                if p0 & 0x08 != 0 || p1 & 0x04 == 0 {
+                    return Err(DecodeError::Invalid);
+                }
+                self.r = (!p0 >> 4) & 8;
+                self.x = (!p0 >> 3) & 8;
+                self.b = (!p0 >> 2) & 8;
+                self.r_high = !p0 & 0x10;
+                self.x_high = (!p0 >> 2) & 0x10;
+                self.w = p1 & 0x80 != 0;
+                vex.evex = true;
+                vex.register = ((!p1 >> 3) & 0xf) | ((!p2 << 1) & 0x10);
+                self.vex_prefix = (p1 & 3) as usize;
+                vex.zeroing = p2 & 0x80 != 0;
+                vex.length = (p2 >> 5) & 3;
+                vex.broadcast = p2 & 0x10 != 0;
+                vex.mask = p2 & 7;
+                p0 & 7
+            }
+        };
+        if !(1..=3).contains(&map) {
+            return Err(DecodeError::Invalid);
+        }
+        self.vex = Some(vex);
+        Ok(map)
+    }
+
+    fn read_modrm(&mut self, register_form: bool) -> Result<(ModRm, Rm), DecodeError> {
+        let byte = self.next()?;
+        let modrm = ModRm {
+            mode: if register_form { 3 } else { byte >> 6 },
+            reg: (byte >> 3) & 7,
+            rm: byte & 7,
+        };
+        if modrm.mode == 3 {
+            return Ok((modrm, Rm::Register(modrm.rm | self.b)));
+        }
+        let mut base = Some(modrm.rm | self.b);
+        let mut index = None;
+        let mut rip = false;
+        let mut mode = modrm.mode;
+        if modrm.rm == 4 {
+            let sib = self.next()?;
+            let number = ((sib >> 3) & 7) | self.x;
+            // no index is encoded as rsp
+            if number != 4 {
+                index = Some((number, 1 << (sib >> 6)));
+            }
+            base = Some((sib & 7) | self.b);
+            if sib & 7 == 5 && mode == 0 {
+                base = None;
+                mode = 2;
+            }
+        } else if modrm.rm == 5 && mode == 0 {
+            base = None;
+            rip = true;
+            mode = 2;
+        }
+        let displacement = match mode {
+            1 => Some(self.signed(1)?),
+            2 => Some(self.signed(4)?),
+            _ => None,
+        };
+        let compressed = mode == 1 && self.vex.is_some_and(|vex| vex.evex);
+        Ok((
+            modrm,
+            Rm::Memory {
+                base,
+                rip,
+                index,
+                displacement,
+                compressed,
+            },
+        ))
+    }
+
+    fn instruction(mut self) -> Result<Instruction, DecodeError> {
+        self.prefixes()?;
+        if let Some(rex) = self.rex {
+            self.w = rex & 8 != 0;
+            self.r = (rex & 4) << 1;
+            self.x = (rex & 2) << 2;
+            self.b = (rex & 1) << 3;
+        }
+        let map = match self.next()? {
+            byte @ (0xc4 | 0xc5 | 0x62) => self.vex(byte)?,
+            0x0f => match self.peek()? {
+                0x38 => {
+                    self.position += 1;
+                    2
+                }
+                0x3a => {
+                    self.position += 1;
+                    3
+                }
+                _ => 1,
+            },
+            _ => {
+                self.position -= 1;
+                0
+            }
+        };
+        self.opcode = self.next()?;
+        if tables::has_modrm(map, self.opcode) {
+            // moves from and to the control and debug registers are always from and to registers
+            let register_form = map == 1 && (0x20..=0x23).contains(&self.opcode);
+            self.modrm = Some(self.read_modrm(register_form)?);
+        }
+
+        let prefix = match (self.vex, self.repeat) {
+            (Some(_), _) => self.vex_prefix,
+            (None, Some(0xf3)) => 2,
+            (None, Some(_)) => 3,
+            (None, None) if self.operand_size_prefixes > 0 => 1,
+            _ => 0,
+        };
+        let vex = self.vex.unwrap_or_default();
+        let (modrm, register_form) = match self.modrm {
+            Some((modrm, _)) => (modrm, modrm.mode == 3),
+            None => (
+                ModRm {
+                    mode: 0,
+                    reg: 0,
+                    rm: 0,
+                },
+                false,
+            ),
+        };
+        let context = Context {
+            prefix,
+            reg: modrm.reg,
+            rm: modrm.rm,
+            register_form,
+            w: self.w,
+            rex_b: self.b != 0,
+            vex: self.vex.is_some(),
+            evex: vex.evex,
+            long_vector: vex.length != 0,
+        };
+        let op = match map {
+            0 => tables::one_byte(self.opcode, &context),
+            1 => tables::two_byte(self.opcode, &context),
+            2 => tables::three_byte_38(self.opcode, &context),
+            _ => tables::three_byte_3a(self.opcode, &context),
+        };
+        // VEX encodes the SSE instructions and some of it's own, and nothing else
+        let vex_only = op.flags & tables::VEX != 0;
+        let valid = match self.vex {
+            Some(_) => vex_only || op.is_vector(),
+            None => !vex_only,
+        };
+        if !op.is_valid() || !valid {
+            return Err(DecodeError::Invalid);
+        }
+
+        // a 66 that picks the instruction doesn't make it 16 bit
+        let mandatory = op.flags & tables::MANDATORY != 0;
+        let size_prefixes = self.operand_size_prefixes - usize::from(mandatory && prefix == 1 && self.vex.is_none());
+        self.operand_size = if self.w || op.flags & tables::F64 != 0 {
+            8
+        } else if size_prefixes > 0 {
+            2
+        } else if op.flags & tables::D64 != 0 {
+            8
+        } else {
+            4
+        };
+        self.address_size = if self.address_size_prefix { 4 } else { 8 };
+        let rounding = vex.evex && vex.broadcast && register_form;
+        self.vector_length = if self.vex.is_none() {
+            16
+        } else if rounding {
+            64
+        } else {
+            16 << vex.length.min(2)
+        };
+
+        let mut operands = vec![];
+        for token in op.operands.split(',').filter(|token| !token.is_empty()) {
+            if let Some(operand) = self.operand(token)? {
+                operands.push(operand);
+            }
+        }
+        let mut mnemonic = self.mnemonic(&op);
+        if let Some(Operand::Immediate { value, .. }) = operands.last() {
+            let value = *value as usize;
+            let named = if op.flags & tables::PREDICATE != 0 {
+                value < if self.vex.is_some() { 32 } else { 8 }
+            } else {
+                op.flags & tables::INTEGER_PREDICATE != 0 && value < 8 && value != 3 && value != 7
+            };
+            if named {
+                mnemonic = mnemonic.replacen("cmp", &format!("cmp{}", tables::PREDICATES[value]), 1);
+                operands.pop();
+            } else if mnemonic.ends_with("pclmulqdq") && matches!(value, 0x00 | 0x01 | 0x10 | 0x11) {
+                // which quadwords are multiplied
+                let (first, second) = (["lq", "hq"][value & 1], ["lq", "hq"][value >> 4]);
+                mnemonic = mnemonic.replace("pclmulqdq", &format!("pclmul{}{}dq", first, second));
+                operands.pop();
+            }
+        }
+        if rounding && op.is_vector() {
+            operands.push(Operand::Rounding(vex.length));
+        }
+        let att_mnemonic = self.att_mnemonic(&op, &mnemonic, &operands);
+        let prefixes = self.shown_prefixes(&op, &mnemonic, size_prefixes, &operands);
+        Ok(Instruction {
+            address: self.address,
+            length: self.position,
+            mnemonic,
+            operands,
+            att_mnemonic,
+            prefixes,
+            mask: (vex.evex && vex.mask != 0).then_some((vex.mask, vex.zeroing)),
+            indirect: op.flags & tables::INDIRECT != 0,
+            keep_order: op.flags & tables::KEEP_ORDER != 0,
+        })
+    }
+
+    // the Intel mnemonic
+    fn mnemonic(&self, op: &Op) -> String {
+        let alternatives: Vec<&str> = op.mnemonic.split('|').collect();
+        let mnemonic = match alternatives.len() {
+            3 => {
+                alternatives[match self.operand_size {
+                    2 => 0,
+                    4 => 1,
+                    _ => 2,
+                }]
+            }
+            2 => alternatives[usize::from(self.w)],
+            _ => alternatives[0],
+        };
+        let moffs = self.modrm.is_none() && self.opcode & 0xfc == 0xa0;
+        let imm64 = self.modrm.is_none() && self.opcode & 0xf8 == 0xb8 && self.operand_size == 8;
+        match mnemonic {
+            "mov" if self.vex.is_none() && (moffs || imm64) => "movabs".to_owned(),
+            "jrcxz" if self.address_size_prefix => "jecxz".to_owned(),
+            _ if self.vex.is_some() && op.flags & tables::VEX == 0 => format!("v{}", mnemonic),
+            _ => mnemonic.to_owned(),
+        }
+    }
+
+    // the size an operand's size code says, None for none
+    fn size(&self, code: &str) -> Option<usize> {
+        Some(match code {
+            "b" => 1,
+            "w" => 2,
+            "d" | "ss" => 4,
+            "q" | "sd" => 8,
+            "dq" => 16,
+            // the 256 bit instructions that EVEX makes 512 bit
+            "qq" => self.vector_length.max(32),
+            "t" => 10,
+            "f" => self.operand_size + 2,
+            "v" => self.operand_size,
+            "y" if self.w => 8,
+            "y" => 4,
+            "z" => self.operand_size.min(4),
+            "x" | "ps" | "pd" => self.vector_length,
+            "xh" => self.vector_length / 2,
+            "xq" => self.vector_length / 4,
+            "xo" => self.vector_length / 8,
+            _ => return None,
+        })
+    }
+
+    fn general(&self, number: u8, size: usize) -> Register {
+        // without REX the byte registers 4 to 7 are ah to bh, with it they're spl to dil
+        if size == 1 && self.rex.is_none() && self.vex.is_none() && (4..8).contains(&number) {
+            Register::HighByte(number - 4)
+        } else {
+            Register::General { number, size }
+        }
+    }
+
+    fn vector(&self, number: u8, size: usize) -> Register {
+        Register::Vector {
+            number,
+            size: size.clamp(16, 64),
+        }
+    }
+
+    fn memory(&self, rm: Rm, size: Option<usize>) -> Result<Memory, DecodeError> {
+        let Rm::Memory {
+            base,
+            rip,
+            index,
+            displacement,
+            compressed,
+        } = rm
+        else {
+            return Err(DecodeError::Invalid);
+        };
+        let general = |number| Register::General {
+            number,
+            size: self.address_size,
+        };
+        // with EVEX embedded broadcast one element is read, and copied to all of them
+        let vex = self.vex.unwrap_or_default();
+        let (size, broadcast) = if vex.evex && vex.broadcast {
+            let element = if self.w { 8 } else { 4 };
+            (Some(element), Some(self.vector_length / element))
+        } else {
+            (size, None)
+        };
+        let displacement = match compressed {
+            true => displacement.map(|displacement| displacement * size.unwrap_or(1) as i64),
+            false => displacement,
+        };
+        Ok(Memory {
+            // the other segments are shown as prefixes
+            segment: self
+                .segment
+                .filter(|segment| *segment >= 4)
+                .map(Register::Segment),
+            base: if rip {
+                Some(Register::Rip)
+            } else {
+                base.map(general)
+            },
+            index: index.map(|(number, scale)| (general(number), scale)),
+            displacement,
+            size,
+            broadcast,
+        })
+    }
+
+    fn rm(&self) -> Result<Rm, DecodeError> {
+        self.modrm.map(|(_, rm)| rm).ok_or(DecodeError::Invalid)
+    }
+
+    fn reg(&self) -> u8 {
+        self.modrm.map_or(0, |(modrm, _)| modrm.reg)
+    }
+
+    fn operand(&mut self, token: &str) -> Result<Option<Operand>, DecodeError> {
+        // a register of one size or memory of another
+        if let Some((register, memory)) = token.split_once('/') {
+            let token = match self.rm()? {
+                Rm::Register(_) => register,
+                Rm::Memory { .. } => memory,
+            };
+            return self.operand(token);
+        }
+        let fixed = match token {
+            "AL" => Some(Register::General { number: 0, size: 1 }),
+            "CL" => Some(Register::General { number: 1, size: 1 }),
+            "AX" => Some(Register::General { number: 0, size: 2 }),
+            "DX" => Some(Register::General { number: 2, size: 2 }),
+            "rAX" => Some(Register::General {
+                number: 0,
+                size: self.operand_size,
+            }),
+            "eAX" => Some(Register::General {
+                number: 0,
+                size: self.operand_size.min(4),
+            }),
+            "FS" => Some(Register::Segment(4)),
+            "GS" => Some(Register::Segment(5)),
+            "ST" => Some(Register::StackTop),
+            "STi" => Some(Register::St(self.modrm.map_or(0, |(modrm, _)| modrm.rm))),
+            "X0" => Some(Register::Vector {
+                number: 0,
+                size: 16,
+            }),
+            "1" => return Ok(Some(Operand::One)),
+            _ => None,
+        };
+        if let Some(register) = fixed {
+            if matches!(token, "AL" | "AX" | "rAX" | "eAX") {
+                self.sized_by_register = true;
+            }
+            return Ok(Some(Operand::Register(register)));
+        }
+
+        let (kind, code) = token.split_at(1);
+        let size = self.size(code);
+        let operand = match kind {
+            "E" | "R" | "M" => match self.rm()? {
+                Rm::Register(_) if kind == "M" => return Err(DecodeError::Invalid),
+                Rm::Register(number) => {
+                    self.sized_by_register = true;
+                    Operand::Register(self.general(number, size.unwrap_or(self.operand_size)))
+                }
+                Rm::Memory { .. } if kind == "R" => return Err(DecodeError::Invalid),
+                rm => {
+                    if kind == "E" {
+                        self.general_memory = size;
+                    }
+                    Operand::Memory(self.memory(rm, size)?)
+                }
+            },
+            "G" => {
+                self.sized_by_register = true;
+                Operand::Register(self.general(self.reg() | self.r, size.unwrap_or(self.operand_size)))
+            }
+            "Z" => {
+                self.sized_by_register = true;
+                Operand::Register(self.general(
+                    (self.opcode & 7) | self.b,
+                    size.unwrap_or(self.operand_size),
+                ))
+            }
+            "B" => {
+                self.sized_by_register = true;
+                let number = self.vex.unwrap_or_default().register & 0xf;
+                Operand::Register(self.general(number, size.unwrap_or(self.operand_size)))
+            }
+            "I" => {
+                let size = size.unwrap_or(1);
+                let value = match code {
+                    // sign extended to the operand size
+                    "bs" | "z" => {
+                        let value = self.signed(size)? as u64;
+                        match self.operand_size {
+                            8 => value,
+                            operand_size => value & ((1 << (operand_size * 8)) - 1),
+                        }
+                    }
+                    _ => self.unsigned(size)?,
+                };
+                let size = if matches!(code, "bs" | "z") {
+                    self.operand_size
+                } else {
+                    size
+                };
+                Operand::Immediate { value, size }
+            }
+            "J" => {
+                let relative = self.signed(if code == "b" { 1 } else { 4 })?;
+                let next = self.address.wrapping_add(self.position as u64);
+                Operand::Target(next.wrapping_add(relative as u64))
+            }
+            "O" => {
+                let address = self.unsigned(self.address_size)?;
+                Operand::Memory(Memory {
+                    segment: self
+                        .segment
+                        .filter(|segment| *segment >= 4)
+                        .map(Register::Segment),
+                    base: None,
+                    index: None,
+                    displacement: Some(address as i64),
+                    size: None,
+                    broadcast: None,
+                })
+            }
+            "S" if self.reg() < 6 => Operand::Register(Register::Segment(self.reg())),
+            "C" => Operand::Register(Register::Control(self.reg() | self.r)),
+            "D" => Operand::Register(Register::Debug(self.reg() | self.r)),
+            "X" | "Y" => {
+                self.general_memory = size;
+                let (segment, base) = match kind {
+                    "X" => (self.segment.unwrap_or(3), 6),
+                    _ => (0, 7),
+                };
+                Operand::Memory(Memory {
+                    segment: Some(Register::Segment(segment)),
+                    base: Some(Register::General {
+                        number: base,
+                        size: self.address_size,
+                    }),
+                    index: None,
+                    displacement: None,
+                    size,
+                    broadcast: None,
+                })
+            }
+            "V" => Operand::Register(self.vector(self.reg() | self.r | self.r_high, size.unwrap_or(16))),
+            "W" | "U" => match self.rm()? {
+                Rm::Register(number) => Operand::Register(self.vector(number | self.x_high, size.unwrap_or(16))),
+                Rm::Memory { .. } if kind == "U" => return Err(DecodeError::Invalid),
+                rm => Operand::Memory(self.memory(rm, size)?),
+            },
+            "H" => match (self.vex, code, self.rm()) {
+                (None, ..) => return Ok(None),
+                (Some(_), "r", Ok(Rm::Memory { .. })) => return Ok(None),
+                (Some(vex), "r", _) => Operand::Register(self.vector(vex.register, 16)),
+                (Some(vex), ..) => Operand::Register(self.vector(vex.register, size.unwrap_or(16))),
+            },
+            "L" => {
+                let number = self.next()? >> 4;
+                Operand::Register(self.vector(number, size.unwrap_or(16)))
+            }
+            "P" => Operand::Register(Register::Mmx(self.reg())),
+            "K" => match code {
+                "" => Operand::Register(Register::Mask(self.reg())),
+                "h" => Operand::Register(Register::Mask(self.vex.unwrap_or_default().register & 7)),
+                _ => match self.rm()? {
+                    Rm::Register(number) => Operand::Register(Register::Mask(number & 7)),
+                    Rm::Memory { .. } => return Err(DecodeError::Invalid),
+                },
+            },
+            "Q" | "N" => match self.rm()? {
+                Rm::Register(number) => Operand::Register(Register::Mmx(number & 7)),
+                Rm::Memory { .. } if kind == "N" => return Err(DecodeError::Invalid),
+                rm => Operand::Memory(self.memory(rm, size)?),
+            },
+            _ => return Err(DecodeError::Invalid),
+        };
+        Ok(Some(operand))
+    }
+
+    // the AT&T mnemonic, which has a suffix for the operand size when the operands don't say it
+    fn att_mnemonic(&self, op: &Op, mnemonic: &str, operands: &[Operand]) -> String {
+        let suffix = |size: usize| match size {
+            1 => "b",
+            2 => "w",
+            4 => "l",
+            _ => "q",
+        };
+        let size_of = |operand: Option<&Operand>| match operand {
+            Some(Operand::Register(Register::General { size, .. })) => *size,
+            Some(Operand::Register(Register::HighByte(_))) => 1,
+            Some(Operand::Memory(memory)) => memory.size.unwrap_or(self.operand_size),
+            _ => self.operand_size,
+        };
+        let memory_size = operands.iter().find_map(|operand| match operand {
+            Operand::Memory(memory) => memory.size,
+            _ => None,
+        });
+        // MMX and vector registers don't say the size of a general operand
+        let vector = operands.iter().any(|operand| {
+            matches!(
+                operand,
+                Operand::Register(Register::Mmx(_) | Register::Vector { .. })
+            )
+        });
+        match mnemonic {
+            "movzx" | "movsx" => format!(
+                "mov{}{}{}",
+                &mnemonic[3..4],
+                suffix(size_of(operands.get(1))),
+                suffix(size_of(operands.first()))
+            ),
+            "movsxd" if self.operand_size == 8 => "movslq".to_owned(),
+            "cbw" => "cbtw".to_owned(),
+            "cwde" => "cwtl".to_owned(),
+            "cdqe" => "cltq".to_owned(),
+            "cwd" => "cwtd".to_owned(),
+            "cdq" => "cltd".to_owned(),
+            "cqo" => "cqto".to_owned(),
+            "crc32" => format!("crc32{}", suffix(size_of(operands.get(1)))),
+            "retf" => "lret".to_owned(),
+            _ if op.flags & tables::FAR != 0 => format!("l{}", mnemonic),
+            _ if op.flags & tables::X87_REVERSED != 0 => match mnemonic.strip_suffix('p') {
+                Some(operation) => format!("{}p", reverse_x87(operation)),
+                None => reverse_x87(mnemonic),
+            },
+            _ if op.flags & tables::X87 != 0 => match memory_size {
+                Some(size) => format!("{}{}", mnemonic, x87_suffix(mnemonic, size)),
+                None => mnemonic.to_owned(),
+            },
+            _ => match self.general_memory {
+                Some(size)
+                    if !self.sized_by_register
+                        && op.flags & (tables::D64 | tables::F64 | tables::NO_SUFFIX) == 0
+                        && (!vector || op.flags & tables::SUFFIX != 0) =>
+                {
+                    format!("{}{}", mnemonic, suffix(size))
+                }
+                _ => mnemonic.to_owned(),
+            },
+        }
+    }
+
+    // the prefixes that aren't part of the instruction, which are shown before it
+    fn shown_prefixes(&self, op: &Op, mnemonic: &str, size_prefixes: usize, operands: &[Operand]) -> Vec<&'static str> {
+        // one 66 is the operand size, any more don't do anything, and neither does it with REX.W
+        let mut prefixes = vec!["data16"; size_prefixes.saturating_sub(usize::from(!self.w))];
+        // like the padding of calls to __tls_get_addr
+        if self.rex == Some(0x48) && op.flags & (tables::D64 | tables::F64) != 0 {
+            prefixes.push("rex.W");
+        }
+        let in_memory = operands.iter().any(|operand| {
+            matches!(
+                operand,
+                Operand::Memory(Memory {
+                    segment: Some(_),
+                    ..
+                })
+            )
+        });
+        match self.segment {
+            Some(3) if op.flags & tables::INDIRECT != 0 && op.flags & tables::BRANCH != 0 => {}
+            Some(3) if op.flags & tables::STRING != 0 => {}
+            Some(segment) if !in_memory => prefixes.push(["es", "cs", "ss", "ds", "fs", "gs"][segment as usize]),
+            _ => {}
+        }
+        if self.lock {
+            prefixes.push("lock");
+        }
+        let consumed = op.flags & tables::MANDATORY != 0 && self.vex.is_none();
+        match self.repeat {
+            Some(_) if consumed => {}
+            Some(0xf3) if op.flags & tables::STRING != 0 && matches!(mnemonic, "cmps" | "scas") => {
+                prefixes.push("repz")
+            }
+            Some(0xf3) if op.flags & tables::STRING != 0 => prefixes.push("rep"),
+            Some(0xf2) if op.flags & tables::STRING != 0 => prefixes.push("repnz"),
+            Some(0xf2) if op.flags & tables::BRANCH != 0 => prefixes.push("bnd"),
+            Some(0xf3) => prefixes.push("repz"),
+            Some(_) => prefixes.push("repnz"),
+            None => {}
+        }
+        if self.segment == Some(3) && op.flags & tables::INDIRECT != 0 && op.flags & tables::BRANCH != 0 {
+            prefixes.push("notrack");
+        }
+        prefixes
+    }
+}
+
+// what AT&T calls the x87 subtractions and divisions that store to st(i)
+fn reverse_x87(mnemonic: &str) -> String {
+    match mnemonic {
+        "fsub" => "fsubr",
+        "fsubr" => "fsub",
+        "fdiv" => "fdivr",
+        "fdivr" => "fdiv",
+        _ => mnemonic,
+    }
+    .to_owned()
+}
+
+// the AT&T suffix of x87 memory operands; s, l and ll for integers, s, l and t for floats
+fn x87_suffix(mnemonic: &str, size: usize) -> &'static str {
+    let integer = mnemonic.starts_with("fi");
+    let float = matches!(
+        mnemonic,
+        "fld" | "fst" | "fstp" | "fadd" | "fmul" | "fcom" | "fcomp" | "fsub" | "fsubr" | "fdiv" | "fdivr"
+    );
+    match size {
+        2 if integer => "s",
+        4 if integer => "l",
+        8 if integer => "ll",
+        4 if float => "s",
+        8 if float => "l",
+        10 if float => "t",
+        _ => "",
+    }
+}
@@ -0,0 +1,354 @@
+//! An x86-64 instruction decoder, for looking at the machine code around the pc. It knows the legacy, REX, VEX and
+//! EVEX encodings of the general purpose, x87, MMX, SSE and AVX instructions compilers emit, and prints them in the
+//! AT&T or the Intel syntax the way gdb does, which is the way objdump does.
+mod decode;
+mod tables;
+
+pub use decode::decode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Att,
+    Intel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The bytes end before the instruction does
+    Truncated,
+    /// The bytes aren't an instruction, or not one we know
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    /// rax to r15, or the lower 4, 2 or 1 bytes of them
+    General {
+        number: u8,
+        size: usize,
+    },
+    /// ah, ch, dh and bh
+    HighByte(u8),
+    Rip,
+    Segment(u8),
+    Control(u8),
+    Debug(u8),
+    /// The top of the x87 stack, which the instruction implies
+    StackTop,
+    /// The x87 stack, from the ModRM byte
+    St(u8),
+    Mmx(u8),
+    /// xmm, ymm or zmm, by size
+    Vector {
+        number: u8,
+        size: usize,
+    },
+    /// The AVX-512 opmask registers
+    Mask(u8),
+}
+
+const GENERAL_64: [&str; 16] = [
+    "rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+];
+const GENERAL_32: [&str; 16] = [
+    "eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "r8d", "r9d", "r10d", "r11d", "r12d", "r13d", "r14d",
+    "r15d",
+];
+const GENERAL_16: [&str; 16] = [
+    "ax", "cx", "dx", "bx", "sp", "bp", "si", "di", "r8w", "r9w", "r10w", "r11w", "r12w", "r13w", "r14w", "r15w",
+];
+const GENERAL_8: [&str; 16] = [
+    "al", "cl", "dl", "bl", "spl", "bpl", "sil", "dil", "r8b", "r9b", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b",
+];
+const SEGMENTS: [&str; 6] = ["es", "cs", "ss", "ds", "fs", "gs"];
+
+impl Register {
+    pub fn name(&self) -> String {
+        match *self {
+            Register::General { number, size } => match size {
+                1 => GENERAL_8[number as usize],
+                2 => GENERAL_16[number as usize],
+                4 => GENERAL_32[number as usize],
+                _ => GENERAL_64[number as usize],
+            }
+            .to_owned(),
+            Register::HighByte(number) => ["ah", "ch", "dh", "bh"][number as usize].to_owned(),
+            Register::Rip => "rip".to_owned(),
+            Register::Segment(number) => SEGMENTS[number as usize].to_owned(),
+            Register::Control(number) => format!("cr{}", number),
+            Register::Debug(number) => format!("db{}", number),
+            Register::StackTop => "st".to_owned(),
+            Register::St(number) => format!("st({})", number),
+            Register::Mmx(number) => format!("mm{}", number),
+            Register::Vector { number, size } => match size {
+                64 => format!("zmm{}", number),
+                32 => format!("ymm{}", number),
+                _ => format!("xmm{}", number),
+            },
+            Register::Mask(number) => format!("k{}", number),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Memory {
+    /// A segment override, or the segment the string instructions always name
+    pub segment: Option<Register>,
+    pub base: Option<Register>,
+    /// The index register and it's scale
+    pub index: Option<(Register, u8)>,
+    /// None if the encoding has no displacement; an encoded displacement of 0 is still shown
+    pub displacement: Option<i64>,
+    /// How many bytes are accessed; None when the instruction doesn't say, like lea which doesn't access memory
+    pub size: Option<usize>,
+    /// How many elements the one element read is broadcast to, with EVEX embedded broadcast
+    pub broadcast: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Register(Register),
+    /// `size` bytes; immediates that the instruction sign extends are extended to it's operand size first
+    Immediate {
+        value: u64,
+        size: usize,
+    },
+    Memory(Memory),
+    /// Where a relative jump or call goes
+    Target(u64),
+    /// The implicit count of a shift by one, which the AT&T syntax leaves out
+    One,
+    /// The EVEX embedded rounding mode
+    Rounding(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u64,
+    pub length: usize,
+    /// The Intel mnemonic
+    pub mnemonic: String,
+    /// In the Intel order; the destination first
+    pub operands: Vec<Operand>,
+    att_mnemonic: String,
+    /// Prefixes that are shown, like lock, rep and notrack
+    prefixes: Vec<&'static str>,
+    /// The EVEX opmask register, and whether masked out elements are zeroed rather than left alone
+    mask: Option<(u8, bool)>,
+    /// AT&T marks the operand of an indirect call or jump with a *
+    indirect: bool,
+    keep_order: bool,
+}
+
+impl Instruction {
+    /// Whether the instruction calls a function; it pushes the address of the instruction after it, which the
+    /// function returns to
+    pub fn is_call(&self) -> bool {
+        self.mnemonic == "call"
+    }
+
+    /// Whether the instruction returns from a function
+    pub fn is_return(&self) -> bool {
+        matches!(
+            self.mnemonic.as_str(),
+            "ret" | "retf" | "iret" | "iretq" | "iretw"
+        )
+    }
+
+    /// The instruction like gdb shows it; `mov    %rsp,%rbp`. `symbolize` names addresses, like `main+4`, for the
+    /// targets of branches and of rip relative operands.
+    pub fn format(&self, syntax: Syntax, symbolize: &dyn Fn(u64) -> Option<String>) -> String {
+        let mut operands: Vec<String> = self
+            .operands
+            .iter()
+            .enumerate()
+            .filter(|(_, operand)| syntax == Syntax::Intel || **operand != Operand::One)
+            .map(|(position, operand)| {
+                let mut text = self.format_operand(operand, syntax, symbolize);
+                if let (0, Some((mask, zeroing))) = (position, self.mask) {
+                    text += &match syntax {
+                        Syntax::Att => format!("{{%k{}}}", mask),
+                        Syntax::Intel => format!("{{k{}}}", mask),
+                    };
+                    if zeroing {
+                        text += "{z}";
+                    }
+                }
+                text
+            })
+            .collect();
+        if syntax == Syntax::Att && !self.keep_order {
+            operands.reverse();
+        }
+        // Intel puts the rounding mode on the last register, zmm2{rn-sae}
+        if let (Syntax::Intel, Some(Operand::Rounding(_))) = (syntax, self.operands.last()) {
+            if let Some(rounding) = operands.pop() {
+                if let Some(last) = operands.last_mut() {
+                    *last += &rounding;
+                }
+            }
+        }
+        let mnemonic = match syntax {
+            Syntax::Att => &self.att_mnemonic,
+            Syntax::Intel => &self.mnemonic,
+        };
+        let mut text = self
+            .prefixes
+            .iter()
+            .copied()
+            .chain(std::iter::once(mnemonic.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !operands.is_empty() {
+            text = format!("{:<6} {}", text, operands.join(","));
+        }
+        // where rip relative operands are, like objdump shows them
+        let rip_relative = self.operands.iter().find_map(|operand| match operand {
+            Operand::Memory(Memory {
+                base: Some(Register::Rip),
+                displacement,
+                ..
+            }) => Some((self.address + self.length as u64).wrapping_add(displacement.unwrap_or(0) as u64)),
+            _ => None,
+        });
+        if let Some(address) = rip_relative {
+            text += &format!("        # {}", label(address, symbolize));
+        }
+        text
+    }
+
+    fn format_operand(&self, operand: &Operand, syntax: Syntax, symbolize: &dyn Fn(u64) -> Option<String>) -> String {
+        match (operand, syntax) {
+            (Operand::Register(register), Syntax::Att) => {
+                let name = format!("%{}", register.name());
+                // the port of in and out is shown as if it was memory
+                if matches!(self.mnemonic.as_str(), "in" | "out" | "ins" | "outs")
+                    && *register == (Register::General { number: 2, size: 2 })
+                {
+                    format!("({})", name)
+                } else if self.indirect {
+                    format!("*{}", name)
+                } else {
+                    name
+                }
+            }
+            (Operand::Register(register), Syntax::Intel) => register.name(),
+            (Operand::Immediate { value, .. }, Syntax::Att) => format!("$0x{:x}", value),
+            (Operand::Immediate { value, .. }, Syntax::Intel) => format!("0x{:x}", value),
+            (Operand::Memory(memory), Syntax::Att) => {
+                let text = format_att_memory(memory);
+                if self.indirect {
+                    format!("*{}", text)
+                } else {
+                    text
+                }
+            }
+            (Operand::Memory(memory), Syntax::Intel) => format_intel_memory(memory),
+            (Operand::Target(address), _) => label(*address, symbolize),
+            (Operand::One, _) => "1".to_owned(),
+            (Operand::Rounding(mode), _) => format!("{{{}-sae}}", ["rn", "rd", "ru", "rz"][*mode as usize & 3]),
+        }
+    }
+}
+
+// an address, with the symbol it's in if there is one; 0x401136 <main+16>
+fn label(address: u64, symbolize: &dyn Fn(u64) -> Option<String>) -> String {
+    match symbolize(address) {
+        Some(symbol) => format!("0x{:x} <{}>", address, symbol),
+        None => format!("0x{:x}", address),
+    }
+}
+
+fn signed_hex(value: i64) -> String {
+    if value < 0 {
+        format!("-0x{:x}", value.unsigned_abs())
+    } else {
+        format!("0x{:x}", value)
+    }
+}
+
+// -0x8(%rbp,%rax,4)
+fn format_att_memory(memory: &Memory) -> String {
+    let mut text = String::new();
+    if let Some(segment) = memory.segment {
+        text += &format!("%{}:", segment.name());
+    }
+    match (memory.base, memory.index, memory.displacement) {
+        // an absolute address
+        (None, None, displacement) => text += &format!("0x{:x}", displacement.unwrap_or(0) as u64),
+        (base, index, displacement) => {
+            if let Some(displacement) = displacement {
+                text += &signed_hex(displacement);
+            }
+            text += "(";
+            if let Some(base) = base {
+                text += &format!("%{}", base.name());
+            }
+            if let Some((index, scale)) = index {
+                text += &format!(",%{},{}", index.name(), scale);
+            }
+            text += ")";
+        }
+    }
+    if let Some(count) = memory.broadcast {
+        text += &format!("{{1to{}}}", count);
+    }
+    text
+}
+
+fn size_name(size: usize) -> &'static str {
+    match size {
+        1 => "BYTE",
+        2 => "WORD",
+        4 => "DWORD",
+        6 => "FWORD",
+        8 => "QWORD",
+        10 => "TBYTE",
+        16 => "XMMWORD",
+        32 => "YMMWORD",
+        _ => "ZMMWORD",
+    }
+}
+
+// DWORD PTR [rbp+rax*4-0x8], or for a broadcast, DWORD BCST [rax]
+fn format_intel_memory(memory: &Memory) -> String {
+    let mut text = String::new();
+    if let Some(size) = memory.size {
+        let kind = if memory.broadcast.is_some() {
+            "BCST"
+        } else {
+            "PTR"
+        };
+        text += &format!("{} {} ", size_name(size), kind);
+    }
+    if let Some(segment) = memory.segment {
+        text += &format!("{}:", segment.name());
+    }
+    match (memory.base, memory.index, memory.displacement) {
+        (None, None, displacement) => {
+            if memory.segment.is_none() {
+                text += "ds:";
+            }
+            text += &format!("0x{:x}", displacement.unwrap_or(0) as u64);
+        }
+        (base, index, displacement) => {
+            let mut parts = vec![];
+            if let Some(base) = base {
+                parts.push(base.name());
+            }
+            if let Some((index, scale)) = index {
+                parts.push(format!("{}*{}", index.name(), scale));
+            }
+            let mut inside = parts.join("+");
+            match displacement {
+                // objdump shows rip relative displacements unsigned
+                Some(displacement) if base == Some(Register::Rip) => inside += &format!("+0x{:x}", displacement as u64),
+                Some(displacement) if displacement < 0 => inside += &signed_hex(displacement),
+                Some(displacement) => inside += &format!("+{}", signed_hex(displacement)),
+                None => {}
+            }
+            text += &format!("[{}]", inside);
+        }
+    }
+    text
+}
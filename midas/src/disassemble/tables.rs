@@ -0,0 +1,1442 @@
+//! The opcode maps. Operands are written like the Intel manual's opcode tables write them; the letter says where the
+//! operand comes from, the rest how big it is:
+//!
+//! - E: the ModRM r/m field, a general register or memory. G: the ModRM reg field, a general register.
+//! - I: an immediate. `Ibs` is a byte sign extended to the operand size. J: a branch target relative to the next
+//!   instruction.
+//! - M: the r/m field, memory only. O: a memory offset encoded in the instruction. R: the r/m field, a general
+//!   register only. `R<size>/M<size>` is a register of one size or memory of another.
+//! - Z: the general register in the low three bits of the opcode. B: the general register in VEX.vvvv.
+//! - V: the ModRM reg field, a vector register. W: the r/m field, a vector register or memory. U: the r/m field, a
+//!   vector register only. H: the vector register in VEX.vvvv; legacy encodings don't have it. `Hr` is only there
+//!   when W is a register. L: the vector register in the upper four bits of the immediate.
+//! - P: the reg field, an MMX register. Q: the r/m field, an MMX register or memory. N: the r/m field, an MMX
+//!   register only.
+//! - S, C and D: the reg field, a segment, control or debug register.
+//! - K: the reg field, an opmask register. `Kr` is the r/m field, a register only, and `Kh` the one in VEX.vvvv.
+//! - X and Y: the memory at ds:rsi and es:rdi of the string instructions.
+//! - Fixed registers by name: AL, CL, AX, DX, rAX (operand sized), eAX (ax or eax), FS, GS, ST, STi and X0 (xmm0),
+//!   and 1 for the count of the shift by one.
+//!
+//! Sizes are b, w, d, q, dq and qq for 1, 2, 4, 8, 16 and 32 bytes, t for 10, v for the operand size, y for 4 or 8
+//! bytes by REX.W, z for the operand size but at most 4 bytes, f for a far pointer, x, ps and pd for the vector length,
+//! ss and sd for a single or double float, and xh, xq and xo for a half, quarter and eighth of the vector length.
+//!
+//! Mnemonics with alternatives separated by `|` are picked by REX.W, or with three of them, by the operand size.
+
+/// The operand size defaults to 64 bits, but a 66 prefix makes it 16
+pub(super) const D64: u16 = 1;
+/// The operand size is 64 bits whatever the prefixes say
+pub(super) const F64: u16 = 1 << 1;
+/// Only VEX or EVEX encoded, and the mnemonic already has it's v
+pub(super) const VEX: u16 = 1 << 2;
+/// A vector instruction that has no VEX encoding
+pub(super) const NO_VEX: u16 = 1 << 3;
+/// An indirect branch; AT&T marks it's operand with a *
+pub(super) const INDIRECT: u16 = 1 << 4;
+/// No AT&T size suffix, even though nothing else says the size
+pub(super) const NO_SUFFIX: u16 = 1 << 5;
+/// A vector instruction that does get an AT&T size suffix, for the size of it's general register or memory operand
+pub(super) const SUFFIX: u16 = 1 << 6;
+/// Picked by a mandatory prefix; that prefix doesn't mean what it usually does
+pub(super) const MANDATORY: u16 = 1 << 7;
+/// The immediate is the comparison predicate, which goes in the mnemonic
+pub(super) const PREDICATE: u16 = 1 << 8;
+/// A string instruction; F3 is a rep prefix
+pub(super) const STRING: u16 = 1 << 9;
+/// A branch; F2 is a bnd prefix and 3E, on indirect ones, notrack
+pub(super) const BRANCH: u16 = 1 << 10;
+/// A far call, jump or return
+pub(super) const FAR: u16 = 1 << 11;
+/// An x87 instruction; AT&T gives the memory forms suffixes of their own
+pub(super) const X87: u16 = 1 << 12;
+/// The x87 register forms that AT&T calls the reverse operation; fsub is fsubr and fdiv fdivr
+pub(super) const X87_REVERSED: u16 = 1 << 13;
+/// The operands are in the same order in both syntaxes
+pub(super) const KEEP_ORDER: u16 = 1 << 14;
+/// The immediate is the predicate of an integer comparison; only some of them have names
+pub(super) const INTEGER_PREDICATE: u16 = 1 << 15;
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Op {
+    pub mnemonic: &'static str,
+    pub operands: &'static str,
+    pub flags: u16,
+}
+
+impl Op {
+    pub fn with(self, flags: u16) -> Op {
+        Op {
+            flags: self.flags | flags,
+            ..self
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        !self.mnemonic.is_empty()
+    }
+
+    /// Whether it's an SSE instruction, which VEX can encode, as opposed to a general purpose or MMX one
+    pub fn is_vector(&self) -> bool {
+        self.flags & NO_VEX == 0
+            && self
+                .operands
+                .split(',')
+                .any(|operand| operand.starts_with(['V', 'W', 'H', 'U']))
+    }
+}
+
+pub(super) const fn op(mnemonic: &'static str, operands: &'static str) -> Op {
+    Op {
+        mnemonic,
+        operands,
+        flags: 0,
+    }
+}
+
+pub(super) const fn op_f(mnemonic: &'static str, operands: &'static str, flags: u16) -> Op {
+    Op {
+        mnemonic,
+        operands,
+        flags,
+    }
+}
+
+pub(super) const BAD: Op = op("", "");
+
+/// What the tables look at besides the opcode
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Context {
+    /// The mandatory prefix: none, 66, F3 or F2
+    pub prefix: usize,
+    /// The ModRM fields, as encoded
+    pub reg: u8,
+    pub rm: u8,
+    pub register_form: bool,
+    pub w: bool,
+    pub rex_b: bool,
+    pub vex: bool,
+    pub evex: bool,
+    /// VEX.L
+    pub long_vector: bool,
+}
+
+const ALU: [&str; 8] = ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"];
+const SHIFTS: [&str; 8] = ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"];
+const JCC: [&str; 16] = [
+    "jo", "jno", "jb", "jae", "je", "jne", "jbe", "ja", "js", "jns", "jp", "jnp", "jl", "jge", "jle", "jg",
+];
+const SETCC: [&str; 16] = [
+    "seto", "setno", "setb", "setae", "sete", "setne", "setbe", "seta", "sets", "setns", "setp", "setnp", "setl",
+    "setge", "setle", "setg",
+];
+const CMOVCC: [&str; 16] = [
+    "cmovo", "cmovno", "cmovb", "cmovae", "cmove", "cmovne", "cmovbe", "cmova", "cmovs", "cmovns", "cmovp", "cmovnp",
+    "cmovl", "cmovge", "cmovle", "cmovg",
+];
+
+/// The predicates of cmpps and friends; the legacy encodings have the first eight, and the integer comparisons of
+/// AVX-512 the first eight but unord and ord
+pub(super) const PREDICATES: [&str; 32] = [
+    "eq", "lt", "le", "unord", "neq", "nlt", "nle", "ord", "eq_uq", "nge", "ngt", "false", "neq_oq", "ge", "gt",
+    "true", "eq_os", "lt_oq", "le_oq", "unord_s", "neq_us", "nlt_uq", "nle_uq", "ord_s", "eq_us", "nge_uq", "ngt_uq",
+    "false_os", "neq_os", "ge_oq", "gt_oq", "true_us",
+];
+
+// the op of the column of the mandatory prefix
+fn by_prefix(context: &Context, ops: [Op; 4]) -> Op {
+    match context.prefix {
+        0 => ops[0],
+        prefix if ops[prefix].is_valid() => ops[prefix].with(MANDATORY),
+        _ => BAD,
+    }
+}
+
+// an op of which the register and memory forms are different instructions
+fn by_form(context: &Context, memory: Op, register: Op) -> Op {
+    if context.register_form {
+        register
+    } else {
+        memory
+    }
+}
+
+/// Whether the opcode is followed by a ModRM byte
+pub(super) fn has_modrm(map: u8, opcode: u8) -> bool {
+    match map {
+        0 => {
+            matches!(opcode,
+            0x00..=0x3f if opcode & 7 < 4)
+                || matches!(opcode, 0x62 | 0x63 | 0x69 | 0x6b | 0x80..=0x8f | 0xc0 | 0xc1 | 0xc4..=0xc7 | 0xd0..=0xd3 | 0xd8..=0xdf | 0xf6 | 0xf7 | 0xfe | 0xff)
+        }
+        1 => !matches!(
+            opcode,
+            0x04..=0x0b | 0x0e | 0x30..=0x37 | 0x77 | 0x80..=0x8f | 0xa0..=0xa2 | 0xa8..=0xaa | 0xc8..=0xcf
+        ),
+        _ => true,
+    }
+}
+
+/// The one byte opcode map; 0F escapes to the others, and D8 to DF are the x87 instructions
+pub(super) fn one_byte(opcode: u8, context: &Context) -> Op {
+    let reg = context.reg as usize;
+    match opcode {
+        0x00..=0x3f if opcode & 7 < 6 => {
+            let operands = ["Eb,Gb", "Ev,Gv", "Gb,Eb", "Gv,Ev", "AL,Ib", "rAX,Iz"][(opcode & 7) as usize];
+            op(ALU[(opcode >> 3) as usize], operands)
+        }
+        0x50..=0x57 => op_f("push", "Zv", D64),
+        0x58..=0x5f => op_f("pop", "Zv", D64),
+        0x63 => op("movsxd", "Gv,Ed"),
+        0x68 => op_f("push", "Iz", D64),
+        0x69 => op("imul", "Gv,Ev,Iz"),
+        0x6a => op_f("push", "Ibs", D64),
+        0x6b => op("imul", "Gv,Ev,Ibs"),
+        0x6c => op_f("ins", "Yb,DX", STRING),
+        0x6d => op_f("ins", "Yz,DX", STRING),
+        0x6e => op_f("outs", "DX,Xb", STRING),
+        0x6f => op_f("outs", "DX,Xz", STRING),
+        0x70..=0x7f => op_f(JCC[(opcode & 0xf) as usize], "Jb", F64 | BRANCH),
+        0x80 => op(ALU[reg], "Eb,Ib"),
+        0x81 => op(ALU[reg], "Ev,Iz"),
+        0x83 => op(ALU[reg], "Ev,Ibs"),
+        0x84 => op("test", "Eb,Gb"),
+        0x85 => op("test", "Ev,Gv"),
+        0x86 => op("xchg", "Eb,Gb"),
+        0x87 => op("xchg", "Ev,Gv"),
+        0x88 => op("mov", "Eb,Gb"),
+        0x89 => op("mov", "Ev,Gv"),
+        0x8a => op("mov", "Gb,Eb"),
+        0x8b => op("mov", "Gv,Ev"),
+        0x8c => op_f("mov", "Rv/Mw,Sw", NO_SUFFIX),
+        0x8d if !context.register_form => op("lea", "Gv,M"),
+        0x8e => op_f("mov", "Sw,Ew", NO_SUFFIX),
+        0x8f if reg == 0 => op_f("pop", "Ev", D64),
+        0x90 if context.rex_b => op("xchg", "Zv,rAX"),
+        0x90 if context.prefix == 2 => op("pause", "").with(MANDATORY),
+        0x90 if context.prefix == 1 => op("xchg", "AX,AX"),
+        0x90 => op("nop", ""),
+        0x91..=0x97 => op("xchg", "Zv,rAX"),
+        0x98 => op("cbw|cwde|cdqe", ""),
+        0x99 => op("cwd|cdq|cqo", ""),
+        0x9b => op("fwait", ""),
+        0x9c => op_f("pushf", "", D64),
+        0x9d => op_f("popf", "", D64),
+        0x9e => op("sahf", ""),
+        0x9f => op("lahf", ""),
+        0xa0 => op("mov", "AL,Ob"),
+        0xa1 => op("mov", "rAX,Ov"),
+        0xa2 => op("mov", "Ob,AL"),
+        0xa3 => op("mov", "Ov,rAX"),
+        0xa4 => op_f("movs", "Yb,Xb", STRING),
+        0xa5 => op_f("movs", "Yv,Xv", STRING),
+        0xa6 => op_f("cmps", "Xb,Yb", STRING),
+        0xa7 => op_f("cmps", "Xv,Yv", STRING),
+        0xa8 => op("test", "AL,Ib"),
+        0xa9 => op("test", "rAX,Iz"),
+        0xaa => op_f("stos", "Yb,AL", STRING),
+        0xab => op_f("stos", "Yv,rAX", STRING),
+        0xac => op_f("lods", "AL,Xb", STRING),
+        0xad => op_f("lods", "rAX,Xv", STRING),
+        0xae => op_f("scas", "AL,Yb", STRING),
+        0xaf => op_f("scas", "rAX,Yv", STRING),
+        0xb0..=0xb7 => op("mov", "Zb,Ib"),
+        0xb8..=0xbf => op("mov", "Zv,Iv"),
+        0xc0 => op(SHIFTS[reg], "Eb,Ib"),
+        0xc1 => op(SHIFTS[reg], "Ev,Ib"),
+        0xc2 => op_f("ret", "Iw", F64 | BRANCH),
+        0xc3 => op_f("ret", "", F64 | BRANCH),
+        0xc6 if reg == 0 => op("mov", "Eb,Ib"),
+        0xc6 if reg == 7 && context.register_form && context.rm == 0 => op("xabort", "Ib"),
+        0xc7 if reg == 0 => op("mov", "Ev,Iz"),
+        0xc7 if reg == 7 && context.register_form && context.rm == 0 => op_f("xbegin", "Jz", F64),
+        0xc8 => op_f("enter", "Iw,Ib", D64 | KEEP_ORDER),
+        0xc9 => op_f("leave", "", D64),
+        0xca => op_f("retf", "Iw", FAR),
+        0xcb => op_f("retf", "", FAR),
+        0xcc => op("int3", ""),
+        0xcd => op("int", "Ib"),
+        0xcf => op("iretw|iret|iretq", ""),
+        0xd0 => op(SHIFTS[reg], "Eb,1"),
+        0xd1 => op(SHIFTS[reg], "Ev,1"),
+        0xd2 => op(SHIFTS[reg], "Eb,CL"),
+        0xd3 => op(SHIFTS[reg], "Ev,CL"),
+        0xd7 => op("xlat", ""),
+        0xd8..=0xdf => x87(opcode, context),
+        0xe0 => op_f("loopne", "Jb", F64),
+        0xe1 => op_f("loope", "Jb", F64),
+        0xe2 => op_f("loop", "Jb", F64),
+        0xe3 => op_f("jrcxz", "Jb", F64),
+        0xe4 => op("in", "AL,Ib"),
+        0xe5 => op("in", "eAX,Ib"),
+        0xe6 => op("out", "Ib,AL"),
+        0xe7 => op("out", "Ib,eAX"),
+        0xe8 => op_f("call", "Jz", F64 | BRANCH),
+        0xe9 => op_f("jmp", "Jz", F64 | BRANCH),
+        0xeb => op_f("jmp", "Jb", F64 | BRANCH),
+        0xec => op("in", "AL,DX"),
+        0xed => op("in", "eAX,DX"),
+        0xee => op("out", "DX,AL"),
+        0xef => op("out", "DX,eAX"),
+        0xf1 => op("int1", ""),
+        0xf4 => op("hlt", ""),
+        0xf5 => op("cmc", ""),
+        0xf6 => match reg {
+            0 | 1 => op("test", "Eb,Ib"),
+            _ => op(
+                ["", "", "not", "neg", "mul", "imul", "div", "idiv"][reg],
+                "Eb",
+            ),
+        },
+        0xf7 => match reg {
+            0 | 1 => op("test", "Ev,Iz"),
+            _ => op(
+                ["", "", "not", "neg", "mul", "imul", "div", "idiv"][reg],
+                "Ev",
+            ),
+        },
+        0xf8 => op("clc", ""),
+        0xf9 => op("stc", ""),
+        0xfa => op("cli", ""),
+        0xfb => op("sti", ""),
+        0xfc => op("cld", ""),
+        0xfd => op("std", ""),
+        0xfe if reg < 2 => op(["inc", "dec"][reg], "Eb"),
+        0xff => match reg {
+            0 => op("inc", "Ev"),
+            1 => op("dec", "Ev"),
+            2 => op_f("call", "Ev", D64 | INDIRECT | BRANCH),
+            3 => op_f("call", "Mf", INDIRECT | FAR),
+            4 => op_f("jmp", "Ev", D64 | INDIRECT | BRANCH),
+            5 => op_f("jmp", "Mf", INDIRECT | FAR),
+            6 => op_f("push", "Ev", D64),
+            _ => BAD,
+        },
+        _ => BAD,
+    }
+}
+
+/// The x87 instructions, D8 to DF. The memory forms are picked by the reg field, the register forms by the whole
+/// ModRM byte.
+fn x87(opcode: u8, context: &Context) -> Op {
+    const ARITHMETIC: [&str; 8] = [
+        "fadd", "fmul", "fcom", "fcomp", "fsub", "fsubr", "fdiv", "fdivr",
+    ];
+    const INTEGER_ARITHMETIC: [&str; 8] = [
+        "fiadd", "fimul", "ficom", "ficomp", "fisub", "fisubr", "fidiv", "fidivr",
+    ];
+    let reg = context.reg as usize;
+    if !context.register_form {
+        let (mnemonic, operands) = match opcode {
+            0xd8 => (ARITHMETIC[reg], "Md"),
+            0xd9 => [
+                ("fld", "Md"),
+                ("", ""),
+                ("fst", "Md"),
+                ("fstp", "Md"),
+                ("fldenv", "M"),
+                ("fldcw", "Mw"),
+                ("fnstenv", "M"),
+                ("fnstcw", "Mw"),
+            ][reg],
+            0xda => (INTEGER_ARITHMETIC[reg], "Md"),
+            0xdb => [
+                ("fild", "Md"),
+                ("fisttp", "Md"),
+                ("fist", "Md"),
+                ("fistp", "Md"),
+                ("", ""),
+                ("fld", "Mt"),
+                ("", ""),
+                ("fstp", "Mt"),
+            ][reg],
+            0xdc => (ARITHMETIC[reg], "Mq"),
+            0xdd => [
+                ("fld", "Mq"),
+                ("fisttp", "Mq"),
+                ("fst", "Mq"),
+                ("fstp", "Mq"),
+                ("frstor", "M"),
+                ("", ""),
+                ("fnsave", "M"),
+                ("fnstsw", "Mw"),
+            ][reg],
+            0xde => (INTEGER_ARITHMETIC[reg], "Mw"),
+            _ => [
+                ("fild", "Mw"),
+                ("fisttp", "Mw"),
+                ("fist", "Mw"),
+                ("fistp", "Mw"),
+                ("fbld", "Mt"),
+                ("fild", "Mq"),
+                ("fbstp", "Mt"),
+                ("fistp", "Mq"),
+            ][reg],
+        };
+        return op_f(mnemonic, operands, X87);
+    }
+    let rm = context.rm as usize;
+    let modrm = 0xc0 | (reg << 3) | rm;
+    let op = match (opcode, reg) {
+        (0xd8, 2 | 3) => op(ARITHMETIC[reg], "STi"),
+        (0xd8, _) => op(ARITHMETIC[reg], "ST,STi"),
+        (0xd9, 0) => op("fld", "STi"),
+        (0xd9, 1) => op("fxch", "STi"),
+        (0xd9, _) => match modrm {
+            0xd0 => op("fnop", ""),
+            0xe0 => op("fchs", ""),
+            0xe1 => op("fabs", ""),
+            0xe4 => op("ftst", ""),
+            0xe5 => op("fxam", ""),
+            0xe8..=0xee => op(
+                [
+                    "fld1", "fldl2t", "fldl2e", "fldpi", "fldlg2", "fldln2", "fldz",
+                ][rm],
+                "",
+            ),
+            0xf0..=0xff => op(
+                [
+                    "f2xm1", "fyl2x", "fptan", "fpatan", "fxtract", "fprem1", "fdecstp", "fincstp", "fprem", "fyl2xp1",
+                    "fsqrt", "fsincos", "frndint", "fscale", "fsin", "fcos",
+                ][modrm - 0xf0],
+                "",
+            ),
+            _ => BAD,
+        },
+        (0xda, 0..=3) => op(["fcmovb", "fcmove", "fcmovbe", "fcmovu"][reg], "ST,STi"),
+        (0xda, _) if modrm == 0xe9 => op("fucompp", ""),
+        (0xdb, 0..=3) => op(["fcmovnb", "fcmovne", "fcmovnbe", "fcmovnu"][reg], "ST,STi"),
+        (0xdb, 4) if modrm == 0xe2 => op("fnclex", ""),
+        (0xdb, 4) if modrm == 0xe3 => op("fninit", ""),
+        (0xdb, 5) => op("fucomi", "ST,STi"),
+        (0xdb, 6) => op("fcomi", "ST,STi"),
+        (0xdc, 0 | 1) => op(ARITHMETIC[reg], "STi,ST"),
+        (0xdc, 4..=7) => op_f(
+            ["fsubr", "fsub", "fdivr", "fdiv"][reg - 4],
+            "STi,ST",
+            X87_REVERSED,
+        ),
+        (0xdd, 0) => op("ffree", "STi"),
+        (0xdd, 2) => op("fst", "STi"),
+        (0xdd, 3) => op("fstp", "STi"),
+        (0xdd, 4) => op("fucom", "STi"),
+        (0xdd, 5) => op("fucomp", "STi"),
+        (0xde, 0) => op("faddp", "STi,ST"),
+        (0xde, 1) => op("fmulp", "STi,ST"),
+        (0xde, 3) if modrm == 0xd9 => op("fcompp", ""),
+        (0xde, 4..=7) => op_f(
+            ["fsubrp", "fsubp", "fdivrp", "fdivp"][reg - 4],
+            "STi,ST",
+            X87_REVERSED,
+        ),
+        (0xdf, 0) => op("ffreep", "STi"),
+        (0xdf, 4) if modrm == 0xe0 => op("fnstsw", "AX"),
+        (0xdf, 5) => op("fucomip", "ST,STi"),
+        (0xdf, 6) => op("fcomip", "ST,STi"),
+        _ => BAD,
+    };
+    op.with(X87)
+}
+
+/// The two byte opcode map, 0F xx
+pub(super) fn two_byte(opcode: u8, context: &Context) -> Op {
+    let reg = context.reg as usize;
+    let prefix = context.prefix;
+    match opcode {
+        0x00 => match reg {
+            0..=5 => op(["sldt", "str", "lldt", "ltr", "verr", "verw"][reg], "Ew").with(NO_SUFFIX),
+            _ => BAD,
+        },
+        0x01 if context.register_form => match (reg, context.rm) {
+            (0, 1..=4) => op(
+                ["", "vmcall", "vmlaunch", "vmresume", "vmxoff"][context.rm as usize],
+                "",
+            ),
+            (1, 0..=3) => op(
+                ["monitor", "mwait", "clac", "stac"][context.rm as usize],
+                "",
+            ),
+            (2, 0) => op("xgetbv", ""),
+            (2, 1) => op("xsetbv", ""),
+            (2, 5) => op("xend", ""),
+            (2, 6) => op("xtest", ""),
+            (4, _) => op("smsw", "Rv"),
+            (5, 6) => op("rdpkru", ""),
+            (5, 7) => op("wrpkru", ""),
+            (6, _) => op("lmsw", "Rw"),
+            (7, 0) => op("swapgs", ""),
+            (7, 1) => op("rdtscp", ""),
+            _ => BAD,
+        },
+        0x01 => match reg {
+            0..=3 => op(["sgdt", "sidt", "lgdt", "lidt"][reg], "M"),
+            4 => op_f("smsw", "Ew", NO_SUFFIX),
+            6 => op_f("lmsw", "Ew", NO_SUFFIX),
+            7 => op("invlpg", "Mb"),
+            _ => BAD,
+        },
+        0x02 => op("lar", "Gv,Ew"),
+        0x03 => op("lsl", "Gv,Ew"),
+        0x05 => op("syscall", ""),
+        0x06 => op("clts", ""),
+        0x07 => op("sysret", ""),
+        0x08 => op("invd", ""),
+        0x09 => op("wbinvd", ""),
+        0x0b => op("ud2", ""),
+        0x0d if !context.register_form && reg < 2 => op(["prefetch", "prefetchw"][reg], "Mb"),
+        0x0d => op("nop", "Ev"),
+        0x10..=0x17 | 0x28..=0x2f | 0x50..=0x7f | 0xc2..=0xc6 | 0xd0..=0xff => sse(opcode, context),
+        0x18 if !context.register_form && reg < 4 => op(
+            ["prefetchnta", "prefetcht0", "prefetcht1", "prefetcht2"][reg],
+            "Mb",
+        ),
+        0x1e if prefix == 2 && context.register_form && reg == 7 && context.rm >= 2 => {
+            op(["endbr64", "endbr32"][context.rm as usize - 2], "").with(MANDATORY)
+        }
+        0x18..=0x1f => op("nop", "Ev"),
+        0x20 => op("mov", "Rq,Cq"),
+        0x21 => op("mov", "Rq,Dq"),
+        0x22 => op("mov", "Cq,Rq"),
+        0x23 => op("mov", "Dq,Rq"),
+        0x30 => op("wrmsr", ""),
+        0x31 => op("rdtsc", ""),
+        0x32 => op("rdmsr", ""),
+        0x33 => op("rdpmc", ""),
+        0x34 => op("sysenter", ""),
+        0x35 => op("sysexit", ""),
+        0x37 => op("getsec", ""),
+        0x41..=0x4b | 0x90..=0x93 | 0x98 | 0x99 if context.vex => opmask(opcode, context),
+        0x40..=0x4f => op(CMOVCC[(opcode & 0xf) as usize], "Gv,Ev"),
+        0x80..=0x8f => op_f(JCC[(opcode & 0xf) as usize], "Jz", F64 | BRANCH),
+        0x90..=0x9f => op_f(SETCC[(opcode & 0xf) as usize], "Eb", NO_SUFFIX),
+        0xa0 => op_f("push", "FS", D64),
+        0xa1 => op_f("pop", "FS", D64),
+        0xa2 => op("cpuid", ""),
+        0xa3 => op("bt", "Ev,Gv"),
+        0xa4 => op("shld", "Ev,Gv,Ib"),
+        0xa5 => op("shld", "Ev,Gv,CL"),
+        0xa8 => op_f("push", "GS", D64),
+        0xa9 => op_f("pop", "GS", D64),
+        0xaa => op("rsm", ""),
+        0xab => op("bts", "Ev,Gv"),
+        0xac => op("shrd", "Ev,Gv,Ib"),
+        0xad => op("shrd", "Ev,Gv,CL"),
+        0xae => group15(context),
+        0xaf => op("imul", "Gv,Ev"),
+        0xb0 => op("cmpxchg", "Eb,Gb"),
+        0xb1 => op("cmpxchg", "Ev,Gv"),
+        0xb2 => op("lss", "Gv,Mf"),
+        0xb3 => op("btr", "Ev,Gv"),
+        0xb4 => op("lfs", "Gv,Mf"),
+        0xb5 => op("lgs", "Gv,Mf"),
+        0xb6 => op("movzx", "Gv,Eb"),
+        0xb7 => op("movzx", "Gv,Ew"),
+        0xb8 if prefix == 2 => op("popcnt", "Gv,Ev").with(MANDATORY),
+        0xb9 => op("ud1", "Gv,Ev"),
+        0xba if reg >= 4 => op(["bt", "bts", "btr", "btc"][reg - 4], "Ev,Ib"),
+        0xbb => op("btc", "Ev,Gv"),
+        0xbc if prefix == 2 => op("tzcnt", "Gv,Ev").with(MANDATORY),
+        0xbc => op("bsf", "Gv,Ev"),
+        0xbd if prefix == 2 => op("lzcnt", "Gv,Ev").with(MANDATORY),
+        0xbd => op("bsr", "Gv,Ev"),
+        0xbe => op("movsx", "Gv,Eb"),
+        0xbf => op("movsx", "Gv,Ew"),
+        0xc0 => op("xadd", "Eb,Gb"),
+        0xc1 => op("xadd", "Ev,Gv"),
+        0xc7 => match (reg, context.register_form) {
+            (1, false) => op("cmpxchg8b|cmpxchg16b", "M"),
+            (6, true) => op("rdrand", "Rv"),
+            (7, true) if prefix == 2 => op("rdpid", "Rq").with(MANDATORY),
+            (7, true) => op("rdseed", "Rv"),
+            _ => BAD,
+        },
+        0xc8..=0xcf => op("bswap", "Zy"),
+        _ => BAD,
+    }
+}
+
+// the AVX-512 instructions on the opmask registers, which are only VEX encoded. The mandatory prefix and VEX.W pick
+// the size of the mask; w, q, b or d.
+fn opmask(opcode: u8, context: &Context) -> Op {
+    let size = match (context.prefix, context.w) {
+        (0, false) => 0,
+        (0, true) => 1,
+        (1, false) => 2,
+        (1, true) => 3,
+        _ => 4,
+    };
+    let sized = |names: [&'static str; 4], operands: &'static str| match names.get(size) {
+        Some(name) if !name.is_empty() => op_f(name, operands, VEX),
+        _ => BAD,
+    };
+    let logic = |names| {
+        if context.long_vector {
+            sized(names, "K,Kh,Kr")
+        } else {
+            BAD
+        }
+    };
+    match opcode {
+        0x41 => logic(["kandw", "kandq", "kandb", "kandd"]),
+        0x42 => logic(["kandnw", "kandnq", "kandnb", "kandnd"]),
+        0x44 => sized(["knotw", "knotq", "knotb", "knotd"], "K,Kr"),
+        0x45 => logic(["korw", "korq", "korb", "kord"]),
+        0x46 => logic(["kxnorw", "kxnorq", "kxnorb", "kxnord"]),
+        0x47 => logic(["kxorw", "kxorq", "kxorb", "kxord"]),
+        0x4a => logic(["kaddw", "kaddq", "kaddb", "kaddd"]),
+        0x4b => logic(["kunpckwd", "kunpckdq", "kunpckbw", ""]),
+        0x90 => match size {
+            0 => op_f("kmovw", "K,Kr/Mw", VEX),
+            1 => op_f("kmovq", "K,Kr/Mq", VEX),
+            2 => op_f("kmovb", "K,Kr/Mb", VEX),
+            3 => op_f("kmovd", "K,Kr/Md", VEX),
+            _ => BAD,
+        },
+        0x91 if !context.register_form => match size {
+            0 => op_f("kmovw", "Mw,K", VEX),
+            1 => op_f("kmovq", "Mq,K", VEX),
+            2 => op_f("kmovb", "Mb,K", VEX),
+            3 => op_f("kmovd", "Md,K", VEX),
+            _ => BAD,
+        },
+        // to and from general registers; F2 is d and q
+        0x92 | 0x93 => {
+            let name = match (context.prefix, context.w) {
+                (0, false) => "kmovw",
+                (1, false) => "kmovb",
+                (3, false) => "kmovd",
+                (3, true) => "kmovq",
+                _ => return BAD,
+            };
+            op_f(name, if opcode == 0x92 { "K,Ry" } else { "Gy,Kr" }, VEX)
+        }
+        0x98 => sized(["kortestw", "kortestq", "kortestb", "kortestd"], "K,Kr"),
+        0x99 => sized(["ktestw", "ktestq", "ktestb", "ktestd"], "K,Kr"),
+        _ => BAD,
+    }
+}
+
+// 0F AE; fences, the state saving and the control and status register
+fn group15(context: &Context) -> Op {
+    let reg = context.reg as usize;
+    match (context.prefix, context.register_form) {
+        _ if context.vex => match (reg, context.register_form) {
+            (2, false) => op_f("vldmxcsr", "Md", VEX),
+            (3, false) => op_f("vstmxcsr", "Md", VEX),
+            _ => BAD,
+        },
+        (0, false) => [
+            op("fxsave|fxsave64", "M"),
+            op("fxrstor|fxrstor64", "M"),
+            op("ldmxcsr", "Md"),
+            op("stmxcsr", "Md"),
+            op("xsave|xsave64", "M"),
+            op("xrstor|xrstor64", "M"),
+            op("xsaveopt|xsaveopt64", "M"),
+            op("clflush", "Mb"),
+        ][reg],
+        (0, true) => match reg {
+            5 => op("lfence", ""),
+            6 => op("mfence", ""),
+            7 => op("sfence", ""),
+            _ => BAD,
+        },
+        (1, false) if reg == 6 => op("clwb", "Mb").with(MANDATORY),
+        (1, false) if reg == 7 => op("clflushopt", "Mb").with(MANDATORY),
+        (2, true) if reg < 4 => op(["rdfsbase", "rdgsbase", "wrfsbase", "wrgsbase"][reg], "Ry").with(MANDATORY),
+        _ => BAD,
+    }
+}
+
+// the SSE part of the two byte map, which is picked by the mandatory prefix; MMX without one, SSE with
+fn sse(opcode: u8, context: &Context) -> Op {
+    // the integer instructions that are both MMX and SSE2
+    const INTEGER: [&str; 16] = [
+        "punpcklbw",
+        "punpcklwd",
+        "punpckldq",
+        "packsswb",
+        "pcmpgtb",
+        "pcmpgtw",
+        "pcmpgtd",
+        "packuswb",
+        "punpckhbw",
+        "punpckhwd",
+        "punpckhdq",
+        "packssdw",
+        "",
+        "",
+        "",
+        "",
+    ];
+    const INTEGER_D: [&str; 16] = [
+        "", "psrlw", "psrld", "psrlq", "paddq", "pmullw", "", "", "psubusb", "psubusw", "pminub", "pand", "paddusb",
+        "paddusw", "pmaxub", "pandn",
+    ];
+    const INTEGER_E: [&str; 16] = [
+        "pavgb", "psraw", "psrad", "pavgw", "pmulhuw", "pmulhw", "", "", "psubsb", "psubsw", "pminsw", "por", "paddsb",
+        "paddsw", "pmaxsw", "pxor",
+    ];
+    const INTEGER_F: [&str; 16] = [
+        "", "psllw", "pslld", "psllq", "pmuludq", "pmaddwd", "psadbw", "", "psubb", "psubw", "psubd", "psubq", "paddb",
+        "paddw", "paddd", "",
+    ];
+    // packed single, packed double, scalar single and scalar double, by the low nibble of the opcode
+    const ARITHMETIC: [[&str; 4]; 16] = [
+        ["", "", "", ""],
+        ["sqrtps", "sqrtpd", "sqrtss", "sqrtsd"],
+        ["rsqrtps", "", "rsqrtss", ""],
+        ["rcpps", "", "rcpss", ""],
+        ["andps", "andpd", "", ""],
+        ["andnps", "andnpd", "", ""],
+        ["orps", "orpd", "", ""],
+        ["xorps", "xorpd", "", ""],
+        ["addps", "addpd", "addss", "addsd"],
+        ["mulps", "mulpd", "mulss", "mulsd"],
+        ["", "", "", ""],
+        ["", "", "", ""],
+        ["subps", "subpd", "subss", "subsd"],
+        ["minps", "minpd", "minss", "minsd"],
+        ["divps", "divpd", "divss", "divsd"],
+        ["maxps", "maxpd", "maxss", "maxsd"],
+    ];
+
+    let reg = context.reg as usize;
+    let arithmetic = |index: usize| -> Op {
+        let names = ARITHMETIC[index];
+        // sqrt, rsqrt and rcp have one source
+        let (single, double) = if index <= 3 {
+            ("Vps,Wps", "Vpd,Wpd")
+        } else {
+            ("Vps,Hps,Wps", "Vpd,Hpd,Wpd")
+        };
+        let column = |name: &'static str, operands| {
+            if name.is_empty() {
+                BAD
+            } else {
+                op(name, operands)
+            }
+        };
+        by_prefix(
+            context,
+            [
+                column(names[0], single),
+                column(names[1], double),
+                column(names[2], "Vss,Hss,Wss"),
+                column(names[3], "Vsd,Hsd,Wsd"),
+            ],
+        )
+    };
+    // MMX without a prefix, SSE2 with 66
+    let integer = |name: &'static str| -> Op {
+        if name.is_empty() {
+            return BAD;
+        }
+        by_prefix(context, [op(name, "Pq,Qq"), op(name, "Vx,Hx,Wx"), BAD, BAD])
+    };
+
+    match opcode {
+        0x10 => by_prefix(
+            context,
+            [
+                op("movups", "Vps,Wps"),
+                op("movupd", "Vpd,Wpd"),
+                op("movss", "Vx,Hr,Wss"),
+                op("movsd", "Vx,Hr,Wsd"),
+            ],
+        ),
+        0x11 => by_prefix(
+            context,
+            [
+                op("movups", "Wps,Vps"),
+                op("movupd", "Wpd,Vpd"),
+                op("movss", "Wss,Hr,Vss"),
+                op("movsd", "Wsd,Hr,Vsd"),
+            ],
+        ),
+        0x12 => by_prefix(
+            context,
+            [
+                by_form(context, op("movlps", "Vq,Hq,Mq"), op("movhlps", "Vq,Hq,Uq")),
+                op("movlpd", "Vq,Hq,Mq"),
+                op("movsldup", "Vx,Wx"),
+                op("movddup", "Vx,Wx"),
+            ],
+        ),
+        0x13 => by_prefix(
+            context,
+            [op("movlps", "Mq,Vq"), op("movlpd", "Mq,Vq"), BAD, BAD],
+        ),
+        0x14 => by_prefix(
+            context,
+            [
+                op("unpcklps", "Vx,Hx,Wx"),
+                op("unpcklpd", "Vx,Hx,Wx"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0x15 => by_prefix(
+            context,
+            [
+                op("unpckhps", "Vx,Hx,Wx"),
+                op("unpckhpd", "Vx,Hx,Wx"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0x16 => by_prefix(
+            context,
+            [
+                by_form(
+                    context,
+                    op("movhps", "Vdq,Hq,Mq"),
+                    op("movlhps", "Vdq,Hq,Uq"),
+                ),
+                op("movhpd", "Vdq,Hq,Mq"),
+                op("movshdup", "Vx,Wx"),
+                BAD,
+            ],
+        ),
+        0x17 => by_prefix(
+            context,
+            [op("movhps", "Mq,Vq"), op("movhpd", "Mq,Vq"), BAD, BAD],
+        ),
+        0x28 => by_prefix(
+            context,
+            [op("movaps", "Vps,Wps"), op("movapd", "Vpd,Wpd"), BAD, BAD],
+        ),
+        0x29 => by_prefix(
+            context,
+            [op("movaps", "Wps,Vps"), op("movapd", "Wpd,Vpd"), BAD, BAD],
+        ),
+        0x2a => by_prefix(
+            context,
+            [
+                op_f("cvtpi2ps", "Vps,Qq", NO_VEX),
+                op_f("cvtpi2pd", "Vpd,Qq", NO_VEX),
+                op_f("cvtsi2ss", "Vss,Hss,Ey", SUFFIX),
+                op_f("cvtsi2sd", "Vsd,Hsd,Ey", SUFFIX),
+            ],
+        ),
+        0x2b => by_prefix(
+            context,
+            [op("movntps", "Mx,Vps"), op("movntpd", "Mx,Vpd"), BAD, BAD],
+        ),
+        0x2c => by_prefix(
+            context,
+            [
+                op_f("cvttps2pi", "Pq,Wq", NO_VEX),
+                op_f("cvttpd2pi", "Pq,Wpd", NO_VEX),
+                op("cvttss2si", "Gy,Wss"),
+                op("cvttsd2si", "Gy,Wsd"),
+            ],
+        ),
+        0x2d => by_prefix(
+            context,
+            [
+                op_f("cvtps2pi", "Pq,Wq", NO_VEX),
+                op_f("cvtpd2pi", "Pq,Wpd", NO_VEX),
+                op("cvtss2si", "Gy,Wss"),
+                op("cvtsd2si", "Gy,Wsd"),
+            ],
+        ),
+        0x2e => by_prefix(
+            context,
+            [op("ucomiss", "Vss,Wss"), op("ucomisd", "Vsd,Wsd"), BAD, BAD],
+        ),
+        0x2f => by_prefix(
+            context,
+            [op("comiss", "Vss,Wss"), op("comisd", "Vsd,Wsd"), BAD, BAD],
+        ),
+        0x50 if context.register_form => by_prefix(
+            context,
+            [op("movmskps", "Gd,Ux"), op("movmskpd", "Gd,Ux"), BAD, BAD],
+        ),
+        0x51..=0x59 | 0x5c..=0x5f => arithmetic((opcode & 0xf) as usize),
+        0x5a => by_prefix(
+            context,
+            [
+                op("cvtps2pd", "Vx,Wxh"),
+                op("cvtpd2ps", "Vxh,Wx"),
+                op("cvtss2sd", "Vsd,Hx,Wss"),
+                op("cvtsd2ss", "Vss,Hx,Wsd"),
+            ],
+        ),
+        0x5b => by_prefix(
+            context,
+            [
+                op("cvtdq2ps", "Vx,Wx"),
+                op("cvtps2dq", "Vx,Wx"),
+                op("cvttps2dq", "Vx,Wx"),
+                BAD,
+            ],
+        ),
+        // the EVEX comparisons set opmask registers
+        0x64..=0x66 | 0x74..=0x76 if context.evex => {
+            let name = [
+                "vpcmpgtb", "vpcmpgtw", "vpcmpgtd", "vpcmpeqb", "vpcmpeqw", "vpcmpeqd",
+            ][(opcode & 3) as usize + if opcode >= 0x74 { 3 } else { 0 }];
+            by_prefix(context, [BAD, op_f(name, "K,Hx,Wx", VEX), BAD, BAD])
+        }
+        0x60..=0x6b => integer(INTEGER[(opcode & 0xf) as usize]),
+        0x6c => by_prefix(context, [BAD, op("punpcklqdq", "Vx,Hx,Wx"), BAD, BAD]),
+        0x6d => by_prefix(context, [BAD, op("punpckhqdq", "Vx,Hx,Wx"), BAD, BAD]),
+        0x6e => by_prefix(
+            context,
+            [
+                op("movd|movq", "Pq,Ey"),
+                op("movd|movq", "Vdq,Ey"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0x6f if context.evex => by_prefix(
+            context,
+            [
+                BAD,
+                op_f("vmovdqa32|vmovdqa64", "Vx,Wx", VEX),
+                op_f("vmovdqu32|vmovdqu64", "Vx,Wx", VEX),
+                op_f("vmovdqu8|vmovdqu16", "Vx,Wx", VEX),
+            ],
+        ),
+        0x6f => by_prefix(
+            context,
+            [
+                op("movq", "Pq,Qq"),
+                op("movdqa", "Vx,Wx"),
+                op("movdqu", "Vx,Wx"),
+                BAD,
+            ],
+        ),
+        0x70 => by_prefix(
+            context,
+            [
+                op("pshufw", "Pq,Qq,Ib"),
+                op("pshufd", "Vx,Wx,Ib"),
+                op("pshufhw", "Vx,Wx,Ib"),
+                op("pshuflw", "Vx,Wx,Ib"),
+            ],
+        ),
+        // shifts by an immediate
+        0x71..=0x73 if context.register_form => {
+            let name = match (opcode, reg) {
+                (0x71, 2) => "psrlw",
+                (0x71, 4) => "psraw",
+                (0x71, 6) => "psllw",
+                (0x72, 0) if context.evex => "prord|prorq",
+                (0x72, 1) if context.evex => "prold|prolq",
+                (0x72, 2) => "psrld",
+                (0x72, 4) => "psrad",
+                (0x72, 6) => "pslld",
+                (0x73, 2) => "psrlq",
+                (0x73, 6) => "psllq",
+                (0x73, 3) if context.prefix == 1 => "psrldq",
+                (0x73, 7) if context.prefix == 1 => "pslldq",
+                _ => return BAD,
+            };
+            by_prefix(context, [op(name, "Nq,Ib"), op(name, "Hx,Ux,Ib"), BAD, BAD])
+        }
+        0x74..=0x76 => integer(["pcmpeqb", "pcmpeqw", "pcmpeqd"][(opcode - 0x74) as usize]),
+        0x77 if context.vex && context.prefix == 0 => op_f(
+            if context.long_vector {
+                "vzeroall"
+            } else {
+                "vzeroupper"
+            },
+            "",
+            VEX,
+        ),
+        0x77 if context.prefix == 0 => op("emms", ""),
+        0x7c => by_prefix(
+            context,
+            [BAD, op("haddpd", "Vx,Hx,Wx"), BAD, op("haddps", "Vx,Hx,Wx")],
+        ),
+        0x7d => by_prefix(
+            context,
+            [BAD, op("hsubpd", "Vx,Hx,Wx"), BAD, op("hsubps", "Vx,Hx,Wx")],
+        ),
+        0x7e => by_prefix(
+            context,
+            [
+                op("movd|movq", "Ey,Pq"),
+                op("movd|movq", "Ey,Vdq"),
+                op("movq", "Vdq,Wq"),
+                BAD,
+            ],
+        ),
+        0x7f if context.evex => by_prefix(
+            context,
+            [
+                BAD,
+                op_f("vmovdqa32|vmovdqa64", "Wx,Vx", VEX),
+                op_f("vmovdqu32|vmovdqu64", "Wx,Vx", VEX),
+                op_f("vmovdqu8|vmovdqu16", "Wx,Vx", VEX),
+            ],
+        ),
+        0x7f => by_prefix(
+            context,
+            [
+                op("movq", "Qq,Pq"),
+                op("movdqa", "Wx,Vx"),
+                op("movdqu", "Wx,Vx"),
+                BAD,
+            ],
+        ),
+        0xc2 => by_prefix(
+            context,
+            [
+                op("cmpps", "Vps,Hps,Wps,Ib"),
+                op("cmppd", "Vpd,Hpd,Wpd,Ib"),
+                op("cmpss", "Vss,Hss,Wss,Ib"),
+                op("cmpsd", "Vsd,Hsd,Wsd,Ib"),
+            ],
+        )
+        .with(PREDICATE),
+        0xc3 if !context.register_form && context.prefix == 0 => op("movnti", "My,Gy"),
+        0xc4 => by_prefix(
+            context,
+            [
+                op("pinsrw", "Pq,Rd/Mw,Ib"),
+                op("pinsrw", "Vdq,Hdq,Rd/Mw,Ib"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0xc5 if context.register_form => by_prefix(
+            context,
+            [
+                op("pextrw", "Gd,Nq,Ib"),
+                op("pextrw", "Gd,Udq,Ib"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0xc6 => by_prefix(
+            context,
+            [
+                op("shufps", "Vps,Hps,Wps,Ib"),
+                op("shufpd", "Vpd,Hpd,Wpd,Ib"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0xd0 => by_prefix(
+            context,
+            [
+                BAD,
+                op("addsubpd", "Vx,Hx,Wx"),
+                BAD,
+                op("addsubps", "Vx,Hx,Wx"),
+            ],
+        ),
+        0xd6 => by_prefix(
+            context,
+            [
+                BAD,
+                op("movq", "Wq,Vq"),
+                op_f("movq2dq", "Vdq,Nq", NO_VEX),
+                op_f("movdq2q", "Pq,Uq", NO_VEX),
+            ],
+        ),
+        0xd7 if context.register_form => by_prefix(
+            context,
+            [op("pmovmskb", "Gd,Nq"), op("pmovmskb", "Gd,Ux"), BAD, BAD],
+        ),
+        // the EVEX forms of the bitwise instructions have an element size
+        0xdb | 0xdf | 0xeb | 0xef if context.evex => {
+            let name = match opcode {
+                0xdb => "vpandd|vpandq",
+                0xdf => "vpandnd|vpandnq",
+                0xeb => "vpord|vporq",
+                _ => "vpxord|vpxorq",
+            };
+            by_prefix(context, [BAD, op_f(name, "Vx,Hx,Wx", VEX), BAD, BAD])
+        }
+        0xd1..=0xdf => integer(INTEGER_D[(opcode & 0xf) as usize]),
+        0xe6 => by_prefix(
+            context,
+            [
+                BAD,
+                op("cvttpd2dq", "Vxh,Wx"),
+                op("cvtdq2pd", "Vx,Wxh"),
+                op("cvtpd2dq", "Vxh,Wx"),
+            ],
+        ),
+        0xe7 if !context.register_form => by_prefix(
+            context,
+            [op("movntq", "Mq,Pq"), op("movntdq", "Mx,Vx"), BAD, BAD],
+        ),
+        0xe0..=0xef => integer(INTEGER_E[(opcode & 0xf) as usize]),
+        0xf0 if !context.register_form => by_prefix(context, [BAD, BAD, BAD, op("lddqu", "Vx,Mx")]),
+        0xf7 if context.register_form => by_prefix(
+            context,
+            [
+                op("maskmovq", "Pq,Nq"),
+                op("maskmovdqu", "Vdq,Udq"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0xf1..=0xfe => integer(INTEGER_F[(opcode & 0xf) as usize]),
+        _ => BAD,
+    }
+}
+
+/// The three byte opcode map 0F 38; all of it but movbe and crc32 is SSE, or only VEX encoded
+pub(super) fn three_byte_38(opcode: u8, context: &Context) -> Op {
+    const SSSE3: [&str; 12] = [
+        "pshufb",
+        "phaddw",
+        "phaddd",
+        "phaddsw",
+        "pmaddubsw",
+        "phsubw",
+        "phsubd",
+        "phsubsw",
+        "psignb",
+        "psignw",
+        "psignd",
+        "pmulhrsw",
+    ];
+    // sign and zero extension, by the low nibble of the opcode; the source is a fraction of the destination
+    const EXTEND: [(&str, &str, &str); 6] = [
+        ("pmovsxbw", "pmovzxbw", "Vx,Wxh"),
+        ("pmovsxbd", "pmovzxbd", "Vx,Wxq"),
+        ("pmovsxbq", "pmovzxbq", "Vx,Wxo"),
+        ("pmovsxwd", "pmovzxwd", "Vx,Wxh"),
+        ("pmovsxwq", "pmovzxwq", "Vx,Wxq"),
+        ("pmovsxdq", "pmovzxdq", "Vx,Wxh"),
+    ];
+    const MIN_MAX: [&str; 9] = [
+        "pminsb", "pminsd", "pminuw", "pminud", "pmaxsb", "pmaxsd", "pmaxuw", "pmaxud", "pmulld",
+    ];
+    const AES: [&str; 5] = ["aesimc", "aesenc", "aesenclast", "aesdec", "aesdeclast"];
+    // with 66; the SSE4 ones
+    let sse =
+        |mnemonic: &'static str, operands: &'static str| by_prefix(context, [BAD, op(mnemonic, operands), BAD, BAD]);
+    // with 66, and only VEX encoded
+    let avx = |mnemonic: &'static str, operands: &'static str| {
+        if context.vex {
+            by_prefix(context, [BAD, op_f(mnemonic, operands, VEX), BAD, BAD])
+        } else {
+            BAD
+        }
+    };
+    // with 66, and only EVEX encoded
+    let avx512 = |mnemonic: &'static str, operands: &'static str| {
+        if context.evex {
+            avx(mnemonic, operands)
+        } else {
+            BAD
+        }
+    };
+    // without a prefix, and only legacy encoded
+    let sha = |mnemonic: &'static str, operands: &'static str| {
+        by_prefix(context, [op_f(mnemonic, operands, NO_VEX), BAD, BAD, BAD])
+    };
+    match opcode {
+        0x00..=0x0b => {
+            let name = SSSE3[opcode as usize];
+            by_prefix(context, [op(name, "Pq,Qq"), op(name, "Vx,Hx,Wx"), BAD, BAD])
+        }
+        0x10 if !context.vex => sse("pblendvb", "Vdq,Wdq,X0").with(NO_VEX),
+        0x14 if !context.vex => sse("blendvps", "Vdq,Wdq,X0").with(NO_VEX),
+        0x15 if !context.vex => sse("blendvpd", "Vdq,Wdq,X0").with(NO_VEX),
+        0x16 => avx("vpermps", "Vqq,Hqq,Wqq"),
+        0x17 => sse("ptest", "Vx,Wx"),
+        0x18 => avx("vbroadcastss", "Vx,Wd"),
+        0x19 => avx("vbroadcastsd", "Vqq,Wq"),
+        0x1a if !context.register_form => avx("vbroadcastf128", "Vqq,Mdq"),
+        0x1c..=0x1e => {
+            let name = ["pabsb", "pabsw", "pabsd"][(opcode - 0x1c) as usize];
+            by_prefix(context, [op(name, "Pq,Qq"), op(name, "Vx,Wx"), BAD, BAD])
+        }
+        0x26 | 0x27 if context.evex => {
+            let (test, not) = if opcode == 0x26 {
+                ("vptestmb|vptestmw", "vptestnmb|vptestnmw")
+            } else {
+                ("vptestmd|vptestmq", "vptestnmd|vptestnmq")
+            };
+            by_prefix(
+                context,
+                [
+                    BAD,
+                    op_f(test, "K,Hx,Wx", VEX),
+                    op_f(not, "K,Hx,Wx", VEX),
+                    BAD,
+                ],
+            )
+        }
+        0x29 if context.evex => avx("vpcmpeqq", "K,Hx,Wx"),
+        0x37 if context.evex => avx("vpcmpgtq", "K,Hx,Wx"),
+        0x20..=0x25 | 0x30..=0x35 => {
+            let (signed, unsigned, operands) = EXTEND[(opcode & 0xf) as usize];
+            sse(if opcode < 0x30 { signed } else { unsigned }, operands)
+        }
+        0x28 => sse("pmuldq", "Vx,Hx,Wx"),
+        0x29 => sse("pcmpeqq", "Vx,Hx,Wx"),
+        0x2a if !context.register_form => sse("movntdqa", "Vx,Mx"),
+        0x2b => sse("packusdw", "Vx,Hx,Wx"),
+        0x2c if !context.register_form => avx("vmaskmovps", "Vx,Hx,Mx"),
+        0x2d if !context.register_form => avx("vmaskmovpd", "Vx,Hx,Mx"),
+        0x2e if !context.register_form => avx("vmaskmovps", "Mx,Hx,Vx"),
+        0x2f if !context.register_form => avx("vmaskmovpd", "Mx,Hx,Vx"),
+        0x36 => avx("vpermd", "Vqq,Hqq,Wqq"),
+        0x37 => sse("pcmpgtq", "Vx,Hx,Wx"),
+        0x38..=0x40 => sse(MIN_MAX[(opcode - 0x38) as usize], "Vx,Hx,Wx"),
+        0x41 => sse("phminposuw", "Vdq,Wdq"),
+        0x45 => avx("vpsrlvd|vpsrlvq", "Vx,Hx,Wx"),
+        0x46 => avx("vpsravd", "Vx,Hx,Wx"),
+        0x47 => avx("vpsllvd|vpsllvq", "Vx,Hx,Wx"),
+        0x58 => avx("vpbroadcastd", "Vx,Wd"),
+        0x59 => avx("vpbroadcastq", "Vx,Wq"),
+        0x5a if !context.register_form => avx("vbroadcasti128", "Vqq,Mdq"),
+        0x78 => avx("vpbroadcastb", "Vx,Wb"),
+        0x79 => avx("vpbroadcastw", "Vx,Ww"),
+        0x7a => avx512("vpbroadcastb", "Vx,Rd"),
+        0x7b => avx512("vpbroadcastw", "Vx,Rd"),
+        0x7c => avx512("vpbroadcastd|vpbroadcastq", "Vx,Ry"),
+        0x8c if !context.register_form => avx("vpmaskmovd|vpmaskmovq", "Vx,Hx,Mx"),
+        0x8e if !context.register_form => avx("vpmaskmovd|vpmaskmovq", "Mx,Hx,Vx"),
+        0x96..=0x9f | 0xa6..=0xaf | 0xb6..=0xbf => fused_multiply_add(opcode, context),
+        0xb4 if context.w => avx512("vpmadd52luq", "Vx,Hx,Wx"),
+        0xb5 if context.w => avx512("vpmadd52huq", "Vx,Hx,Wx"),
+        0xc8 => sha("sha1nexte", "Vdq,Wdq"),
+        0xc9 => sha("sha1msg1", "Vdq,Wdq"),
+        0xca => sha("sha1msg2", "Vdq,Wdq"),
+        0xcb => sha("sha256rnds2", "Vdq,Wdq,X0"),
+        0xcc => sha("sha256msg1", "Vdq,Wdq"),
+        0xcd => sha("sha256msg2", "Vdq,Wdq"),
+        0xdb..=0xdf => sse(
+            AES[(opcode - 0xdb) as usize],
+            if opcode == 0xdb {
+                "Vdq,Wdq"
+            } else {
+                "Vdq,Hdq,Wdq"
+            },
+        ),
+        0xf0 | 0xf1 if !context.vex => {
+            let (movbe, crc32) = if opcode == 0xf0 {
+                (op("movbe", "Gv,Mv"), op("crc32", "Gy,Eb"))
+            } else {
+                (op("movbe", "Mv,Gv"), op("crc32", "Gy,Ev"))
+            };
+            match context.prefix {
+                0 if !context.register_form => movbe,
+                1 if !context.register_form => movbe,
+                3 => crc32.with(MANDATORY),
+                _ => BAD,
+            }
+        }
+        // BMI; general purpose instructions that are only VEX encoded
+        0xf2 if context.vex && context.prefix == 0 => op_f("andn", "Gy,By,Ey", VEX),
+        0xf3 if context.vex && context.prefix == 0 && (1..=3).contains(&context.reg) => op_f(
+            ["", "blsr", "blsmsk", "blsi"][context.reg as usize],
+            "By,Ey",
+            VEX,
+        ),
+        0xf5 if context.vex => by_prefix(
+            context,
+            [
+                op_f("bzhi", "Gy,Ey,By", VEX),
+                BAD,
+                op_f("pext", "Gy,By,Ey", VEX),
+                op_f("pdep", "Gy,By,Ey", VEX),
+            ],
+        ),
+        0xf6 if context.vex => by_prefix(context, [BAD, BAD, BAD, op_f("mulx", "Gy,By,Ey", VEX)]),
+        0xf6 => by_prefix(
+            context,
+            [BAD, op("adcx", "Gy,Ey"), op("adox", "Gy,Ey"), BAD],
+        ),
+        0xf7 if context.vex => by_prefix(
+            context,
+            [
+                op_f("bextr", "Gy,Ey,By", VEX),
+                op_f("shlx", "Gy,Ey,By", VEX),
+                op_f("sarx", "Gy,Ey,By", VEX),
+                op_f("shrx", "Gy,Ey,By", VEX),
+            ],
+        ),
+        _ => BAD,
+    }
+}
+
+// vfmadd132ps and it's many relatives. The high nibble of the opcode picks the order of the operands, the low one
+// the operation and whether it's packed, and VEX.W the precision.
+fn fused_multiply_add(opcode: u8, context: &Context) -> Op {
+    const ORDERS: [[&str; 10]; 3] = [
+        [
+            "vfmaddsub132ps|vfmaddsub132pd",
+            "vfmsubadd132ps|vfmsubadd132pd",
+            "vfmadd132ps|vfmadd132pd",
+            "vfmadd132ss|vfmadd132sd",
+            "vfmsub132ps|vfmsub132pd",
+            "vfmsub132ss|vfmsub132sd",
+            "vfnmadd132ps|vfnmadd132pd",
+            "vfnmadd132ss|vfnmadd132sd",
+            "vfnmsub132ps|vfnmsub132pd",
+            "vfnmsub132ss|vfnmsub132sd",
+        ],
+        [
+            "vfmaddsub213ps|vfmaddsub213pd",
+            "vfmsubadd213ps|vfmsubadd213pd",
+            "vfmadd213ps|vfmadd213pd",
+            "vfmadd213ss|vfmadd213sd",
+            "vfmsub213ps|vfmsub213pd",
+            "vfmsub213ss|vfmsub213sd",
+            "vfnmadd213ps|vfnmadd213pd",
+            "vfnmadd213ss|vfnmadd213sd",
+            "vfnmsub213ps|vfnmsub213pd",
+            "vfnmsub213ss|vfnmsub213sd",
+        ],
+        [
+            "vfmaddsub231ps|vfmaddsub231pd",
+            "vfmsubadd231ps|vfmsubadd231pd",
+            "vfmadd231ps|vfmadd231pd",
+            "vfmadd231ss|vfmadd231sd",
+            "vfmsub231ps|vfmsub231pd",
+            "vfmsub231ss|vfmsub231sd",
+            "vfnmadd231ps|vfnmadd231pd",
+            "vfnmadd231ss|vfnmadd231sd",
+            "vfnmsub231ps|vfnmsub231pd",
+            "vfnmsub231ss|vfnmsub231sd",
+        ],
+    ];
+    if !context.vex || context.prefix != 1 {
+        return BAD;
+    }
+    let index = (opcode & 0xf) as usize - 6;
+    let name = ORDERS[(opcode >> 4) as usize - 9][index];
+    let operands = match (index, context.w) {
+        (0 | 1 | 2 | 4 | 6 | 8, _) => "Vx,Hx,Wx",
+        (_, false) => "Vss,Hss,Wss",
+        (_, true) => "Vsd,Hsd,Wsd",
+    };
+    op_f(name, operands, VEX | MANDATORY)
+}
+
+/// The three byte opcode map 0F 3A; all of it takes an immediate
+pub(super) fn three_byte_3a(opcode: u8, context: &Context) -> Op {
+    let sse =
+        |mnemonic: &'static str, operands: &'static str| by_prefix(context, [BAD, op(mnemonic, operands), BAD, BAD]);
+    let avx = |mnemonic: &'static str, operands: &'static str| {
+        if context.vex {
+            by_prefix(context, [BAD, op_f(mnemonic, operands, VEX), BAD, BAD])
+        } else {
+            BAD
+        }
+    };
+    let avx512 = |mnemonic: &'static str, operands: &'static str| {
+        if context.evex {
+            avx(mnemonic, operands)
+        } else {
+            BAD
+        }
+    };
+    match opcode {
+        0x00 => avx("vpermq", "Vqq,Wqq,Ib"),
+        0x01 => avx("vpermpd", "Vqq,Wqq,Ib"),
+        0x02 => avx("vpblendd", "Vx,Hx,Wx,Ib"),
+        0x03 => avx512("valignd|valignq", "Vx,Hx,Wx,Ib"),
+        0x04 => avx("vpermilps", "Vx,Wx,Ib"),
+        0x05 => avx("vpermilpd", "Vx,Wx,Ib"),
+        0x06 => avx("vperm2f128", "Vqq,Hqq,Wqq,Ib"),
+        0x08 => sse("roundps", "Vx,Wx,Ib"),
+        0x09 => sse("roundpd", "Vx,Wx,Ib"),
+        0x0a => sse("roundss", "Vss,Hss,Wss,Ib"),
+        0x0b => sse("roundsd", "Vsd,Hsd,Wsd,Ib"),
+        0x0c => sse("blendps", "Vx,Hx,Wx,Ib"),
+        0x0d => sse("blendpd", "Vx,Hx,Wx,Ib"),
+        0x0e => sse("pblendw", "Vx,Hx,Wx,Ib"),
+        0x0f => by_prefix(
+            context,
+            [
+                op("palignr", "Pq,Qq,Ib"),
+                op("palignr", "Vx,Hx,Wx,Ib"),
+                BAD,
+                BAD,
+            ],
+        ),
+        0x14 => sse("pextrb", "Rd/Mb,Vdq,Ib"),
+        0x15 => sse("pextrw", "Rd/Mw,Vdq,Ib"),
+        0x16 => sse("pextrd|pextrq", "Ey,Vdq,Ib"),
+        0x17 => sse("extractps", "Ed,Vdq,Ib"),
+        0x18 => avx("vinsertf128", "Vqq,Hqq,Wdq,Ib"),
+        0x19 => avx("vextractf128", "Wdq,Vqq,Ib"),
+        0x1d => avx("vcvtps2ph", "Wxh,Vx,Ib"),
+        0x1e => avx512("vpcmpud|vpcmpuq", "K,Hx,Wx,Ib").with(INTEGER_PREDICATE),
+        0x1f => avx512("vpcmpd|vpcmpq", "K,Hx,Wx,Ib").with(INTEGER_PREDICATE),
+        0x20 => sse("pinsrb", "Vdq,Hdq,Rd/Mb,Ib"),
+        0x21 => sse("insertps", "Vdq,Hdq,Wd,Ib"),
+        0x22 => sse("pinsrd|pinsrq", "Vdq,Hdq,Ey,Ib"),
+        0x23 => avx512("vshuff32x4|vshuff64x2", "Vx,Hx,Wx,Ib"),
+        0x25 => avx512("vpternlogd|vpternlogq", "Vx,Hx,Wx,Ib"),
+        0x38 => avx("vinserti128", "Vqq,Hqq,Wdq,Ib"),
+        0x39 => avx("vextracti128", "Wdq,Vqq,Ib"),
+        0x3e => avx512("vpcmpub|vpcmpuw", "K,Hx,Wx,Ib").with(INTEGER_PREDICATE),
+        0x3f => avx512("vpcmpb|vpcmpw", "K,Hx,Wx,Ib").with(INTEGER_PREDICATE),
+        0x40 => sse("dpps", "Vx,Hx,Wx,Ib"),
+        0x41 => sse("dppd", "Vdq,Hdq,Wdq,Ib"),
+        0x42 => sse("mpsadbw", "Vx,Hx,Wx,Ib"),
+        0x44 => sse("pclmulqdq", "Vdq,Hdq,Wdq,Ib"),
+        0x43 => avx512("vshufi32x4|vshufi64x2", "Vx,Hx,Wx,Ib"),
+        0x46 => avx("vperm2i128", "Vqq,Hqq,Wqq,Ib"),
+        0x4a => avx("vblendvps", "Vx,Hx,Wx,Lx"),
+        0x4b => avx("vblendvpd", "Vx,Hx,Wx,Lx"),
+        0x4c => avx("vpblendvb", "Vx,Hx,Wx,Lx"),
+        0x5c..=0x5f | 0x68..=0x6f | 0x78..=0x7f if context.vex => four_operand_fma(opcode, context),
+        0x60..=0x63 => sse(
+            ["pcmpestrm", "pcmpestri", "pcmpistrm", "pcmpistri"][(opcode - 0x60) as usize],
+            "Vdq,Wdq,Ib",
+        ),
+        0xcc => by_prefix(
+            context,
+            [op_f("sha1rnds4", "Vdq,Wdq,Ib", NO_VEX), BAD, BAD, BAD],
+        ),
+        0xdf => sse("aeskeygenassist", "Vdq,Wdq,Ib"),
+        0xf0 if context.vex && context.prefix == 3 => op_f("rorx", "Gy,Ey,Ib", VEX | MANDATORY),
+        _ => BAD,
+    }
+}
+
+// AMD's FMA4, vfmaddps and friends with four operands. VEX.W swaps the operand in r/m with the one in the immediate.
+fn four_operand_fma(opcode: u8, context: &Context) -> Op {
+    const NAMES: [&str; 16] = [
+        "vfmaddps",
+        "vfmaddpd",
+        "vfmaddss",
+        "vfmaddsd",
+        "vfmsubps",
+        "vfmsubpd",
+        "vfmsubss",
+        "vfmsubsd",
+        "vfnmaddps",
+        "vfnmaddpd",
+        "vfnmaddss",
+        "vfnmaddsd",
+        "vfnmsubps",
+        "vfnmsubpd",
+        "vfnmsubss",
+        "vfnmsubsd",
+    ];
+    const ALTERNATING: [&str; 4] = ["vfmaddsubps", "vfmaddsubpd", "vfmsubaddps", "vfmsubaddpd"];
+    if context.prefix != 1 {
+        return BAD;
+    }
+    let name = match opcode {
+        0x5c..=0x5f => ALTERNATING[(opcode - 0x5c) as usize],
+        0x68..=0x6f => NAMES[(opcode - 0x68) as usize],
+        _ => NAMES[(opcode - 0x70) as usize],
+    };
+    let operands = match (&name[name.len() - 2..], context.w) {
+        ("ss", false) => "Vdq,Hdq,Wss,Ldq",
+        ("ss", true) => "Vdq,Hdq,Ldq,Wss",
+        ("sd", false) => "Vdq,Hdq,Wsd,Ldq",
+        ("sd", true) => "Vdq,Hdq,Ldq,Wsd",
+        (_, false) => "Vx,Hx,Wx,Lx",
+        (_, true) => "Vx,Hx,Lx,Wx",
+    };
+    op_f(name, operands, VEX | MANDATORY)
+}
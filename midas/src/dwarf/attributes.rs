@@ -515,6 +515,16 @@ pub enum Attribute {
     DW_AT_defaulted = 0x8b,
     DW_AT_loclists_base = 0x8c,
     DW_AT_lo_user = 0x2000,
+    // GNU extensions that gcc emits, even for DWARF 4 and 5
+    DW_AT_GNU_call_site_value = 0x2111,
+    DW_AT_GNU_call_site_target = 0x2113,
+    DW_AT_GNU_tail_call = 0x2115,
+    DW_AT_GNU_all_tail_call_sites = 0x2116,
+    DW_AT_GNU_all_call_sites = 0x2117,
+    DW_AT_GNU_macros = 0x2119,
+    DW_AT_GNU_pubnames = 0x2134,
+    DW_AT_GNU_locviews = 0x2137,
+    DW_AT_GNU_entry_view = 0x2138,
     DW_AT_hi_user = 0x3fff,
 }
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -1,9 +1,12 @@
-#![allow(unused, non_camel_case_types)]
-pub struct CIEHeader {}
+//! Call frame information (CFI) from .eh_frame or .debug_frame. CIEs and FDEs describe, for every address of a
+//! function, how to compute the canonical frame address (CFA) and where the caller's registers were saved. Finding
+//! the row of that table for a pc is what lets us identify and unwind frames.
+#![allow(non_camel_case_types)]
+use std::collections::HashMap;
 
-pub struct CIE {
-    header: CIEHeader,
-}
+use nixwrap::MidasSysResultDynamic;
+
+use crate::bytereader::ConsumeReader;
 
 pub enum CallFrameOp {
     DW_CFA_advance_loc = 0b0100_0000,
@@ -33,5 +36,435 @@ pub enum CallFrameOp {
     DW_CFA_val_offset_sf = 0x15,
     DW_CFA_val_expression = 0x16,
     DW_CFA_lo_user = 0x1c,
+    DW_CFA_GNU_args_size = 0x2e,
+    DW_CFA_GNU_negative_offset_extended = 0x2f,
     DW_CFA_hi_user = 0x3f,
 }
+
+/// Which section the CFI comes from. They differ in how CIEs are identified and in how addresses are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallFrameSection {
+    /// .eh_frame, loaded at `address`; needed for resolving pc relative addresses
+    EhFrame {
+        address: usize,
+    },
+    DebugFrame,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CfaRule {
+    RegisterOffset { register: u16, offset: i64 },
+    Expression(Vec<u8>),
+}
+
+/// How to recover the caller's value of a register
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterRule {
+    Undefined,
+    SameValue,
+    /// Saved at CFA + offset
+    Offset(i64),
+    /// The value is CFA + offset
+    ValOffset(i64),
+    Register(u16),
+    /// Saved at the address computed by the expression
+    Expression(Vec<u8>),
+    ValExpression(Vec<u8>),
+}
+
+/// The row of the CFI table for an address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwindRow {
+    /// The first address the row applies to
+    pub address: usize,
+    pub cfa: CfaRule,
+    /// Registers without a rule keep their value in the caller
+    pub registers: HashMap<u16, RegisterRule>,
+    /// The column of the return address
+    pub return_address_register: u16,
+}
+
+struct CommonInformationEntry<'a> {
+    code_alignment: u64,
+    data_alignment: i64,
+    return_address_register: u16,
+    // DW_EH_PE_* encoding of addresses in FDEs
+    address_encoding: u8,
+    has_augmentation_data: bool,
+    instructions: &'a [u8],
+}
+
+const DW_EH_PE_OMIT: u8 = 0xff;
+const DW_EH_PE_PCREL: u8 = 0x10;
+
+/// Reads a pointer encoded as `encoding` (DW_EH_PE_*), which is located at `position` bytes into a section loaded
+/// at `section_address`.
+fn read_encoded(
+    reader: &mut ConsumeReader,
+    encoding: u8,
+    position: usize,
+    section_address: usize,
+) -> MidasSysResultDynamic<u64> {
+    let leb_error = |err: crate::MidasError| err.description().to_string();
+    let value = match encoding & 0x0f {
+        0x00 => reader.read_u64(),
+        0x01 => reader.read_uleb128().map_err(leb_error)?,
+        0x02 => reader.read_u16() as u64,
+        0x03 => reader.read_u32() as u64,
+        0x04 => reader.read_u64(),
+        0x09 => reader.read_ileb128().map_err(leb_error)? as u64,
+        0x0a => reader.read_u16() as i16 as u64,
+        0x0b => reader.read_u32() as i32 as u64,
+        0x0c => reader.read_u64(),
+        format => return Err(format!("unsupported pointer encoding 0x{:02x}", format)),
+    };
+    match encoding & 0x70 {
+        0x00 => Ok(value),
+        DW_EH_PE_PCREL => Ok(((section_address + position) as u64).wrapping_add(value)),
+        application => Err(format!(
+            "unsupported pointer application 0x{:02x}",
+            application
+        )),
+    }
+}
+
+// an entry's length, where it's contents begin and where it ends
+fn entry_bounds(data: &[u8], offset: usize) -> Option<(usize, usize, bool)> {
+    let mut reader = ConsumeReader::wrap(data.get(offset..)?);
+    if reader.length() < 4 {
+        return None;
+    }
+    let length = reader.read_u32();
+    if length == 0xffff_ffff {
+        let length = reader.read_u64() as usize;
+        Some((offset + 12, offset + 12 + length, true))
+    } else {
+        Some((offset + 4, offset + 4 + length as usize, false))
+    }
+}
+
+fn parse_cie(
+    data: &[u8],
+    offset: usize,
+    section: CallFrameSection,
+) -> MidasSysResultDynamic<CommonInformationEntry<'_>> {
+    let leb_error = |err: crate::MidasError| err.description().to_string();
+    let (start, end, is_64) = entry_bounds(data, offset).ok_or("CIE out of bounds")?;
+    let mut reader = ConsumeReader::wrap(data.get(start..end).ok_or("CIE out of bounds")?);
+    if is_64 {
+        reader.read_u64();
+    } else {
+        reader.read_u32();
+    }
+    let version = reader.read_u8();
+    let augmentation = reader.read_str().map_err(leb_error)?.to_owned();
+    reader.read_u8();
+    if augmentation.contains("eh") {
+        reader.read_u64();
+    }
+    if section == CallFrameSection::DebugFrame && version >= 4 {
+        // address size and segment selector size
+        reader.read_u8();
+        reader.read_u8();
+    }
+    let code_alignment = reader.read_uleb128().map_err(leb_error)?;
+    let data_alignment = reader.read_ileb128().map_err(leb_error)?;
+    let return_address_register = if version == 1 {
+        reader.read_u8() as u16
+    } else {
+        reader.read_uleb128().map_err(leb_error)? as u16
+    };
+    let mut address_encoding = 0;
+    let has_augmentation_data = augmentation.starts_with('z');
+    if has_augmentation_data {
+        let length = reader.read_uleb128().map_err(leb_error)? as usize;
+        let augmentation_end = reader.length() - length;
+        for c in augmentation.chars().skip(1) {
+            match c {
+                'R' => address_encoding = reader.read_u8(),
+                'L' => {
+                    reader.read_u8();
+                }
+                'P' => {
+                    let encoding = reader.read_u8();
+                    // we only need to get past the personality routine's address, not resolve it
+                    read_encoded(&mut reader, encoding & 0x0f, 0, 0)?;
+                }
+                _ => {}
+            }
+        }
+        // skip whatever augmentations we don't know about
+        let remaining = reader.length() - augmentation_end;
+        reader.read_slice(remaining).map_err(leb_error)?;
+    }
+    Ok(CommonInformationEntry {
+        code_alignment,
+        data_alignment,
+        return_address_register,
+        address_encoding,
+        has_augmentation_data,
+        instructions: &data[end - reader.length()..end],
+    })
+}
+
+/// Finds the row of the call frame information in `data` (the contents of `section`) that applies to `pc`. Returns
+/// `None` if no FDE covers `pc`.
+pub fn find_unwind_row(data: &[u8], section: CallFrameSection, pc: usize) -> MidasSysResultDynamic<Option<UnwindRow>> {
+    let section_address = match section {
+        CallFrameSection::EhFrame { address } => address,
+        CallFrameSection::DebugFrame => 0,
+    };
+    let leb_error = |err: crate::MidasError| err.description().to_string();
+    let mut offset = 0;
+    while let Some((start, end, is_64)) = entry_bounds(data, offset) {
+        // .eh_frame is terminated by an entry of length 0
+        if start == end || end > data.len() {
+            break;
+        }
+        offset = end;
+        let mut reader = ConsumeReader::wrap(&data[start..end]);
+        let id = if is_64 {
+            reader.read_u64()
+        } else {
+            reader.read_u32() as u64
+        };
+        let cie_offset = match section {
+            CallFrameSection::EhFrame { .. } if id == 0 => continue,
+            CallFrameSection::EhFrame { .. } => start - id as usize,
+            CallFrameSection::DebugFrame if id == 0xffff_ffff || id == u64::MAX => continue,
+            CallFrameSection::DebugFrame => id as usize,
+        };
+        let cie = parse_cie(data, cie_offset, section)?;
+        let encoding = match section {
+            CallFrameSection::EhFrame { .. } => cie.address_encoding,
+            CallFrameSection::DebugFrame => 0,
+        };
+        if encoding == DW_EH_PE_OMIT {
+            continue;
+        }
+        let position = end - reader.length();
+        let begin = read_encoded(&mut reader, encoding, position, section_address)? as usize;
+        let position = end - reader.length();
+        // the range is a length, never relative to anything
+        let range = read_encoded(&mut reader, encoding & 0x0f, position, section_address)? as usize;
+        if !(begin..begin + range).contains(&pc) {
+            continue;
+        }
+        if cie.has_augmentation_data {
+            let length = reader.read_uleb128().map_err(leb_error)? as usize;
+            reader.read_slice(length).map_err(leb_error)?;
+        }
+        let mut row = UnwindRow {
+            address: begin,
+            cfa: CfaRule::RegisterOffset {
+                register: 0,
+                offset: 0,
+            },
+            registers: HashMap::new(),
+            return_address_register: cie.return_address_register,
+        };
+        let mut machine = CfaMachine {
+            cie: &cie,
+            encoding,
+            section_address,
+            pc,
+            initial: None,
+            remembered: vec![],
+        };
+        machine.execute(cie.instructions, 0, &mut row)?;
+        machine.initial = Some(row.clone());
+        let instructions = end - reader.length();
+        machine.execute(&data[instructions..end], instructions, &mut row)?;
+        return Ok(Some(row));
+    }
+    Ok(None)
+}
+
+struct CfaMachine<'a> {
+    cie: &'a CommonInformationEntry<'a>,
+    encoding: u8,
+    section_address: usize,
+    pc: usize,
+    // the row after the CIE's instructions, which DW_CFA_restore restores to
+    initial: Option<UnwindRow>,
+    remembered: Vec<UnwindRow>,
+}
+
+impl<'a> CfaMachine<'a> {
+    // runs `instructions` until the location they describe moves past the pc
+    fn execute(&mut self, instructions: &[u8], position: usize, row: &mut UnwindRow) -> MidasSysResultDynamic<()> {
+        let leb_error = |err: crate::MidasError| err.description().to_string();
+        let mut reader = ConsumeReader::wrap(instructions);
+        let code_alignment = self.cie.code_alignment;
+        let data_alignment = self.cie.data_alignment;
+        while reader.has_more() {
+            let instruction = reader.read_u8();
+            let operand = instruction & 0x3f;
+            let new_address = match instruction & 0xc0 {
+                0x40 => Some(row.address as u64 + operand as u64 * code_alignment),
+                0x80 => {
+                    let offset = reader.read_uleb128().map_err(leb_error)? as i64 * data_alignment;
+                    row.registers
+                        .insert(operand as u16, RegisterRule::Offset(offset));
+                    None
+                }
+                0xc0 => {
+                    self.restore(row, operand as u16);
+                    None
+                }
+                _ => match instruction {
+                    // DW_CFA_nop
+                    0x00 => None,
+                    // DW_CFA_set_loc
+                    0x01 => {
+                        let field = position + instructions.len() - reader.length();
+                        Some(read_encoded(
+                            &mut reader,
+                            self.encoding,
+                            field,
+                            self.section_address,
+                        )?)
+                    }
+                    // DW_CFA_advance_loc1, 2 and 4
+                    0x02 => Some(row.address as u64 + reader.read_u8() as u64 * code_alignment),
+                    0x03 => Some(row.address as u64 + reader.read_u16() as u64 * code_alignment),
+                    0x04 => Some(row.address as u64 + reader.read_u32() as u64 * code_alignment),
+                    // DW_CFA_offset_extended(_sf), DW_CFA_val_offset(_sf) and DW_CFA_GNU_negative_offset_extended
+                    0x05 | 0x11 | 0x14 | 0x15 | 0x2f => {
+                        let register = reader.read_uleb128().map_err(leb_error)? as u16;
+                        let offset = match instruction {
+                            0x05 | 0x14 => reader.read_uleb128().map_err(leb_error)? as i64 * data_alignment,
+                            0x2f => -(reader.read_uleb128().map_err(leb_error)? as i64) * data_alignment,
+                            _ => reader.read_ileb128().map_err(leb_error)? * data_alignment,
+                        };
+                        let rule = match instruction {
+                            0x14 | 0x15 => RegisterRule::ValOffset(offset),
+                            _ => RegisterRule::Offset(offset),
+                        };
+                        row.registers.insert(register, rule);
+                        None
+                    }
+                    // DW_CFA_restore_extended
+                    0x06 => {
+                        let register = reader.read_uleb128().map_err(leb_error)? as u16;
+                        self.restore(row, register);
+                        None
+                    }
+                    // DW_CFA_undefined and DW_CFA_same_value
+                    0x07 | 0x08 => {
+                        let register = reader.read_uleb128().map_err(leb_error)? as u16;
+                        let rule = if instruction == 0x07 {
+                            RegisterRule::Undefined
+                        } else {
+                            RegisterRule::SameValue
+                        };
+                        row.registers.insert(register, rule);
+                        None
+                    }
+                    // DW_CFA_register
+                    0x09 => {
+                        let register = reader.read_uleb128().map_err(leb_error)? as u16;
+                        let other = reader.read_uleb128().map_err(leb_error)? as u16;
+                        row.registers
+                            .insert(register, RegisterRule::Register(other));
+                        None
+                    }
+                    // DW_CFA_remember_state
+                    0x0a => {
+                        self.remembered.push(row.clone());
+                        None
+                    }
+                    // DW_CFA_restore_state; the location isn't part of the state
+                    0x0b => {
+                        let remembered = self
+                            .remembered
+                            .pop()
+                            .ok_or("DW_CFA_restore_state without state")?;
+                        row.cfa = remembered.cfa;
+                        row.registers = remembered.registers;
+                        None
+                    }
+                    // DW_CFA_def_cfa(_sf)
+                    0x0c | 0x12 => {
+                        let register = reader.read_uleb128().map_err(leb_error)? as u16;
+                        let offset = if instruction == 0x0c {
+                            reader.read_uleb128().map_err(leb_error)? as i64
+                        } else {
+                            reader.read_ileb128().map_err(leb_error)? * data_alignment
+                        };
+                        row.cfa = CfaRule::RegisterOffset { register, offset };
+                        None
+                    }
+                    // DW_CFA_def_cfa_register
+                    0x0d => {
+                        let new_register = reader.read_uleb128().map_err(leb_error)? as u16;
+                        if let CfaRule::RegisterOffset { register, .. } = &mut row.cfa {
+                            *register = new_register;
+                        }
+                        None
+                    }
+                    // DW_CFA_def_cfa_offset(_sf)
+                    0x0e | 0x13 => {
+                        let new_offset = if instruction == 0x0e {
+                            reader.read_uleb128().map_err(leb_error)? as i64
+                        } else {
+                            reader.read_ileb128().map_err(leb_error)? * data_alignment
+                        };
+                        if let CfaRule::RegisterOffset { offset, .. } = &mut row.cfa {
+                            *offset = new_offset;
+                        }
+                        None
+                    }
+                    // DW_CFA_def_cfa_expression
+                    0x0f => {
+                        let length = reader.read_uleb128().map_err(leb_error)? as usize;
+                        row.cfa = CfaRule::Expression(reader.clone_slice(length).map_err(leb_error)?);
+                        None
+                    }
+                    // DW_CFA_expression and DW_CFA_val_expression
+                    0x10 | 0x16 => {
+                        let register = reader.read_uleb128().map_err(leb_error)? as u16;
+                        let length = reader.read_uleb128().map_err(leb_error)? as usize;
+                        let expression = reader.clone_slice(length).map_err(leb_error)?;
+                        let rule = if instruction == 0x10 {
+                            RegisterRule::Expression(expression)
+                        } else {
+                            RegisterRule::ValExpression(expression)
+                        };
+                        row.registers.insert(register, rule);
+                        None
+                    }
+                    // DW_CFA_GNU_args_size
+                    0x2e => {
+                        reader.read_uleb128().map_err(leb_error)?;
+                        None
+                    }
+                    unknown => {
+                        return Err(format!(
+                            "unsupported call frame instruction 0x{:02x}",
+                            unknown
+                        ))
+                    }
+                },
+            };
+            if let Some(address) = new_address {
+                if address as usize > self.pc {
+                    return Ok(());
+                }
+                row.address = address as usize;
+            }
+        }
+        Ok(())
+    }
+
+    fn restore(&self, row: &mut UnwindRow, register: u16) {
+        match self
+            .initial
+            .as_ref()
+            .and_then(|initial| initial.registers.get(&register))
+        {
+            Some(rule) => row.registers.insert(register, rule.clone()),
+            None => row.registers.remove(&register),
+        };
+    }
+}
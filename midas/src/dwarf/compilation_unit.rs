@@ -38,7 +38,7 @@ const fn header_size_bytes(format: DWARF) -> usize {
 }
 #[allow(unused)]
 impl CompilationUnitHeader {
-    const DWARF4_32_SIZE: usize = header_size_bytes(DWARF::Version4(DWARFEncoding::BITS32));
+    pub(crate) const DWARF4_32_SIZE: usize = header_size_bytes(DWARF::Version4(DWARFEncoding::BITS32));
     const DWARF5_32_SIZE: usize = header_size_bytes(DWARF::Version5(DWARFEncoding::BITS32));
     const DWARF4_64_SIZE: usize = header_size_bytes(DWARF::Version4(DWARFEncoding::BITS64));
     const DWARF5_64_SIZE: usize = header_size_bytes(DWARF::Version5(DWARFEncoding::BITS64));
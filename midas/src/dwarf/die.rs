@@ -1,9 +1,177 @@
-#![allow(unused, non_camel_case_types)]
-pub struct DIE {
-    tag: usize,
-}
+//! Debugging information entries (DIEs); the tree of entries in .debug_info that describes the compilation units,
+//! their functions, variables and types. Entries are read in order, one compilation unit at a time. The tree
+//! structure is given by each entry's depth.
+use std::collections::HashMap;
 
+use super::{
+    attributes::{
+        parse_attribute, parse_cu_attributes, AbbreviationsTableEntry, Attribute, AttributeValue, ParsedAttribute,
+    },
+    compilation_unit::CompilationUnitHeader,
+    tag::DwarfTag,
+};
+use crate::bytereader::{self, NonConsumingReader};
+
+#[allow(unused)]
 pub enum DIEHasChildren {
     No = 0x00,
     Yes = 0x01,
 }
+
+#[derive(Debug)]
+pub struct DIE {
+    /// Offset of the entry into .debug_info
+    pub offset: usize,
+    pub tag: DwarfTag,
+    /// How far down the tree of it's compilation unit the entry is; the unit itself is at depth 0
+    pub depth: usize,
+    pub has_children: bool,
+    pub attributes: Vec<ParsedAttribute>,
+}
+
+impl DIE {
+    pub fn attribute(&self, attribute: Attribute) -> Option<&AttributeValue> {
+        self.attributes
+            .iter()
+            .find(|parsed| parsed.attribute == attribute)
+            .map(|parsed| &parsed.value)
+    }
+
+    /// DW_AT_name, which is either inline or a string in .debug_str
+    pub fn name<'a>(&'a self, debug_str: &'a [u8]) -> Option<&'a str> {
+        self.string(Attribute::DW_AT_name, debug_str)
+    }
+
+    /// The value of string attribute `attribute`, inline or in .debug_str
+    pub fn string<'a>(&'a self, attribute: Attribute, debug_str: &'a [u8]) -> Option<&'a str> {
+        match self.attribute(attribute)? {
+            AttributeValue::String(name) => Some(name),
+            AttributeValue::DebugStrOffset(offset) => NonConsumingReader::new(debug_str)
+                .read_str_from(*offset)
+                .ok(),
+            _ => None,
+        }
+    }
+
+    /// The addresses covered by the entry. DW_AT_high_pc is either an address or, since DWARF 4, an offset from
+    /// DW_AT_low_pc.
+    pub fn pc_range(&self) -> Option<std::ops::Range<usize>> {
+        let low = match self.attribute(Attribute::DW_AT_low_pc)? {
+            AttributeValue::Address(low) => *low,
+            _ => return None,
+        };
+        let high = match self.attribute(Attribute::DW_AT_high_pc)? {
+            AttributeValue::Address(high) => *high,
+            value => low + constant(value)? as usize,
+        };
+        Some(low..high)
+    }
+
+    /// The .debug_info offset of the entry that reference `attribute` (like DW_AT_type) refers to
+    pub fn reference(&self, attribute: Attribute, unit_offset: usize) -> Option<usize> {
+        match self.attribute(attribute)? {
+            AttributeValue::CompilationUnitOffset(offset) => Some(unit_offset + offset),
+            AttributeValue::DebugInfoOffset(offset) => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+/// The value of an attribute of one of the constant classes
+pub fn constant(value: &AttributeValue) -> Option<u64> {
+    match value {
+        AttributeValue::Data1(value) => Some(*value as u64),
+        AttributeValue::Data2(value) => Some(*value as u64),
+        AttributeValue::Data4(value) => Some(*value as u64),
+        AttributeValue::Data8(value) => Some(*value),
+        AttributeValue::UData(value) => Some(*value),
+        AttributeValue::SData(value) => Some(*value as u64),
+        _ => None,
+    }
+}
+
+pub struct CompilationUnit<'a> {
+    /// Offset of the unit's header into .debug_info
+    pub offset: usize,
+    pub header: CompilationUnitHeader,
+    // the entire unit, header included
+    data: &'a [u8],
+    abbreviations: HashMap<u64, AbbreviationsTableEntry>,
+}
+
+impl<'a> CompilationUnit<'a> {
+    pub fn entries(&self) -> DIEIterator<'_> {
+        DIEIterator {
+            unit: self,
+            reader: bytereader::ConsumeReader::wrap(&self.data[self.header.stride()..]),
+            depth: 0,
+        }
+    }
+}
+
+/// Iterates over the compilation units of .debug_info
+pub fn compilation_units<'a>(
+    debug_info: &'a [u8],
+    debug_abbrev: &'a [u8],
+) -> impl Iterator<Item = CompilationUnit<'a>> + 'a {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if debug_info.len() < offset + CompilationUnitHeader::DWARF4_32_SIZE {
+            return None;
+        }
+        let header = CompilationUnitHeader::from_bytes(&debug_info[offset..]);
+        let length = header.unit_length();
+        let abbreviations = parse_cu_attributes(debug_abbrev.get(header.abbreviation_offset..)?).ok()?;
+        let unit = CompilationUnit {
+            offset,
+            data: debug_info.get(offset..offset + length)?,
+            header,
+            abbreviations,
+        };
+        offset += length;
+        Some(unit)
+    })
+}
+
+pub struct DIEIterator<'a> {
+    unit: &'a CompilationUnit<'a>,
+    reader: bytereader::ConsumeReader<'a>,
+    depth: usize,
+}
+
+impl<'a> Iterator for DIEIterator<'a> {
+    type Item = DIE;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if !self.reader.has_more() {
+                return None;
+            }
+            let offset = self.unit.offset + self.unit.data.len() - self.reader.length();
+            let abbreviation_code = self.reader.read_uleb128().ok()?;
+            // a null entry ends a list of siblings
+            if abbreviation_code == 0 {
+                self.depth = self.depth.saturating_sub(1);
+                continue;
+            }
+            let abbreviation = self.unit.abbreviations.get(&abbreviation_code)?;
+            let encoding = self.unit.header.encoding();
+            let attributes = abbreviation
+                .attrs_list
+                .iter()
+                .map(|attr| parse_attribute(&mut self.reader, encoding, *attr))
+                .collect();
+            let die = DIE {
+                offset,
+                tag: abbreviation.tag,
+                depth: self.depth,
+                has_children: abbreviation.has_children,
+                attributes,
+            };
+            if abbreviation.has_children {
+                self.depth += 1;
+            }
+            return Some(die);
+        }
+    }
+}
@@ -448,6 +448,194 @@ impl ComputationResult {
     }
 }
 
+impl ComputationResult {
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn file(&self) -> u32 {
+        self.file
+    }
+
+    pub fn is_statement(&self) -> bool {
+        self.description & 0b10000 != 0
+    }
+
+    pub fn end_sequence(&self) -> bool {
+        self.description & 0b100 != 0
+    }
+
+    pub fn prologue_end(&self) -> bool {
+        self.description & 0b10 != 0
+    }
+}
+
+/// The rows of the line number programs of every compilation unit in .debug_line, and the names of the files they
+/// refer to. Units of versions we can't parse yet (DWARF 5) are skipped.
+#[derive(Debug, Default)]
+pub struct LineTable {
+    pub rows: Vec<ComputationResult>,
+    // the file names of all units; the rows' file indices are rewritten to index into this (starting at 1)
+    files: Vec<String>,
+}
+
+impl LineTable {
+    pub fn new(address_size: u8, debug_line: &[u8]) -> LineTable {
+        let mut table = LineTable::default();
+        let mut offset = 0;
+        while debug_line.len() >= offset + 12 {
+            let unit = &debug_line[offset..];
+            let length = super::InitialLengthField::from_bytes(unit);
+            let unit_size = length.entry_length() + length.offsets_bytes();
+            if bytereader::ConsumeReader::wrap(&unit[length.offsets_bytes()..]).read_u16() <= 4 {
+                let mut program = LineNumberProgram::new(address_size, unit);
+                let rows = program.run();
+                let first_file = table.files.len() as u32;
+                table
+                    .files
+                    .extend(program.header.file_names.iter().map(|file| {
+                        match program.header.get_dir_by_index(file.dir_index) {
+                            Some(directory) if file.dir_index != 0 && !file.path.starts_with('/') => {
+                                format!("{}/{}", directory, file.path)
+                            }
+                            _ => file.path.clone(),
+                        }
+                    }));
+                table.rows.extend(rows.into_iter().map(|mut row| {
+                    row.file += first_file;
+                    row
+                }));
+            }
+            offset += unit_size;
+        }
+        table
+    }
+
+    /// The table with every address moved `bias` bytes, to where the object it's from is loaded
+    pub fn relocated(mut self, bias: usize) -> LineTable {
+        for row in self.rows.iter_mut() {
+            row.address = row.address.wrapping_add(bias);
+        }
+        self
+    }
+
+    /// The name of the file `row` is in, with the directory it's in if the compiler recorded one
+    pub fn file_name(&self, row: &ComputationResult) -> Option<&str> {
+        self.files
+            .get((row.file as usize).checked_sub(1)?)
+            .map(|name| name.as_str())
+    }
+
+    /// The names of the files of all units, as `file_name` gives them
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().map(|name| name.as_str())
+    }
+
+    // the index of the row that `pc` is in; the last row at or before `pc`, in a sequence that continues past it
+    fn index_of(&self, pc: usize) -> Option<usize> {
+        self.rows
+            .windows(2)
+            .position(|rows| rows[0].address <= pc && pc < rows[1].address && !rows[0].end_sequence())
+    }
+
+    /// The row that describes the instruction at `pc`
+    pub fn row_at(&self, pc: usize) -> Option<&ComputationResult> {
+        self.index_of(pc).map(|index| &self.rows[index])
+    }
+
+    /// The addresses of the line that `pc` is in; the consecutive rows around it that are in the same file and
+    /// line. Line 0 is code that doesn't belong to any line.
+    pub fn line_range(&self, pc: usize) -> Option<std::ops::Range<usize>> {
+        let index = self.index_of(pc)?;
+        let same_line = |row: &ComputationResult| {
+            row.file == self.rows[index].file && row.line == self.rows[index].line && !row.end_sequence()
+        };
+        let first = self.rows[..index]
+            .iter()
+            .rposition(|row| !same_line(row))
+            .map(|position| position + 1)
+            .unwrap_or(0);
+        let end = self.rows[index + 1..]
+            .iter()
+            .find(|row| !same_line(row))
+            .map(|row| row.address)?;
+        Some(self.rows[first].address..end)
+    }
+
+    /// Where to break for line `line` of `file`; the first instruction of every run of statements the line compiled
+    /// to, and the line they're on. A line that has no code of it's own, like a comment or a blank line, resolves to
+    /// the next one that does. `file` matches the files the table names, when either is the other with directories in
+    /// front; an absolute path matches the name the compiler recorded, relative to where it compiled.
+    pub fn addresses_of_line(&self, file: &str, line: u32) -> Option<(u32, Vec<usize>)> {
+        let same_file = |name: &str| {
+            name == file
+                || file
+                    .strip_suffix(name)
+                    .is_some_and(|directory| directory.ends_with('/'))
+                || name
+                    .strip_suffix(file)
+                    .is_some_and(|directory| directory.ends_with('/'))
+        };
+        let in_file = |row: &ComputationResult| {
+            row.is_statement() && !row.end_sequence() && self.file_name(row).is_some_and(same_file)
+        };
+        let found = self
+            .rows
+            .iter()
+            .filter(|row| in_file(row) && row.line >= line)
+            .map(|row| row.line)
+            .min()?;
+        let mut addresses: Vec<usize> = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(index, row)| {
+                // a row that continues the line of the one before it isn't where the line begins
+                in_file(row)
+                    && row.line == found
+                    && (*index == 0 || {
+                        let previous = &self.rows[index - 1];
+                        previous.end_sequence() || previous.line != found || previous.file != row.file
+                    })
+            })
+            .map(|(_, row)| row.address)
+            .collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+        Some((found, addresses))
+    }
+
+    /// Whether `pc` is the first instruction of a statement; where a step stops
+    pub fn is_statement_start(&self, pc: usize) -> bool {
+        self.rows
+            .iter()
+            .any(|row| row.address == pc && row.is_statement() && !row.end_sequence() && row.line != 0)
+    }
+
+    /// Where the body of the function at `pc_range` begins, after the prologue that sets up it's frame and stores
+    /// it's parameters; the row marked as the end of the prologue, or else the second row of the function's line
+    /// table.
+    pub fn address_after_prologue(&self, pc_range: std::ops::Range<usize>) -> Option<usize> {
+        let mut function_rows: Vec<&ComputationResult> = self
+            .rows
+            .iter()
+            .filter(|row| pc_range.contains(&row.address) && row.is_statement() && !row.end_sequence())
+            .collect();
+        function_rows.sort_by_key(|row| row.address);
+        if let Some(row) = function_rows.iter().find(|row| row.prologue_end()) {
+            return Some(row.address);
+        }
+        function_rows
+            .iter()
+            .find(|row| row.address > pc_range.start)
+            .map(|row| row.address)
+    }
+}
+
 pub fn encode_description(
     is_statement: bool,
     basic_block: bool,
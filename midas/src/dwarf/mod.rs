@@ -14,6 +14,7 @@ pub mod sections;
 pub mod stack;
 pub mod stringoffset;
 pub mod tag;
+pub mod variables;
 
 pub use sections::*;
 
@@ -1 +1,188 @@
+//! The DWARF expression stack machine. It's what computes where a variable lives (DW_AT_location), a function's
+//! frame base (DW_AT_frame_base) and the CFA rules of call frame information that can't be expressed as a register
+//! plus offset. The registers and memory of the inferior are accessed through a `LocationContext`.
+use nixwrap::MidasSysResultDynamic;
 
+use crate::bytereader::ConsumeReader;
+
+use super::operations::Operation;
+
+/// What the expression needs to know about the inferior and the frame it's evaluated in
+pub trait LocationContext {
+    /// The value of register `number`, in the DWARF register numbering
+    fn register(&self, number: u16) -> Option<u64>;
+    /// The value of DW_AT_frame_base of the function the frame belongs to
+    fn frame_base(&self) -> Option<u64>;
+    /// The canonical frame address of the frame
+    fn call_frame_cfa(&self) -> Option<u64>;
+    /// Read a pointer sized value from `address`
+    fn read_memory(&self, address: u64) -> Option<u64>;
+    /// What's added to the addresses of the object the expression is from, to get where they're loaded; DW_OP_addr
+    /// operands are addresses in the file
+    fn load_bias(&self) -> u64 {
+        0
+    }
+}
+
+/// Where the value described by an expression is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Address(u64),
+    /// The value lives in a register, not in memory
+    Register(u16),
+    /// The expression computed the value itself (DW_OP_stack_value)
+    Value(u64),
+}
+
+/// The name of DWARF register `number` in the x86-64 System V ABI, as `UserRegisters::get_by_name` knows it
+pub fn register_name(number: u16) -> Option<&'static str> {
+    const NAMES: [&str; 17] = [
+        "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+        // the return address column
+        "rip",
+    ];
+    NAMES.get(number as usize).copied()
+}
+
+/// Evaluates the DWARF expression `expression`. Only the operations that compilers use to describe locations are
+/// supported; no typed stack entries, no calls to other expressions and no multi-piece values.
+pub fn evaluate(expression: &[u8], context: &dyn LocationContext) -> MidasSysResultDynamic<Location> {
+    let mut stack: Vec<u64> = vec![];
+    let mut reader = ConsumeReader::wrap(expression);
+    let leb = |result: crate::MidasSysResult<u64>| result.map_err(|err| err.description().to_string());
+    let sleb = |result: crate::MidasSysResult<i64>| result.map_err(|err| err.description().to_string());
+    let register = |number: u16| {
+        context
+            .register(number)
+            .ok_or(format!("can't read DWARF register {}", number))
+    };
+    let underflow = || "DWARF expression stack underflow".to_string();
+    let mut location = None;
+
+    while reader.has_more() {
+        let opcode = reader.read_u8();
+        match opcode {
+            op if op == Operation::DW_OP_addr as u8 => stack.push(reader.read_u64().wrapping_add(context.load_bias())),
+            op if op == Operation::DW_OP_deref as u8 => {
+                let address = stack.pop().ok_or_else(underflow)?;
+                stack.push(
+                    context
+                        .read_memory(address)
+                        .ok_or(format!("cannot access memory at address 0x{:x}", address))?,
+                );
+            }
+            op if op == Operation::DW_OP_const1u as u8 => stack.push(reader.read_u8() as u64),
+            op if op == Operation::DW_OP_const1s as u8 => stack.push(reader.read_u8() as i8 as u64),
+            op if op == Operation::DW_OP_const2u as u8 => stack.push(reader.read_u16() as u64),
+            op if op == Operation::DW_OP_const2s as u8 => stack.push(reader.read_u16() as i16 as u64),
+            op if op == Operation::DW_OP_const4u as u8 => stack.push(reader.read_u32() as u64),
+            op if op == Operation::DW_OP_const4s as u8 => stack.push(reader.read_u32() as i32 as u64),
+            op if op == Operation::DW_OP_const8u as u8 || op == Operation::DW_OP_const8s as u8 => {
+                stack.push(reader.read_u64())
+            }
+            op if op == Operation::DW_OP_constu as u8 => stack.push(leb(reader.read_uleb128())?),
+            op if op == Operation::DW_OP_consts as u8 => stack.push(sleb(reader.read_ileb128())? as u64),
+            op if op == Operation::DW_OP_dup as u8 => stack.push(*stack.last().ok_or_else(underflow)?),
+            op if op == Operation::DW_OP_drop as u8 => {
+                stack.pop().ok_or_else(underflow)?;
+            }
+            op if op == Operation::DW_OP_plus_uconst as u8 => {
+                let value = stack.pop().ok_or_else(underflow)?;
+                stack.push(value.wrapping_add(leb(reader.read_uleb128())?));
+            }
+            op if (Operation::DW_OP_and as u8..=Operation::DW_OP_xor as u8).contains(&op)
+                && op != Operation::DW_OP_neg as u8
+                && op != Operation::DW_OP_not as u8 =>
+            {
+                let rhs = stack.pop().ok_or_else(underflow)?;
+                let lhs = stack.pop().ok_or_else(underflow)?;
+                stack.push(binary(op, lhs, rhs)?);
+            }
+            op if op == Operation::DW_OP_neg as u8 => {
+                let value = stack.pop().ok_or_else(underflow)?;
+                stack.push(value.wrapping_neg());
+            }
+            op if op == Operation::DW_OP_not as u8 => {
+                let value = stack.pop().ok_or_else(underflow)?;
+                stack.push(!value);
+            }
+            op if (Operation::DW_OP_lit0 as u8..=Operation::DW_OP_lit31 as u8).contains(&op) => {
+                stack.push((op - Operation::DW_OP_lit0 as u8) as u64)
+            }
+            op if (Operation::DW_OP_reg0 as u8..=Operation::DW_OP_reg31 as u8).contains(&op) => {
+                location = Some(Location::Register(
+                    (op - Operation::DW_OP_reg0 as u8) as u16,
+                ))
+            }
+            op if op == Operation::DW_OP_regx as u8 => {
+                location = Some(Location::Register(leb(reader.read_uleb128())? as u16))
+            }
+            op if (Operation::DW_OP_breg0 as u8..=Operation::DW_OP_breg31 as u8).contains(&op) => {
+                let value = register((op - Operation::DW_OP_breg0 as u8) as u16)?;
+                stack.push(value.wrapping_add(sleb(reader.read_ileb128())? as u64));
+            }
+            op if op == Operation::DW_OP_bregx as u8 => {
+                let value = register(leb(reader.read_uleb128())? as u16)?;
+                stack.push(value.wrapping_add(sleb(reader.read_ileb128())? as u64));
+            }
+            op if op == Operation::DW_OP_fbreg as u8 => {
+                let frame_base = context
+                    .frame_base()
+                    .ok_or("the frame base of the function is not known")?;
+                stack.push(frame_base.wrapping_add(sleb(reader.read_ileb128())? as u64));
+            }
+            op if op == Operation::DW_OP_call_frame_cfa as u8 => stack.push(
+                context
+                    .call_frame_cfa()
+                    .ok_or("the canonical frame address is not known")?,
+            ),
+            op if op == Operation::DW_OP_stack_value as u8 => {
+                location = Some(Location::Value(stack.pop().ok_or_else(underflow)?))
+            }
+            op if op == Operation::DW_OP_nop as u8 => {}
+            op => {
+                return Err(format!(
+                    "unsupported DWARF expression operation 0x{:02x}",
+                    op
+                ))
+            }
+        }
+    }
+    match location {
+        Some(location) => Ok(location),
+        None => Ok(Location::Address(stack.pop().ok_or_else(underflow)?)),
+    }
+}
+
+fn binary(op: u8, lhs: u64, rhs: u64) -> MidasSysResultDynamic<u64> {
+    let (signed_lhs, signed_rhs) = (lhs as i64, rhs as i64);
+    Ok(match op {
+        op if op == Operation::DW_OP_and as u8 => lhs & rhs,
+        op if op == Operation::DW_OP_div as u8 => {
+            if rhs == 0 {
+                return Err("division by zero in DWARF expression".into());
+            }
+            signed_lhs.wrapping_div(signed_rhs) as u64
+        }
+        op if op == Operation::DW_OP_minus as u8 => lhs.wrapping_sub(rhs),
+        op if op == Operation::DW_OP_mod as u8 => {
+            if rhs == 0 {
+                return Err("division by zero in DWARF expression".into());
+            }
+            lhs % rhs
+        }
+        op if op == Operation::DW_OP_mul as u8 => lhs.wrapping_mul(rhs),
+        op if op == Operation::DW_OP_or as u8 => lhs | rhs,
+        op if op == Operation::DW_OP_plus as u8 => lhs.wrapping_add(rhs),
+        op if op == Operation::DW_OP_shl as u8 => lhs.wrapping_shl(rhs as u32),
+        op if op == Operation::DW_OP_shr as u8 => lhs.wrapping_shr(rhs as u32),
+        op if op == Operation::DW_OP_shra as u8 => signed_lhs.wrapping_shr(rhs as u32) as u64,
+        op if op == Operation::DW_OP_xor as u8 => lhs ^ rhs,
+        op => {
+            return Err(format!(
+                "unsupported DWARF expression operation 0x{:02x}",
+                op
+            ))
+        }
+    })
+}
@@ -62,7 +62,7 @@ pub enum Tag {
     WithStatement,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[repr(u16)]
 pub enum DwarfTag {
     DW_TAG_array_type = 0x01,
@@ -141,5 +141,7 @@ pub enum DwarfTag {
     DW_TAG_skeleton_unit = 0x4a,       // ‡
     DW_TAG_immutable_type = 0x4b,      // ‡
     DW_TAG_lo_user = 0x4080,
+    DW_TAG_GNU_call_site = 0x4109,
+    DW_TAG_GNU_call_site_parameter = 0x410a,
     DW_TAG_hi_user = 0xffff,
 }
@@ -0,0 +1,375 @@
+//! Functions and the variables in scope in them, as far as evaluating expressions needs to know; where a variable
+//! lives, how big it is and whether it's signed.
+use std::collections::HashMap;
+
+use super::{
+    attributes::{Attribute, AttributeValue},
+    die::{compilation_units, constant, CompilationUnit, DIE},
+    tag::DwarfTag,
+};
+
+const DW_ATE_FLOAT: u64 = 0x04;
+const DW_ATE_SIGNED: u64 = 0x05;
+const DW_ATE_SIGNED_CHAR: u64 = 0x06;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    /// DW_AT_location; a DWARF expression. None if the variable has been optimized out.
+    pub location: Option<Vec<u8>>,
+    /// Size of the variable's type in bytes, 0 if not known
+    pub size: usize,
+    pub signed: bool,
+    /// A float or double, rather than an integer or a pointer
+    pub float: bool,
+    /// A parameter of the function, rather than a local
+    pub parameter: bool,
+}
+
+/// How a function returns it's value, according to the x86-64 System V ABI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReturnType {
+    Void,
+    /// Integers, pointers, enums and bools; in rax
+    Integer {
+        size: usize,
+        signed: bool,
+    },
+    /// float and double; in xmm0
+    Float {
+        size: usize,
+    },
+    /// Structs, unions and arrays. Up to 16 bytes, each eightbyte is in the next free integer register (rax, rdx),
+    /// or SSE register (xmm0, xmm1) if `sse` says so. Larger ones are returned in memory, at the address in rax.
+    Aggregate {
+        size: usize,
+        sse: Vec<bool>,
+    },
+    /// long double, complex numbers and types we know too little about
+    Unsupported,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub pc_range: std::ops::Range<usize>,
+    /// DW_AT_frame_base; a DWARF expression
+    pub frame_base: Option<Vec<u8>>,
+    pub return_type: ReturnType,
+    /// Parameters and locals in scope at the pc the function was looked up for, outermost scope first
+    pub variables: Vec<Variable>,
+}
+
+impl Function {
+    /// The function with it's addresses moved `bias` bytes, to where the object it's from is loaded
+    pub fn relocated(mut self, bias: usize) -> Function {
+        self.pc_range = self.pc_range.start.wrapping_add(bias)..self.pc_range.end.wrapping_add(bias);
+        self
+    }
+
+    /// Variable `name`; the innermost one, if it's shadowed
+    pub fn variable(&self, name: &str) -> Option<&Variable> {
+        self.variables
+            .iter()
+            .rev()
+            .find(|variable| variable.name == name)
+    }
+}
+
+// the entries of a compilation unit in order, so that an entry's children can be found, looked up by their offset
+struct Entries<'a> {
+    unit: &'a CompilationUnit<'a>,
+    dies: Vec<DIE>,
+    index: HashMap<usize, usize>,
+}
+
+impl<'a> Entries<'a> {
+    fn new(unit: &'a CompilationUnit<'a>, dies: Vec<DIE>) -> Entries<'a> {
+        let index = dies
+            .iter()
+            .enumerate()
+            .map(|(index, die)| (die.offset, index))
+            .collect();
+        Entries { unit, dies, index }
+    }
+
+    fn get(&self, offset: usize) -> Option<&DIE> {
+        self.index.get(&offset).map(|index| &self.dies[*index])
+    }
+
+    // the entry that `die`'s DW_AT_type refers to
+    fn type_of(&self, die: &DIE) -> Option<&DIE> {
+        self.get(die.reference(Attribute::DW_AT_type, self.unit.offset)?)
+    }
+
+    fn children(&self, die: &DIE) -> impl Iterator<Item = &DIE> {
+        let depth = die.depth;
+        self.index
+            .get(&die.offset)
+            .map(|index| &self.dies[index + 1..])
+            .unwrap_or(&[])
+            .iter()
+            .take_while(move |child| child.depth > depth)
+            .filter(move |child| child.depth == depth + 1)
+    }
+
+    // the type that typedefs and qualifiers like const refer to, in the end
+    fn strip<'b>(&'b self, die: &'b DIE) -> &'b DIE {
+        let mut current = die;
+        // guard against reference cycles in broken DWARF
+        for _ in 0..32 {
+            match current.tag {
+                DwarfTag::DW_TAG_typedef
+                | DwarfTag::DW_TAG_const_type
+                | DwarfTag::DW_TAG_volatile_type
+                | DwarfTag::DW_TAG_restrict_type
+                | DwarfTag::DW_TAG_atomic_type => match self.type_of(current) {
+                    Some(next) => current = next,
+                    None => break,
+                },
+                _ => break,
+            }
+        }
+        current
+    }
+
+    // the size of type `die` in bytes; arrays often only say how many elements they have
+    fn size_of(&self, die: &DIE) -> Option<usize> {
+        let die = self.strip(die);
+        if let Some(size) = die.attribute(Attribute::DW_AT_byte_size).and_then(constant) {
+            return Some(size as usize);
+        }
+        match die.tag {
+            DwarfTag::DW_TAG_array_type => {
+                let element = self.size_of(self.type_of(die)?)?;
+                self.children(die)
+                    .filter(|child| child.tag == DwarfTag::DW_TAG_subrange_type)
+                    .try_fold(element, |size, subrange| {
+                        let count = match subrange
+                            .attribute(Attribute::DW_AT_count)
+                            .and_then(constant)
+                        {
+                            Some(count) => count,
+                            None => {
+                                subrange
+                                    .attribute(Attribute::DW_AT_upper_bound)
+                                    .and_then(constant)?
+                                    + 1
+                            }
+                        };
+                        Some(size * count as usize)
+                    })
+            }
+            DwarfTag::DW_TAG_pointer_type
+            | DwarfTag::DW_TAG_reference_type
+            | DwarfTag::DW_TAG_rvalue_reference_type => Some(8),
+            _ => None,
+        }
+    }
+
+    // the size of the type of `die`, and whether it's signed or a float
+    fn scalar_type(&self, die: &DIE) -> (usize, bool, bool) {
+        let die = match self.type_of(die) {
+            Some(die) => self.strip(die),
+            None => return (0, false, false),
+        };
+        let encoding = die.attribute(Attribute::DW_AT_encoding).and_then(constant);
+        let signed = matches!(encoding, Some(DW_ATE_SIGNED) | Some(DW_ATE_SIGNED_CHAR));
+        let float = die.tag == DwarfTag::DW_TAG_base_type && encoding == Some(DW_ATE_FLOAT);
+        (self.size_of(die).unwrap_or(0), signed, float)
+    }
+
+    // marks the eightbytes of `sse` that the scalars of type `die` at `offset` occupy; true for SSE, false for
+    // INTEGER, which wins when they share an eightbyte. None if the type has to be returned in memory regardless.
+    fn classify(&self, die: &DIE, offset: usize, sse: &mut [Option<bool>]) -> Option<()> {
+        let die = self.strip(die);
+        let size = self.size_of(die)?;
+        match die.tag {
+            DwarfTag::DW_TAG_structure_type | DwarfTag::DW_TAG_class_type | DwarfTag::DW_TAG_union_type => {
+                for member in self.children(die).filter(|child| {
+                    matches!(
+                        child.tag,
+                        DwarfTag::DW_TAG_member | DwarfTag::DW_TAG_inheritance
+                    )
+                }) {
+                    // members of unions have no location
+                    let member_offset = match member.attribute(Attribute::DW_AT_data_member_location) {
+                        Some(location) => constant(location)? as usize,
+                        None => 0,
+                    };
+                    self.classify(self.type_of(member)?, offset + member_offset, sse)?;
+                }
+            }
+            DwarfTag::DW_TAG_array_type => {
+                let element = self.type_of(die)?;
+                let element_size = self.size_of(element)?;
+                if element_size == 0 {
+                    return Some(());
+                }
+                for index in 0..size / element_size {
+                    self.classify(element, offset + index * element_size, sse)?;
+                }
+            }
+            DwarfTag::DW_TAG_base_type if size > 8 => return None,
+            tag => {
+                let float = tag == DwarfTag::DW_TAG_base_type
+                    && die.attribute(Attribute::DW_AT_encoding).and_then(constant) == Some(DW_ATE_FLOAT);
+                for eightbyte in offset / 8..=(offset + size.max(1) - 1) / 8 {
+                    let class = sse.get_mut(eightbyte)?;
+                    *class = Some(class.unwrap_or(true) && float);
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn return_type(&self, function: &DIE) -> ReturnType {
+        let die = match self.type_of(function) {
+            Some(die) => self.strip(die),
+            None => return ReturnType::Void,
+        };
+        let size = match self.size_of(die) {
+            Some(size) => size,
+            None => return ReturnType::Unsupported,
+        };
+        match die.tag {
+            DwarfTag::DW_TAG_base_type => match die.attribute(Attribute::DW_AT_encoding).and_then(constant) {
+                Some(DW_ATE_FLOAT) if size <= 8 => ReturnType::Float { size },
+                Some(DW_ATE_FLOAT) => ReturnType::Unsupported,
+                Some(encoding) if size <= 8 => ReturnType::Integer {
+                    size,
+                    signed: encoding == DW_ATE_SIGNED || encoding == DW_ATE_SIGNED_CHAR,
+                },
+                _ => ReturnType::Unsupported,
+            },
+            DwarfTag::DW_TAG_pointer_type
+            | DwarfTag::DW_TAG_reference_type
+            | DwarfTag::DW_TAG_rvalue_reference_type
+            | DwarfTag::DW_TAG_enumeration_type => ReturnType::Integer {
+                size,
+                signed: false,
+            },
+            DwarfTag::DW_TAG_structure_type
+            | DwarfTag::DW_TAG_class_type
+            | DwarfTag::DW_TAG_union_type
+            | DwarfTag::DW_TAG_array_type => {
+                if size > 16 {
+                    return ReturnType::Aggregate { size, sse: vec![] };
+                }
+                let mut classes = vec![None; size.div_ceil(8)];
+                match self.classify(die, 0, &mut classes) {
+                    // padding is as good as an integer
+                    Some(()) => ReturnType::Aggregate {
+                        size,
+                        sse: classes
+                            .into_iter()
+                            .map(|class| class.unwrap_or(false))
+                            .collect(),
+                    },
+                    None => ReturnType::Aggregate { size, sse: vec![] },
+                }
+            }
+            _ => ReturnType::Unsupported,
+        }
+    }
+
+    fn variable(&self, die: &DIE, debug_str: &[u8]) -> Option<Variable> {
+        let name = die.name(debug_str)?.to_owned();
+        let location = match die.attribute(Attribute::DW_AT_location) {
+            Some(AttributeValue::Expression(expression)) | Some(AttributeValue::Block(expression)) => {
+                Some(expression.clone())
+            }
+            _ => None,
+        };
+        let (size, signed, float) = self.scalar_type(die);
+        Some(Variable {
+            name,
+            location,
+            size,
+            signed,
+            float,
+            parameter: die.tag == DwarfTag::DW_TAG_formal_parameter,
+        })
+    }
+}
+
+fn is_variable(die: &DIE) -> bool {
+    matches!(
+        die.tag,
+        DwarfTag::DW_TAG_variable | DwarfTag::DW_TAG_formal_parameter
+    )
+}
+
+/// The function that `pc` is in, with the variables that are in scope at `pc`
+pub fn function_containing(pc: usize, debug_info: &[u8], debug_abbrev: &[u8], debug_str: &[u8]) -> Option<Function> {
+    for unit in compilation_units(debug_info, debug_abbrev) {
+        let dies: Vec<DIE> = unit.entries().collect();
+        let subprogram = match dies.iter().position(|die| {
+            die.tag == DwarfTag::DW_TAG_subprogram && die.pc_range().is_some_and(|range| range.contains(&pc))
+        }) {
+            Some(index) => index,
+            None => continue,
+        };
+        let entries = Entries::new(&unit, dies);
+        let function = &entries.dies[subprogram];
+        let name = function.name(debug_str).unwrap_or("??").to_owned();
+        let pc_range = function.pc_range()?;
+        let frame_base = match function.attribute(Attribute::DW_AT_frame_base) {
+            Some(AttributeValue::Expression(expression)) | Some(AttributeValue::Block(expression)) => {
+                Some(expression.clone())
+            }
+            _ => None,
+        };
+
+        let mut variables = vec![];
+        // the depth of the lexical block we're in that doesn't contain pc; everything under it is out of scope
+        let mut out_of_scope: Option<usize> = None;
+        for die in entries.dies[subprogram + 1..]
+            .iter()
+            .take_while(|die| die.depth > function.depth)
+        {
+            if let Some(depth) = out_of_scope {
+                if die.depth > depth {
+                    continue;
+                }
+                out_of_scope = None;
+            }
+            match die.tag {
+                DwarfTag::DW_TAG_lexical_block if !die.pc_range().is_none_or(|range| range.contains(&pc)) => {
+                    out_of_scope = Some(die.depth)
+                }
+                // nested functions have their own variables
+                DwarfTag::DW_TAG_subprogram | DwarfTag::DW_TAG_inlined_subroutine => out_of_scope = Some(die.depth),
+                _ if is_variable(die) => variables.extend(entries.variable(die, debug_str)),
+                _ => {}
+            }
+        }
+
+        return Some(Function {
+            name,
+            pc_range,
+            frame_base,
+            return_type: entries.return_type(function),
+            variables,
+        });
+    }
+    None
+}
+
+/// The global (or file static) variable `name`
+pub fn global_variable(name: &str, debug_info: &[u8], debug_abbrev: &[u8], debug_str: &[u8]) -> Option<Variable> {
+    for unit in compilation_units(debug_info, debug_abbrev) {
+        let dies: Vec<DIE> = unit.entries().collect();
+        let found = dies.iter().position(|die| {
+            die.depth == 1
+                && die.tag == DwarfTag::DW_TAG_variable
+                && die.attribute(Attribute::DW_AT_location).is_some()
+                && die.name(debug_str) == Some(name)
+        });
+        if let Some(index) = found {
+            let entries = Entries::new(&unit, dies);
+            return entries.variable(&entries.dies[index], debug_str);
+        }
+    }
+    None
+}
@@ -67,6 +67,8 @@ pub struct ParsedELF<'object> {
     dwarf_sections: ParsedSections,
     sections: HashMap<String, (section::SectionHeader, section::Section)>,
     pub symbol_table: SymbolTable<'object>,
+    // what's added to the addresses in the file, to get where they're loaded
+    load_bias: usize,
 }
 
 impl<'object> ParsedELF<'object> {
@@ -101,12 +103,20 @@ impl<'object> ParsedELF<'object> {
             sections.insert(section_name.to_owned(), (sh, section));
         }
 
-        let (strtab_header, strtab_sec) = sections.get(".strtab").as_ref().unwrap();
-        let (symtab_header, symtab_sec) = sections.get(".symtab").as_ref().unwrap();
-
-        let mut reader = bytereader::ConsumeReader::wrap(&obj_ref.data[symtab_header.address_range()]);
-        let st_reader = NonConsumingReader::new(&obj_ref.data[strtab_header.address_range()]);
-        let symbol_table = SymbolTable::parse_symbol_table(&symtab_header, reader, &st_reader)?;
+        // stripped objects, like the shared libraries of most distributions, only have the symbols the dynamic
+        // linker needs
+        let symbol_sections = match (sections.get(".symtab"), sections.get(".strtab")) {
+            (Some(symtab), Some(strtab)) => Some((symtab, strtab)),
+            _ => sections.get(".dynsym").zip(sections.get(".dynstr")),
+        };
+        let symbol_table = match symbol_sections {
+            Some(((symtab_header, _), (strtab_header, _))) => {
+                let mut reader = bytereader::ConsumeReader::wrap(&obj_ref.data[symtab_header.address_range()]);
+                let st_reader = NonConsumingReader::new(&obj_ref.data[strtab_header.address_range()]);
+                SymbolTable::parse_symbol_table(symtab_header, reader, &st_reader)?
+            }
+            None => SymbolTable::default(),
+        };
 
         // todo(simon): this is hacky as shit. I've done this, because I had to figure out how dwarf elf etc actually works first
         // when it's functioning, this *will* be refactored, so that we don't create unnecessary hashmaps
@@ -116,14 +126,109 @@ impl<'object> ParsedELF<'object> {
             dwarf_sections,
             sections,
             symbol_table,
+            load_bias: 0,
         };
         Ok(pe)
     }
 
+    /// The address execution of the program starts at
+    pub fn entry_point(&self) -> usize {
+        self.header.entry_point_addr.wrapping_add(self.load_bias)
+    }
+
+    /// Whether the object can be loaded anywhere; a shared library, or a position independent executable. The
+    /// addresses in it's file are then relative to where it's loaded.
+    pub fn is_position_independent(&self) -> bool {
+        self.header.object_type == elf64::ObjectType::SharedObject
+    }
+
+    /// What's added to the addresses in the file, to get where they are in the inferior
+    pub fn load_bias(&self) -> usize {
+        self.load_bias
+    }
+
+    /// Makes the addresses of the symbols, sections and the entry point, and of what `function_containing` and
+    /// `line_table` return, be where the object is loaded, `bias` bytes from the addresses in it's file. DWARF read
+    /// directly from the sections is not relocated; subtract `load_bias` from addresses to look them up in it.
+    pub fn relocate(&mut self, bias: usize) {
+        self.symbol_table
+            .relocate(bias.wrapping_sub(self.load_bias));
+        self.load_bias = bias;
+    }
+
+    /// The load bias of the program, from the auxiliary vector of the process running it; the kernel tells where it
+    /// put the program headers, and where the entry point is. Executables that aren't position independent are
+    /// loaded where their file says.
+    pub fn load_bias_from_auxv(&self, auxv: &crate::procfs::AuxiliaryVector) -> Option<usize> {
+        if !self.is_position_independent() {
+            return Some(0);
+        }
+        match self
+            .get_program_segment_headers_of(programheader::Type::HeaderTable)
+            .and_then(|headers| headers.into_iter().next())
+        {
+            Some(header) => Some(
+                auxv.program_headers()?
+                    .wrapping_sub(header.virtual_address as usize),
+            ),
+            None => Some(auxv.entry()?.wrapping_sub(self.header.entry_point_addr)),
+        }
+    }
+
+    /// The function with debug information that `pc`, an address in the inferior, is in
+    pub fn function_containing(&self, pc: usize) -> Option<dwarf::variables::Function> {
+        let function = dwarf::variables::function_containing(
+            pc.wrapping_sub(self.load_bias),
+            self.get_dwarf_section(dwarf::Section::DebugInfo).ok()?,
+            self.get_dwarf_section(dwarf::Section::DebugAbbrev).ok()?,
+            self.get_dwarf_section(dwarf::Section::DebugStr)
+                .unwrap_or(&[]),
+        )?;
+        Some(function.relocated(self.load_bias))
+    }
+
+    /// The line table of the program, with the addresses it's loaded at
+    pub fn line_table(&self) -> Option<dwarf::linenumber::LineTable> {
+        let debug_line = self.get_dwarf_section(dwarf::Section::DebugLine).ok()?;
+        Some(dwarf::linenumber::LineTable::new(8, debug_line).relocated(self.load_bias))
+    }
+
+    /// Where the source file `file`, as the line table names it, is on this machine. Names without a directory are
+    /// relative to the directory a compilation unit was compiled in. None if it's nowhere to be found.
+    pub fn source_path(&self, file: &str) -> Option<std::path::PathBuf> {
+        let mut paths = vec![std::path::PathBuf::from(file)];
+        if !file.starts_with('/') {
+            if let (Ok(debug_info), Ok(debug_abbrev)) = (
+                self.get_dwarf_section(dwarf::Section::DebugInfo),
+                self.get_dwarf_section(dwarf::Section::DebugAbbrev),
+            ) {
+                let debug_str = self
+                    .get_dwarf_section(dwarf::Section::DebugStr)
+                    .unwrap_or(&[]);
+                for unit in dwarf::die::compilation_units(debug_info, debug_abbrev) {
+                    if let Some(directory) = unit.entries().next().and_then(|die| {
+                        die.string(dwarf::attributes::Attribute::DW_AT_comp_dir, debug_str)
+                            .map(str::to_owned)
+                    }) {
+                        paths.push(std::path::Path::new(&directory).join(file));
+                    }
+                }
+            }
+        }
+        paths.into_iter().find(|path| path.is_file())
+    }
+
     pub fn get_section_data(&'object self, name: &str) -> Option<&'object [u8]> {
         self.sections.get(name).map(|(header, sec)| sec.data())
     }
 
+    /// The virtual address section `name` is loaded at
+    pub fn get_section_address(&self, name: &str) -> Option<usize> {
+        self.sections
+            .get(name)
+            .map(|(header, _)| (header.address as usize).wrapping_add(self.load_bias))
+    }
+
     // a bit more optimized search, we don't have to hash a string first
     pub fn get_dwarf_section_data(&self, dwarf_section: dwarf::Section) -> Option<&[u8]> {
         self.dwarf_sections.get(dwarf_section)
@@ -17,8 +17,7 @@ impl Section {
             None
         };
 
-        let slice = &obj.data[section_header.section_data_offset as usize
-            ..section_header.section_data_offset as usize + section_header.size as usize];
+        let slice = &obj.data[section_header.address_range()];
 
         let data = (slice.as_ptr(), slice.len());
 
@@ -79,6 +78,10 @@ impl SectionHeader {
     /// Returns the address range where the data exists in the binary object file
     pub fn address_range(&self) -> std::ops::Range<usize> {
         let start = self.section_data_offset as usize;
+        // like .bss, which only takes up memory once loaded
+        if self.segment_type == SectionType::ProgramSpaceWithNoData {
+            return start..start;
+        }
         let end = start + self.size as usize;
         start..end
     }
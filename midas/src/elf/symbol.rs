@@ -2,6 +2,11 @@ use super::section;
 use crate::bytereader::{ConsumeReader, NonConsumingReader};
 use std::collections::HashMap;
 
+// the section indices of symbols that aren't defined in the object, and of those that aren't addresses
+const SHN_UNDEF: usize = 0;
+const SHN_ABS: usize = 0xfff1;
+
+#[derive(Default)]
 pub struct SymbolTable<'object> {
     unnamed_symbols: Vec<Symbol>,
     no_type: HashMap<&'object str, Symbol>,
@@ -62,7 +67,7 @@ impl<'object> SymbolTable<'object> {
                                 ),
                             );
                         }
-                        Type::Object => {
+                        Type::Object | Type::Common => {
                             st.objects.insert(
                                 name,
                                 Symbol::new(
@@ -74,7 +79,7 @@ impl<'object> SymbolTable<'object> {
                                 ),
                             );
                         }
-                        Type::Function => {
+                        Type::Function | Type::IndirectFunction => {
                             st.functions.insert(
                                 name,
                                 Symbol::new(
@@ -110,13 +115,14 @@ impl<'object> SymbolTable<'object> {
                                 ),
                             );
                         }
+                        // their values are offsets into the thread's storage, not addresses
+                        Type::ThreadLocal => {}
                         Type::LOProc => {
                             println!("error parsing type");
                         }
                         Type::HIProc => {
                             println!("error parsing type");
                         }
-                        _ => println!("error parsing type!?"),
                     }
                 }
             }
@@ -179,6 +185,56 @@ impl<'object> SymbolTable<'object> {
     pub fn get_function_symbol(&self, name: &str) -> Option<&Symbol> {
         self.functions.get(name)
     }
+
+    /// The function symbols by name, in no particular order
+    pub fn functions(&self) -> impl Iterator<Item = (&'object str, &Symbol)> + '_ {
+        self.functions.iter().map(|(name, symbol)| (*name, symbol))
+    }
+
+    /// The object symbols by name, in no particular order
+    pub fn objects(&self) -> impl Iterator<Item = (&'object str, &Symbol)> + '_ {
+        self.objects.iter().map(|(name, symbol)| (*name, symbol))
+    }
+
+    /// The names of the functions and objects, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &'object str> + '_ {
+        self.functions.keys().chain(self.objects.keys()).copied()
+    }
+
+    /// Moves the symbols that are addresses by `delta`, to where the object is loaded. Undefined and absolute
+    /// symbols stay where they are.
+    pub fn relocate(&mut self, delta: usize) {
+        let symbols = self
+            .unnamed_symbols
+            .iter_mut()
+            .chain(self.no_type.values_mut())
+            .chain(self.objects.values_mut())
+            .chain(self.functions.values_mut())
+            .chain(self.sections.values_mut());
+        for symbol in symbols.filter(|symbol| !matches!(symbol.section_index, SHN_UNDEF | SHN_ABS)) {
+            symbol.value = symbol
+                .value
+                .and_then(|value| std::num::NonZeroUsize::new(value.get().wrapping_add(delta)));
+        }
+    }
+
+    /// Finds the function or object symbol that `address` lies within. Returns the name of the symbol and the offset
+    /// of `address` into it, i.e. what is needed to display an address as <main+12>.
+    pub fn symbolize(&self, address: usize) -> Option<(&'object str, usize)> {
+        self.functions
+            .iter()
+            .chain(self.objects.iter())
+            .find_map(|(name, symbol)| {
+                let start = symbol.value?.get();
+                // zero sized symbols (hand written assembly, mostly) can still be the target of an address
+                let end = start + symbol.size.max(1);
+                if (start..end).contains(&address) {
+                    Some((*name, address - start))
+                } else {
+                    None
+                }
+            })
+    }
 }
 
 pub struct Symbol {
@@ -231,6 +287,8 @@ pub enum Binding {
     Local = 0,
     Global = 1,
     Weak = 2,
+    /// GNU's STB_GNU_UNIQUE; a global symbol the dynamic linker makes sure there's only one of
+    Unique = 10,
     LOProc = 13,
     HIProc = 15,
 }
@@ -243,18 +301,36 @@ pub enum Type {
     Function = 2,
     Section = 3,
     File = 4,
+    Common = 5,
+    ThreadLocal = 6,
+    /// GNU's STT_GNU_IFUNC; a function that returns the address of the implementation to use, like one of the
+    /// vectorized versions of memcpy
+    IndirectFunction = 10,
     LOProc = 13,
     HIProc = 15,
 }
 
 #[inline]
 pub fn parse_symbol_info(byte: u8) -> (Binding, Type) {
-    let symbol_binding = byte >> 4;
-    let symbol_type = byte & 0x0f;
-    unsafe {
-        (
-            std::mem::transmute::<u8, Binding>(symbol_binding),
-            std::mem::transmute::<u8, Type>(symbol_type),
-        )
-    }
+    let symbol_binding = match byte >> 4 {
+        0 => Binding::Local,
+        2 => Binding::Weak,
+        10 => Binding::Unique,
+        13 | 14 => Binding::LOProc,
+        15 => Binding::HIProc,
+        _ => Binding::Global,
+    };
+    let symbol_type = match byte & 0x0f {
+        1 => Type::Object,
+        2 => Type::Function,
+        3 => Type::Section,
+        4 => Type::File,
+        5 => Type::Common,
+        6 => Type::ThreadLocal,
+        10 => Type::IndirectFunction,
+        13 | 14 => Type::LOProc,
+        15 => Type::HIProc,
+        _ => Type::None,
+    };
+    (symbol_binding, symbol_type)
 }
@@ -0,0 +1,343 @@
+//! Parsing and evaluation of the (C-like, integer only) expressions that the user can type where an address or a
+//! value is expected, like `x/4gx $rsp + 8` or `x/s *argv`. Names, registers and memory are resolved through an
+//! `EvaluationContext`, so that the same expressions can be evaluated against a live process or anything else that can
+//! answer those questions.
+use nixwrap::MidasSysResultDynamic;
+
+/// What an expression needs to know about the inferior, to be evaluated
+pub trait EvaluationContext {
+    /// The value of register `name` (written without the leading $)
+    fn register(&self, name: &str) -> Option<u64>;
+    /// The value of the symbol or variable `name`
+    fn symbol(&self, name: &str) -> Option<u64>;
+    /// The address of the symbol or variable `name`
+    fn address_of(&self, name: &str) -> Option<u64>;
+    /// Read a pointer sized value from `address`
+    fn read_pointer(&self, address: u64) -> Option<u64>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+    Complement,
+    Dereference,
+    AddressOf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    ShiftLeft,
+    ShiftRight,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    LogicalAnd,
+    LogicalOr,
+}
+
+impl BinaryOperator {
+    // binding power, C precedence rules. Higher binds tighter.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::LogicalOr => 1,
+            BinaryOperator::LogicalAnd => 2,
+            BinaryOperator::BitOr => 3,
+            BinaryOperator::BitXor => 4,
+            BinaryOperator::BitAnd => 5,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 6,
+            BinaryOperator::Less
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterOrEqual => 7,
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 8,
+            BinaryOperator::Add | BinaryOperator::Subtract => 9,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder => 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Number(u64),
+    Register(String),
+    Name(String),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(u64),
+    Register(String),
+    Name(String),
+    Operator(&'static str),
+    OpenParen,
+    CloseParen,
+}
+
+const OPERATORS: [&str; 23] = [
+    "<<", ">>", "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "&", "|", "^", "<", ">", "!", "~", "(",
+    ")", "=",
+];
+
+/// Parses an integer literal in any of the bases C and gdb users expect; 0x hex, 0o or leading 0 octal, 0b binary,
+/// or decimal.
+pub fn parse_number(literal: &str) -> Option<u64> {
+    let literal = literal.replace('_', "");
+    let lower = literal.to_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        u64::from_str_radix(binary, 2).ok()
+    } else if let Some(octal) = lower.strip_prefix("0o") {
+        u64::from_str_radix(octal, 8).ok()
+    } else if lower.len() > 1 && lower.starts_with('0') {
+        u64::from_str_radix(&lower[1..], 8).ok()
+    } else {
+        lower.parse::<u64>().ok()
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '@'
+}
+
+fn tokenize(input: &str) -> MidasSysResultDynamic<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+        } else if c.is_ascii_digit() {
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            let literal: String = chars[start..index].iter().collect();
+            tokens.push(Token::Number(
+                parse_number(&literal).ok_or(format!("invalid number '{}'", literal))?,
+            ));
+        } else if c == '$' {
+            let start = index + 1;
+            index += 1;
+            while index < chars.len() && is_name_char(chars[index]) {
+                index += 1;
+            }
+            if start == index {
+                return Err("expected register name after '$'".into());
+            }
+            tokens.push(Token::Register(chars[start..index].iter().collect()));
+        } else if is_name_char(c) || c == ':' {
+            // C++ names can contain ::, which is why : is allowed here but not in is_name_char (it's not valid in a register name)
+            let start = index;
+            while index < chars.len() && (is_name_char(chars[index]) || chars[index] == ':') {
+                index += 1;
+            }
+            tokens.push(Token::Name(chars[start..index].iter().collect()));
+        } else if c == '(' {
+            tokens.push(Token::OpenParen);
+            index += 1;
+        } else if c == ')' {
+            tokens.push(Token::CloseParen);
+            index += 1;
+        } else {
+            let rest: String = chars[index..].iter().take(2).collect();
+            let operator = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or(format!("unexpected character '{}'", c))?;
+            if *operator == "=" {
+                return Err("assignment is not supported in expressions, did you mean '=='?".into());
+            }
+            tokens.push(Token::Operator(operator));
+            index += operator.len();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn binary_operator(&self) -> Option<BinaryOperator> {
+        let op = match self.peek()? {
+            Token::Operator(op) => *op,
+            _ => return None,
+        };
+        Some(match op {
+            "+" => BinaryOperator::Add,
+            "-" => BinaryOperator::Subtract,
+            "*" => BinaryOperator::Multiply,
+            "/" => BinaryOperator::Divide,
+            "%" => BinaryOperator::Remainder,
+            "<<" => BinaryOperator::ShiftLeft,
+            ">>" => BinaryOperator::ShiftRight,
+            "&" => BinaryOperator::BitAnd,
+            "|" => BinaryOperator::BitOr,
+            "^" => BinaryOperator::BitXor,
+            "==" => BinaryOperator::Equal,
+            "!=" => BinaryOperator::NotEqual,
+            "<" => BinaryOperator::Less,
+            "<=" => BinaryOperator::LessOrEqual,
+            ">" => BinaryOperator::Greater,
+            ">=" => BinaryOperator::GreaterOrEqual,
+            "&&" => BinaryOperator::LogicalAnd,
+            "||" => BinaryOperator::LogicalOr,
+            _ => return None,
+        })
+    }
+
+    // precedence climbing; parses operators that bind at least as tight as `min_precedence`
+    fn expression(&mut self, min_precedence: u8) -> MidasSysResultDynamic<Expression> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.binary_operator() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.next();
+            let rhs = self.expression(op.precedence() + 1)?;
+            lhs = Expression::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> MidasSysResultDynamic<Expression> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expression::Number(value)),
+            Some(Token::Register(name)) => Ok(Expression::Register(name)),
+            Some(Token::Name(name)) => Ok(Expression::Name(name)),
+            Some(Token::OpenParen) => {
+                let expr = self.expression(0)?;
+                match self.next() {
+                    Some(Token::CloseParen) => Ok(expr),
+                    _ => Err("expected ')'".into()),
+                }
+            }
+            Some(Token::Operator(op)) => {
+                let op = match op {
+                    "-" => UnaryOperator::Negate,
+                    "!" => UnaryOperator::Not,
+                    "~" => UnaryOperator::Complement,
+                    "*" => UnaryOperator::Dereference,
+                    "&" => UnaryOperator::AddressOf,
+                    "+" => return self.unary(),
+                    _ => return Err(format!("unexpected operator '{}'", op)),
+                };
+                Ok(Expression::Unary(op, Box::new(self.unary()?)))
+            }
+            Some(Token::CloseParen) => Err("unexpected ')'".into()),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(input: &str) -> MidasSysResultDynamic<Expression> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err("empty expression".into());
+        }
+        let expr = parser.expression(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {:?} after expression", token)),
+        }
+    }
+
+    /// Evaluates the expression. All arithmetic is done on 64-bit two's complement values, and comparisons are signed,
+    /// like a C expression where everything is a `long`.
+    pub fn evaluate(&self, context: &dyn EvaluationContext) -> MidasSysResultDynamic<u64> {
+        match self {
+            Expression::Number(value) => Ok(*value),
+            Expression::Register(name) => context
+                .register(name)
+                .ok_or(format!("unknown register '${}'", name)),
+            Expression::Name(name) => context
+                .symbol(name)
+                .ok_or(format!("no symbol \"{}\" in current context", name)),
+            Expression::Unary(UnaryOperator::AddressOf, operand) => match operand.as_ref() {
+                Expression::Name(name) => context
+                    .address_of(name)
+                    .ok_or(format!("no symbol \"{}\" in current context", name)),
+                // &*p == p
+                Expression::Unary(UnaryOperator::Dereference, pointer) => pointer.evaluate(context),
+                _ => Err("attempt to take address of value not located in memory".into()),
+            },
+            Expression::Unary(op, operand) => {
+                let value = operand.evaluate(context)?;
+                match op {
+                    UnaryOperator::Negate => Ok(value.wrapping_neg()),
+                    UnaryOperator::Not => Ok((value == 0) as u64),
+                    UnaryOperator::Complement => Ok(!value),
+                    UnaryOperator::Dereference => context
+                        .read_pointer(value)
+                        .ok_or(format!("cannot access memory at address 0x{:x}", value)),
+                    UnaryOperator::AddressOf => unreachable!("handled above"),
+                }
+            }
+            Expression::Binary(op, lhs, rhs) => {
+                let lhs = lhs.evaluate(context)?;
+                // short circuit, so that `p && *p` doesn't read through a null pointer
+                match op {
+                    BinaryOperator::LogicalAnd if lhs == 0 => return Ok(0),
+                    BinaryOperator::LogicalOr if lhs != 0 => return Ok(1),
+                    _ => {}
+                }
+                let rhs = rhs.evaluate(context)?;
+                let (signed_lhs, signed_rhs) = (lhs as i64, rhs as i64);
+                Ok(match op {
+                    BinaryOperator::Add => lhs.wrapping_add(rhs),
+                    BinaryOperator::Subtract => lhs.wrapping_sub(rhs),
+                    BinaryOperator::Multiply => lhs.wrapping_mul(rhs),
+                    BinaryOperator::Divide | BinaryOperator::Remainder if rhs == 0 => {
+                        return Err("division by zero".into())
+                    }
+                    BinaryOperator::Divide => signed_lhs.wrapping_div(signed_rhs) as u64,
+                    BinaryOperator::Remainder => signed_lhs.wrapping_rem(signed_rhs) as u64,
+                    BinaryOperator::ShiftLeft => lhs.wrapping_shl(rhs as u32),
+                    BinaryOperator::ShiftRight => signed_lhs.wrapping_shr(rhs as u32) as u64,
+                    BinaryOperator::BitAnd => lhs & rhs,
+                    BinaryOperator::BitOr => lhs | rhs,
+                    BinaryOperator::BitXor => lhs ^ rhs,
+                    BinaryOperator::Equal => (lhs == rhs) as u64,
+                    BinaryOperator::NotEqual => (lhs != rhs) as u64,
+                    BinaryOperator::Less => (signed_lhs < signed_rhs) as u64,
+                    BinaryOperator::LessOrEqual => (signed_lhs <= signed_rhs) as u64,
+                    BinaryOperator::Greater => (signed_lhs > signed_rhs) as u64,
+                    BinaryOperator::GreaterOrEqual => (signed_lhs >= signed_rhs) as u64,
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => (rhs != 0) as u64,
+                })
+            }
+        }
+    }
+}
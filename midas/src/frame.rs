@@ -0,0 +1,425 @@
+//! The frames of the stopped inferior; the function each is executing, it's canonical frame address (CFA) and the
+//! variables in scope, whose values can be read through it. The frames of the callers are found by unwinding the
+//! stack with the call frame information (CFI) of the code.
+use nixwrap::{ptrace::UserRegisters, MidasSysResultDynamic};
+
+use crate::{
+    dwarf::{
+        callframe::{find_unwind_row, CallFrameSection, CfaRule, RegisterRule, UnwindRow},
+        stack::{self, register_name, Location, LocationContext},
+        variables::{global_variable, Function, Variable},
+        Section,
+    },
+    elf::ParsedELF,
+    expression::EvaluationContext,
+    shared_library::SharedLibraries,
+    target::Target,
+    types::Address,
+};
+
+pub struct Frame {
+    pub pc: usize,
+    /// How many calls out from the innermost frame this frame is; 0 for the innermost frame
+    pub level: usize,
+    /// The value of the stack pointer before the call that created the frame. It stays the same for as long as the
+    /// frame lives, no matter what the function does with it's stack, which is what makes it the frame's identity.
+    pub cfa: Option<u64>,
+    /// The function with debug information that the pc is in
+    pub function: Option<Function>,
+    registers: UserRegisters,
+    // the load bias of the object the frame's code is in, for the addresses in it's DWARF
+    load_bias: usize,
+}
+
+struct FrameLocation<'a> {
+    frame: &'a Frame,
+    target: &'a dyn Target,
+    frame_base: Option<u64>,
+}
+
+impl<'a> LocationContext for FrameLocation<'a> {
+    fn register(&self, number: u16) -> Option<u64> {
+        self.frame.registers.get_by_name(register_name(number)?)
+    }
+
+    fn frame_base(&self) -> Option<u64> {
+        self.frame_base
+    }
+
+    fn call_frame_cfa(&self) -> Option<u64> {
+        self.frame.cfa
+    }
+
+    fn read_memory(&self, address: u64) -> Option<u64> {
+        read_integer(self.target, address, 8, false)
+    }
+
+    fn load_bias(&self) -> u64 {
+        self.frame.load_bias as u64
+    }
+}
+
+fn read_integer(target: &dyn Target, address: u64, size: usize, signed: bool) -> Option<u64> {
+    let read = target.read_memory(Address(address as usize), size).ok()?;
+    if read.is_partial() {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    bytes[..size].copy_from_slice(&read.data);
+    Some(extend(u64::from_le_bytes(bytes), size, signed))
+}
+
+// sign or zero extends the `size` byte value in the low bytes of `value`
+pub(crate) fn extend(value: u64, size: usize, signed: bool) -> u64 {
+    let unused = 64 - size as u32 * 8;
+    if signed {
+        (((value << unused) as i64) >> unused) as u64
+    } else {
+        (value << unused) >> unused
+    }
+}
+
+// the unwind row for `pc`, from .eh_frame, or .debug_frame if the binary has no .eh_frame entry for it. .eh_frame
+// addresses are relative to where it's loaded, and .debug_frame's are the addresses in the file.
+fn unwind_row(elf: &ParsedELF, pc: usize) -> Option<UnwindRow> {
+    let eh_frame = elf
+        .get_dwarf_section(Section::EhFrame)
+        .ok()
+        .and_then(|data| {
+            let address = elf.get_section_address(".eh_frame")?;
+            find_unwind_row(data, CallFrameSection::EhFrame { address }, pc)
+                .ok()
+                .flatten()
+        });
+    eh_frame.or_else(|| {
+        let data = elf.get_dwarf_section(Section::DebugFrame).ok()?;
+        find_unwind_row(
+            data,
+            CallFrameSection::DebugFrame,
+            pc.wrapping_sub(elf.load_bias()),
+        )
+        .ok()
+        .flatten()
+    })
+}
+
+// the most frames a backtrace has; a stack deeper than this is more likely to be a corrupt one
+const MAX_FRAMES: usize = 4096;
+
+impl Frame {
+    /// The innermost frame of the stopped inferior
+    pub fn current(target: &dyn Target, elf: &ParsedELF) -> MidasSysResultDynamic<Frame> {
+        Ok(Frame::new(target, elf, target.read_registers()?, 0))
+    }
+
+    /// The frame `level` calls out from the innermost one, with the registers `registers`, whose code is in `elf`.
+    /// The registers of the frames that aren't the innermost, are what `caller_registers` recovered.
+    pub fn new(target: &dyn Target, elf: &ParsedELF, registers: UserRegisters, level: usize) -> Frame {
+        let mut frame = Frame {
+            pc: registers.pc() as usize,
+            level,
+            cfa: None,
+            function: None,
+            registers,
+            load_bias: elf.load_bias(),
+        };
+        let pc = frame.lookup_pc();
+        frame.function = elf.function_containing(pc);
+        frame.cfa = match unwind_row(elf, pc).map(|row| row.cfa) {
+            Some(CfaRule::RegisterOffset { register, offset }) => register_name(register)
+                .and_then(|name| frame.registers.get_by_name(name))
+                .map(|value| value.wrapping_add(offset as u64)),
+            Some(CfaRule::Expression(expression)) => {
+                match stack::evaluate(&expression, &frame.location(target, None)) {
+                    Ok(Location::Address(cfa)) => Some(cfa),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+        frame
+    }
+
+    /// The address of the code the frame is executing. For the innermost frame that's the pc; the pc of an outer
+    /// frame is the return address of the call it made, the instruction after the call, which can be on the next
+    /// line, or in the next function when the call doesn't return. The call itself is what's in the frame.
+    pub fn lookup_pc(&self) -> usize {
+        if self.level == 0 {
+            self.pc
+        } else {
+            self.pc.saturating_sub(1)
+        }
+    }
+
+    /// The registers of the frame that called this one, as they were when it made the call. They're recovered with
+    /// the CFI of `elf`, which must be the object this frame's code is in; registers it says nothing about keep their
+    /// value. None for the outermost frame, and frames without CFI.
+    pub fn caller_registers(&self, target: &dyn Target, elf: &ParsedELF) -> Option<UserRegisters> {
+        let cfa = self.cfa?;
+        let row = unwind_row(elf, self.lookup_pc())?;
+        let mut registers = self.registers.clone();
+        let mut return_address = None;
+        for (&number, rule) in row.registers.iter() {
+            let value = match rule {
+                // an undefined return address is how the outermost frame is marked
+                RegisterRule::Undefined if number == row.return_address_register => return None,
+                RegisterRule::Undefined | RegisterRule::SameValue => continue,
+                RegisterRule::Offset(offset) => read_integer(target, cfa.wrapping_add(*offset as u64), 8, false)?,
+                RegisterRule::ValOffset(offset) => cfa.wrapping_add(*offset as u64),
+                RegisterRule::Register(other) => self.registers.get_by_name(register_name(*other)?)?,
+                RegisterRule::Expression(expression) => {
+                    match stack::evaluate(expression, &self.location(target, None)).ok()? {
+                        Location::Address(address) => read_integer(target, address, 8, false)?,
+                        _ => return None,
+                    }
+                }
+                RegisterRule::ValExpression(expression) => {
+                    match stack::evaluate(expression, &self.location(target, None)).ok()? {
+                        Location::Address(value) | Location::Value(value) => value,
+                        Location::Register(other) => self.registers.get_by_name(register_name(other)?)?,
+                    }
+                }
+            };
+            if number == row.return_address_register {
+                return_address = Some(value);
+            } else if let Some(name) = register_name(number) {
+                registers.set_by_name(name, value);
+            }
+        }
+        // the caller's stack pointer is where it was before the call; the CFA
+        registers.set_by_name("rsp", cfa);
+        registers.set_by_name("rip", return_address.filter(|address| *address != 0)?);
+        Some(registers)
+    }
+
+    /// Where the frame's function returns to; the call that created the frame pushed it right below the CFA
+    pub fn return_address(&self, target: &dyn Target) -> Option<u64> {
+        read_integer(target, self.cfa?.checked_sub(8)?, 8, false)
+    }
+
+    fn location<'a>(&'a self, target: &'a dyn Target, frame_base: Option<u64>) -> FrameLocation<'a> {
+        FrameLocation {
+            frame: self,
+            target,
+            frame_base,
+        }
+    }
+
+    /// Variable `name`; a local of the frame's function if there is one by that name, otherwise a global
+    pub fn variable(&self, elf: &ParsedELF, name: &str) -> Option<Variable> {
+        if let Some(local) = self
+            .function
+            .as_ref()
+            .and_then(|function| function.variable(name))
+        {
+            return Some(local.clone());
+        }
+        global_variable(
+            name,
+            elf.get_dwarf_section(Section::DebugInfo).ok()?,
+            elf.get_dwarf_section(Section::DebugAbbrev).ok()?,
+            elf.get_dwarf_section(Section::DebugStr).unwrap_or(&[]),
+        )
+    }
+
+    /// Where `variable` lives in this frame
+    pub fn locate(&self, target: &dyn Target, variable: &Variable) -> MidasSysResultDynamic<Location> {
+        let location = variable
+            .location
+            .as_ref()
+            .ok_or(format!("\"{}\" has been optimized out", variable.name))?;
+        let frame_base = match self
+            .function
+            .as_ref()
+            .and_then(|function| function.frame_base.as_ref())
+        {
+            Some(frame_base) => match stack::evaluate(frame_base, &self.location(target, None))? {
+                Location::Address(address) | Location::Value(address) => Some(address),
+                Location::Register(number) => register_name(number).and_then(|name| self.registers.get_by_name(name)),
+            },
+            None => None,
+        };
+        stack::evaluate(location, &self.location(target, frame_base))
+    }
+
+    /// The value of `variable` in this frame, sign extended if it's type is signed. Only variables of up to 8 bytes
+    /// have a value that fits.
+    pub fn value_of(&self, target: &dyn Target, variable: &Variable) -> MidasSysResultDynamic<u64> {
+        if variable.size == 0 || variable.size > 8 {
+            return Err(format!(
+                "\"{}\" is not an integer or a pointer; it's value can't be used in an expression",
+                variable.name
+            ));
+        }
+        match self.locate(target, variable)? {
+            Location::Address(address) => read_integer(target, address, variable.size, variable.signed)
+                .ok_or(format!("Cannot access memory at address 0x{:x}", address)),
+            Location::Register(number) => register_name(number)
+                .and_then(|name| self.registers.get_by_name(name))
+                .map(|value| extend(value, variable.size, variable.signed))
+                .ok_or(format!("can't read DWARF register {}", number)),
+            Location::Value(value) => Ok(extend(value, variable.size, variable.signed)),
+        }
+    }
+}
+
+// runs `f` with the object that the code at `pc` is in; a shared library, or the program
+fn in_object<T>(elf: &ParsedELF, libraries: &SharedLibraries, pc: usize, f: impl FnOnce(&ParsedELF) -> T) -> T {
+    match libraries.containing(pc).and_then(|library| library.elf()) {
+        Some(library) => f(&library),
+        None => f(elf),
+    }
+}
+
+// whether `frame` is main's; frames further out are the C runtime's, which gdb doesn't show either
+fn is_main(elf: &ParsedELF, frame: &Frame) -> bool {
+    match &frame.function {
+        Some(function) => function.name == "main",
+        None => elf
+            .symbol_table
+            .symbolize(frame.lookup_pc())
+            .is_some_and(|(name, _)| name == "main"),
+    }
+}
+
+/// The frames of the stopped inferior, from the innermost out to main's, or for as long as the stack can be unwound.
+/// Frames in the code of shared libraries are unwound with the library's CFI.
+pub fn backtrace(
+    target: &dyn Target,
+    elf: &ParsedELF,
+    libraries: &SharedLibraries,
+) -> MidasSysResultDynamic<Vec<Frame>> {
+    let registers = target.read_registers()?;
+    let pc = registers.pc() as usize;
+    let mut frames = vec![in_object(elf, libraries, pc, |object| {
+        Frame::new(target, object, registers, 0)
+    })];
+    while frames.len() < MAX_FRAMES {
+        let frame = frames.last().unwrap();
+        if is_main(elf, frame) {
+            break;
+        }
+        let registers = match in_object(elf, libraries, frame.lookup_pc(), |object| {
+            frame.caller_registers(target, object)
+        }) {
+            Some(registers) => registers,
+            None => break,
+        };
+        // the pc of the caller is a return address, so it's the instruction before it that's in the caller's code
+        let pc = (registers.pc() as usize).saturating_sub(1);
+        let caller = in_object(elf, libraries, pc, |object| {
+            Frame::new(target, object, registers, frame.level + 1)
+        });
+        // the stack grows down, so callers' frames are above their callees'; anything else is a corrupt stack
+        if caller
+            .cfa
+            .zip(frame.cfa)
+            .is_some_and(|(caller, callee)| caller <= callee)
+        {
+            break;
+        }
+        frames.push(caller);
+    }
+    Ok(frames)
+}
+
+/// Resolves the names, registers and memory of expressions against the inferior. Names are looked up as variables in
+/// the frame first, then as globals and then as symbols; registers are the frame's.
+pub struct InferiorContext<'a, 'elf> {
+    pub target: &'a dyn Target,
+    pub elf: &'a ParsedELF<'elf>,
+    pub frame: Option<Frame>,
+}
+
+impl<'a, 'elf> InferiorContext<'a, 'elf> {
+    /// Evaluates in the frame the inferior is stopped in
+    pub fn new(target: &'a dyn Target, elf: &'a ParsedELF<'elf>) -> InferiorContext<'a, 'elf> {
+        InferiorContext {
+            target,
+            elf,
+            frame: Frame::current(target, elf).ok(),
+        }
+    }
+
+    /// The size of the type of the variable `name`, a local of the frame or a global, that `address_of` finds; None
+    /// without debug information for it
+    pub fn size_of(&self, name: &str) -> Option<usize> {
+        let variable = self.frame.as_ref()?.variable(self.elf, name)?;
+        Some(variable.size).filter(|size| *size != 0)
+    }
+
+    /// Evaluates in `frame`, one of the frames of a backtrace
+    pub fn in_frame(target: &'a dyn Target, elf: &'a ParsedELF<'elf>, frame: Frame) -> InferiorContext<'a, 'elf> {
+        InferiorContext {
+            target,
+            elf,
+            frame: Some(frame),
+        }
+    }
+}
+
+impl<'a, 'elf> EvaluationContext for InferiorContext<'a, 'elf> {
+    fn register(&self, name: &str) -> Option<u64> {
+        match &self.frame {
+            Some(frame) => frame.registers.get_by_name(name),
+            None => self.target.read_registers().ok()?.get_by_name(name),
+        }
+    }
+
+    // variables evaluate to their value. Without type information, the best we can do for any other name is it's
+    // address; which is also what a function name evaluates to in C
+    fn symbol(&self, name: &str) -> Option<u64> {
+        if let Some(frame) = &self.frame {
+            if let Some(variable) = frame.variable(self.elf, name) {
+                return frame.value_of(self.target, &variable).ok();
+            }
+        }
+        self.address_of(name)
+    }
+
+    fn address_of(&self, name: &str) -> Option<u64> {
+        if let Some(frame) = &self.frame {
+            if let Some(variable) = frame.variable(self.elf, name) {
+                return match frame.locate(self.target, &variable).ok()? {
+                    Location::Address(address) => Some(address),
+                    _ => None,
+                };
+            }
+        }
+        if let Some(value) = self.elf.symbol_table.get_symbol(name).and_then(|s| s.value) {
+            return Some(value.get() as u64);
+        }
+        crate::find_low_pc_of(
+            name,
+            self.elf.get_dwarf_section(Section::DebugInfo).ok()?,
+            self.elf.get_dwarf_section(Section::DebugPubNames).ok()?,
+            self.elf.get_dwarf_section(Section::DebugAbbrev).ok()?,
+        )
+        .map(|addr| (addr + self.elf.load_bias()) as u64)
+    }
+
+    fn read_pointer(&self, address: u64) -> Option<u64> {
+        read_integer(self.target, address, 8, false)
+    }
+}
+
+/// `value` in the notation a C programmer reads it in; very large or very small values in scientific notation,
+/// instead of with hundreds of digits
+pub fn format_float(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude != 0.0 && magnitude.is_finite() && !(1e-4..1e16).contains(&magnitude) {
+        format!("{:e}", value)
+    } else {
+        format!("{}", value)
+    }
+}
+
+/// `value`, as the type of `variable` says it's to be read; a float, or a signed or unsigned integer
+pub fn format_variable(variable: &Variable, value: u64) -> String {
+    match variable {
+        variable if variable.float && variable.size == 4 => format_float(f32::from_bits(value as u32) as f64),
+        variable if variable.float => format_float(f64::from_bits(value)),
+        variable if variable.signed => (value as i64).to_string(),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,151 @@
+//! Hardware breakpoints and watchpoints, using the x86-64 debug registers. DR0-DR3 hold up to four addresses, DR7
+//! says which of them are enabled, on what kind of access they trigger and how many bytes they cover. After the
+//! inferior stops with a SIGTRAP, DR6 tells us which of them triggered. All of them live in the inferior's
+//! `struct user` and are read and written with PTRACE_PEEKUSER/PTRACE_POKEUSER.
+use crate::types::Address;
+use nixwrap::{ptrace, MidasSysResultDynamic, Pid};
+
+/// The number of debug address registers, DR0 to DR3
+pub const SLOTS: usize = 4;
+const DR6: usize = 6;
+const DR7: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// Break when the instruction at the address is about to execute
+    Execute,
+    /// Break after the watched bytes have been written to
+    Write,
+    /// Break after the watched bytes have been read or written. x86 has no read-only watchpoints.
+    ReadWrite,
+}
+
+impl WatchKind {
+    // the R/W field of DR7
+    fn condition_bits(&self) -> u64 {
+        match self {
+            WatchKind::Execute => 0b00,
+            WatchKind::Write => 0b01,
+            WatchKind::ReadWrite => 0b11,
+        }
+    }
+}
+
+// the LEN field of DR7. Yes, 8 bytes is 0b10.
+fn length_bits(len: usize) -> MidasSysResultDynamic<u64> {
+    match len {
+        1 => Ok(0b00),
+        2 => Ok(0b01),
+        8 => Ok(0b10),
+        4 => Ok(0b11),
+        _ => Err(format!(
+            "hardware watchpoints can watch 1, 2, 4 or 8 bytes, not {}",
+            len
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HWBreakpoint {
+    /// Which of DR0-DR3 holds the address
+    pub slot: usize,
+    pub address: Address,
+    pub kind: WatchKind,
+    pub len: usize,
+}
+
+/// The debug registers of a traced process, and what we've programmed them with
+#[derive(Debug, Default)]
+pub struct DebugRegisters {
+    slots: [Option<HWBreakpoint>; SLOTS],
+}
+
+impl DebugRegisters {
+    /// Programs a free debug register to trigger on `kind` accesses of the `len` bytes at `address`. Execute
+    /// breakpoints must have a length of 1 and watched addresses must be aligned to their length.
+    pub fn set(
+        &mut self,
+        pid: Pid,
+        address: Address,
+        kind: WatchKind,
+        len: usize,
+    ) -> MidasSysResultDynamic<HWBreakpoint> {
+        if kind == WatchKind::Execute && len != 1 {
+            return Err("hardware breakpoints on execution must have a length of 1".into());
+        }
+        let length = length_bits(len)?;
+        if !address.value().is_multiple_of(len) {
+            return Err(format!(
+                "address 0x{:x} is not aligned to the watched length of {} bytes",
+                address.value(),
+                len
+            ));
+        }
+        let slot = self
+            .slots
+            .iter()
+            .position(|slot| slot.is_none())
+            .ok_or(format!("all {} hardware debug registers are in use", SLOTS))?;
+
+        // the address must be in place before DR7 enables it, or the kernel rejects the write to DR7
+        ptrace::set_debug_register(pid, slot, address.value() as u64)?;
+        let mut dr7 = ptrace::get_debug_register(pid, DR7)?;
+        let control_shift = 16 + slot * 4;
+        dr7 &= !(0b1111 << control_shift);
+        dr7 |= (kind.condition_bits() | length << 2) << control_shift;
+        // the local enable bit
+        dr7 |= 1 << (slot * 2);
+        ptrace::set_debug_register(pid, DR7, dr7)?;
+
+        let hw_breakpoint = HWBreakpoint {
+            slot,
+            address,
+            kind,
+            len,
+        };
+        self.slots[slot] = Some(hw_breakpoint);
+        Ok(hw_breakpoint)
+    }
+
+    /// Disables and frees debug register `slot`
+    pub fn remove(&mut self, pid: Pid, slot: usize) -> MidasSysResultDynamic<HWBreakpoint> {
+        let hw_breakpoint = self.forget(slot)?;
+        let mut dr7 = ptrace::get_debug_register(pid, DR7)?;
+        dr7 &= !(0b1111 << (16 + slot * 4));
+        dr7 &= !(0b11 << (slot * 2));
+        ptrace::set_debug_register(pid, DR7, dr7)?;
+        ptrace::set_debug_register(pid, slot, 0)?;
+        Ok(hw_breakpoint)
+    }
+
+    /// Frees `slot` without touching the debug registers; for when the process they belonged to is gone
+    pub fn forget(&mut self, slot: usize) -> MidasSysResultDynamic<HWBreakpoint> {
+        self.slots
+            .get_mut(slot)
+            .and_then(|slot| slot.take())
+            .ok_or(format!(
+                "no hardware breakpoint in debug register DR{}",
+                slot
+            ))
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&HWBreakpoint> {
+        self.slots.get(slot).and_then(|slot| slot.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HWBreakpoint> {
+        self.slots.iter().flatten()
+    }
+
+    /// Decodes DR6 to find which of our breakpoints made the inferior trap, if any. DR6 is then cleared, since
+    /// the status bits are sticky; the CPU never clears them on it's own.
+    pub fn triggered(&self, pid: Pid) -> MidasSysResultDynamic<Option<HWBreakpoint>> {
+        let dr6 = ptrace::get_debug_register(pid, DR6)?;
+        let status = dr6 & 0b1111;
+        if status == 0 {
+            return Ok(None);
+        }
+        ptrace::set_debug_register(pid, DR6, 0)?;
+        Ok(self.get(status.trailing_zeros() as usize).copied())
+    }
+}
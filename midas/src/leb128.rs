@@ -3,6 +3,7 @@ use crate::{MidasError, MidasSysResult};
 // These algorithms; taken directly from the DWARF 5.0 standards examples for algorithms to encode and decode signed and unsigned LEB128's
 
 const LEB128_MASK: u8 = 0b0111_1111;
+const LEB128_SIGN_BIT: u8 = 0b0100_0000;
 
 pub struct DecodeResult<T> {
     pub value: T,
@@ -51,7 +52,7 @@ pub fn decode_signed(bytes: &[u8]) -> MidasSysResult<DecodeResult<i64>> {
             break 'decode;
         }
     }
-    if shift < size && byte & !LEB128_MASK != 0 {
+    if shift < size && byte & LEB128_SIGN_BIT != 0 {
         result |= -(1 << shift);
     }
     Ok(DecodeResult {
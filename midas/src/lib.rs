@@ -2,13 +2,23 @@ use std::str::Utf8Error;
 
 extern crate linuxwrapper as nixwrap;
 
+pub mod breakpoints;
 pub mod bytereader;
 pub mod commands;
+pub mod disassemble;
 pub mod dwarf;
 pub mod elf;
+pub mod expression;
+pub mod frame;
+pub mod hardware_breakpoint;
+pub mod json;
 // used to live in /dwarf module, but moved here, due to wrapping reading operations in bytereader::Reader
 pub mod leb128;
+pub mod procfs;
+pub mod session;
+pub mod shared_library;
 pub mod software_breakpoint;
+pub mod stepping;
 pub mod target;
 pub mod types;
 pub mod utils;
@@ -0,0 +1,229 @@
+//! What the kernel tells about a process through /proc/<pid>; the regions of memory it has mapped, and the
+//! auxiliary vector it was started with. The auxiliary vector says where the kernel loaded the program, the dynamic
+//! linker and the vDSO, which is what finding the program's load bias and it's shared libraries start from.
+use std::ops::Range;
+
+use nixwrap::{MidasSysResultDynamic, Pid};
+
+/// The address of the program's program headers
+pub const AT_PHDR: u64 = 3;
+/// Where the dynamic linker is loaded; 0 for statically linked programs
+pub const AT_BASE: u64 = 7;
+/// The program's entry point
+pub const AT_ENTRY: u64 = 9;
+/// Where the vDSO's ELF header is
+pub const AT_SYSINFO_EHDR: u64 = 33;
+
+// the key that ends the vector
+const AT_NULL: u64 = 0;
+
+/// The access a process has to a region of it's memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    /// Whether writes are seen by the other processes mapping the same file, rather than being copied on write
+    pub shared: bool,
+}
+
+impl std::fmt::Display for Permissions {
+    /// Like /proc/<pid>/maps shows them; r-xp
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}{}",
+            flag(self.read, 'r'),
+            flag(self.write, 'w'),
+            flag(self.execute, 'x'),
+            if self.shared { 's' } else { 'p' }
+        )
+    }
+}
+
+/// A mapped region of a process' memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub range: Range<usize>,
+    pub permissions: Permissions,
+    /// Where in the file the region starts; 0 when nothing is mapped from a file
+    pub offset: usize,
+    /// The file mapped into the region, or what the kernel calls it, like [stack] or [vdso]. None for anonymous
+    /// memory.
+    pub path: Option<String>,
+}
+
+impl MemoryRegion {
+    /// Whether the region is memory the kernel made for the process, like it's stack, heap or the vDSO, rather than
+    /// a file mapped into it
+    pub fn is_pseudo(&self) -> bool {
+        self.path
+            .as_ref()
+            .is_some_and(|path| path.starts_with('[') && path.ends_with(']'))
+    }
+}
+
+// a line of /proc/<pid>/maps;
+// 555555554000-555555555000 r--p 00000000 08:01 1234    /usr/bin/true
+fn parse_region(line: &str) -> Option<MemoryRegion> {
+    let mut fields = line.splitn(6, ' ');
+    let (start, end) = fields.next()?.split_once('-')?;
+    let permissions = fields.next()?.as_bytes();
+    let offset = fields.next()?;
+    // the device and the inode
+    fields.next()?;
+    fields.next()?;
+    let path = fields.next().map(str::trim).filter(|path| !path.is_empty());
+    if permissions.len() != 4 {
+        return None;
+    }
+    Some(MemoryRegion {
+        range: usize::from_str_radix(start, 16).ok()?..usize::from_str_radix(end, 16).ok()?,
+        permissions: Permissions {
+            read: permissions[0] == b'r',
+            write: permissions[1] == b'w',
+            execute: permissions[2] == b'x',
+            shared: permissions[3] == b's',
+        },
+        offset: usize::from_str_radix(offset, 16).ok()?,
+        path: path.map(str::to_owned),
+    })
+}
+
+/// The regions in `maps`, which is in the format of /proc/<pid>/maps
+pub fn parse_maps(maps: &str) -> MidasSysResultDynamic<Vec<MemoryRegion>> {
+    maps.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| parse_region(line).ok_or(format!("malformed memory map entry: {}", line)))
+        .collect()
+}
+
+/// The memory regions of process `pid`, in the order of their addresses
+pub fn memory_map(pid: Pid) -> MidasSysResultDynamic<Vec<MemoryRegion>> {
+    let path = format!("/proc/{}/maps", *pid);
+    parse_maps(&std::fs::read_to_string(&path).map_err(|err| format!("couldn't read {}: {}", path, err))?)
+}
+
+/// The region of `regions` that `address` is in
+pub fn region_containing(regions: &[MemoryRegion], address: usize) -> Option<&MemoryRegion> {
+    regions
+        .iter()
+        .find(|region| region.range.contains(&address))
+}
+
+/// The key value pairs the kernel puts on the stack of a new process, below the environment
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AuxiliaryVector {
+    pub entries: Vec<(u64, u64)>,
+}
+
+impl AuxiliaryVector {
+    /// Parses the vector, as it's laid out in memory and in /proc/<pid>/auxv; pairs of 8 byte words, ending with
+    /// AT_NULL
+    pub fn parse(data: &[u8]) -> AuxiliaryVector {
+        let entries = data
+            .chunks_exact(16)
+            .map(|entry| {
+                let (key, value) = entry.split_at(8);
+                (
+                    u64::from_ne_bytes(key.try_into().unwrap()),
+                    u64::from_ne_bytes(value.try_into().unwrap()),
+                )
+            })
+            .take_while(|(key, _)| *key != AT_NULL)
+            .collect();
+        AuxiliaryVector { entries }
+    }
+
+    /// The vector laid out like `parse` reads it, AT_NULL included
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .chain(std::iter::once(&(AT_NULL, 0)))
+            .flat_map(|(key, value)| key.to_ne_bytes().into_iter().chain(value.to_ne_bytes()))
+            .collect()
+    }
+
+    /// The value of `key`
+    pub fn get(&self, key: u64) -> Option<u64> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| *entry == key)
+            .map(|(_, value)| *value)
+    }
+
+    /// Where the program's entry point is loaded
+    pub fn entry(&self) -> Option<usize> {
+        self.get(AT_ENTRY).map(|entry| entry as usize)
+    }
+
+    /// Where the program's program headers are loaded
+    pub fn program_headers(&self) -> Option<usize> {
+        self.get(AT_PHDR).map(|phdr| phdr as usize)
+    }
+
+    /// Where the dynamic linker is loaded; None for statically linked programs
+    pub fn interpreter_base(&self) -> Option<usize> {
+        self.get(AT_BASE)
+            .filter(|base| *base != 0)
+            .map(|base| base as usize)
+    }
+
+    /// Where the vDSO is loaded
+    pub fn vdso(&self) -> Option<usize> {
+        self.get(AT_SYSINFO_EHDR)
+            .filter(|vdso| *vdso != 0)
+            .map(|vdso| vdso as usize)
+    }
+}
+
+/// The auxiliary vector of process `pid`
+pub fn auxiliary_vector(pid: Pid) -> MidasSysResultDynamic<AuxiliaryVector> {
+    let path = format!("/proc/{}/auxv", *pid);
+    let data = std::fs::read(&path).map_err(|err| format!("couldn't read {}: {}", path, err))?;
+    Ok(AuxiliaryVector::parse(&data))
+}
+
+// the text of /proc/<pid>/<name>, or where the link /proc/<pid>/<name> points
+fn read_file(pid: Pid, name: &str) -> MidasSysResultDynamic<String> {
+    let path = format!("/proc/{}/{}", *pid, name);
+    std::fs::read_to_string(&path).map_err(|err| format!("couldn't read {}: {}", path, err))
+}
+
+fn read_link(pid: Pid, name: &str) -> MidasSysResultDynamic<String> {
+    let path = format!("/proc/{}/{}", *pid, name);
+    std::fs::read_link(&path)
+        .map(|target| target.display().to_string())
+        .map_err(|err| format!("couldn't read {}: {}", path, err))
+}
+
+/// The program and arguments process `pid` was started with
+pub fn command_line(pid: Pid) -> MidasSysResultDynamic<Vec<String>> {
+    Ok(read_file(pid, "cmdline")?
+        .split_terminator('\0')
+        .map(str::to_owned)
+        .collect())
+}
+
+/// The threads of process `pid`, by their thread ids; the first thread's is `pid`
+pub fn tasks(pid: Pid) -> MidasSysResultDynamic<Vec<Pid>> {
+    let path = format!("/proc/{}/task", *pid);
+    let entries = std::fs::read_dir(&path).map_err(|err| format!("couldn't read {}: {}", path, err))?;
+    let mut tasks: Vec<Pid> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .map(Pid)
+        .collect();
+    tasks.sort_by_key(|task| (*task != pid, **task));
+    Ok(tasks)
+}
+
+/// The directory process `pid` is working in
+pub fn working_directory(pid: Pid) -> MidasSysResultDynamic<String> {
+    read_link(pid, "cwd")
+}
+
+/// The file process `pid` is executing
+pub fn executable(pid: Pid) -> MidasSysResultDynamic<String> {
+    read_link(pid, "exe")
+}
@@ -0,0 +1,585 @@
+//! A debugging session; the inferior, it's program and the breakpoints in it, driven by `Command`s. What happens to
+//! the inferior is told as events, on a channel, so that every frontend debugs the same way, and only has to decide
+//! how to show what happened; as text, as JSON, or in the messages of a protocol.
+use std::{collections::HashMap, sync::mpsc::Sender};
+
+use nixwrap::{signals::Signal, MidasSysResultDynamic, WaitStatus};
+
+use crate::{
+    breakpoints::{skip_prologue, BreakpointKind, BreakpointTable, Stop, UserBreakpoint, WatchAccess},
+    commands::Command,
+    dwarf::linenumber::LineTable,
+    elf::ParsedELF,
+    expression::{EvaluationContext, Expression, UnaryOperator},
+    frame::{Frame, InferiorContext},
+    software_breakpoint::BreakpointRequest,
+    stepping::{self, ReturnValue},
+    target::Target,
+    types::Address,
+};
+
+/// Where the inferior is stopped; the function, file and line are None where there's no symbol or line information
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Location {
+    pub pc: usize,
+    pub function: Option<String>,
+    /// The path of the source file, if it could be found, otherwise it's name as the line table has it
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub enum StopReason {
+    /// Breakpoints whose conditions held stopped the inferior, or ones whose conditions couldn't be evaluated
+    Breakpoint {
+        breakpoints: Vec<UserBreakpoint>,
+        condition_errors: Vec<(usize, String)>,
+    },
+    /// A watchpoint saw it's value accessed; the values are None where they couldn't be read
+    Watchpoint {
+        watchpoint: UserBreakpoint,
+        old_value: Option<i64>,
+        new_value: Option<i64>,
+    },
+    Signal(Signal),
+    /// A step got to where it was going
+    EndSteppingRange,
+    /// `Until` or `Advance` got to it's location, or out of the function
+    LocationReached,
+    /// `Finish` returned from the function; the value is None for functions without debug information
+    FunctionFinished {
+        value: Option<ReturnValue>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Something to tell the user that isn't an error of a command; that the program's symbols are at the wrong
+    /// addresses, say
+    Warning(String),
+    /// A breakpoint or watchpoint was created; a breakpoint without locations is pending
+    BreakpointCreated(UserBreakpoint),
+    /// A breakpoint was enabled or disabled, or it's condition or ignore count changed
+    BreakpointModified(UserBreakpoint),
+    BreakpointDeleted(usize),
+    Stopped {
+        reason: StopReason,
+        location: Location,
+        /// Whether it's stopped in another frame than the one it was in when it was resumed
+        new_frame: bool,
+    },
+    /// The inferior is gone; it exited, or was killed
+    Exited(WaitStatus),
+}
+
+pub struct Session<'elf> {
+    target: Box<dyn Target>,
+    elf: ParsedELF<'elf>,
+    lines: Option<LineTable>,
+    breakpoints: BreakpointTable,
+    // the last value seen by each watchpoint, by breakpoint number
+    watched: HashMap<usize, Option<i64>>,
+    events: Sender<Event>,
+}
+
+// the value of the `len` bytes at `address`, sign extended
+fn read_value(target: &dyn Target, address: Address, len: usize) -> Option<i64> {
+    let read = target.read_memory(address, len).ok()?;
+    if read.is_partial() {
+        return None;
+    }
+    let mut buf = [0u8; 8];
+    buf[..len].copy_from_slice(&read.data);
+    let bits = len * 8;
+    Some(((u64::from_le_bytes(buf) << (64 - bits)) as i64) >> (64 - bits))
+}
+
+fn is_watchpoint(breakpoint: &UserBreakpoint) -> bool {
+    matches!(breakpoint.kind, BreakpointKind::Watchpoint { .. })
+}
+
+impl<'elf> Session<'elf> {
+    /// A session debugging `target`, the program of which is `elf`. The program is relocated to where it was loaded.
+    /// A process we started can have the libraries it loads followed, if `track_libraries`; for core dumps and
+    /// processes we attached to, the libraries that are loaded already are all that's known.
+    pub fn new(
+        mut target: Box<dyn Target>,
+        mut elf: ParsedELF<'elf>,
+        track_libraries: bool,
+        events: Sender<Event>,
+    ) -> Session<'elf> {
+        // position independent programs are loaded wherever the kernel chose to put them
+        match target
+            .auxiliary_vector()
+            .ok()
+            .and_then(|auxv| elf.load_bias_from_auxv(&auxv))
+        {
+            Some(bias) => elf.relocate(bias),
+            None => {
+                let _ = events.send(Event::Warning(
+                    "the load address of the program is not known; it's symbols will be at the wrong addresses".into(),
+                ));
+            }
+        }
+        let mut breakpoints = BreakpointTable::default();
+        let libraries = if track_libraries {
+            breakpoints.track_shared_libraries(target.as_mut(), &elf)
+        } else {
+            breakpoints.read_shared_libraries(target.as_ref(), &elf)
+        };
+        if let Err(err) = libraries {
+            let _ = events.send(Event::Warning(format!(
+                "shared libraries will not be known: {}",
+                err
+            )));
+        }
+        Session {
+            target,
+            lines: elf.line_table(),
+            elf,
+            breakpoints,
+            watched: HashMap::new(),
+            events,
+        }
+    }
+
+    pub fn target(&self) -> &dyn Target {
+        self.target.as_ref()
+    }
+
+    /// The target, for changes the session needn't know about; writing the inferior's memory and registers
+    pub fn target_mut(&mut self) -> &mut dyn Target {
+        self.target.as_mut()
+    }
+
+    pub fn elf(&self) -> &ParsedELF<'elf> {
+        &self.elf
+    }
+
+    pub fn lines(&self) -> Option<&LineTable> {
+        self.lines.as_ref()
+    }
+
+    pub fn breakpoints(&self) -> &BreakpointTable {
+        &self.breakpoints
+    }
+
+    fn send(&self, event: Event) {
+        // a frontend that's stopped listening has no use for events
+        let _ = self.events.send(event);
+    }
+
+    /// Does what `command` says. Errors are the reason the command couldn't be done, as they're shown to the user;
+    /// everything else that happened is sent as events.
+    pub fn handle(&mut self, command: Command) -> MidasSysResultDynamic<()> {
+        match command {
+            Command::SetBreakpoint {
+                location,
+                hardware,
+                temporary,
+                condition,
+            } => self.set_breakpoint(location, hardware, temporary, condition),
+            Command::Watch { expression, access } => self.watch(&expression, access),
+            Command::Delete(numbers) => self.for_each_breakpoint(numbers, |session, number| {
+                session
+                    .breakpoints
+                    .delete(session.target.as_mut(), number)?;
+                session.watched.remove(&number);
+                session.send(Event::BreakpointDeleted(number));
+                Ok(())
+            }),
+            Command::Enable(numbers) => self.for_each_breakpoint(numbers, |session, number| {
+                session
+                    .breakpoints
+                    .enable(session.target.as_mut(), number)?;
+                session.modified(number);
+                Ok(())
+            }),
+            Command::Disable(numbers) => self.for_each_breakpoint(numbers, |session, number| {
+                session
+                    .breakpoints
+                    .disable(session.target.as_mut(), number)?;
+                session.modified(number);
+                Ok(())
+            }),
+            Command::Condition { number, condition } => {
+                self.breakpoints
+                    .set_condition(number, condition.as_deref())?;
+                self.modified(number);
+                Ok(())
+            }
+            Command::Ignore { number, count } => {
+                self.breakpoints.set_ignore_count(number, count)?;
+                self.modified(number);
+                Ok(())
+            }
+            Command::Kill => {
+                let status = self.target.kill()?;
+                self.send(Event::Exited(status));
+                Ok(())
+            }
+            Command::QueueSignal(signal) => self.target.queue_signal(signal),
+            Command::Detach => self.detach(),
+            command => self.resume(command),
+        }
+    }
+
+    // does `action` to each breakpoint of `numbers`, even if it fails for some of them
+    fn for_each_breakpoint(
+        &mut self,
+        numbers: Vec<usize>,
+        action: impl Fn(&mut Self, usize) -> MidasSysResultDynamic<()>,
+    ) -> MidasSysResultDynamic<()> {
+        let errors: Vec<String> = numbers
+            .into_iter()
+            .filter_map(|number| action(self, number).err())
+            .collect();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors.join("\n")),
+        }
+    }
+
+    fn modified(&self, number: usize) {
+        if let Some(breakpoint) = self.breakpoints.get(number) {
+            self.send(Event::BreakpointModified(breakpoint.clone()));
+        }
+    }
+
+    /// Resolves `location` to the addresses to break at, and the location as it ended up; a line without code is
+    /// moved to the next one that has some. Functions are looked for in the program first, then in the shared
+    /// libraries that are loaded; a function that's in neither has no addresses.
+    pub fn resolve(&self, location: BreakpointRequest) -> MidasSysResultDynamic<(BreakpointRequest, Vec<Address>)> {
+        let name = match location {
+            BreakpointRequest::Address(address) => return Ok((location, vec![address])),
+            BreakpointRequest::Line { number, file } => {
+                let (line, addresses) = self
+                    .lines
+                    .as_ref()
+                    .and_then(|lines| lines.addresses_of_line(&file, number as u32))
+                    .filter(|(_, addresses)| !addresses.is_empty())
+                    .ok_or_else(|| format!("No line {} in file \"{}\".", number, file))?;
+                let location = BreakpointRequest::Line {
+                    number: line as usize,
+                    file,
+                };
+                return Ok((location, addresses.into_iter().map(Address).collect()));
+            }
+            BreakpointRequest::Function { ref name, .. } => name.clone(),
+        };
+        let elf = &self.elf;
+        if let Some(address) = elf
+            .symbol_table
+            .get_function_symbol(&name)
+            .and_then(|s| s.value.map(|v| Address(v.get())))
+        {
+            return Ok((location, vec![skip_prologue(elf, address)]));
+        }
+        let section = |section| elf.get_dwarf_section(section).ok();
+        if let (Some(debug_info), Some(debug_pubnames), Some(debug_abbrev)) = (
+            section(crate::dwarf::Section::DebugInfo),
+            section(crate::dwarf::Section::DebugPubNames),
+            section(crate::dwarf::Section::DebugAbbrev),
+        ) {
+            if let Some(address) = crate::find_low_pc_of(&name, debug_info, debug_pubnames, debug_abbrev) {
+                let address = skip_prologue(elf, Address(address + elf.load_bias()));
+                return Ok((location, vec![address]));
+            }
+        }
+        let address = self
+            .breakpoints
+            .shared_libraries()
+            .iter()
+            .find_map(|library| library.breakpoint_address(&name));
+        Ok((location, address.into_iter().map(Address).collect()))
+    }
+
+    // the address to run to, for `until` and `advance`
+    fn address_of(&self, location: BreakpointRequest) -> MidasSysResultDynamic<u64> {
+        match self.resolve(location)? {
+            (_, addresses) if !addresses.is_empty() => Ok(addresses[0].value() as u64),
+            (location, _) => Err(format!(
+                "Function \"{}\" not defined.",
+                match location {
+                    BreakpointRequest::Function { name, .. } => name,
+                    location => format!("{:?}", location),
+                }
+            )),
+        }
+    }
+
+    fn set_breakpoint(
+        &mut self,
+        location: BreakpointRequest,
+        hardware: bool,
+        temporary: bool,
+        condition: Option<String>,
+    ) -> MidasSysResultDynamic<()> {
+        // the condition is checked before the breakpoint is inserted, so that a typo doesn't leave one behind
+        if let Some(condition) = &condition {
+            Expression::parse(condition)?;
+        }
+        let kind = match hardware {
+            true => BreakpointKind::Hardware,
+            false => BreakpointKind::Software,
+        };
+        let (location, addresses) = self.resolve(location)?;
+        // functions that aren't loaded yet may be in a library that's loaded later
+        let number = match addresses.is_empty() {
+            true => self.breakpoints.insert_pending(kind, location)?.number,
+            false => {
+                self.breakpoints
+                    .insert(self.target.as_mut(), kind, location, addresses)?
+                    .number
+            }
+        };
+        self.breakpoints
+            .set_condition(number, condition.as_deref())?;
+        if temporary {
+            self.breakpoints.set_temporary(number)?;
+        }
+        if let Some(breakpoint) = self.breakpoints.get(number) {
+            self.send(Event::BreakpointCreated(breakpoint.clone()));
+        }
+        Ok(())
+    }
+
+    // watches `expression`, which is either the name of a variable, in which case it's size comes from the symbol
+    // table, or `*address` / `-l address`, which watches the pointer sized value at address (or less, if address
+    // isn't 8 byte aligned)
+    fn watch(&mut self, expression: &str, access: WatchAccess) -> MidasSysResultDynamic<()> {
+        let expression = expression.trim();
+        if expression.is_empty() {
+            return Err("Argument required (expression to compute).".into());
+        }
+        let location = expression
+            .strip_prefix("-location ")
+            .or_else(|| expression.strip_prefix("-l "));
+        let context = InferiorContext::new(self.target.as_ref(), &self.elf);
+        // a location is watched with the largest length it's aligned to
+        let location_of = |address: Expression| -> MidasSysResultDynamic<(usize, usize)> {
+            let address = address.evaluate(&context)? as usize;
+            Ok((address, 8.min(1 << address.trailing_zeros().min(3))))
+        };
+        let (address, len) = match location {
+            Some(location) => location_of(Expression::parse(location)?)?,
+            None => match Expression::parse(expression)? {
+                Expression::Name(name) => {
+                    let address = context
+                        .address_of(&name)
+                        .ok_or(format!("No symbol \"{}\" in current context.", name))?;
+                    // the symbol's size, for globals without debug information
+                    let size = context
+                        .size_of(&name)
+                        .or_else(|| self.elf.symbol_table.get_symbol(&name).map(|s| s.size))
+                        .unwrap_or(0);
+                    if size == 0 {
+                        return Err(format!(
+                            "Can't tell the size of \"{}\"; use watch *address instead",
+                            name
+                        ));
+                    }
+                    (address as usize, size)
+                }
+                Expression::Unary(UnaryOperator::Dereference, address) => location_of(*address)?,
+                _ => return Err("Cannot watch constant value; watch a variable, *address or -l address".into()),
+            },
+        };
+        let watchpoint = self
+            .breakpoints
+            .insert(
+                self.target.as_mut(),
+                BreakpointKind::Watchpoint {
+                    access,
+                    expression: expression.to_owned(),
+                    len,
+                },
+                BreakpointRequest::Address(Address(address)),
+                vec![Address(address)],
+            )?
+            .clone();
+        self.watched.insert(
+            watchpoint.number,
+            read_value(self.target.as_ref(), Address(address), len),
+        );
+        self.send(Event::BreakpointCreated(watchpoint));
+        Ok(())
+    }
+
+    // lets the inferior run as `command` says, as many times as it says, unless something stops it on the way
+    fn resume(&mut self, command: Command) -> MidasSysResultDynamic<()> {
+        let (count, destination) = match &command {
+            Command::Step { count }
+            | Command::Next { count }
+            | Command::StepInstruction { count }
+            | Command::NextInstruction { count } => (*count, None),
+            Command::Until(Some(location)) | Command::Advance(location) => {
+                (1, Some(self.address_of(location.clone())?))
+            }
+            _ => (1, None),
+        };
+        for _ in 0..count {
+            let before = Frame::current(self.target.as_ref(), &self.elf).ok();
+            let elf = &self.elf;
+            let evaluate =
+                |target: &dyn Target, condition: &Expression| condition.evaluate(&InferiorContext::new(target, elf));
+            let target = self.target.as_mut();
+            let breakpoints = &mut self.breakpoints;
+            let (stop, finished) = match (&command, destination) {
+                (Command::Continue, _) => (
+                    breakpoints.continue_execution(target, &evaluate)?,
+                    StopReason::Signal(Signal::Trap),
+                ),
+                (Command::Step { .. }, _) => (
+                    stepping::step(target, elf, breakpoints, &evaluate)?,
+                    StopReason::EndSteppingRange,
+                ),
+                (Command::Next { .. }, _) => (
+                    stepping::next(target, elf, breakpoints, &evaluate)?,
+                    StopReason::EndSteppingRange,
+                ),
+                (Command::StepInstruction { .. }, _) => (
+                    stepping::stepi(target, breakpoints, &evaluate)?,
+                    StopReason::EndSteppingRange,
+                ),
+                (Command::NextInstruction { .. }, _) => (
+                    stepping::nexti(target, breakpoints, &evaluate)?,
+                    StopReason::EndSteppingRange,
+                ),
+                (Command::Until(None), _) => (
+                    stepping::until(target, elf, breakpoints, &evaluate)?,
+                    StopReason::LocationReached,
+                ),
+                (command, Some(address)) => (
+                    stepping::advance(
+                        target,
+                        elf,
+                        breakpoints,
+                        &evaluate,
+                        address,
+                        matches!(command, Command::Until(_)),
+                    )?,
+                    StopReason::LocationReached,
+                ),
+                (Command::Finish, _) => {
+                    let finish = stepping::finish(target, elf, breakpoints, &evaluate)?;
+                    (
+                        finish.stop,
+                        StopReason::FunctionFinished {
+                            value: finish.value,
+                        },
+                    )
+                }
+                (command, _) => return Err(format!("{:?} doesn't resume the inferior", command)),
+            };
+            if !self.report_stop(stop, finished, before.as_ref()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // tells why the inferior stopped; `finished` is the reason when it's a trap that no breakpoint is responsible
+    // for, which is what a step ends with. Returns whether that's what it was; if it's not, a step was interrupted.
+    fn report_stop(&mut self, stop: Stop, finished: StopReason, before: Option<&Frame>) -> bool {
+        let signal = match stop.status {
+            WaitStatus::Stopped(_, signal) => signal,
+            status => {
+                self.send(Event::Exited(status));
+                return false;
+            }
+        };
+        let (reason, expected) = match stop.breakpoints.first() {
+            Some(watchpoint) if is_watchpoint(watchpoint) => (self.watchpoint_triggered(watchpoint.clone()), false),
+            _ if stop.by_breakpoint() => (
+                StopReason::Breakpoint {
+                    breakpoints: stop.breakpoints,
+                    condition_errors: stop.condition_errors,
+                },
+                false,
+            ),
+            _ if signal == Signal::Trap => (finished, true),
+            _ => (StopReason::Signal(signal), false),
+        };
+        let after = Frame::current(self.target.as_ref(), &self.elf).ok();
+        let new_frame = match (before, &after) {
+            (Some(before), Some(after)) => {
+                before.cfa != after.cfa
+                    || before.function.as_ref().map(|function| &function.name)
+                        != after.function.as_ref().map(|function| &function.name)
+            }
+            _ => true,
+        };
+        self.send(Event::Stopped {
+            reason,
+            location: self.location(after),
+            new_frame,
+        });
+        expected
+    }
+
+    // the old and new value of a watchpoint that triggered; the new one is the old one next time
+    fn watchpoint_triggered(&mut self, watchpoint: UserBreakpoint) -> StopReason {
+        let len = match watchpoint.kind {
+            BreakpointKind::Watchpoint { len, .. } => len,
+            _ => 0,
+        };
+        let new_value = read_value(self.target.as_ref(), watchpoint.locations[0], len);
+        let old_value = self.watched.insert(watchpoint.number, new_value).flatten();
+        StopReason::Watchpoint {
+            watchpoint,
+            old_value,
+            new_value,
+        }
+    }
+
+    /// Where the inferior is, in `frame`, the innermost one
+    pub fn location(&self, frame: Option<Frame>) -> Location {
+        let pc = match self.target.read_registers() {
+            Ok(registers) => registers.pc() as usize,
+            Err(_) => return Location::default(),
+        };
+        let function = frame
+            .and_then(|frame| frame.function)
+            .map(|function| function.name)
+            .or_else(|| {
+                self.elf
+                    .symbol_table
+                    .symbolize(pc)
+                    .map(|(name, _)| name.to_owned())
+            })
+            .or_else(|| {
+                self.breakpoints
+                    .shared_libraries()
+                    .symbolize(pc)
+                    .map(|(name, _)| name)
+            });
+        let row = self.lines.as_ref().and_then(|lines| lines.row_at(pc));
+        let file = row
+            .and_then(|row| self.lines.as_ref()?.file_name(row))
+            .map(|file| {
+                self.elf.source_path(file).map_or_else(
+                    || file.to_owned(),
+                    |path| path.to_string_lossy().into_owned(),
+                )
+            });
+        Location {
+            pc,
+            function,
+            file,
+            line: row.map(|row| row.line()),
+        }
+    }
+
+    // deletes the breakpoints, and lets go of the inferior; the target takes out the trap that follows the libraries
+    // it loads too, and the inferior runs on without us
+    fn detach(&mut self) -> MidasSysResultDynamic<()> {
+        let numbers: Vec<usize> = self
+            .breakpoints
+            .iter()
+            .map(|breakpoint| breakpoint.number)
+            .collect();
+        self.handle(Command::Delete(numbers))?;
+        self.target.detach()
+    }
+}
@@ -0,0 +1,316 @@
+//! The shared libraries the dynamic linker has loaded into the inferior. The dynamic linker keeps a list of them, the
+//! link_map, in it's r_debug structure, and calls the function at r_debug.r_brk before and after it changes the list;
+//! a debugger puts a breakpoint there to hear about libraries being loaded and unloaded. Where r_debug is, the
+//! dynamic linker writes into the DT_DEBUG entry of the executable's dynamic section, before it runs the program, so
+//! the first place to look for it is the program's entry point.
+use std::{ops::Range, rc::Rc};
+
+use nixwrap::MidasSysResultDynamic;
+
+use crate::{
+    dwarf::{linenumber::LineTable, Section},
+    elf::{load_object, Object, ParsedELF},
+    target::Target,
+    types::Address,
+};
+
+// the tags of the dynamic section entries we're interested in
+const DT_NULL: u64 = 0;
+const DT_DEBUG: u64 = 21;
+
+// r_debug.r_state when the link_map is not being changed
+const RT_CONSISTENT: u64 = 0;
+
+// a library's link_map entry refers to the next one; a list that's longer than this is corrupt, or a loop
+const MAX_LIBRARIES: usize = 4096;
+
+// the longest library path we read
+const MAX_PATH_LENGTH: usize = 4096;
+
+pub struct SharedLibrary {
+    /// The file the dynamic linker loaded the library from
+    pub path: String,
+    /// What's added to the addresses in the library's file, to get the addresses it's loaded at
+    pub bias: usize,
+    /// Where the library's dynamic section is loaded; with `path` this tells libraries apart
+    pub dynamic: usize,
+    /// Where the library's code, it's .text section, is loaded
+    pub text: Option<Range<usize>>,
+    /// Whether the library has DWARF debug information
+    pub debug_info: bool,
+    // the library's file; None if it couldn't be read
+    object: Option<Rc<Object>>,
+    // the library's function and object symbols, where they're loaded
+    symbols: Vec<LibrarySymbol>,
+    // the library's line table, where it's loaded
+    lines: Option<LineTable>,
+}
+
+// a symbol of a library; the library's own symbol table borrows the names from it's file
+struct LibrarySymbol {
+    name: String,
+    address: usize,
+    size: usize,
+    function: bool,
+}
+
+impl SharedLibrary {
+    fn load(path: String, bias: usize, dynamic: usize) -> SharedLibrary {
+        let mut library = SharedLibrary {
+            path,
+            bias,
+            dynamic,
+            text: None,
+            debug_info: false,
+            object: None,
+            symbols: vec![],
+            lines: None,
+        };
+        library.object = load_object(std::path::Path::new(&library.path)).ok();
+        // what's looked up on every stop is read from the file once, here
+        let Some(elf) = library.elf() else {
+            return library;
+        };
+        let text = elf
+            .get_section_address(".text")
+            .zip(elf.get_section_data(".text"))
+            .map(|(address, data)| address..address + data.len());
+        let debug_info = elf.get_dwarf_section(Section::DebugInfo).is_ok();
+        let functions = elf.symbol_table.functions().map(|symbol| (symbol, true));
+        let objects = elf.symbol_table.objects().map(|symbol| (symbol, false));
+        let symbols = functions
+            .chain(objects)
+            .filter_map(|((name, symbol), function)| {
+                Some(LibrarySymbol {
+                    name: name.to_owned(),
+                    address: symbol.value?.get(),
+                    size: symbol.size,
+                    function,
+                })
+            })
+            .collect();
+        let lines = elf.line_table();
+        drop(elf);
+        library.text = text;
+        library.debug_info = debug_info;
+        library.symbols = symbols;
+        library.lines = lines;
+        library
+    }
+
+    /// The library's ELF file, with it's symbols and debug information, relocated to where it's loaded. None if the
+    /// file couldn't be read or parsed. It's parsed anew on every call; the symbols and lines are kept by the library.
+    pub fn elf(&self) -> Option<ParsedELF<'_>> {
+        let mut elf = ParsedELF::parse_elf(self.object.as_ref()?).ok()?;
+        elf.relocate(self.bias);
+        Some(elf)
+    }
+
+    /// Whether the library's symbols could be read
+    pub fn symbols_read(&self) -> bool {
+        self.object.is_some()
+    }
+
+    /// The library's line table, with the addresses it's loaded at
+    pub fn line_table(&self) -> Option<&LineTable> {
+        self.lines.as_ref()
+    }
+
+    fn function(&self, name: &str) -> Option<&LibrarySymbol> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.function && symbol.name == name)
+    }
+
+    /// The address of function `name` in the inferior, if the library has it
+    pub fn function_address(&self, name: &str) -> Option<usize> {
+        Some(self.function(name)?.address)
+    }
+
+    /// Where a breakpoint on function `name` goes in the inferior; after it's prologue, if the library has the line
+    /// information to tell where that ends
+    pub fn breakpoint_address(&self, name: &str) -> Option<usize> {
+        let function = self.function(name)?;
+        let start = function.address;
+        Some(
+            self.lines
+                .as_ref()
+                .and_then(|lines| lines.address_after_prologue(start..start + function.size))
+                .unwrap_or(start),
+        )
+    }
+
+    /// The symbol `address` is in, and the offset of `address` into it
+    pub fn symbolize(&self, address: usize) -> Option<(String, usize)> {
+        self.symbols.iter().find_map(|symbol| {
+            // zero sized symbols (hand written assembly, mostly) can still be the target of an address
+            let end = symbol.address + symbol.size.max(1);
+            (symbol.address..end)
+                .contains(&address)
+                .then(|| (symbol.name.clone(), address - symbol.address))
+        })
+    }
+}
+
+/// The shared libraries that are loaded into the inferior, as the dynamic linker reports them
+#[derive(Default)]
+pub struct SharedLibraries {
+    // where the executable's dynamic section is; None if it's statically linked
+    dynamic: Option<usize>,
+    entry: usize,
+    // where the dynamic linker's r_debug is, once it's told us
+    r_debug: Option<usize>,
+    // r_debug.r_brk
+    rendezvous: Option<usize>,
+    libraries: Vec<SharedLibrary>,
+}
+
+fn read_u64(target: &dyn Target, address: usize) -> MidasSysResultDynamic<u64> {
+    let read = target.read_memory(Address(address), 8)?;
+    if read.is_partial() {
+        return Err(format!("Cannot access memory at address 0x{:x}", address));
+    }
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&read.data);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// the nul terminated string at `address`
+fn read_string(target: &dyn Target, address: usize) -> MidasSysResultDynamic<String> {
+    let read = target.read_memory(Address(address), MAX_PATH_LENGTH)?;
+    let end = read
+        .data
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(read.data.len());
+    Ok(String::from_utf8_lossy(&read.data[..end]).into_owned())
+}
+
+impl SharedLibraries {
+    /// The shared libraries of the program `elf`, which are none until `update` has read the dynamic linker's list.
+    /// `elf` must be relocated to where the program is loaded.
+    pub fn new(elf: &ParsedELF) -> SharedLibraries {
+        SharedLibraries {
+            dynamic: elf.get_section_address(".dynamic"),
+            entry: elf.entry_point(),
+            ..Default::default()
+        }
+    }
+
+    /// Where the inferior must be stopped, for `update` to see the libraries change; the program's entry point,
+    /// until the dynamic linker has said where r_debug is, and it's rendezvous function after that. None for
+    /// statically linked programs.
+    pub fn event_address(&self) -> Option<Address> {
+        match (self.dynamic, self.rendezvous) {
+            (None, _) => None,
+            (Some(_), Some(rendezvous)) => Some(Address(rendezvous)),
+            (Some(_), None) => Some(Address(self.entry)),
+        }
+    }
+
+    /// Reads the dynamic linker's list of libraries. Returns None if the libraries didn't change, and the libraries
+    /// that were unloaded if they did. While the dynamic linker is in the middle of changing the list, it's left for
+    /// the next time.
+    pub fn update(&mut self, target: &dyn Target) -> MidasSysResultDynamic<Option<Vec<SharedLibrary>>> {
+        let r_debug = match self.r_debug {
+            Some(r_debug) => r_debug,
+            None => match self.find_r_debug(target)? {
+                Some(r_debug) => r_debug,
+                None => return Ok(None),
+            },
+        };
+        self.r_debug = Some(r_debug);
+        self.rendezvous = Some(read_u64(target, r_debug + 16)? as usize);
+        if read_u64(target, r_debug + 24)? & 0xffff_ffff != RT_CONSISTENT {
+            return Ok(None);
+        }
+
+        let mut libraries = vec![];
+        // the first entry is the executable
+        let mut entry = read_u64(target, r_debug + 8)? as usize;
+        let mut first = true;
+        for _ in 0..MAX_LIBRARIES {
+            if entry == 0 {
+                break;
+            }
+            // struct link_map { l_addr, l_name, l_ld, l_next, l_prev }
+            let bias = read_u64(target, entry)? as usize;
+            let name = read_u64(target, entry + 8)? as usize;
+            let dynamic = read_u64(target, entry + 16)? as usize;
+            let next = read_u64(target, entry + 24)? as usize;
+            let path = if name == 0 {
+                String::new()
+            } else {
+                read_string(target, name)?
+            };
+            // the vdso is in memory only; it's the one library without a path
+            if !first && path.contains('/') {
+                libraries.push((path, bias, dynamic));
+            }
+            first = false;
+            entry = next;
+        }
+
+        let unchanged = libraries.len() == self.libraries.len()
+            && libraries
+                .iter()
+                .zip(self.libraries.iter())
+                .all(|((path, _, dynamic), library)| *path == library.path && *dynamic == library.dynamic);
+        if unchanged {
+            return Ok(None);
+        }
+        let mut previous = std::mem::take(&mut self.libraries);
+        for (path, bias, dynamic) in libraries {
+            // libraries that were loaded already keep their parsed file
+            let library = match previous
+                .iter()
+                .position(|library| library.path == path && library.dynamic == dynamic)
+            {
+                Some(index) => previous.swap_remove(index),
+                None => SharedLibrary::load(path, bias, dynamic),
+            };
+            self.libraries.push(library);
+        }
+        // what's left of the previous libraries is what's been unloaded
+        Ok(Some(previous))
+    }
+
+    // r_debug, from the DT_DEBUG entry of the executable's dynamic section; None until the dynamic linker has set it
+    fn find_r_debug(&self, target: &dyn Target) -> MidasSysResultDynamic<Option<usize>> {
+        let dynamic = match self.dynamic {
+            Some(dynamic) => dynamic,
+            None => return Ok(None),
+        };
+        let mut entry = dynamic;
+        loop {
+            let tag = read_u64(target, entry)?;
+            let value = read_u64(target, entry + 8)?;
+            match tag {
+                DT_NULL => return Ok(None),
+                DT_DEBUG if value != 0 => return Ok(Some(value as usize)),
+                DT_DEBUG => return Ok(None),
+                _ => entry += 16,
+            }
+        }
+    }
+
+    /// The libraries, in the order the dynamic linker loaded them
+    pub fn iter(&self) -> impl Iterator<Item = &SharedLibrary> {
+        self.libraries.iter()
+    }
+
+    /// The library whose code `address` is in
+    pub fn containing(&self, address: usize) -> Option<&SharedLibrary> {
+        self.libraries.iter().find(|library| {
+            library
+                .text
+                .as_ref()
+                .is_some_and(|text| text.contains(&address))
+        })
+    }
+
+    /// The symbol `address` is in, and the offset of `address` into it, if it's in the code of a library
+    pub fn symbolize(&self, address: usize) -> Option<(String, usize)> {
+        self.containing(address)?.symbolize(address)
+    }
+}
@@ -2,11 +2,8 @@ use super::types::Address;
 use nixwrap::ptrace;
 use nixwrap::{MidasSysResultDynamic, Pid};
 
-// todo(simon): implement hardware breakpoints. a bit more involved and platform-dependent to the MAX(nth) degree.
-pub struct HWBreakpoint {}
-impl HWBreakpoint {}
-
 #[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BreakpointRequest {
     Address(Address),
     Line { number: usize, file: String },
@@ -49,13 +46,34 @@ impl Breakpoint {
         ))
     }
 
-    pub fn disable(&mut self) {
+    /// Re-inserts the int3 of a disabled breakpoint
+    pub fn enable(&mut self) -> MidasSysResultDynamic<()> {
+        if !self.enabled {
+            let instruction = nixwrap::ptrace::peek_data(self.pid, self.address.value())?;
+            ptrace::poke_data(self.pid, self.address.value(), (instruction & !0xff) | 0xcc)?;
+            self.instruction_encoding = instruction & 0xff;
+        }
+        self.enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self) -> MidasSysResultDynamic<()> {
         if self.enabled {
-            let instruction = nixwrap::ptrace::peek_data(self.pid, self.address.value()).unwrap();
+            let instruction = nixwrap::ptrace::peek_data(self.pid, self.address.value())?;
             let restored = (instruction & !0xff) | self.instruction_encoding;
-            ptrace::poke_data(self.pid, self.address.value(), restored).unwrap();
+            ptrace::poke_data(self.pid, self.address.value(), restored)?;
         }
         self.enabled = false;
+        Ok(())
+    }
+
+    /// The byte of the instruction that the int3 replaced
+    pub fn original_byte(&self) -> u8 {
+        (self.instruction_encoding & 0xff) as u8
+    }
+
+    pub fn set_original_byte(&mut self, byte: u8) {
+        self.instruction_encoding = byte as i64;
     }
 
     // for when we might have a set of breakpoints, we don't want to keep poke_data'ing if we disable them all
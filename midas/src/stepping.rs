@@ -0,0 +1,484 @@
+//! Source level stepping. `step` and `next` run the inferior to the start of another line, `finish` runs it until
+//! the function it's in returns. Lines come from the line table and frames are told apart by their CFA, so this
+//! needs debug information. Breakpoints the inferior runs into on the way stop it, as they do when continuing.
+//! `stepi` and `nexti` step single instructions, and need none.
+use nixwrap::{signals::Signal, MidasSysResultDynamic, WaitStatus};
+
+use crate::{
+    breakpoints::{BreakpointTable, Stop},
+    disassemble::{self, Instruction},
+    dwarf::{
+        linenumber::LineTable,
+        variables::{Function, ReturnType},
+    },
+    elf::ParsedELF,
+    expression::Expression,
+    frame::{extend, Frame},
+    software_breakpoint::BreakpointRequest,
+    target::Target,
+    types::Address,
+};
+
+/// The longest an x86-64 instruction can be
+const MAX_INSTRUCTION_LENGTH: usize = 15;
+
+/// What a finished function returned
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnValue {
+    Void,
+    /// Sign extended if `signed`
+    Integer {
+        value: u64,
+        signed: bool,
+    },
+    Float(f64),
+    /// The bytes of a struct, union or array
+    Bytes(Vec<u8>),
+    /// A type we don't know how it's returned
+    Unknown,
+}
+
+/// The result of `finish`
+#[derive(Debug)]
+pub struct Finish {
+    pub stop: Stop,
+    /// The function that was finished, if it has debug information
+    pub function: Option<Function>,
+    /// None if the inferior stopped for another reason before the function returned
+    pub value: Option<ReturnValue>,
+}
+
+// what a line step does with calls and backward jumps
+#[derive(Clone, Copy, PartialEq)]
+enum Stepping {
+    // into the functions called that have line information, over the others
+    Into,
+    // over all functions called
+    Over,
+    // over all functions called, and on through jumps back to earlier addresses of the function; out of loops
+    Until,
+}
+
+// how running to an address ended
+enum RunTo {
+    Arrived,
+    Stopped(Stop),
+}
+
+fn line_table(elf: &ParsedELF) -> MidasSysResultDynamic<LineTable> {
+    elf.line_table()
+        .ok_or_else(|| "the program has no line number information".to_string())
+}
+
+// the instruction at `pc`; None if it can't be read or isn't one we know
+fn instruction_at(target: &dyn Target, pc: u64) -> Option<Instruction> {
+    let read = target
+        .read_memory(Address(pc as usize), MAX_INSTRUCTION_LENGTH)
+        .ok()?;
+    disassemble::decode(&read.data, pc).ok()
+}
+
+// where the call at `pc` returns to; None if it's not a call
+fn return_address_of_call(target: &dyn Target, pc: u64) -> Option<u64> {
+    instruction_at(target, pc)
+        .filter(Instruction::is_call)
+        .map(|call| pc + call.length as u64)
+}
+
+// a stop that's no breakpoint's doing; the step finished
+fn finished_step(target: &dyn Target) -> Stop {
+    Stop {
+        status: WaitStatus::Stopped(target.process_id(), Signal::Trap),
+        breakpoints: vec![],
+        condition_errors: vec![],
+    }
+}
+
+// A place to run to, and whether the inferior has arrived when it gets there; in the right frame, rather than in a
+// recursive call of the same function
+type Destination<'a> = (u64, &'a dyn Fn(&dyn Target) -> bool);
+
+// Runs the inferior until it arrives at one of `destinations`. If anything else stops the inferior first, that's the
+// stop.
+fn run_to(
+    target: &mut dyn Target,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+    destinations: &[Destination],
+) -> MidasSysResultDynamic<RunTo> {
+    // a user's breakpoint might have put a trap there already; it's theirs to remove
+    let mut inserted: Vec<Address> = vec![];
+    let mut result = Ok(());
+    for (address, _) in destinations {
+        let address = Address(*address as usize);
+        if !breakpoints.software_trap_in_use(address) && !inserted.contains(&address) {
+            result = target.set_breakpoint(BreakpointRequest::Address(address));
+            if result.is_err() {
+                break;
+            }
+            inserted.push(address);
+        }
+    }
+    let result = result.and_then(|_| continue_to(target, breakpoints, evaluate, destinations));
+    let removed: MidasSysResultDynamic<Vec<()>> = inserted
+        .into_iter()
+        .map(|address| target.remove_breakpoint(address))
+        .collect();
+    let result = result?;
+    removed?;
+    Ok(result)
+}
+
+// the continuing of `run_to`, once the traps at the destinations are in place
+fn continue_to(
+    target: &mut dyn Target,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+    destinations: &[Destination],
+) -> MidasSysResultDynamic<RunTo> {
+    loop {
+        let status = target.continue_execution()?;
+        let stopped_at = target.stopped_at_breakpoint();
+        let arrived = destinations
+            .iter()
+            .any(|(address, arrived)| stopped_at == Some(Address(*address as usize)) && arrived(&*target));
+        match breakpoints.check_stop(target, status, evaluate)? {
+            Some(stop) if stop.by_breakpoint() => return Ok(RunTo::Stopped(stop)),
+            _ if arrived => return Ok(RunTo::Arrived),
+            Some(stop) if !matches!(stop.status, WaitStatus::Stopped(_, Signal::Trap)) => {
+                return Ok(RunTo::Stopped(stop))
+            }
+            _ => {}
+        }
+    }
+}
+
+// Executes one instruction. None if that's all that happened, or the stop if the inferior got a signal or stepped onto
+// a breakpoint or watchpoint that stops it.
+fn step_instruction(
+    target: &mut dyn Target,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+) -> MidasSysResultDynamic<Option<Stop>> {
+    let status = target.step(1)?;
+    if !matches!(status, WaitStatus::Stopped(_, Signal::Trap)) {
+        return Ok(Some(Stop {
+            status,
+            breakpoints: vec![],
+            condition_errors: vec![],
+        }));
+    }
+    if target.stopped_at_breakpoint().is_some() || target.stopped_at_hardware_breakpoint().is_some() {
+        if let Some(stop) = breakpoints.check_stop(target, status, evaluate)? {
+            if stop.by_breakpoint() {
+                return Ok(Some(stop));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Runs a call until it returns to `return_address`, with the stack pointer back at `caller_sp`, where it was before
+// the call; the return pops the return address, which is how a recursive call returning is told apart. None if it
+// returned, or the stop if something else stopped the inferior first.
+fn run_over_call(
+    target: &mut dyn Target,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+    return_address: u64,
+    caller_sp: u64,
+) -> MidasSysResultDynamic<Option<Stop>> {
+    let returned = |target: &dyn Target| stack_pointer(target) == Some(caller_sp);
+    match run_to(
+        target,
+        breakpoints,
+        evaluate,
+        &[(return_address, &returned)],
+    )? {
+        RunTo::Arrived => Ok(None),
+        RunTo::Stopped(stop) => Ok(Some(stop)),
+    }
+}
+
+fn ensure_running(target: &dyn Target) -> MidasSysResultDynamic<()> {
+    match target.read_registers() {
+        Ok(_) => Ok(()),
+        Err(_) => Err("The program is not being run.".into()),
+    }
+}
+
+fn stack_pointer(target: &dyn Target) -> Option<u64> {
+    target.read_registers().ok().map(|registers| registers.rsp)
+}
+
+// The range of addresses a step continues through while the inferior is in `frame`; the line at `pc`, and with
+// `Stepping::Until`, everything in the function before it too.
+fn step_range(lines: &LineTable, frame: &Frame, pc: usize, stepping: Stepping) -> Option<std::ops::Range<usize>> {
+    let range = lines.line_range(pc)?;
+    match &frame.function {
+        Some(function) if stepping == Stepping::Until => Some(function.pc_range.start.min(range.start)..range.end),
+        _ => Some(range),
+    }
+}
+
+// steps to the start of a line other than the one the inferior is at, handling calls as `stepping` says
+fn step_line(
+    target: &mut dyn Target,
+    elf: &ParsedELF,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+    stepping: Stepping,
+) -> MidasSysResultDynamic<Stop> {
+    ensure_running(target)?;
+    let lines = line_table(elf)?;
+    let frame = Frame::current(target, elf)?;
+    let (mut range, row) = match (
+        step_range(&lines, &frame, frame.pc, stepping),
+        lines.row_at(frame.pc),
+    ) {
+        (Some(range), Some(row)) => (range, row),
+        _ => return Err("Cannot find bounds of current function".into()),
+    };
+    let mut line = (row.file(), row.line());
+    let mut cfa = frame.cfa;
+
+    loop {
+        let before = target.read_registers()?;
+        let call = return_address_of_call(&*target, before.pc());
+        // calls that aren't stepped into are run over, rather than stepped through an instruction at a time
+        let stopped = match call {
+            Some(return_address) if stepping != Stepping::Into => {
+                run_over_call(target, breakpoints, evaluate, return_address, before.rsp)?
+            }
+            _ => step_instruction(target, breakpoints, evaluate)?,
+        };
+        if let Some(stop) = stopped {
+            return Ok(stop);
+        }
+        let registers = target.read_registers()?;
+        if range.contains(&(registers.pc() as usize)) {
+            continue;
+        }
+
+        if let (Some(return_address), Stepping::Into) = (call, stepping) {
+            let pc = registers.pc() as usize;
+            let callee = Frame::current(target, elf)?.function;
+            if let Some(callee) = callee.filter(|_| lines.row_at(pc).is_some()) {
+                let body = lines.address_after_prologue(callee.pc_range).unwrap_or(pc);
+                if body == pc {
+                    return Ok(finished_step(target));
+                }
+                // the CFA of the callee's frame is the stack pointer before the call
+                let cfa = before.rsp;
+                let in_callee =
+                    |target: &dyn Target| Frame::current(target, elf).is_ok_and(|frame| frame.cfa == Some(cfa));
+                return match run_to(target, breakpoints, evaluate, &[(body as u64, &in_callee)])? {
+                    RunTo::Arrived => Ok(finished_step(target)),
+                    RunTo::Stopped(stop) => Ok(stop),
+                };
+            }
+            // a function without line information is stepped over
+            if let Some(stop) = run_over_call(target, breakpoints, evaluate, return_address, before.rsp)? {
+                return Ok(stop);
+            }
+            if range.contains(&(target.read_registers()?.pc() as usize)) {
+                continue;
+            }
+        }
+        let registers = target.read_registers()?;
+
+        let pc = registers.pc() as usize;
+        let row = match lines.row_at(pc) {
+            Some(row) => row,
+            // returned to, or jumped into, code we know nothing about
+            None => return Ok(finished_step(target)),
+        };
+        // the function returned, or a recursive call of it did
+        let current = Frame::current(target, elf)?;
+        let returned = current.cfa != cfa;
+        if lines.is_statement_start(pc) && ((row.file(), row.line()) != line || returned) {
+            return Ok(finished_step(target));
+        }
+        // in the middle of a line, or at another statement of the line we're stepping. When the function returned
+        // to the middle of the line with the call, the step continues to the caller's next line.
+        range = match step_range(&lines, &current, pc, stepping) {
+            Some(range) => range,
+            None => return Ok(finished_step(target)),
+        };
+        if returned {
+            line = (row.file(), row.line());
+            cfa = current.cfa;
+        }
+    }
+}
+
+/// Steps to the next line, into the functions it calls that have line information
+pub fn step(
+    target: &mut dyn Target,
+    elf: &ParsedELF,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+) -> MidasSysResultDynamic<Stop> {
+    step_line(target, elf, breakpoints, evaluate, Stepping::Into)
+}
+
+/// Steps to the next line, over the functions it calls
+pub fn next(
+    target: &mut dyn Target,
+    elf: &ParsedELF,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+) -> MidasSysResultDynamic<Stop> {
+    step_line(target, elf, breakpoints, evaluate, Stepping::Over)
+}
+
+/// Executes one instruction
+pub fn stepi(
+    target: &mut dyn Target,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+) -> MidasSysResultDynamic<Stop> {
+    ensure_running(target)?;
+    Ok(step_instruction(target, breakpoints, evaluate)?.unwrap_or_else(|| finished_step(target)))
+}
+
+/// Executes one instruction, and if it's a call, runs on until the function returns
+pub fn nexti(
+    target: &mut dyn Target,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+) -> MidasSysResultDynamic<Stop> {
+    ensure_running(target)?;
+    let registers = target.read_registers()?;
+    let stopped = match return_address_of_call(&*target, registers.pc()) {
+        Some(return_address) => run_over_call(target, breakpoints, evaluate, return_address, registers.rsp)?,
+        None => step_instruction(target, breakpoints, evaluate)?,
+    };
+    Ok(stopped.unwrap_or_else(|| finished_step(target)))
+}
+
+/// Like `next`, but doesn't stop at lines the inferior jumps back to in the same frame, so at the end of a loop it
+/// runs on until the loop is done
+pub fn until(
+    target: &mut dyn Target,
+    elf: &ParsedELF,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+) -> MidasSysResultDynamic<Stop> {
+    step_line(target, elf, breakpoints, evaluate, Stepping::Until)
+}
+
+/// Runs until the inferior reaches `address`, or the function it's in returns, whichever is first. With
+/// `in_this_frame`, reaching `address` in a call the function makes doesn't count; only in it's frame or a caller's,
+/// which is what `until LOCATION` does. `advance LOCATION` stops at it in any frame.
+pub fn advance(
+    target: &mut dyn Target,
+    elf: &ParsedELF,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+    address: u64,
+    in_this_frame: bool,
+) -> MidasSysResultDynamic<Stop> {
+    ensure_running(target)?;
+    let frame = Frame::current(target, elf)?;
+    // the stack grows down, so callers' frames have greater CFAs than the frames of their callees
+    let reached = |target: &dyn Target| match frame.cfa.filter(|_| in_this_frame) {
+        Some(cfa) => Frame::current(target, elf).map_or(true, |current| current.cfa.is_none_or(|at| at >= cfa)),
+        None => true,
+    };
+    let returned = |target: &dyn Target| stack_pointer(target) == frame.cfa;
+    let mut destinations: Vec<Destination> = vec![(address, &reached)];
+    if let Some(return_address) = frame.return_address(target) {
+        destinations.push((return_address, &returned));
+    }
+    match run_to(target, breakpoints, evaluate, &destinations)? {
+        RunTo::Arrived => Ok(finished_step(target)),
+        RunTo::Stopped(stop) => Ok(stop),
+    }
+}
+
+/// Runs until the function the inferior is in returns to it's caller, and reads what it returned
+pub fn finish(
+    target: &mut dyn Target,
+    elf: &ParsedELF,
+    breakpoints: &mut BreakpointTable,
+    evaluate: &dyn Fn(&dyn Target, &Expression) -> MidasSysResultDynamic<u64>,
+) -> MidasSysResultDynamic<Finish> {
+    ensure_running(target)?;
+    let frame = Frame::current(target, elf)?;
+    let (cfa, return_address) = match (frame.cfa, frame.return_address(target)) {
+        (Some(cfa), Some(return_address)) => (cfa, return_address),
+        _ => return Err("\"finish\" not meaningful in the outermost frame.".into()),
+    };
+    let returned = |target: &dyn Target| stack_pointer(target) == Some(cfa);
+    match run_to(
+        target,
+        breakpoints,
+        evaluate,
+        &[(return_address, &returned)],
+    )? {
+        RunTo::Arrived => {
+            let value = match &frame.function {
+                Some(function) => Some(return_value(target, &function.return_type)?),
+                None => None,
+            };
+            Ok(Finish {
+                stop: finished_step(target),
+                function: frame.function,
+                value,
+            })
+        }
+        RunTo::Stopped(stop) => Ok(Finish {
+            stop,
+            function: frame.function,
+            value: None,
+        }),
+    }
+}
+
+/// What a function that just returned, returned. Integers are in rax, floating point values in xmm0, small
+/// aggregates in a combination of rax, rdx, xmm0 and xmm1, and the caller passes the address of larger ones, which
+/// the function returns in rax.
+pub fn return_value(target: &dyn Target, return_type: &ReturnType) -> MidasSysResultDynamic<ReturnValue> {
+    let registers = target.read_registers()?;
+    Ok(match return_type {
+        ReturnType::Void => ReturnValue::Void,
+        ReturnType::Integer { size, signed } => ReturnValue::Integer {
+            value: extend(registers.rax, *size, *signed),
+            signed: *signed,
+        },
+        ReturnType::Float { size: 4 } => {
+            ReturnValue::Float(f32::from_bits(target.read_xmm_registers()?[0] as u32) as f64)
+        }
+        ReturnType::Float { .. } => ReturnValue::Float(f64::from_bits(target.read_xmm_registers()?[0] as u64)),
+        ReturnType::Aggregate { size, sse } if sse.is_empty() => {
+            let read = target.read_memory(Address(registers.rax as usize), *size)?;
+            if read.is_partial() {
+                return Err(format!(
+                    "Cannot access memory at address 0x{:x}",
+                    registers.rax
+                ));
+            }
+            ReturnValue::Bytes(read.data)
+        }
+        ReturnType::Aggregate { size, sse } => {
+            let xmm = target.read_xmm_registers()?;
+            let mut integers = [registers.rax, registers.rdx].into_iter();
+            let mut vectors = [xmm[0] as u64, xmm[1] as u64].into_iter();
+            let mut bytes: Vec<u8> = sse
+                .iter()
+                .flat_map(|sse| {
+                    let eightbyte = if *sse {
+                        vectors.next()
+                    } else {
+                        integers.next()
+                    };
+                    eightbyte.unwrap_or(0).to_le_bytes()
+                })
+                .collect();
+            bytes.truncate(*size);
+            ReturnValue::Bytes(bytes)
+        }
+        ReturnType::Unsupported => ReturnValue::Unknown,
+    })
+}
@@ -0,0 +1,768 @@
+//! Post-mortem debugging of a core dump; the ELF file (of type ET_CORE) the kernel writes when a program crashes.
+//! It's PT_LOAD segments are the memory of the process, and it's PT_NOTE segments describe the rest of it; the
+//! registers of every thread (NT_PRSTATUS, NT_FPREGSET), the process (NT_PRPSINFO), the auxiliary vector (NT_AUXV)
+//! and what files were mapped where (NT_FILE). Memory that's mapped from a file and wasn't changed, like the code of
+//! the program and it's libraries, is left out of the dump; it's read from the mapped files instead.
+//!
+//! [write_core] writes one of a live process, like `gcore` does, for when it's state is worth keeping.
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use nixwrap::{
+    ptrace::{self, UserRegisters},
+    signals::Signal,
+    MidasSysResultDynamic, Pid, WaitStatus,
+};
+
+use crate::{
+    elf::{load_object, ELFHeader, Object, ObjectType},
+    hardware_breakpoint::{HWBreakpoint, WatchKind},
+    procfs::{self, AuxiliaryVector, MemoryRegion, Permissions},
+    software_breakpoint::BreakpointRequest,
+    types::Address,
+    utils::unchecked::as_bytes,
+};
+
+use super::Target;
+
+// the types of the notes we read and write, in notes owned by "CORE"
+const NT_PRSTATUS: u32 = 1;
+const NT_FPREGSET: u32 = 2;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x46494c45;
+
+// segment flags
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+// where in struct elf_prstatus the fields we read and write are
+const PRSTATUS_CURSIG: usize = 12;
+const PRSTATUS_PID: usize = 32;
+const PRSTATUS_REGISTERS: usize = 112;
+const PRSTATUS_FPVALID: usize = 328;
+const PRSTATUS_SIZE: usize = 336;
+// the registers of pr_reg, in the order of a user_regs_struct
+const PRSTATUS_REGISTER_NAMES: [&str; 27] = [
+    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax", "rcx", "rdx", "rsi", "rdi", "orig_rax",
+    "rip", "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds", "es", "fs", "gs",
+];
+// where in struct elf_prpsinfo the fields we read and write are
+const PRPSINFO_SNAME: usize = 1;
+const PRPSINFO_PID: usize = 24;
+const PRPSINFO_FNAME: Range<usize> = 40..56;
+const PRPSINFO_PSARGS: Range<usize> = 56..136;
+const PRPSINFO_SIZE: usize = 136;
+// where in the fxsave area of NT_FPREGSET the fields we read and write are
+const FPREGSET_FCW: usize = 0;
+const FPREGSET_MXCSR: usize = 24;
+const FPREGSET_XMM: usize = 160;
+const FPREGSET_SIZE: usize = 512;
+
+const NOT_RUNNING: &str = "The program is not being run.";
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+// a nul padded string of a fixed size field
+fn fixed_string(data: &[u8], field: Range<usize>) -> Option<String> {
+    let bytes = data.get(field)?;
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+/// A thread of the process when it dumped core
+pub struct CoreThread {
+    pub pid: Pid,
+    /// The signal the thread was handling; the one that killed the process, for the thread that received it
+    pub signal: i32,
+    pub registers: UserRegisters,
+    xmm_registers: Option<[u128; 16]>,
+}
+
+/// A file that was mapped into the process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedFile {
+    pub range: Range<usize>,
+    /// Where in the file the mapping starts
+    pub offset: usize,
+    pub path: String,
+}
+
+// a PT_LOAD segment; a region of the process' memory, of which the first `file_size` bytes were dumped
+struct Segment {
+    range: Range<usize>,
+    file_offset: usize,
+    file_size: usize,
+    permissions: Permissions,
+}
+
+pub struct CoreTarget {
+    path: PathBuf,
+    core: Rc<Object>,
+    segments: Vec<Segment>,
+    files: Vec<MappedFile>,
+    threads: Vec<CoreThread>,
+    // the thread whose registers are read
+    selected: usize,
+    auxiliary_vector: AuxiliaryVector,
+    pid: Option<Pid>,
+    program: Option<String>,
+    arguments: Option<String>,
+    // the mapped files that have been read, or couldn't be
+    mapped_objects: RefCell<HashMap<String, Option<Rc<Object>>>>,
+}
+
+// the notes of a PT_NOTE segment; (owner, type, description)
+fn parse_notes(mut data: &[u8]) -> Vec<(&[u8], u32, &[u8])> {
+    let aligned = |size: usize| (size + 3) & !3;
+    let mut notes = vec![];
+    while let (Some(name_size), Some(description_size), Some(kind)) =
+        (u32_at(data, 0), u32_at(data, 4), u32_at(data, 8))
+    {
+        let name_start = 12;
+        let description_start = name_start + aligned(name_size as usize);
+        let end = description_start + aligned(description_size as usize);
+        match (
+            data.get(name_start..name_start + name_size as usize),
+            data.get(description_start..description_start + description_size as usize),
+        ) {
+            (Some(name), Some(description)) => notes.push((name, kind, description)),
+            _ => break,
+        }
+        data = data.get(end..).unwrap_or(&[]);
+    }
+    notes
+}
+
+// NT_FILE; the number of mappings and the page size, then (start, end, offset in pages) for each, then their paths
+fn parse_mapped_files(description: &[u8]) -> Vec<MappedFile> {
+    let (count, page_size) = match (u64_at(description, 0), u64_at(description, 8)) {
+        (Some(count), Some(page_size)) => (count as usize, page_size as usize),
+        _ => return vec![],
+    };
+    let paths_start = 16 + count.saturating_mul(24);
+    let paths = description
+        .get(paths_start..)
+        .unwrap_or(&[])
+        .split(|&byte| byte == 0)
+        .map(|path| String::from_utf8_lossy(path).into_owned());
+    (0..count)
+        .map_while(|index| {
+            let entry = 16 + index * 24;
+            Some((
+                u64_at(description, entry)? as usize,
+                u64_at(description, entry + 8)? as usize,
+                u64_at(description, entry + 16)? as usize,
+            ))
+        })
+        .zip(paths)
+        .map(|((start, end, page_offset), path)| MappedFile {
+            range: start..end,
+            offset: page_offset * page_size,
+            path,
+        })
+        .collect()
+}
+
+fn parse_registers(prstatus: &[u8]) -> Option<UserRegisters> {
+    let size = std::mem::size_of::<libc::user_regs_struct>();
+    let bytes = prstatus.get(PRSTATUS_REGISTERS..PRSTATUS_REGISTERS + size)?;
+    // pr_reg is a user_regs_struct, laid out like ptrace hands them out
+    let registers = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const libc::user_regs_struct) };
+    Some(UserRegisters::from(registers))
+}
+
+fn parse_xmm_registers(fpregset: &[u8]) -> Option<[u128; 16]> {
+    let mut registers = [0u128; 16];
+    for (index, register) in registers.iter_mut().enumerate() {
+        let start = FPREGSET_XMM + index * 16;
+        *register = u128::from_le_bytes(fpregset.get(start..start + 16)?.try_into().ok()?);
+    }
+    Some(registers)
+}
+
+impl CoreTarget {
+    /// Reads the core dump at `path`
+    pub fn open(path: &Path) -> MidasSysResultDynamic<CoreTarget> {
+        let core = load_object(path)?;
+        let not_a_core = || {
+            format!(
+                "\"{}\" is not a core dump: file format not recognized",
+                path.display()
+            )
+        };
+        if core.data.len() < 64 || core.data[0..4] != ELFHeader::MAGIC {
+            return Err(not_a_core());
+        }
+        let header = ELFHeader::from(&core.data).map_err(|err| err.description().to_string())?;
+        if header.object_type != ObjectType::Core {
+            return Err(not_a_core());
+        }
+
+        let mut target = CoreTarget {
+            path: path.to_owned(),
+            core: core.clone(),
+            segments: vec![],
+            files: vec![],
+            threads: vec![],
+            selected: 0,
+            auxiliary_vector: AuxiliaryVector::default(),
+            pid: None,
+            program: None,
+            arguments: None,
+            mapped_objects: RefCell::new(HashMap::new()),
+        };
+        let mut notes = vec![];
+        for index in 0..header.program_header_entries as usize {
+            let offset = header.program_header_offset as usize + index * header.ph_entry_size();
+            let bytes = core
+                .data
+                .get(offset..offset + std::mem::size_of::<libc::Elf64_Phdr>())
+                .ok_or_else(|| {
+                    format!(
+                        "{}: program header {} is past the end of the file",
+                        path.display(),
+                        index
+                    )
+                })?;
+            let program_header = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const libc::Elf64_Phdr) };
+            match program_header.p_type {
+                libc::PT_LOAD => target.segments.push(Segment {
+                    range: program_header.p_vaddr as usize..(program_header.p_vaddr + program_header.p_memsz) as usize,
+                    file_offset: program_header.p_offset as usize,
+                    file_size: program_header.p_filesz as usize,
+                    permissions: Permissions {
+                        read: program_header.p_flags & PF_R != 0,
+                        write: program_header.p_flags & PF_W != 0,
+                        execute: program_header.p_flags & PF_X != 0,
+                        shared: false,
+                    },
+                }),
+                libc::PT_NOTE => notes.push(
+                    program_header.p_offset as usize..(program_header.p_offset + program_header.p_filesz) as usize,
+                ),
+                _ => {}
+            }
+        }
+        target.segments.sort_by_key(|segment| segment.range.start);
+
+        for range in notes {
+            let data = core
+                .data
+                .get(range)
+                .ok_or_else(|| format!("{}: the notes are past the end of the file", path.display()))?;
+            for (owner, kind, description) in parse_notes(data) {
+                if owner != b"CORE\0" {
+                    continue;
+                }
+                match kind {
+                    // every thread has a NT_PRSTATUS, followed by the rest of it's registers
+                    NT_PRSTATUS => {
+                        if let (Some(pid), Some(signal), Some(registers)) = (
+                            u32_at(description, PRSTATUS_PID),
+                            u32_at(description, PRSTATUS_CURSIG),
+                            parse_registers(description),
+                        ) {
+                            target.threads.push(CoreThread {
+                                pid: Pid(pid as _),
+                                signal: (signal & 0xffff) as i32,
+                                registers,
+                                xmm_registers: None,
+                            });
+                        }
+                    }
+                    NT_FPREGSET => {
+                        if let Some(thread) = target.threads.last_mut() {
+                            thread.xmm_registers = parse_xmm_registers(description);
+                        }
+                    }
+                    NT_PRPSINFO => {
+                        target.pid = u32_at(description, PRPSINFO_PID).map(|pid| Pid(pid as _));
+                        target.program = fixed_string(description, PRPSINFO_FNAME);
+                        target.arguments =
+                            fixed_string(description, PRPSINFO_PSARGS).map(|args| args.trim_end().to_owned());
+                    }
+                    NT_AUXV => target.auxiliary_vector = AuxiliaryVector::parse(description),
+                    NT_FILE => target.files = parse_mapped_files(description),
+                    _ => {}
+                }
+            }
+        }
+        if target.threads.is_empty() {
+            return Err(format!("{}: the core dump has no threads", path.display()));
+        }
+        Ok(target)
+    }
+
+    /// The file the core dump was read from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The threads of the process, the one that received the signal that killed it first
+    pub fn threads(&self) -> &[CoreThread] {
+        &self.threads
+    }
+
+    /// Makes the thread `pid` the one whose registers are read
+    pub fn select_thread(&mut self, pid: Pid) -> MidasSysResultDynamic<()> {
+        self.selected = self
+            .threads
+            .iter()
+            .position(|thread| thread.pid == pid)
+            .ok_or(format!("Thread ID {} not known.", *pid))?;
+        Ok(())
+    }
+
+    /// The signal that killed the process
+    pub fn signal(&self) -> i32 {
+        self.threads[0].signal
+    }
+
+    /// The name of the program that dumped core, as the kernel knows it; at most 15 characters of it's file name
+    pub fn program(&self) -> Option<&str> {
+        self.program.as_deref()
+    }
+
+    /// The command line of the process, as far as the first 80 characters of it
+    pub fn arguments(&self) -> Option<&str> {
+        self.arguments.as_deref()
+    }
+
+    /// The files that were mapped into the process, in the order of their addresses
+    pub fn mapped_files(&self) -> &[MappedFile] {
+        &self.files
+    }
+
+    // the contents of the file at `path`, read the first time it's needed
+    fn mapped_object(&self, path: &str) -> Option<Rc<Object>> {
+        self.mapped_objects
+            .borrow_mut()
+            .entry(path.to_owned())
+            .or_insert_with(|| load_object(Path::new(path)).ok())
+            .clone()
+    }
+
+    // reads from `address` into `buffer`, as much as is in the same segment or mapped file; 0 if nothing can be read
+    fn read_some(&self, address: usize, buffer: &mut [u8]) -> usize {
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| segment.range.contains(&address));
+        if let Some(segment) = segment {
+            let into_segment = address - segment.range.start;
+            if into_segment < segment.file_size {
+                let start = segment.file_offset + into_segment;
+                let available = &self.core.data[start.min(self.core.data.len())..];
+                let count = buffer
+                    .len()
+                    .min(segment.file_size - into_segment)
+                    .min(available.len());
+                buffer[..count].copy_from_slice(&available[..count]);
+                return count;
+            }
+        }
+        // what wasn't dumped is what the mapped file still has
+        let file = match self.files.iter().find(|file| file.range.contains(&address)) {
+            Some(file) => file,
+            None => return 0,
+        };
+        let object = match self.mapped_object(&file.path) {
+            Some(object) => object,
+            None => return 0,
+        };
+        let start = file.offset + (address - file.range.start);
+        let available = object.data.get(start..).unwrap_or(&[]);
+        let mut count = buffer
+            .len()
+            .min(file.range.end - address)
+            .min(available.len());
+        if let Some(segment) = segment {
+            count = count.min(segment.range.end - address);
+        }
+        buffer[..count].copy_from_slice(&available[..count]);
+        count
+    }
+}
+
+impl Target for CoreTarget {
+    fn launch(_command: &mut std::process::Command) -> MidasSysResultDynamic<(Box<dyn super::Target>, WaitStatus)> {
+        Err("a core dump can't be launched; it's opened with CoreTarget::open".into())
+    }
+
+    fn process_id(&self) -> Pid {
+        self.pid.unwrap_or(self.threads[0].pid)
+    }
+
+    fn step(&mut self, _steps: usize) -> MidasSysResultDynamic<WaitStatus> {
+        Err(NOT_RUNNING.into())
+    }
+
+    fn continue_execution(&mut self) -> MidasSysResultDynamic<WaitStatus> {
+        Err(NOT_RUNNING.into())
+    }
+
+    fn queue_signal(&mut self, _signal: Signal) -> MidasSysResultDynamic<()> {
+        Err(NOT_RUNNING.into())
+    }
+
+    fn detach(&mut self) -> MidasSysResultDynamic<()> {
+        Err(NOT_RUNNING.into())
+    }
+
+    fn kill(&mut self) -> MidasSysResultDynamic<WaitStatus> {
+        Err(NOT_RUNNING.into())
+    }
+
+    fn read_memory(&self, address: Address, bytes: usize) -> MidasSysResultDynamic<super::MemoryReadResult> {
+        let mut data = vec![0u8; bytes];
+        let mut read = 0;
+        while read < bytes {
+            let count = self.read_some(address.value() + read, &mut data[read..]);
+            if count == 0 {
+                break;
+            }
+            read += count;
+        }
+        let error = (read < bytes).then(|| {
+            format!(
+                "Cannot access memory at address 0x{:x}",
+                address.value() + read
+            )
+        });
+        if read == 0 && bytes != 0 {
+            return Err(error.unwrap());
+        }
+        data.truncate(read);
+        Ok(super::MemoryReadResult {
+            address,
+            requested: bytes,
+            data,
+            error,
+        })
+    }
+
+    fn write_memory(&mut self, address: Address, _data: &[u8]) -> MidasSysResultDynamic<usize> {
+        Err(format!(
+            "Cannot access memory at address 0x{:x}; the memory of a core dump can't be written",
+            address.value()
+        ))
+    }
+
+    fn read_registers(&self) -> MidasSysResultDynamic<UserRegisters> {
+        Ok(self.threads[self.selected].registers.clone())
+    }
+
+    fn write_registers(&mut self, _registers: UserRegisters) -> MidasSysResultDynamic<()> {
+        Err("the registers of a core dump can't be written".into())
+    }
+
+    fn read_xmm_registers(&self) -> MidasSysResultDynamic<[u128; 16]> {
+        self.threads[self.selected]
+            .xmm_registers
+            .ok_or("the core dump has no floating point registers".into())
+    }
+
+    fn kill_on_tracer_exit(&mut self) -> MidasSysResultDynamic<()> {
+        Ok(())
+    }
+
+    fn set_breakpoint(&mut self, _bp: BreakpointRequest) -> MidasSysResultDynamic<()> {
+        Err(NOT_RUNNING.into())
+    }
+
+    fn remove_breakpoint(&mut self, address: Address) -> MidasSysResultDynamic<()> {
+        Err(format!("no breakpoint set at {:X?}", address))
+    }
+
+    fn stopped_at_breakpoint(&self) -> Option<Address> {
+        None
+    }
+
+    fn set_hardware_breakpoint(
+        &mut self,
+        _address: Address,
+        _kind: WatchKind,
+        _len: usize,
+    ) -> MidasSysResultDynamic<HWBreakpoint> {
+        Err(NOT_RUNNING.into())
+    }
+
+    fn remove_hardware_breakpoint(&mut self, slot: usize) -> MidasSysResultDynamic<HWBreakpoint> {
+        Err(format!("no hardware breakpoint in slot {}", slot))
+    }
+
+    fn stopped_at_hardware_breakpoint(&self) -> Option<HWBreakpoint> {
+        None
+    }
+
+    fn auxiliary_vector(&self) -> MidasSysResultDynamic<AuxiliaryVector> {
+        Ok(self.auxiliary_vector.clone())
+    }
+
+    fn memory_map(&self) -> MidasSysResultDynamic<Vec<MemoryRegion>> {
+        Ok(self
+            .segments
+            .iter()
+            .map(|segment| {
+                let file = self
+                    .files
+                    .iter()
+                    .find(|file| file.range.contains(&segment.range.start));
+                MemoryRegion {
+                    range: segment.range.clone(),
+                    permissions: segment.permissions,
+                    offset: file.map_or(0, |file| {
+                        file.offset + (segment.range.start - file.range.start)
+                    }),
+                    path: file.map(|file| file.path.clone()),
+                }
+            })
+            .collect())
+    }
+}
+
+fn push_note(notes: &mut Vec<u8>, kind: u32, description: &[u8]) {
+    const OWNER: &[u8] = b"CORE\0";
+    let pad = |notes: &mut Vec<u8>| notes.resize((notes.len() + 3) & !3, 0);
+    notes.extend_from_slice(&(OWNER.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&(description.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&kind.to_le_bytes());
+    notes.extend_from_slice(OWNER);
+    pad(notes);
+    notes.extend_from_slice(description);
+    pad(notes);
+}
+
+// copies `value` into the nul padded field, cutting it short if it doesn't fit
+fn write_fixed_string(data: &mut [u8], field: Range<usize>, value: &str) {
+    let field = &mut data[field];
+    let count = value.len().min(field.len() - 1);
+    field[..count].copy_from_slice(&value.as_bytes()[..count]);
+}
+
+fn prstatus(pid: Pid, signal: i32, registers: &UserRegisters, has_fpregset: bool) -> Vec<u8> {
+    let mut prstatus = vec![0u8; PRSTATUS_SIZE];
+    prstatus[PRSTATUS_CURSIG..PRSTATUS_CURSIG + 2].copy_from_slice(&(signal as u16).to_le_bytes());
+    prstatus[PRSTATUS_PID..PRSTATUS_PID + 4].copy_from_slice(&(*pid as u32).to_le_bytes());
+    for (index, name) in PRSTATUS_REGISTER_NAMES.iter().enumerate() {
+        let offset = PRSTATUS_REGISTERS + index * 8;
+        let value = registers.get_by_name(name).unwrap_or(0);
+        prstatus[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    prstatus[PRSTATUS_FPVALID..PRSTATUS_FPVALID + 4].copy_from_slice(&(has_fpregset as u32).to_le_bytes());
+    prstatus
+}
+
+// the fxsave area of the x87 and SSE state; of which we only know the xmm registers, the rest is as it is when the
+// process starts
+fn fpregset(xmm_registers: &[u128; 16]) -> Vec<u8> {
+    let mut fpregset = vec![0u8; FPREGSET_SIZE];
+    fpregset[FPREGSET_FCW..FPREGSET_FCW + 2].copy_from_slice(&0x37fu16.to_le_bytes());
+    fpregset[FPREGSET_MXCSR..FPREGSET_MXCSR + 4].copy_from_slice(&0x1f80u32.to_le_bytes());
+    for (index, register) in xmm_registers.iter().enumerate() {
+        let offset = FPREGSET_XMM + index * 16;
+        fpregset[offset..offset + 16].copy_from_slice(&register.to_le_bytes());
+    }
+    fpregset
+}
+
+fn prpsinfo(pid: Pid) -> Vec<u8> {
+    let mut prpsinfo = vec![0u8; PRPSINFO_SIZE];
+    prpsinfo[PRPSINFO_SNAME] = b't';
+    prpsinfo[PRPSINFO_PID..PRPSINFO_PID + 4].copy_from_slice(&(*pid as u32).to_le_bytes());
+    let command_line = procfs::command_line(pid).unwrap_or_default();
+    let program = procfs::executable(pid)
+        .ok()
+        .or_else(|| command_line.first().cloned())
+        .unwrap_or_default();
+    let program = Path::new(&program)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    write_fixed_string(&mut prpsinfo, PRPSINFO_FNAME, &program);
+    write_fixed_string(&mut prpsinfo, PRPSINFO_PSARGS, &command_line.join(" "));
+    prpsinfo
+}
+
+fn mapped_files(regions: &[MemoryRegion], page_size: usize) -> Vec<u8> {
+    let files: Vec<(&MemoryRegion, &String)> = regions
+        .iter()
+        .filter(|region| !region.is_pseudo())
+        .filter_map(|region| Some((region, region.path.as_ref()?)))
+        .collect();
+    let mut description = vec![];
+    description.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    description.extend_from_slice(&(page_size as u64).to_le_bytes());
+    for (region, _) in files.iter() {
+        for value in [
+            region.range.start,
+            region.range.end,
+            region.offset / page_size,
+        ] {
+            description.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+    }
+    for (_, path) in files.iter() {
+        description.extend_from_slice(path.as_bytes());
+        description.push(0);
+    }
+    description
+}
+
+// the registers of thread `tid` of the process; the threads other than the one the debugger stopped aren't traced,
+// so the thread is seized and interrupted for as long as it takes to read them, and left to run on after
+fn thread_registers(tid: Pid) -> MidasSysResultDynamic<(UserRegisters, Option<[u128; 16]>)> {
+    ptrace::seize(tid)?;
+    let registers = ptrace::interrupt(tid)
+        .and_then(|_| nixwrap::waitpid(*tid, libc::__WALL))
+        .and_then(|status| match status {
+            WaitStatus::Stopped(..) => Ok((ptrace::get_regs(tid), ptrace::get_xmm_registers(tid).ok())),
+            _ => Err(format!("thread {} is gone", *tid)),
+        });
+    let detached = ptrace::detach(tid, 0);
+    let registers = registers?;
+    detached?;
+    Ok(registers)
+}
+
+// how much of a mapping is read at a time, while it's written to the core
+const DUMP_CHUNK_SIZE: usize = 1 << 20;
+
+/// Writes a core dump of the process of `target` to `path`, that `CoreTarget::open` and other tools that read core
+/// files can read. Every thread of the process is in it, the one that's stopped first, and every readable mapping,
+/// as much of it as can be read. The mappings are read and written a piece at a time, so dumping a large process
+/// doesn't take as much memory.
+pub fn write_core(target: &dyn Target, path: &Path) -> MidasSysResultDynamic<()> {
+    let pid = target.process_id();
+    let registers = target.read_registers()?;
+    let xmm_registers = target.read_xmm_registers().ok();
+    let regions: Vec<MemoryRegion> = target
+        .memory_map()?
+        .into_iter()
+        .filter(|region| region.permissions.read)
+        .collect();
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+
+    // the process is stopped by the debugger, and that's the signal it's thread was handling; the other threads are
+    // left out if they exit before their registers are read
+    let mut threads = vec![(pid, Signal::Trap as i32, registers, xmm_registers)];
+    for tid in procfs::tasks(pid).unwrap_or_default() {
+        if tid != pid {
+            if let Ok((registers, xmm_registers)) = thread_registers(tid) {
+                threads.push((tid, 0, registers, xmm_registers));
+            }
+        }
+    }
+    let mut notes = vec![];
+    for (tid, signal, registers, xmm_registers) in threads.iter() {
+        push_note(
+            &mut notes,
+            NT_PRSTATUS,
+            &prstatus(*tid, *signal, registers, xmm_registers.is_some()),
+        );
+        if let Some(xmm_registers) = xmm_registers.as_ref() {
+            push_note(&mut notes, NT_FPREGSET, &fpregset(xmm_registers));
+        }
+    }
+    push_note(&mut notes, NT_PRPSINFO, &prpsinfo(pid));
+    push_note(&mut notes, NT_AUXV, &target.auxiliary_vector()?.to_bytes());
+    push_note(&mut notes, NT_FILE, &mapped_files(&regions, page_size));
+
+    let header_size = std::mem::size_of::<libc::Elf64_Ehdr>();
+    let program_header_size = std::mem::size_of::<libc::Elf64_Phdr>();
+    let program_header_count = 1 + regions.len();
+    let notes_offset = header_size + program_header_count * program_header_size;
+    let page_aligned = |offset: usize| (offset + page_size - 1) & !(page_size - 1);
+
+    // how much of each mapping can be read is only known once it's read, so the segments are written first, and the
+    // headers that say where they are after
+    let couldnt_write = |err: std::io::Error| format!("{}: {}", path.display(), err);
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path).map_err(couldnt_write)?);
+    let mut program_headers = vec![libc::Elf64_Phdr {
+        p_type: libc::PT_NOTE,
+        p_flags: 0,
+        p_offset: notes_offset as u64,
+        p_vaddr: 0,
+        p_paddr: 0,
+        p_filesz: notes.len() as u64,
+        p_memsz: 0,
+        p_align: 4,
+    }];
+    let mut offset = page_aligned(notes_offset + notes.len());
+    for region in regions.iter() {
+        file.seek(SeekFrom::Start(offset as u64))
+            .map_err(couldnt_write)?;
+        let mut dumped = 0;
+        while dumped < region.range.len() {
+            let count = DUMP_CHUNK_SIZE.min(region.range.len() - dumped);
+            let data = match target.read_memory(Address(region.range.start + dumped), count) {
+                Ok(read) => read.data,
+                Err(_) => break,
+            };
+            file.write_all(&data).map_err(couldnt_write)?;
+            dumped += data.len();
+            if data.len() < count {
+                break;
+            }
+        }
+        let permissions = region.permissions;
+        program_headers.push(libc::Elf64_Phdr {
+            p_type: libc::PT_LOAD,
+            p_flags: (permissions.read as u32 * PF_R)
+                | (permissions.write as u32 * PF_W)
+                | (permissions.execute as u32 * PF_X),
+            p_offset: offset as u64,
+            p_vaddr: region.range.start as u64,
+            p_paddr: 0,
+            p_filesz: dumped as u64,
+            p_memsz: region.range.len() as u64,
+            p_align: page_size as u64,
+        });
+        offset = page_aligned(offset + dumped);
+    }
+
+    let mut header: libc::Elf64_Ehdr = unsafe { std::mem::zeroed() };
+    header.e_ident[..4].copy_from_slice(&ELFHeader::MAGIC);
+    header.e_ident[libc::EI_CLASS] = libc::ELFCLASS64;
+    header.e_ident[libc::EI_DATA] = libc::ELFDATA2LSB;
+    header.e_ident[libc::EI_VERSION] = libc::EV_CURRENT as u8;
+    header.e_type = libc::ET_CORE;
+    header.e_machine = libc::EM_X86_64;
+    header.e_version = libc::EV_CURRENT;
+    header.e_phoff = header_size as u64;
+    header.e_ehsize = header_size as u16;
+    header.e_phentsize = program_header_size as u16;
+    header.e_phnum = program_header_count as u16;
+
+    file.seek(SeekFrom::Start(0)).map_err(couldnt_write)?;
+    file.write_all(unsafe { as_bytes(&header) })
+        .map_err(couldnt_write)?;
+    for program_header in program_headers.iter() {
+        file.write_all(unsafe { as_bytes(program_header) })
+            .map_err(couldnt_write)?;
+    }
+    file.write_all(&notes).map_err(couldnt_write)?;
+    file.flush().map_err(couldnt_write)?;
+    Ok(())
+}
@@ -0,0 +1,394 @@
+use nixwrap::{signals::Signal, waitpid, MidasSysResultDynamic, Pid, WaitStatus};
+use std::{
+    collections::{HashMap, HashSet},
+    os::unix::prelude::CommandExt,
+};
+
+use crate::{
+    hardware_breakpoint::{DebugRegisters, HWBreakpoint, WatchKind},
+    procfs::{self, AuxiliaryVector, MemoryRegion},
+    software_breakpoint::Breakpoint,
+    types::Address,
+    MidasSysResult,
+};
+
+pub struct LinuxTarget {
+    _binary: String,
+    pid: Pid,
+    _software_breakpoints: HashMap<Address, Breakpoint>,
+    // the software breakpoint the inferior is stopped at. The pc has already been moved back to it.
+    stopped_at: Option<Address>,
+    // once the inferior is gone, so are it's breakpoints; removing them is just bookkeeping
+    exited: bool,
+    hardware_breakpoints: DebugRegisters,
+    // decoded from DR6 when the inferior stops, since reading it also clears it
+    last_hardware_stop: Option<HWBreakpoint>,
+    // the signal the inferior is sent when it's next resumed; 0 for none
+    resume_signal: i32,
+}
+
+const INT3: u8 = 0xcc;
+
+impl LinuxTarget {
+    /// Replaces the int3's of inserted software breakpoints in `data` (read from `address`), with the original bytes
+    fn hide_breakpoints(&self, address: Address, data: &mut [u8]) {
+        let range = address.value()..address.value() + data.len();
+        for (bp_address, bp) in self._software_breakpoints.iter() {
+            if range.contains(&bp_address.value()) && bp.enabled {
+                data[bp_address.value() - address.value()] = bp.original_byte();
+            }
+        }
+    }
+
+    /// If we're stopped at an inserted breakpoint, execute the instruction it replaced, and put the breakpoint
+    /// back. Returns the wait status if the single step stopped for any other reason than finishing the step.
+    fn step_over_breakpoint(&mut self) -> MidasSysResultDynamic<Option<WaitStatus>> {
+        let pc = Address(nixwrap::ptrace::get_regs(self.pid).pc() as usize);
+        let bp = match self._software_breakpoints.get_mut(&pc) {
+            Some(bp) if bp.enabled => bp,
+            _ => return Ok(None),
+        };
+        bp.disable()?;
+        nixwrap::ptrace::single_step(self.pid, std::mem::take(&mut self.resume_signal))?;
+        let status = waitpid(*self.pid, 0)?;
+        if let WaitStatus::Stopped(_, nixwrap::signals::Signal::Trap) = status {
+            bp.enable()?;
+            // the stepped instruction might've triggered a watchpoint, which is a stop the user wants to see
+            self.last_hardware_stop = self.hardware_breakpoints.triggered(self.pid)?;
+            Ok(self.last_hardware_stop.map(|_| status))
+        } else {
+            Ok(Some(status))
+        }
+    }
+
+    /// Starts debugging process `pid`, that's already running, and returns the status of the stop attaching makes.
+    /// Once we exit the process is no longer traced, and runs on.
+    pub fn attach(pid: Pid) -> MidasSysResultDynamic<(Box<dyn super::Target>, WaitStatus)> {
+        let binary = std::fs::read_link(format!("/proc/{}/exe", *pid))
+            .map_err(|err| format!("Can't attach to process {}: {}", *pid, err))?;
+        nixwrap::ptrace::attach(pid).map_err(|err| format!("Can't attach to process {}: {}", *pid, err))?;
+        let status = waitpid(*pid, 0)?;
+        let target = Box::new(LinuxTarget {
+            _binary: binary.to_string_lossy().into_owned(),
+            pid,
+            _software_breakpoints: HashMap::new(),
+            stopped_at: None,
+            exited: false,
+            hardware_breakpoints: DebugRegisters::default(),
+            last_hardware_stop: None,
+            resume_signal: 0,
+        });
+        Ok((target, status))
+    }
+
+    /// Figure out why the inferior stopped; which hardware breakpoint triggered or if we hit one of our int3's, in
+    /// which case the pc is moved back to the breakpoint's address.
+    fn record_stop(&mut self, status: WaitStatus) -> MidasSysResultDynamic<()> {
+        self.stopped_at = None;
+        self.last_hardware_stop = None;
+        self.exited = matches!(
+            status,
+            WaitStatus::ExitedNormally(..) | WaitStatus::Killed(..) | WaitStatus::CoreDumped(..)
+        );
+        if let WaitStatus::Stopped(_, nixwrap::signals::Signal::Trap) = status {
+            self.last_hardware_stop = self.hardware_breakpoints.triggered(self.pid)?;
+            if self.last_hardware_stop.is_none() {
+                let pc = nixwrap::ptrace::get_regs(self.pid).pc().saturating_sub(1) as usize;
+                if self
+                    ._software_breakpoints
+                    .get(&Address(pc))
+                    .is_some_and(|bp| bp.enabled)
+                {
+                    nixwrap::ptrace::set_pc(self.pid, pc)?;
+                    self.stopped_at = Some(Address(pc));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl super::Target for LinuxTarget {
+    fn launch(command: &mut std::process::Command) -> MidasSysResultDynamic<(Box<dyn super::Target>, WaitStatus)> {
+        let pathstr = command.get_program().to_owned();
+        let path = std::path::Path::new(&pathstr);
+        if !path.exists() {
+            Err(format!("binary {} could not be found", path.display()))
+        } else {
+            unsafe {
+                // this closure executes in the forked child code. So in a "regular" old fork situation
+                // we would check pid if == 0 or something similar, and then handle accordingly. This closure always execs in the child.
+                command.pre_exec(|| {
+                    #[cfg(target_os = "linux")]
+                    {
+                        nixwrap::ptrace::trace_me().map_err(std::io::Error::other)?;
+                        Ok(())
+                    }
+                });
+                let child = command
+                    .spawn()
+                    .map_err(|err| format!("Spawning child failed: {}", err))?;
+                let pid = Pid(child.id() as _);
+                let status = waitpid(*pid, 0)?;
+                let target = Box::new(LinuxTarget {
+                    _binary: path.to_str().unwrap().to_string(),
+                    pid,
+                    _software_breakpoints: HashMap::new(),
+                    stopped_at: None,
+                    exited: false,
+                    hardware_breakpoints: DebugRegisters::default(),
+                    last_hardware_stop: None,
+                    resume_signal: 0,
+                });
+                Ok((target, status))
+            }
+        }
+    }
+
+    fn process_id(&self) -> Pid {
+        self.pid
+    }
+
+    fn step(&mut self, steps: usize) -> MidasSysResultDynamic<WaitStatus> {
+        let mut status = WaitStatus::Stopped(self.pid, nixwrap::signals::Signal::Trap);
+        for _ in 0..steps {
+            let pc = Address(nixwrap::ptrace::get_regs(self.pid).pc() as usize);
+            if self
+                ._software_breakpoints
+                .get(&pc)
+                .is_some_and(|bp| bp.enabled)
+            {
+                if let Some(other) = self.step_over_breakpoint()? {
+                    self.stopped_at = None;
+                    self.exited = !matches!(other, WaitStatus::Stopped(..));
+                    return Ok(other);
+                }
+            } else {
+                nixwrap::ptrace::single_step(self.pid, std::mem::take(&mut self.resume_signal))?;
+                status = waitpid(*self.pid, 0)?;
+                // not `record_stop`; the trap of a step is not an int3 that needs the pc moved back
+                self.stopped_at = None;
+                self.exited = !matches!(status, WaitStatus::Stopped(..));
+                self.last_hardware_stop = None;
+                if !matches!(
+                    status,
+                    WaitStatus::Stopped(_, nixwrap::signals::Signal::Trap)
+                ) {
+                    return Ok(status);
+                }
+                self.last_hardware_stop = self.hardware_breakpoints.triggered(self.pid)?;
+                if self.last_hardware_stop.is_some() {
+                    return Ok(status);
+                }
+            }
+            // a step executes the instruction at the pc and stops at the next one; it never executes an int3, so
+            // there's no pc to move back, but we do stop at any breakpoint we step onto
+            let pc = Address(nixwrap::ptrace::get_regs(self.pid).pc() as usize);
+            self.stopped_at = self
+                ._software_breakpoints
+                .get(&pc)
+                .filter(|bp| bp.enabled)
+                .map(|_| pc);
+            if self.stopped_at.is_some() {
+                break;
+            }
+        }
+        Ok(status)
+    }
+
+    fn continue_execution(&mut self) -> nixwrap::MidasSysResultDynamic<nixwrap::WaitStatus> {
+        if let Some(status) = self.step_over_breakpoint()? {
+            self.stopped_at = None;
+            self.exited = !matches!(status, WaitStatus::Stopped(..));
+            return Ok(status);
+        }
+        nixwrap::continue_execution(*self.pid, std::mem::take(&mut self.resume_signal))?;
+        let opts = 0;
+        let status = nixwrap::waitpid(*self.pid, opts)?;
+        self.record_stop(status)?;
+        Ok(status)
+    }
+
+    fn queue_signal(&mut self, signal: Signal) -> MidasSysResultDynamic<()> {
+        if self.exited {
+            return Err("The program is not being run.".into());
+        }
+        self.resume_signal = signal as i32;
+        Ok(())
+    }
+
+    fn detach(&mut self) -> MidasSysResultDynamic<()> {
+        if self.exited {
+            return Err("The program is not being run.".into());
+        }
+        for (_, mut bp) in self._software_breakpoints.drain() {
+            if bp.enabled {
+                bp.disable()?;
+            }
+        }
+        let slots: Vec<usize> = self.hardware_breakpoints.iter().map(|hw| hw.slot).collect();
+        for slot in slots {
+            self.hardware_breakpoints.remove(self.pid, slot)?;
+        }
+        nixwrap::ptrace::detach(self.pid, 0)?;
+        // it's no longer ours to debug; as far as we're concerned, it's gone
+        self.stopped_at = None;
+        self.last_hardware_stop = None;
+        self.exited = true;
+        Ok(())
+    }
+
+    fn kill(&mut self) -> nixwrap::MidasSysResultDynamic<nixwrap::WaitStatus> {
+        if self.exited {
+            return Err("The program is not being run.".into());
+        }
+        nixwrap::signals::send(self.pid, nixwrap::signals::Signal::Kill)?;
+        let status = nixwrap::waitpid(*self.pid, 0)?;
+        self.record_stop(status)?;
+        Ok(status)
+    }
+
+    fn read_memory(&self, address: Address, bytes: usize) -> MidasSysResultDynamic<super::MemoryReadResult> {
+        let mut data = vec![0u8; bytes];
+        let (read, error) = super::memory::read_bytes(self.pid, address, &mut data);
+        // reads past what's mapped fail for the obvious reason, whatever the last way of reading said
+        let unmapped = |address: usize| {
+            procfs::memory_map(self.pid)
+                .is_ok_and(|regions| procfs::region_containing(&regions, address).is_none())
+                .then(|| format!("Cannot access memory at address 0x{:x}", address))
+        };
+        let error = error.map(|error| unmapped(address.value() + read).unwrap_or(error));
+        if read == 0 && bytes != 0 {
+            return Err(error.unwrap_or_else(|| format!("could not read memory at {:X?}", address)));
+        }
+        data.truncate(read);
+        self.hide_breakpoints(address, &mut data);
+        Ok(super::MemoryReadResult {
+            address,
+            requested: bytes,
+            data,
+            error,
+        })
+    }
+
+    fn write_memory(&mut self, address: Address, data: &[u8]) -> MidasSysResultDynamic<usize> {
+        let mut patched = data.to_vec();
+        let range = address.value()..address.value() + data.len();
+        // the int3's stay in memory; what the caller wrote instead becomes what the breakpoints restore
+        for (bp_address, bp) in self._software_breakpoints.iter() {
+            if range.contains(&bp_address.value()) && bp.enabled {
+                patched[bp_address.value() - address.value()] = INT3;
+            }
+        }
+        let (written, error) = super::memory::write_bytes(self.pid, address, &patched);
+        if written == 0 && !data.is_empty() {
+            return Err(error.unwrap_or_else(|| format!("could not write memory at {:X?}", address)));
+        }
+        let written_range = address.value()..address.value() + written;
+        for (bp_address, bp) in self._software_breakpoints.iter_mut() {
+            if written_range.contains(&bp_address.value()) {
+                bp.set_original_byte(data[bp_address.value() - address.value()]);
+            }
+        }
+        Ok(written)
+    }
+
+    fn read_registers(&self) -> MidasSysResultDynamic<nixwrap::ptrace::UserRegisters> {
+        if self.exited {
+            return Err("The program has no registers now.".into());
+        }
+        Ok(nixwrap::ptrace::get_regs(self.pid))
+    }
+
+    fn write_registers(&mut self, registers: nixwrap::ptrace::UserRegisters) -> MidasSysResultDynamic<()> {
+        if self.exited {
+            return Err("The program has no registers now.".into());
+        }
+        self.stopped_at = self
+            .stopped_at
+            .filter(|address| address.value() as u64 == registers.rip);
+        nixwrap::ptrace::set_regs(self.pid, &registers)
+    }
+
+    fn read_xmm_registers(&self) -> MidasSysResultDynamic<[u128; 16]> {
+        nixwrap::ptrace::get_xmm_registers(self.pid)
+    }
+
+    fn kill_on_tracer_exit(&mut self) -> nixwrap::MidasSysResultDynamic<()> {
+        nixwrap::ptrace::kill_on_midas_exit(self.process_id())
+    }
+
+    fn set_breakpoint(&mut self, bp: crate::software_breakpoint::BreakpointRequest) -> MidasSysResultDynamic<()> {
+        if self.exited {
+            return Err("The program is not being run.".into());
+        }
+        match bp {
+            crate::software_breakpoint::BreakpointRequest::Address(addr) => {
+                // there's only ever one int3 at an address, no matter how many breakpoints the user has there
+                match self._software_breakpoints.get_mut(&addr) {
+                    Some(bp) => bp.enable(),
+                    None => {
+                        let bp = super::super::software_breakpoint::Breakpoint::set_enabled(self.pid, addr.value())?;
+                        self._software_breakpoints.insert(addr, bp);
+                        Ok(())
+                    }
+                }
+            }
+            crate::software_breakpoint::BreakpointRequest::Line { .. }
+            | crate::software_breakpoint::BreakpointRequest::Function { .. } => {
+                Err("breakpoint requests must be resolved to an address before they can be set".into())
+            }
+        }
+    }
+
+    fn remove_breakpoint(&mut self, address: Address) -> MidasSysResultDynamic<()> {
+        let mut bp = self
+            ._software_breakpoints
+            .remove(&address)
+            .ok_or(format!("no breakpoint set at {:X?}", address))?;
+        if self.exited {
+            Ok(())
+        } else {
+            bp.disable()
+        }
+    }
+
+    fn stopped_at_breakpoint(&self) -> Option<Address> {
+        self.stopped_at
+    }
+
+    fn set_hardware_breakpoint(
+        &mut self,
+        address: Address,
+        kind: WatchKind,
+        len: usize,
+    ) -> MidasSysResultDynamic<HWBreakpoint> {
+        if self.exited {
+            return Err("The program is not being run.".into());
+        }
+        self.hardware_breakpoints.set(self.pid, address, kind, len)
+    }
+
+    fn remove_hardware_breakpoint(&mut self, slot: usize) -> MidasSysResultDynamic<HWBreakpoint> {
+        if self.last_hardware_stop.map(|hw| hw.slot) == Some(slot) {
+            self.last_hardware_stop = None;
+        }
+        if self.exited {
+            self.hardware_breakpoints.forget(slot)
+        } else {
+            self.hardware_breakpoints.remove(self.pid, slot)
+        }
+    }
+
+    fn stopped_at_hardware_breakpoint(&self) -> Option<HWBreakpoint> {
+        self.last_hardware_stop
+    }
+
+    fn auxiliary_vector(&self) -> MidasSysResultDynamic<AuxiliaryVector> {
+        procfs::auxiliary_vector(self.pid)
+    }
+
+    fn memory_map(&self) -> MidasSysResultDynamic<Vec<MemoryRegion>> {
+        procfs::memory_map(self.pid)
+    }
+}
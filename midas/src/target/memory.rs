@@ -0,0 +1,234 @@
+use nixwrap::{ptrace, Pid};
+use std::os::unix::fs::FileExt;
+
+use crate::types::Address;
+
+const WORD_SIZE: usize = std::mem::size_of::<i64>();
+
+// Each of the three ways we have of reading or writing the inferior's memory has it's own failure modes:
+// process_vm_readv/writev is the fastest but honors page protections (so it can't write to .text, nor read PROT_NONE pages),
+// /proc/<pid>/mem ignores page protections for a tracer, but might not be accessible (mounted with hidepid, or no /proc at all)
+// and PTRACE_PEEKDATA/POKEDATA always work for a tracer, but do one syscall per word.
+// So we try them in that order, and each one picks up where the previous one stopped.
+
+/// Reads as many bytes as possible into `buf`, starting at `address`. Returns how many bytes were read, and if
+/// that was less than `buf.len()`, the error message of the last method that failed.
+pub(crate) fn read_bytes(pid: Pid, address: Address, buf: &mut [u8]) -> (usize, Option<String>) {
+    let mut read = 0;
+    let mut error = None;
+    for method in [vm_read, proc_mem_read, peek_read] {
+        if read == buf.len() {
+            return (read, None);
+        }
+        match method(pid, address.value() + read, &mut buf[read..]) {
+            Ok(bytes) => read += bytes,
+            Err(err) => error = Some(err),
+        }
+    }
+    if read == buf.len() {
+        (read, None)
+    } else {
+        (
+            read,
+            Some(error.unwrap_or_else(|| format!("could not read memory at 0x{:X}", address.value() + read))),
+        )
+    }
+}
+
+/// Writes as many bytes of `data` as possible to `address`. Returns how many bytes were written, and if that was
+/// less than `data.len()`, the error message of the last method that failed.
+pub(crate) fn write_bytes(pid: Pid, address: Address, data: &[u8]) -> (usize, Option<String>) {
+    let mut written = 0;
+    let mut error = None;
+    for method in [vm_write, proc_mem_write, poke_write] {
+        if written == data.len() {
+            return (written, None);
+        }
+        match method(pid, address.value() + written, &data[written..]) {
+            Ok(bytes) => written += bytes,
+            Err(err) => error = Some(err),
+        }
+    }
+    if written == data.len() {
+        (written, None)
+    } else {
+        (
+            written,
+            Some(error.unwrap_or_else(|| {
+                format!(
+                    "could not write memory at 0x{:X}",
+                    address.value() + written
+                )
+            })),
+        )
+    }
+}
+
+fn vm_read(pid: Pid, address: usize, buf: &mut [u8]) -> Result<usize, String> {
+    let local = libc::iovec {
+        iov_base: buf.as_mut_ptr() as _,
+        iov_len: buf.len(),
+    };
+    let remote = libc::iovec {
+        iov_base: address as _,
+        iov_len: buf.len(),
+    };
+    let bytes_read = unsafe { libc::process_vm_readv(*pid, &local, 1, &remote, 1, 0) };
+    if bytes_read == -1 {
+        Err(format!(
+            "process_vm_readv at 0x{:X} failed: {}",
+            address,
+            nixwrap::errno::get_errno_msg()
+        ))
+    } else {
+        Ok(bytes_read as usize)
+    }
+}
+
+fn vm_write(pid: Pid, address: usize, data: &[u8]) -> Result<usize, String> {
+    let local = libc::iovec {
+        iov_base: data.as_ptr() as _,
+        iov_len: data.len(),
+    };
+    let remote = libc::iovec {
+        iov_base: address as _,
+        iov_len: data.len(),
+    };
+    let bytes_written = unsafe { libc::process_vm_writev(*pid, &local, 1, &remote, 1, 0) };
+    if bytes_written == -1 {
+        Err(format!(
+            "process_vm_writev at 0x{:X} failed: {}",
+            address,
+            nixwrap::errno::get_errno_msg()
+        ))
+    } else {
+        Ok(bytes_written as usize)
+    }
+}
+
+fn open_proc_mem(pid: Pid, write: bool) -> Result<std::fs::File, String> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(format!("/proc/{}/mem", *pid))
+        .map_err(|err| format!("could not open /proc/{}/mem: {}", *pid, err))
+}
+
+fn proc_mem_read(pid: Pid, address: usize, buf: &mut [u8]) -> Result<usize, String> {
+    let file = open_proc_mem(pid, false)?;
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read_at(&mut buf[read..], (address + read) as u64) {
+            Ok(0) => break,
+            Ok(bytes) => read += bytes,
+            Err(err) if read == 0 => {
+                return Err(format!(
+                    "reading /proc/{}/mem at 0x{:X} failed: {}",
+                    *pid, address, err
+                ))
+            }
+            Err(_) => break,
+        }
+    }
+    Ok(read)
+}
+
+fn proc_mem_write(pid: Pid, address: usize, data: &[u8]) -> Result<usize, String> {
+    let file = open_proc_mem(pid, true)?;
+    let mut written = 0;
+    while written < data.len() {
+        match file.write_at(&data[written..], (address + written) as u64) {
+            Ok(0) => break,
+            Ok(bytes) => written += bytes,
+            Err(err) if written == 0 => {
+                return Err(format!(
+                    "writing /proc/{}/mem at 0x{:X} failed: {}",
+                    *pid, address, err
+                ))
+            }
+            Err(_) => break,
+        }
+    }
+    Ok(written)
+}
+
+fn peek_read(pid: Pid, address: usize, buf: &mut [u8]) -> Result<usize, String> {
+    let mut read = 0;
+    while read < buf.len() {
+        let count = WORD_SIZE.min(buf.len() - read);
+        // a trailing partial word is read as the word that ends where the range does, so that none of the bytes
+        // after the range have to be readable. When the range is shorter than a word, that word starts before it,
+        // and the bytes there might not be readable either, so the word that starts at the range is tried too.
+        let ending = (count < WORD_SIZE)
+            .then(|| (address + buf.len()).checked_sub(WORD_SIZE))
+            .flatten()
+            .and_then(|start| ptrace::peek_data(pid, start).ok())
+            .map(|word| word.to_ne_bytes()[WORD_SIZE - count..].to_vec());
+        let word = match ending {
+            Some(word) => word,
+            None => match ptrace::peek_data(pid, address + read) {
+                Ok(word) => word.to_ne_bytes()[..count].to_vec(),
+                Err(err) if read == 0 => return Err(err),
+                Err(_) => break,
+            },
+        };
+        buf[read..read + count].copy_from_slice(&word);
+        read += count;
+    }
+    Ok(read)
+}
+
+fn poke_write(pid: Pid, address: usize, data: &[u8]) -> Result<usize, String> {
+    let mut written = 0;
+    while written < data.len() {
+        let count = WORD_SIZE.min(data.len() - written);
+        let result = if count == WORD_SIZE {
+            let mut word = [0u8; WORD_SIZE];
+            word.copy_from_slice(&data[written..written + WORD_SIZE]);
+            ptrace::poke_data(pid, address + written, i64::from_ne_bytes(word))
+        } else {
+            // a trailing partial word; we must not clobber the bytes that follow it
+            ptrace::peek_data(pid, address + written).and_then(|word| {
+                let mut word = word.to_ne_bytes();
+                word[..count].copy_from_slice(&data[written..]);
+                ptrace::poke_data(pid, address + written, i64::from_ne_bytes(word))
+            })
+        };
+        match result {
+            Ok(_) => written += count,
+            Err(err) if written == 0 => return Err(err),
+            Err(_) => break,
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        procfs,
+        target::{linux::LinuxTarget, make_command, Target},
+    };
+
+    #[test]
+    fn peek_read_stops_at_the_end_of_a_mapping() {
+        let (target, _status) = LinuxTarget::launch(&mut make_command("/bin/true", vec![]).unwrap()).unwrap();
+        let regions = procfs::memory_map(target.process_id()).unwrap();
+        // a region with nothing mapped right after it
+        let end = regions
+            .iter()
+            .map(|region| region.range.end)
+            .find(|end| regions.iter().all(|region| region.range.start != *end))
+            .unwrap();
+        let mut buf = [0u8; WORD_SIZE + 3];
+        assert_eq!(
+            peek_read(target.process_id(), end - buf.len(), &mut buf),
+            Ok(buf.len())
+        );
+        let mut tail = [0u8; 3];
+        assert_eq!(peek_read(target.process_id(), end - 3, &mut tail), Ok(3));
+        assert_eq!(tail, buf[WORD_SIZE..]);
+        assert!(peek_read(target.process_id(), end, &mut tail).is_err());
+    }
+}
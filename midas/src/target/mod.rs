@@ -0,0 +1,172 @@
+#![allow(unused, non_camel_case_types)]
+use nixwrap::{signals::Signal, Pid, WaitStatus};
+pub mod coredump;
+pub mod linux;
+mod memory;
+
+use nixwrap::ptrace::UserRegisters;
+use nixwrap::MidasSysResultDynamic;
+
+use crate::hardware_breakpoint::{HWBreakpoint, WatchKind};
+use crate::procfs::{AuxiliaryVector, MemoryRegion};
+use crate::software_breakpoint::BreakpointRequest;
+use crate::types::Address;
+use std::os::unix::process::CommandExt;
+
+pub struct MemoryRead {
+    pub result: Vec<Vec<u8>>,
+    bytes_read: usize,
+}
+
+impl MemoryRead {
+    pub fn read_memory(pid: Pid, ranges: Vec<(Address, usize)>) -> MidasSysResultDynamic<MemoryRead> {
+        // the iovecs, containing a { pointer to a buffer where the bytes should be read from, and the length }
+        let mut read_parameters = Vec::with_capacity(ranges.len());
+        // the actual backing storage where we copy the data into. Each element in read_parameters, have a pointer, that points into this buffer of buffers
+        let mut backing_storage: Vec<Vec<u8>> = Vec::with_capacity(ranges.len());
+        // the iovecs, containing a { pointer to a buffer where the bytes should be copied to, and the length }
+        let mut store_parameters = Vec::with_capacity(ranges.len());
+
+        for (index, (addr, bytes)) in ranges.iter().enumerate() {
+            // push is safe here; because we've allocated the vectors up front with_capacity, so *no* re-allocation or moving *should* happen
+            backing_storage.push(vec![0u8; *bytes]);
+            read_parameters.push(libc::iovec {
+                iov_base: addr.value() as *mut _,
+                iov_len: *bytes,
+            });
+            store_parameters.push(libc::iovec {
+                iov_base: backing_storage.get_mut(index).unwrap().as_mut_ptr() as _,
+                iov_len: *bytes,
+            });
+        }
+
+        unsafe {
+            let bytes_read = libc::process_vm_readv(
+                *pid,
+                store_parameters.as_ptr() as _,
+                store_parameters.len() as _,
+                read_parameters.as_ptr() as _,
+                read_parameters.len() as _,
+                0,
+            );
+            if bytes_read == -1 {
+                Err(nixwrap::errno::get_errno_msg())
+            } else {
+                Ok(MemoryRead {
+                    result: backing_storage,
+                    bytes_read: bytes_read as usize,
+                })
+            }
+        }
+    }
+}
+
+/// The result of `Target::read_memory`. A read can be partial, for instance when the requested range runs into
+/// unmapped memory; `data` then holds the bytes that could be read and `error` describes why the rest could not.
+#[derive(Debug)]
+pub struct MemoryReadResult {
+    pub address: Address,
+    pub requested: usize,
+    pub data: Vec<u8>,
+    pub error: Option<String>,
+}
+
+impl MemoryReadResult {
+    pub fn is_partial(&self) -> bool {
+        self.data.len() < self.requested
+    }
+}
+
+// represents the state operations we can do on the debuggeee
+pub trait Target {
+    fn launch(command: &mut std::process::Command) -> MidasSysResultDynamic<(Box<dyn Target>, WaitStatus)>
+    where
+        Self: Sized;
+    fn process_id(&self) -> Pid;
+    /// Executes `steps` instructions, one at a time. Stops early if the inferior stops for any other reason than
+    /// finishing a step, or steps onto an enabled software breakpoint, which `stopped_at_breakpoint` then reports.
+    fn step(&mut self, steps: usize) -> MidasSysResultDynamic<WaitStatus>;
+    fn continue_execution(&mut self) -> MidasSysResultDynamic<WaitStatus>;
+    /// Has `signal` delivered to the inferior when it next resumes, by a step or by continuing; how a signal it
+    /// stopped for is passed on to it. If it handles the signal, a step stops at the handler.
+    fn queue_signal(&mut self, signal: Signal) -> MidasSysResultDynamic<()>;
+    /// Takes the breakpoints out and stops tracing the inferior, which runs on without us
+    fn detach(&mut self) -> MidasSysResultDynamic<()>;
+    /// Kills the inferior with SIGKILL, and returns the status it died with
+    fn kill(&mut self) -> MidasSysResultDynamic<WaitStatus>;
+    /// Reads `bytes` bytes at `address`. Software breakpoints are hidden, i.e. the bytes returned are the original
+    /// instructions and not the int3's we've patched in. Returns an error only if not a single byte could be read.
+    fn read_memory(&self, address: Address, bytes: usize) -> MidasSysResultDynamic<MemoryReadResult>;
+    /// Writes `data` to `address` and returns the number of bytes written. Writes to addresses where software
+    /// breakpoints are set, update the instruction the breakpoint restores, leaving the breakpoint in place.
+    fn write_memory(&mut self, address: Address, data: &[u8]) -> MidasSysResultDynamic<usize>;
+    fn read_registers(&self) -> MidasSysResultDynamic<UserRegisters>;
+    /// Sets the general purpose registers. Moving the pc away from a software breakpoint the inferior is stopped at,
+    /// means it's no longer stopped at it.
+    fn write_registers(&mut self, registers: UserRegisters) -> MidasSysResultDynamic<()>;
+    /// The SSE registers xmm0-xmm15
+    fn read_xmm_registers(&self) -> MidasSysResultDynamic<[u128; 16]>;
+    fn kill_on_tracer_exit(&mut self) -> MidasSysResultDynamic<()>;
+    /// Inserts a software breakpoint. Only `BreakpointRequest::Address` can be set; other requests must be
+    /// resolved to addresses first. Setting a breakpoint where one already is, does nothing.
+    fn set_breakpoint(&mut self, bp: BreakpointRequest) -> MidasSysResultDynamic<()>;
+    /// Removes the software breakpoint at `address`, restoring the instruction it replaced
+    fn remove_breakpoint(&mut self, address: Address) -> MidasSysResultDynamic<()>;
+    /// The software breakpoint that the inferior stopped at. When stopped at a breakpoint, the pc has been moved
+    /// back to the breakpoint's address, i.e. to the instruction the int3 replaced.
+    fn stopped_at_breakpoint(&self) -> Option<Address>;
+    /// Sets a hardware breakpoint (`WatchKind::Execute`) or watchpoint on the `len` bytes at `address`
+    fn set_hardware_breakpoint(
+        &mut self,
+        address: Address,
+        kind: WatchKind,
+        len: usize,
+    ) -> MidasSysResultDynamic<HWBreakpoint>;
+    fn remove_hardware_breakpoint(&mut self, slot: usize) -> MidasSysResultDynamic<HWBreakpoint>;
+    /// The hardware breakpoint or watchpoint that triggered the last stop, if any
+    fn stopped_at_hardware_breakpoint(&self) -> Option<HWBreakpoint>;
+    /// The auxiliary vector the kernel gave the inferior
+    fn auxiliary_vector(&self) -> MidasSysResultDynamic<AuxiliaryVector>;
+    /// The regions of memory the inferior has mapped, in the order of their addresses
+    fn memory_map(&self) -> MidasSysResultDynamic<Vec<MemoryRegion>>;
+}
+
+/// The command that launches `program_path`, with address space randomization disabled like gdb does, so that the
+/// stack, the libraries and position independent programs are where they were the last time
+pub fn make_command(program_path: &str, args: Vec<&str>) -> MidasSysResultDynamic<std::process::Command> {
+    make_command_with(program_path, args, true)
+}
+
+/// The command that launches `program_path`; with address space randomization disabled if `disable_randomization`,
+/// which is what gdb's `set disable-randomization` chooses
+pub fn make_command_with(
+    program_path: &str,
+    args: Vec<&str>,
+    disable_randomization: bool,
+) -> MidasSysResultDynamic<std::process::Command> {
+    let mut command = make_randomized_command(program_path, args)?;
+    if !disable_randomization {
+        return Ok(command);
+    }
+    unsafe {
+        command.pre_exec(|| {
+            if libc::personality(libc::ADDR_NO_RANDOMIZE as _) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(command)
+}
+
+/// The command that launches `program_path`, loaded wherever the kernel puts it
+pub fn make_randomized_command(program_path: &str, args: Vec<&str>) -> MidasSysResultDynamic<std::process::Command> {
+    let program = std::path::Path::new(program_path);
+    if !program.exists() {
+        Err(format!("{} doesn't exist", program.display()))
+    } else {
+        let mut command = std::process::Command::new(program);
+        command.args(args.iter());
+        Ok(command)
+    }
+}
@@ -0,0 +1,291 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    breakpoints::{BreakpointKind, BreakpointTable},
+    expression::{EvaluationContext, Expression},
+    software_breakpoint::BreakpointRequest,
+    target::{self, Target},
+    types::Address,
+};
+use nixwrap::{signals::Signal, MidasSysResultDynamic, WaitStatus};
+
+const INFERIOR: &str = "/bin/true";
+
+// overwrites the code at the inferior's first instruction with a loop that executes the nop at offset 5 three
+// times and then exits with status 0
+const LOOP: [u8; 19] = [
+    0xb9, 0x03, 0x00, 0x00, 0x00, // mov ecx, 3
+    0x90, // loop: nop
+    0xff, 0xc9, // dec ecx
+    0x75, 0xfb, // jnz loop
+    0xb8, 0x3c, 0x00, 0x00, 0x00, // mov eax, 60 (exit)
+    0x31, 0xff, // xor edi, edi
+    0x0f, 0x05, // syscall
+];
+const NOP: usize = 5;
+const DEC: usize = 6;
+
+fn launch_loop() -> (Box<dyn Target>, Address) {
+    let (mut target, _waitstatus) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(INFERIOR, vec![]).unwrap()).unwrap();
+    let pc = Address(target.read_registers().unwrap().pc() as usize);
+    assert_eq!(target.write_memory(pc, &LOOP).unwrap(), LOOP.len());
+    (target, pc)
+}
+
+fn software(table: &mut BreakpointTable, target: &mut dyn Target, address: Address) -> usize {
+    table
+        .insert(
+            target,
+            BreakpointKind::Software,
+            BreakpointRequest::Address(address),
+            vec![address],
+        )
+        .unwrap()
+        .number
+}
+
+fn continue_to_trap(target: &mut dyn Target) {
+    let status = target.continue_execution().unwrap();
+    assert!(matches!(status, WaitStatus::Stopped(_, Signal::Trap)));
+}
+
+fn continue_to_exit(target: &mut dyn Target) {
+    let status = target.continue_execution().unwrap();
+    assert!(matches!(status, WaitStatus::ExitedNormally(_, 0)));
+}
+
+// conditions in these tests only use registers
+struct Registers<'a>(&'a dyn Target);
+
+impl<'a> EvaluationContext for Registers<'a> {
+    fn register(&self, name: &str) -> Option<u64> {
+        self.0.read_registers().ok()?.get_by_name(name)
+    }
+
+    fn symbol(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    fn address_of(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    fn read_pointer(&self, _address: u64) -> Option<u64> {
+        None
+    }
+}
+
+fn evaluate(target: &dyn Target, condition: &Expression) -> MidasSysResultDynamic<u64> {
+    condition.evaluate(&Registers(target))
+}
+
+#[test]
+pub fn breakpoint_is_hit_every_iteration() {
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    let nop = Address(start.value() + NOP);
+    let number = software(&mut table, target.as_mut(), nop);
+    assert_eq!(number, 1);
+    for _ in 0..3 {
+        continue_to_trap(target.as_mut());
+        assert_eq!(target.stopped_at_breakpoint(), Some(nop));
+        // the pc is moved back to the breakpoint
+        assert_eq!(target.read_registers().unwrap().pc() as usize, nop.value());
+        assert_eq!(table.record_hit(target.as_ref()), vec![number]);
+    }
+    assert_eq!(table.get(number).unwrap().hit_count, 3);
+    continue_to_exit(target.as_mut());
+}
+
+#[test]
+pub fn shared_and_disabled_breakpoints() {
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    let nop = Address(start.value() + NOP);
+    let dec = Address(start.value() + DEC);
+    let first = software(&mut table, target.as_mut(), nop);
+    let second = software(&mut table, target.as_mut(), nop);
+    let third = software(&mut table, target.as_mut(), dec);
+
+    continue_to_trap(target.as_mut());
+    assert_eq!(table.record_hit(target.as_ref()), vec![first, second]);
+
+    // the trap stays, as long as one of the breakpoints at an address needs it
+    table.delete(target.as_mut(), first).unwrap();
+    table.disable(target.as_mut(), third).unwrap();
+    assert_eq!(
+        target.read_memory(start, LOOP.len()).unwrap().data,
+        LOOP.to_vec()
+    );
+    continue_to_trap(target.as_mut());
+    assert_eq!(table.record_hit(target.as_ref()), vec![second]);
+
+    table.disable(target.as_mut(), second).unwrap();
+    table.enable(target.as_mut(), third).unwrap();
+    continue_to_trap(target.as_mut());
+    assert_eq!(target.stopped_at_breakpoint(), Some(dec));
+    assert_eq!(table.record_hit(target.as_ref()), vec![third]);
+
+    // with every breakpoint gone, the original code is back in place
+    table.delete(target.as_mut(), second).unwrap();
+    table.delete(target.as_mut(), third).unwrap();
+    assert!(table.get(second).is_none());
+    assert!(table.delete(target.as_mut(), third).is_err());
+    let raw = nixwrap::ptrace::peek_data(target.process_id(), start.value()).unwrap();
+    assert_eq!(raw.to_le_bytes(), LOOP[..8]);
+
+    // numbers are never reused
+    let fourth = software(&mut table, target.as_mut(), dec);
+    assert_eq!(fourth, 4);
+    table.delete(target.as_mut(), fourth).unwrap();
+    continue_to_exit(target.as_mut());
+    assert!(table
+        .insert(
+            target.as_mut(),
+            BreakpointKind::Software,
+            BreakpointRequest::Address(nop),
+            vec![nop]
+        )
+        .is_err());
+}
+
+#[test]
+pub fn conditions_are_continued_past_while_false() {
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    let number = software(&mut table, target.as_mut(), Address(start.value() + NOP));
+    assert!(table.set_condition(number, Some("$rcx ==")).is_err());
+    table.set_condition(number, Some("$rcx == 1")).unwrap();
+
+    // ecx counts down from 3; only the last iteration stops
+    let stop = table
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(stop.condition_errors.is_empty());
+    assert_eq!(stop.breakpoints.len(), 1);
+    assert_eq!(stop.breakpoints[0].number, number);
+    assert_eq!(target.read_registers().unwrap().get_by_name("rcx"), Some(1));
+    // false conditions aren't hits
+    assert_eq!(table.get(number).unwrap().hit_count, 1);
+
+    let stop = table
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(matches!(stop.status, WaitStatus::ExitedNormally(_, 0)));
+    assert!(stop.breakpoints.is_empty());
+}
+
+#[test]
+pub fn condition_errors_stop() {
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    let number = software(&mut table, target.as_mut(), Address(start.value() + NOP));
+    table
+        .set_condition(number, Some("$nosuchregister == 1"))
+        .unwrap();
+    let stop = table
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.condition_errors.len(), 1);
+    assert_eq!(stop.condition_errors[0].0, number);
+    assert_eq!(target.read_registers().unwrap().get_by_name("rcx"), Some(3));
+
+    table.set_condition(number, None).unwrap();
+    assert!(table.get(number).unwrap().condition.is_none());
+}
+
+#[test]
+pub fn ignored_and_temporary_breakpoints() {
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    let number = software(&mut table, target.as_mut(), Address(start.value() + NOP));
+    table.set_ignore_count(number, 1).unwrap();
+    table.set_temporary(number).unwrap();
+
+    let stop = table
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints.len(), 1);
+    assert_eq!(stop.breakpoints[0].hit_count, 2);
+    assert_eq!(stop.breakpoints[0].ignore_count, 0);
+    assert_eq!(target.read_registers().unwrap().get_by_name("rcx"), Some(2));
+    // a temporary breakpoint is gone once it has stopped the inferior, trap and all
+    assert!(table.get(number).is_none());
+    assert_eq!(
+        target.read_memory(start, LOOP.len()).unwrap().data,
+        LOOP.to_vec()
+    );
+
+    let stop = table
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(matches!(stop.status, WaitStatus::ExitedNormally(_, 0)));
+}
+
+#[test]
+pub fn steps_stop_at_breakpoints_stepped_onto() {
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    let nop = Address(start.value() + NOP);
+    software(&mut table, target.as_mut(), nop);
+
+    // mov ecx, 3 is one instruction; the step ends on the breakpoint, without executing it's int3
+    let status = target.step(1).unwrap();
+    assert!(matches!(status, WaitStatus::Stopped(_, Signal::Trap)));
+    assert_eq!(target.stopped_at_breakpoint(), Some(nop));
+    assert_eq!(target.read_registers().unwrap().pc() as usize, nop.value());
+
+    // stepping from the breakpoint executes the nop it replaced, then dec ecx, which is 2 bytes
+    target.step(2).unwrap();
+    assert_eq!(target.stopped_at_breakpoint(), None);
+    assert_eq!(
+        target.read_registers().unwrap().pc() as usize,
+        start.value() + DEC + 2
+    );
+    assert_eq!(target.read_registers().unwrap().get_by_name("rcx"), Some(2));
+    continue_to_trap(target.as_mut());
+    assert_eq!(target.stopped_at_breakpoint(), Some(nop));
+}
+
+#[test]
+pub fn detaching_takes_the_breakpoints_out() {
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    software(&mut table, target.as_mut(), Address(start.value() + NOP));
+    target.detach().unwrap();
+    assert!(target.continue_execution().is_err());
+    // it's still our child, if no longer traced; without the int3's it runs the loop to the end
+    let status = nixwrap::waitpid(*target.process_id(), 0).unwrap();
+    assert!(
+        matches!(status, WaitStatus::ExitedNormally(_, 0)),
+        "{:?}",
+        status
+    );
+}
+
+#[test]
+pub fn signals_are_delivered_on_resuming() {
+    // a step delivers the signal first; it isn't handled, so it kills the inferior
+    let (mut target, _) = launch_loop();
+    target.queue_signal(Signal::UserDefined1).unwrap();
+    let status = target.step(1).unwrap();
+    assert!(
+        matches!(status, WaitStatus::Killed(_, Signal::UserDefined1)),
+        "{:?}",
+        status
+    );
+
+    // as does continuing from a breakpoint
+    let (mut target, start) = launch_loop();
+    let mut table = BreakpointTable::default();
+    software(&mut table, target.as_mut(), Address(start.value() + DEC));
+    continue_to_trap(target.as_mut());
+    target.queue_signal(Signal::Termination).unwrap();
+    let status = target.continue_execution().unwrap();
+    assert!(
+        matches!(status, WaitStatus::Killed(_, Signal::Termination)),
+        "{:?}",
+        status
+    );
+}
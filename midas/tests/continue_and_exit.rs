@@ -106,12 +106,12 @@ pub fn set_bp_at_main_and_stops() {
             WaitStatus::Stopped(pid, signal) => {
                 assert_eq!(signal, nixwrap::signals::Signal::Trap);
                 let regs = nixwrap::ptrace::get_regs(pid);
-                assert_eq!(regs.rip - 1, main_address_of_helloworld as _);
+                assert_eq!(regs.rip, main_address_of_helloworld as _);
                 target
                     .continue_execution()
                     .expect("failed to continue execution");
                 let regs = nixwrap::ptrace::get_regs(target.process_id());
-                assert_eq!(regs.rip - 1, before_print as _);
+                assert_eq!(regs.rip, before_print as _);
                 target
                     .continue_execution()
                     .expect("failed to continue execution");
@@ -0,0 +1,275 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    breakpoints::BreakpointTable,
+    elf::ParsedELF,
+    frame::{self, Frame},
+    procfs,
+    target::{
+        self,
+        coredump::{self, CoreTarget},
+        Target,
+    },
+    types::Address,
+};
+use std::{os::unix::process::CommandExt, path::PathBuf, process::Command, sync::Once};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/crash");
+const THREADS: &str = concat!(tests_dir!(), "/executables/threads");
+
+// lines of crash.c
+const CRASH_LINE: u32 = 13;
+const RECURSION_LINE: u32 = 9;
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .args(["crash", "threads"])
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// runs the subject in a directory of it's own, and returns the core it dumped there, and it's pid. None if the kernel
+// doesn't write core dumps where the process runs, or isn't allowed to.
+fn dump_core() -> Option<(PathBuf, i32)> {
+    let pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern").ok()?;
+    if pattern.starts_with('|') || pattern.contains('/') {
+        return None;
+    }
+    let directory = std::env::temp_dir().join(format!("midas-coredump-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let mut command = Command::new(SUBJECT);
+    command.current_dir(&directory);
+    unsafe {
+        command.pre_exec(|| {
+            let mut limit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            libc::getrlimit(libc::RLIMIT_CORE, &mut limit);
+            limit.rlim_cur = limit.rlim_max;
+            libc::setrlimit(libc::RLIMIT_CORE, &limit);
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().unwrap();
+    let pid = child.id() as i32;
+    child.wait().unwrap();
+    let core = std::fs::read_dir(&directory)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("core"))
+        })?;
+    Some((core, pid))
+}
+
+fn variable(target: &dyn Target, elf: &ParsedELF, frame: &Frame, name: &str) -> u64 {
+    let variable = frame.variable(elf, name).unwrap();
+    frame.value_of(target, &variable).unwrap()
+}
+
+#[test]
+pub fn crashed_programs_are_debugged_from_their_core() {
+    compile_subject();
+    let (core_path, pid) = match dump_core() {
+        Some(core) => core,
+        None => {
+            eprintln!("no core dump was written; the kernel's core_pattern or core size limit doesn't allow it");
+            return;
+        }
+    };
+    let mut target = CoreTarget::open(&core_path).unwrap();
+    assert_eq!(*target.process_id(), pid);
+    assert_eq!(
+        target.signal(),
+        nixwrap::signals::Signal::SegmentationFault as i32
+    );
+    assert_eq!(target.program(), Some("crash"));
+    assert!(target.arguments().unwrap().ends_with("executables/crash"));
+    assert_eq!(target.threads().len(), 1);
+    assert!(target.read_xmm_registers().is_ok());
+
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let mut elf = ParsedELF::parse_elf(&object).unwrap();
+    let auxv = target.auxiliary_vector().unwrap();
+    assert_eq!(auxv.entry(), Some(elf.entry_point()));
+    elf.relocate(elf.load_bias_from_auxv(&auxv).unwrap());
+    let mut breakpoints = BreakpointTable::default();
+    breakpoints.read_shared_libraries(&target, &elf).unwrap();
+    assert!(breakpoints
+        .shared_libraries()
+        .iter()
+        .any(|library| library.path.ends_with("libc.so.6")));
+
+    // the stack is unwound through the recursion, out to main
+    let frames = frame::backtrace(&target, &elf, breakpoints.shared_libraries()).unwrap();
+    let names: Vec<&str> = frames
+        .iter()
+        .map(|frame| frame.function.as_ref().unwrap().name.as_str())
+        .collect();
+    assert_eq!(names, ["crash", "crash", "crash", "main"]);
+    let lines = elf.line_table().unwrap();
+    assert_eq!(
+        lines.row_at(frames[0].lookup_pc()).unwrap().line(),
+        CRASH_LINE
+    );
+    assert_eq!(
+        lines.row_at(frames[1].lookup_pc()).unwrap().line(),
+        RECURSION_LINE
+    );
+    for (level, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.level, level);
+    }
+    // and every frame has the variables it had
+    for (depth, frame) in frames[..3].iter().enumerate() {
+        assert_eq!(variable(&target, &elf, frame, "depth"), depth as u64);
+        assert_eq!(variable(&target, &elf, frame, "pointer"), 0);
+    }
+    assert_eq!(variable(&target, &elf, &frames[3], "local"), 7);
+    assert_eq!(variable(&target, &elf, &frames[0], "crashes"), 1);
+
+    // code isn't dumped, but it's read from the file it was mapped from
+    let pc = frames[0].pc;
+    let text = elf.get_section_data(".text").unwrap();
+    let text_address = elf.get_section_address(".text").unwrap();
+    let read = target.read_memory(Address(pc), 16).unwrap();
+    assert_eq!(read.data, text[pc - text_address..pc - text_address + 16]);
+    let regions = target.memory_map().unwrap();
+    assert!(
+        procfs::region_containing(&regions, pc).is_some_and(|region| region.permissions.execute
+            && region
+                .path
+                .as_ref()
+                .is_some_and(|path| path.ends_with("executables/crash")))
+    );
+    assert_eq!(
+        target.read_memory(Address(0), 8).unwrap_err(),
+        "Cannot access memory at address 0x0"
+    );
+
+    // there's no process to run
+    assert!(target.continue_execution().is_err());
+    assert!(target.step(1).is_err());
+    assert!(target.write_memory(Address(pc), &[0x90]).is_err());
+
+    // only core dumps are core dumps
+    assert!(CoreTarget::open(std::path::Path::new(SUBJECT))
+        .err()
+        .is_some_and(|err| err.contains("is not a core dump")));
+    std::fs::remove_dir_all(core_path.parent().unwrap()).unwrap();
+}
+
+#[test]
+pub fn live_processes_are_dumped_and_read_back() {
+    compile_subject();
+    let (mut live, _status) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(SUBJECT, vec![]).unwrap()).unwrap();
+    // it runs until it dereferences the null pointer, where it stays, stopped by the signal
+    live.continue_execution().unwrap();
+    let path = std::env::temp_dir().join(format!("midas-gcore-{}", std::process::id()));
+    coredump::write_core(live.as_ref(), &path).unwrap();
+
+    let core = CoreTarget::open(&path).unwrap();
+    assert_eq!(core.process_id(), live.process_id());
+    assert_eq!(core.program(), Some("crash"));
+    assert!(core.arguments().unwrap().ends_with("executables/crash"));
+    assert_eq!(
+        core.read_registers().unwrap(),
+        live.read_registers().unwrap()
+    );
+    assert_eq!(
+        core.read_xmm_registers().unwrap(),
+        live.read_xmm_registers().unwrap()
+    );
+    assert_eq!(
+        core.auxiliary_vector().unwrap().entries,
+        live.auxiliary_vector().unwrap().entries
+    );
+    // every readable mapping is in it, where it was and with what it had
+    let live_regions = live.memory_map().unwrap();
+    let regions = core.memory_map().unwrap();
+    let readable: Vec<_> = live_regions
+        .iter()
+        .filter(|region| region.permissions.read)
+        .collect();
+    assert_eq!(regions.len(), readable.len());
+    for (region, live_region) in regions.iter().zip(readable) {
+        assert_eq!(region.range, live_region.range);
+        if !live_region.is_pseudo() {
+            assert_eq!(region.path, live_region.path);
+        }
+    }
+    let stack = live.read_registers().unwrap().rsp as usize;
+    assert_eq!(
+        core.read_memory(Address(stack), 64).unwrap().data,
+        live.read_memory(Address(stack), 64).unwrap().data
+    );
+
+    // and it's debugged like the one the kernel writes
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let mut elf = ParsedELF::parse_elf(&object).unwrap();
+    elf.relocate(
+        elf.load_bias_from_auxv(&core.auxiliary_vector().unwrap())
+            .unwrap(),
+    );
+    let mut breakpoints = BreakpointTable::default();
+    breakpoints.read_shared_libraries(&core, &elf).unwrap();
+    let frames = frame::backtrace(&core, &elf, breakpoints.shared_libraries()).unwrap();
+    let names: Vec<&str> = frames
+        .iter()
+        .map(|frame| frame.function.as_ref().unwrap().name.as_str())
+        .collect();
+    assert_eq!(names, ["crash", "crash", "crash", "main"]);
+    for (depth, frame) in frames[..3].iter().enumerate() {
+        assert_eq!(variable(&core, &elf, frame, "depth"), depth as u64);
+    }
+    assert_eq!(variable(&core, &elf, &frames[3], "local"), 7);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+pub fn every_thread_is_dumped() {
+    compile_subject();
+    let (mut live, _status) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(THREADS, vec![]).unwrap()).unwrap();
+    // it runs until it's threads have started, and the main thread traps
+    live.continue_execution().unwrap();
+    let tasks = procfs::tasks(live.process_id()).unwrap();
+    assert_eq!(tasks.len(), 3);
+    let path = std::env::temp_dir().join(format!("midas-gcore-threads-{}", std::process::id()));
+    coredump::write_core(live.as_ref(), &path).unwrap();
+
+    let core = CoreTarget::open(&path).unwrap();
+    let threads: Vec<_> = core.threads().iter().map(|thread| thread.pid).collect();
+    assert_eq!(threads, tasks);
+    assert_eq!(core.threads()[0].registers, live.read_registers().unwrap());
+    assert_eq!(core.threads()[0].signal, libc::SIGTRAP);
+    for thread in &core.threads()[1..] {
+        assert_eq!(thread.signal, 0);
+        assert_ne!(thread.registers.rip, 0);
+        assert_ne!(thread.registers.rsp, 0);
+    }
+    // the threads were left to run, and the process is still the debugger's
+    assert_eq!(procfs::tasks(live.process_id()).unwrap().len(), 3);
+    live.kill().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
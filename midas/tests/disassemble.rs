@@ -0,0 +1,193 @@
+use midas::disassemble::{decode, DecodeError, Syntax};
+
+// names addresses in a pretend program where main is at 0x401126
+fn symbolize(address: u64) -> Option<String> {
+    match address {
+        0x401126..=0x40117f => Some(format!("main+{}", address - 0x401126)),
+        0x404028 => Some("counter".to_owned()),
+        _ => None,
+    }
+}
+
+// the instruction the bytes are, in both syntaxes
+fn disassemble(address: u64, bytes: &[u8]) -> (String, String) {
+    let instruction = decode(bytes, address).unwrap();
+    assert_eq!(instruction.length, bytes.len());
+    (
+        instruction.format(Syntax::Att, &symbolize),
+        instruction.format(Syntax::Intel, &symbolize),
+    )
+}
+
+fn check(bytes: &[u8], att: &str, intel: &str) {
+    assert_eq!(
+        disassemble(0x401126, bytes),
+        (att.to_owned(), intel.to_owned())
+    );
+}
+
+#[test]
+pub fn general_purpose_instructions() {
+    check(&[0x55], "push   %rbp", "push   rbp");
+    check(&[0x48, 0x89, 0xe5], "mov    %rsp,%rbp", "mov    rbp,rsp");
+    check(
+        &[0x48, 0x83, 0xec, 0x10],
+        "sub    $0x10,%rsp",
+        "sub    rsp,0x10",
+    );
+    check(
+        &[0x89, 0x7d, 0xfc],
+        "mov    %edi,-0x4(%rbp)",
+        "mov    DWORD PTR [rbp-0x4],edi",
+    );
+    // nothing but the suffix says how big the memory is
+    check(
+        &[0xc7, 0x45, 0xf8, 0x00, 0x00, 0x00, 0x00],
+        "movl   $0x0,-0x8(%rbp)",
+        "mov    DWORD PTR [rbp-0x8],0x0",
+    );
+    check(
+        &[0x48, 0x63, 0x4d, 0xf8],
+        "movslq -0x8(%rbp),%rcx",
+        "movsxd rcx,DWORD PTR [rbp-0x8]",
+    );
+    check(
+        &[0x0f, 0xb6, 0x45, 0xff],
+        "movzbl -0x1(%rbp),%eax",
+        "movzx  eax,BYTE PTR [rbp-0x1]",
+    );
+    check(&[0x48, 0x98], "cltq", "cdqe");
+    check(
+        &[0x66, 0xc1, 0xe0, 0x03],
+        "shl    $0x3,%ax",
+        "shl    ax,0x3",
+    );
+    // the byte registers 4 to 7 are ah to bh without REX, and spl to dil with it
+    check(&[0xb4, 0x01], "mov    $0x1,%ah", "mov    ah,0x1");
+    check(&[0x40, 0xb6, 0x01], "mov    $0x1,%sil", "mov    sil,0x1");
+    check(&[0x41, 0xb0, 0x01], "mov    $0x1,%r8b", "mov    r8b,0x1");
+    check(
+        &[0x48, 0xb8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11],
+        "movabs $0x1122334455667788,%rax",
+        "movabs rax,0x1122334455667788",
+    );
+    check(
+        &[0x64, 0x48, 0x8b, 0x04, 0x25, 0x28, 0x00, 0x00, 0x00],
+        "mov    %fs:0x28,%rax",
+        "mov    rax,QWORD PTR fs:0x28",
+    );
+}
+
+#[test]
+pub fn branches_and_rip_relative_operands_are_symbolized() {
+    check(
+        &[0xe8, 0xd8, 0xff, 0xff, 0xff],
+        "call   0x401103",
+        "call   0x401103",
+    );
+    check(
+        &[0xeb, 0x10],
+        "jmp    0x401138 <main+18>",
+        "jmp    0x401138 <main+18>",
+    );
+    check(
+        &[0x8b, 0x05, 0xfc, 0x2e, 0x00, 0x00],
+        "mov    0x2efc(%rip),%eax        # 0x404028 <counter>",
+        "mov    eax,DWORD PTR [rip+0x2efc]        # 0x404028 <counter>",
+    );
+    check(
+        &[0xff, 0x24, 0xc5, 0xe0, 0x10, 0x40, 0x00],
+        "jmp    *0x4010e0(,%rax,8)",
+        "jmp    QWORD PTR [rax*8+0x4010e0]",
+    );
+}
+
+#[test]
+pub fn prefixes() {
+    check(
+        &[0xf3, 0xab],
+        "rep stos %eax,%es:(%rdi)",
+        "rep stos DWORD PTR es:[rdi],eax",
+    );
+    check(
+        &[0xf0, 0x48, 0x0f, 0xb1, 0x0a],
+        "lock cmpxchg %rcx,(%rdx)",
+        "lock cmpxchg QWORD PTR [rdx],rcx",
+    );
+    check(&[0xf3, 0x0f, 0x1e, 0xfa], "endbr64", "endbr64");
+    check(&[0x3e, 0xff, 0xe0], "notrack jmp *%rax", "notrack jmp rax");
+    check(
+        &[0x66, 0x2e, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+        "cs nopw 0x0(%rax,%rax,1)",
+        "cs nop WORD PTR [rax+rax*1+0x0]",
+    );
+    check(&[0xf3, 0xc3], "repz ret", "repz ret");
+}
+
+#[test]
+pub fn vector_and_x87_instructions() {
+    check(
+        &[0xf2, 0x0f, 0x59, 0x45, 0xf0],
+        "mulsd  -0x10(%rbp),%xmm0",
+        "mulsd  xmm0,QWORD PTR [rbp-0x10]",
+    );
+    check(
+        &[0xc5, 0xfd, 0xd5, 0x44, 0x24, 0x08],
+        "vpmullw 0x8(%rsp),%ymm0,%ymm0",
+        "vpmullw ymm0,ymm0,YMMWORD PTR [rsp+0x8]",
+    );
+    check(
+        &[0xc4, 0xe2, 0xf9, 0xf7, 0xc1],
+        "shlx   %rax,%rcx,%rax",
+        "shlx   rax,rcx,rax",
+    );
+    check(
+        &[0x0f, 0xc2, 0xc1, 0x01],
+        "cmpltps %xmm1,%xmm0",
+        "cmpltps xmm0,xmm1",
+    );
+    // EVEX scales 8 bit displacements by the size of the memory, or of the element that is broadcast
+    check(
+        &[0x62, 0xf1, 0xfe, 0xc9, 0x6f, 0x40, 0x01],
+        "vmovdqu64 0x40(%rax),%zmm0{%k1}{z}",
+        "vmovdqu64 zmm0{k1}{z},ZMMWORD PTR [rax+0x40]",
+    );
+    check(
+        &[0x62, 0xf1, 0x7c, 0x59, 0x58, 0x40, 0x01],
+        "vaddps 0x4(%rax){1to16},%zmm0,%zmm0{%k1}",
+        "vaddps zmm0{k1},zmm0,DWORD BCST [rax+0x4]",
+    );
+    check(
+        &[0x62, 0xf2, 0x7d, 0x48, 0x26, 0xc9],
+        "vptestmb %zmm1,%zmm0,%k1",
+        "vptestmb k1,zmm0,zmm1",
+    );
+    check(
+        &[0xdc, 0x45, 0x08],
+        "faddl  0x8(%rbp)",
+        "fadd   QWORD PTR [rbp+0x8]",
+    );
+    // what AT&T calls the reverse operation
+    check(&[0xde, 0xe9], "fsubrp %st,%st(1)", "fsubp  st(1),st");
+    check(&[0x9b, 0xdf, 0xe0], "fstsw  %ax", "fstsw  ax");
+}
+
+#[test]
+pub fn truncated_and_invalid_bytes() {
+    assert_eq!(
+        decode(&[0x48, 0x8b], 0).unwrap_err(),
+        DecodeError::Truncated
+    );
+    assert_eq!(
+        decode(&[0xe8, 0x00, 0x00], 0).unwrap_err(),
+        DecodeError::Truncated
+    );
+    assert_eq!(decode(&[0x0f, 0x0b], 0).unwrap().mnemonic, "ud2");
+    assert_eq!(decode(&[0x0f, 0x04], 0).unwrap_err(), DecodeError::Invalid);
+    // lea of a register
+    assert_eq!(decode(&[0x8d, 0xc0], 0).unwrap_err(), DecodeError::Invalid);
+    // no instruction is longer than 15 bytes
+    assert_eq!(decode(&[0x66; 16], 0).unwrap_err(), DecodeError::Invalid);
+    // only what's needed is read
+    assert_eq!(decode(&[0x90, 0xff, 0xff], 0).unwrap().length, 1);
+}
@@ -0,0 +1,90 @@
+use midas::expression::{parse_number, EvaluationContext, Expression};
+use std::collections::HashMap;
+
+struct Context {
+    registers: HashMap<&'static str, u64>,
+    symbols: HashMap<&'static str, u64>,
+    memory: HashMap<u64, u64>,
+}
+
+impl Context {
+    fn new() -> Context {
+        Context {
+            registers: HashMap::from([("rsp", 0x7ffe_0000), ("rax", 42)]),
+            symbols: HashMap::from([("main", 0x401000), ("argv", 0x7ffe_0100)]),
+            memory: HashMap::from([(0x7ffe_0100, 0x7ffe_0200), (0x7ffe_0200, 0x1234)]),
+        }
+    }
+}
+
+impl EvaluationContext for Context {
+    fn register(&self, name: &str) -> Option<u64> {
+        self.registers.get(name).copied()
+    }
+
+    fn symbol(&self, name: &str) -> Option<u64> {
+        self.address_of(name)
+            .and_then(|address| self.read_pointer(address))
+    }
+
+    fn address_of(&self, name: &str) -> Option<u64> {
+        self.symbols.get(name).copied()
+    }
+
+    fn read_pointer(&self, address: u64) -> Option<u64> {
+        self.memory.get(&address).copied()
+    }
+}
+
+fn evaluate(input: &str) -> Result<u64, String> {
+    Expression::parse(input)?.evaluate(&Context::new())
+}
+
+#[test]
+pub fn number_literals() {
+    assert_eq!(parse_number("0x1F"), Some(31));
+    assert_eq!(parse_number("0b101"), Some(5));
+    assert_eq!(parse_number("0o17"), Some(15));
+    assert_eq!(parse_number("017"), Some(15));
+    assert_eq!(parse_number("1_000"), Some(1000));
+    assert_eq!(parse_number("0"), Some(0));
+    assert_eq!(parse_number("0xg"), None);
+}
+
+#[test]
+pub fn precedence_and_arithmetic() {
+    assert_eq!(evaluate("1 + 2 * 3"), Ok(7));
+    assert_eq!(evaluate("(1 + 2) * 3"), Ok(9));
+    assert_eq!(evaluate("1 << 4 | 1"), Ok(17));
+    assert_eq!(evaluate("10 - 4 - 3"), Ok(3));
+    assert_eq!(evaluate("-8 / 2"), Ok(-4i64 as u64));
+    assert_eq!(evaluate("-1 < 0"), Ok(1));
+    assert_eq!(evaluate("1 + 1 == 2 && !0"), Ok(1));
+    assert_eq!(evaluate("~0"), Ok(u64::MAX));
+    assert!(evaluate("1 / 0").is_err());
+}
+
+#[test]
+pub fn registers_symbols_and_memory() {
+    assert_eq!(evaluate("$rsp + 8"), Ok(0x7ffe_0008));
+    assert_eq!(evaluate("$rax * 2"), Ok(84));
+    assert_eq!(evaluate("&main + 4"), Ok(0x401004));
+    assert_eq!(evaluate("argv"), Ok(0x7ffe_0200));
+    assert_eq!(evaluate("*argv"), Ok(0x1234));
+    assert_eq!(evaluate("&*argv"), Ok(0x7ffe_0200));
+    // short circuiting keeps us from reading through a null pointer
+    assert_eq!(evaluate("0 && *0"), Ok(0));
+    assert!(evaluate("*0").is_err());
+    assert!(evaluate("$nope").is_err());
+    assert!(evaluate("nope").is_err());
+}
+
+#[test]
+pub fn syntax_errors() {
+    assert!(Expression::parse("").is_err());
+    assert!(Expression::parse("1 +").is_err());
+    assert!(Expression::parse("(1 + 2").is_err());
+    assert!(Expression::parse("1 2").is_err());
+    assert!(Expression::parse("a = 1").is_err());
+    assert!(Expression::parse("$").is_err());
+}
@@ -0,0 +1,94 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    hardware_breakpoint::WatchKind,
+    target::{self, Target},
+    types::Address,
+};
+use nixwrap::{signals::Signal, WaitStatus};
+
+// like tests/memory.rs, any binary will do. The inferior is stopped at the dynamic linker's entry point, which
+// starts with `mov %rsp, %rdi; call _dl_start`; so the call pushes a return address right below the stack pointer
+const INFERIOR: &str = "/bin/true";
+
+fn launch() -> Box<dyn Target> {
+    let (target, _waitstatus) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(INFERIOR, vec![]).unwrap()).unwrap();
+    target
+}
+
+fn is_trap(status: WaitStatus) -> bool {
+    matches!(status, WaitStatus::Stopped(_, Signal::Trap))
+}
+
+#[test]
+pub fn write_watchpoint_triggers() {
+    let mut target = launch();
+    let rsp = target.read_registers().unwrap().rsp as usize;
+    let watched = Address(rsp - 8);
+    let watchpoint = target
+        .set_hardware_breakpoint(watched, WatchKind::Write, 8)
+        .unwrap();
+    assert_eq!(watchpoint.slot, 0);
+
+    let status = target.continue_execution().unwrap();
+    assert!(is_trap(status));
+    assert_eq!(target.stopped_at_hardware_breakpoint(), Some(watchpoint));
+    let return_address = target.read_memory(watched, 8).unwrap().data;
+    assert_ne!(return_address, vec![0u8; 8]);
+
+    target.remove_hardware_breakpoint(watchpoint.slot).unwrap();
+    assert!(target.stopped_at_hardware_breakpoint().is_none());
+    let status = target.continue_execution().unwrap();
+    assert!(matches!(status, WaitStatus::ExitedNormally(_, 0)));
+}
+
+#[test]
+pub fn execute_breakpoint_triggers_once() {
+    let mut target = launch();
+    let pc = target.read_registers().unwrap().pc() as usize;
+    let breakpoint = target
+        .set_hardware_breakpoint(Address(pc), WatchKind::Execute, 1)
+        .unwrap();
+    let status = target.continue_execution().unwrap();
+    assert!(is_trap(status));
+    assert_eq!(target.stopped_at_hardware_breakpoint(), Some(breakpoint));
+    assert_eq!(target.read_registers().unwrap().pc() as usize, pc);
+
+    // continuing must execute the instruction, instead of trapping on it again
+    let status = target.continue_execution().unwrap();
+    assert!(matches!(status, WaitStatus::ExitedNormally(_, 0)));
+}
+
+#[test]
+pub fn invalid_hardware_breakpoints() {
+    let mut target = launch();
+    let rsp = target.read_registers().unwrap().rsp as usize;
+    assert!(target
+        .set_hardware_breakpoint(Address(rsp - 8), WatchKind::Write, 3)
+        .is_err());
+    assert!(target
+        .set_hardware_breakpoint(Address(rsp - 7), WatchKind::Write, 4)
+        .is_err());
+    assert!(target
+        .set_hardware_breakpoint(Address(rsp), WatchKind::Execute, 8)
+        .is_err());
+    for slot in 0..midas::hardware_breakpoint::SLOTS {
+        let hw = target
+            .set_hardware_breakpoint(Address(rsp - 8 * (slot + 1)), WatchKind::ReadWrite, 8)
+            .unwrap();
+        assert_eq!(hw.slot, slot);
+    }
+    assert!(target
+        .set_hardware_breakpoint(Address(rsp - 64), WatchKind::Write, 8)
+        .is_err());
+    target.remove_hardware_breakpoint(2).unwrap();
+    assert!(target.remove_hardware_breakpoint(2).is_err());
+    assert_eq!(
+        target
+            .set_hardware_breakpoint(Address(rsp - 64), WatchKind::Write, 8)
+            .unwrap()
+            .slot,
+        2
+    );
+}
@@ -0,0 +1,65 @@
+use midas::json::Value;
+
+#[test]
+pub fn values_are_parsed() {
+    let value = Value::parse(
+        r#" {"seq": 1, "type": "request", "arguments": {"lines": [3, -4], "ratio": 0.5, "on": true, "off": null}} "#,
+    )
+    .unwrap();
+    assert_eq!(value.get("seq").and_then(Value::as_u64), Some(1));
+    assert_eq!(value.get("type").and_then(Value::as_str), Some("request"));
+    let arguments = value.get("arguments").unwrap();
+    assert_eq!(
+        arguments.get("lines").and_then(Value::as_array),
+        Some(&[Value::Integer(3), Value::Integer(-4)][..])
+    );
+    assert_eq!(arguments.get("ratio"), Some(&Value::Float(0.5)));
+    assert_eq!(arguments.get("on").and_then(Value::as_bool), Some(true));
+    assert_eq!(arguments.get("off"), Some(&Value::Null));
+    assert_eq!(arguments.get("missing"), None);
+    assert_eq!(Value::parse("1e3"), Ok(Value::Float(1000.0)));
+    assert_eq!(Value::parse("[]"), Ok(Value::Array(vec![])));
+}
+
+#[test]
+pub fn strings_are_escaped() {
+    assert_eq!(
+        Value::parse(r#""a\"b\\c\ndé😀""#),
+        Ok(Value::String("a\"b\\c\nd\u{e9}\u{1f600}".into()))
+    );
+    let value = Value::from("tab\there \"quoted\"\u{1}");
+    assert_eq!(value.to_string(), r#""tab\there \"quoted\"\u0001""#);
+    assert_eq!(Value::parse(&value.to_string()), Ok(value));
+}
+
+#[test]
+pub fn values_are_written_compactly() {
+    let mut value = Value::object(vec![
+        ("id", 7usize.into()),
+        ("name", "main".into()),
+        ("frames", Value::Array(vec![1.5f64.into(), Value::Null])),
+    ]);
+    value.insert("verified", true.into());
+    value.insert("id", 8usize.into());
+    assert_eq!(
+        value.to_string(),
+        r#"{"id":8,"name":"main","frames":[1.5,null],"verified":true}"#
+    );
+    assert_eq!(Value::Float(2.0).to_string(), "2.0");
+}
+
+#[test]
+pub fn malformed_text_is_an_error() {
+    for text in [
+        "",
+        "{",
+        "[1,]",
+        r#"{"a" 1}"#,
+        r#""unterminated"#,
+        "tru",
+        "1 2",
+        r#""\x""#,
+    ] {
+        assert!(Value::parse(text).is_err(), "{} parsed", text);
+    }
+}
@@ -0,0 +1,138 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    breakpoints::{skip_prologue, BreakpointKind, BreakpointTable},
+    elf::ParsedELF,
+    expression::Expression,
+    frame::Frame,
+    software_breakpoint::BreakpointRequest,
+    stepping::{self, ReturnValue},
+    target::{self, Target},
+    types::Address,
+};
+use nixwrap::{MidasSysResultDynamic, WaitStatus};
+use std::{process::Command, sync::Once};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/stepping_pie");
+
+// lines of stepping.c
+const ADD_BODY: u32 = 9;
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .arg("stepping_pie")
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// no conditions are set in these tests
+fn evaluate(_target: &dyn Target, _condition: &Expression) -> MidasSysResultDynamic<u64> {
+    Err("no conditions here".into())
+}
+
+fn global(target: &dyn Target, elf: &ParsedELF, name: &str) -> u64 {
+    let frame = Frame::current(target, elf).unwrap();
+    let variable = frame.variable(elf, name).unwrap();
+    frame.value_of(target, &variable).unwrap()
+}
+
+#[test]
+pub fn position_independent_programs_are_debugged_where_they_are_loaded() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let mut elf = ParsedELF::parse_elf(&object).unwrap();
+    assert!(elf.is_position_independent());
+    let (mut target, _status) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_randomized_command(SUBJECT, vec![]).unwrap())
+            .unwrap();
+    let file_address = elf
+        .symbol_table
+        .get_function_symbol("add")
+        .unwrap()
+        .value
+        .unwrap()
+        .get();
+
+    let bias = elf
+        .load_bias_from_auxv(&target.auxiliary_vector().unwrap())
+        .unwrap();
+    assert_ne!(bias, 0);
+    assert_eq!(bias % 4096, 0);
+    elf.relocate(bias);
+    let add = elf
+        .symbol_table
+        .get_function_symbol("add")
+        .unwrap()
+        .value
+        .unwrap()
+        .get();
+    assert_eq!(add, file_address + bias);
+
+    // symbols, functions and lines are all found where the program is loaded
+    let body = skip_prologue(&elf, Address(add));
+    assert!(body.value() > add);
+    let mut breakpoints = BreakpointTable::default();
+    breakpoints
+        .track_shared_libraries(target.as_mut(), &elf)
+        .unwrap();
+    let number = breakpoints
+        .insert(
+            target.as_mut(),
+            BreakpointKind::Software,
+            BreakpointRequest::Address(body),
+            vec![body],
+        )
+        .unwrap()
+        .number;
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints[0].number, number);
+    assert!(breakpoints
+        .shared_libraries()
+        .iter()
+        .any(|library| library.path.ends_with("libc.so.6")));
+    let frame = Frame::current(target.as_ref(), &elf).unwrap();
+    assert_eq!(frame.function.as_ref().unwrap().name, "add");
+    assert!(frame.cfa.is_some());
+    let lines = elf.line_table().unwrap();
+    assert_eq!(lines.row_at(body.value()).unwrap().line(), ADD_BODY);
+
+    // globals are at addresses in the file, and need relocating too
+    assert_eq!(global(target.as_ref(), &elf, "total"), 0);
+    breakpoints.delete(target.as_mut(), number).unwrap();
+    let finish = stepping::finish(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert!(matches!(
+        finish.value,
+        Some(ReturnValue::Integer { value: 2, .. })
+    ));
+    assert_eq!(global(target.as_ref(), &elf, "total"), 2);
+    assert_eq!(
+        Frame::current(target.as_ref(), &elf)
+            .unwrap()
+            .function
+            .unwrap()
+            .name,
+        "main"
+    );
+
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(matches!(stop.status, WaitStatus::ExitedNormally(_, 0)));
+}
@@ -0,0 +1,100 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    software_breakpoint::BreakpointRequest,
+    target::{self, Target},
+    types::Address,
+};
+
+// these tests don't need the subjects from tests/subjects; any binary will do, since we only poke at it's memory
+// while it's stopped at the first instruction
+const INFERIOR: &str = "/bin/true";
+
+fn launch() -> Box<dyn Target> {
+    let (target, _waitstatus) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(INFERIOR, vec![]).unwrap()).unwrap();
+    target
+}
+
+fn pc(target: &dyn Target) -> Address {
+    Address(nixwrap::ptrace::get_regs(target.process_id()).pc() as usize)
+}
+
+#[test]
+pub fn read_and_write_stack() {
+    let mut target = launch();
+    let rsp = nixwrap::ptrace::get_regs(target.process_id()).rsp as usize;
+    let data = [0xde, 0xad, 0xbe, 0xef, 0x01, 0x02, 0x03];
+    assert_eq!(
+        target.write_memory(Address(rsp - 64), &data).unwrap(),
+        data.len()
+    );
+    let read = target.read_memory(Address(rsp - 64), data.len()).unwrap();
+    assert!(!read.is_partial());
+    assert_eq!(&read.data[..], &data[..]);
+}
+
+#[test]
+pub fn write_to_read_only_text() {
+    let mut target = launch();
+    let pc = pc(target.as_ref());
+    let original = target.read_memory(pc, 16).unwrap().data;
+    let patched = [0x90u8; 3];
+    assert_eq!(target.write_memory(pc, &patched).unwrap(), 3);
+    let read = target.read_memory(pc, 16).unwrap().data;
+    assert_eq!(&read[..3], &patched[..]);
+    assert_eq!(&read[3..], &original[3..]);
+}
+
+#[test]
+pub fn breakpoints_are_hidden() {
+    let mut target = launch();
+    let pc = pc(target.as_ref());
+    let original = target.read_memory(pc, 32).unwrap().data;
+    target
+        .set_breakpoint(BreakpointRequest::Address(Address(pc.value() + 4)))
+        .unwrap();
+    let raw = nixwrap::ptrace::peek_data(target.process_id(), pc.value() + 4).unwrap();
+    assert_eq!(raw & 0xff, 0xcc);
+    assert_eq!(target.read_memory(pc, 32).unwrap().data, original);
+
+    // writing over a breakpoint changes what it restores, but the int3 stays
+    target
+        .write_memory(Address(pc.value() + 4), &[0x90])
+        .unwrap();
+    let raw = nixwrap::ptrace::peek_data(target.process_id(), pc.value() + 4).unwrap();
+    assert_eq!(raw & 0xff, 0xcc);
+    assert_eq!(
+        target.read_memory(Address(pc.value() + 4), 1).unwrap().data,
+        vec![0x90]
+    );
+}
+
+#[test]
+pub fn partial_and_failed_reads() {
+    let target = launch();
+    assert!(target.read_memory(Address(0), 8).is_err());
+
+    // find the end of a mapping that isn't directly followed by another one
+    let maps = std::fs::read_to_string(format!("/proc/{}/maps", *target.process_id())).unwrap();
+    let ranges: Vec<(usize, usize)> = maps
+        .lines()
+        .map(|line| {
+            let range = line.split(' ').next().unwrap();
+            let (start, end) = range.split_once('-').unwrap();
+            (
+                usize::from_str_radix(start, 16).unwrap(),
+                usize::from_str_radix(end, 16).unwrap(),
+            )
+        })
+        .collect();
+    let end = ranges
+        .windows(2)
+        .find(|pair| pair[0].1 != pair[1].0)
+        .map(|pair| pair[0].1)
+        .unwrap();
+    let read = target.read_memory(Address(end - 8), 16).unwrap();
+    assert!(read.is_partial());
+    assert_eq!(read.data.len(), 8);
+    assert!(read.error.is_some());
+}
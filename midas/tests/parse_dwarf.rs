@@ -8,7 +8,7 @@ use midas::{
         compilation_unit::CompilationUnitHeaderIterator,
         linenumber::{LineNumberProgram, LineNumberProgramHeaderVersion4},
     },
-    leb128::decode_unsigned,
+    leb128::{decode_signed, decode_unsigned},
 };
 
 static BUILT_TEST_DEBUGGEES: Once = Once::new();
@@ -299,3 +299,22 @@ pub fn run_line_number_program_of_first_debug_line_section_ddump_cpp() {
         println!("printing the data took: {}us", after);
     });
 }
+
+#[test]
+pub fn decode_signed_leb128() {
+    // examples from the DWARF 5 standard, figure 7.6
+    for (bytes, value) in [
+        (vec![0x02u8], 2i64),
+        (vec![0x7e], -2),
+        (vec![0xff, 0x00], 127),
+        (vec![0x81, 0x7f], -127),
+        (vec![0x80, 0x01], 128),
+        (vec![0x80, 0x7f], -128),
+        // DW_OP_fbreg -36
+        (vec![0x5c], -36),
+    ] {
+        let decoded = decode_signed(&bytes).unwrap();
+        assert_eq!(decoded.value, value);
+        assert_eq!(decoded.bytes_read, bytes.len());
+    }
+}
@@ -0,0 +1,128 @@
+use midas::{
+    self,
+    elf::ParsedELF,
+    procfs::{self, AuxiliaryVector, Permissions, AT_BASE, AT_ENTRY, AT_SYSINFO_EHDR},
+    target::{self, Target},
+};
+use std::{process::Command, sync::Once};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/stepping_pie");
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .arg("stepping_pie")
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+const MAPS: &str = "\
+555555554000-555555555000 r--p 00000000 08:01 1835101                    /usr/bin/true
+555555555000-555555559000 r-xp 00001000 08:01 1835101                    /usr/bin/true
+7ffff7fab000-7ffff7fb8000 rw-p 00000000 00:00 0
+7ffff7fc8000-7ffff7fca000 r-xp 00000000 00:00 0                          [vdso]
+7ffff7ff0000-7ffff7ff1000 rw-s 00000000 00:05 42                         /dev/shm/with spaces
+";
+
+#[test]
+pub fn memory_maps_are_parsed() {
+    let regions = procfs::parse_maps(MAPS).unwrap();
+    assert_eq!(regions.len(), 5);
+    assert_eq!(regions[1].range, 0x555555555000..0x555555559000);
+    assert_eq!(regions[1].offset, 0x1000);
+    assert_eq!(regions[1].path.as_deref(), Some("/usr/bin/true"));
+    assert_eq!(
+        regions[1].permissions,
+        Permissions {
+            read: true,
+            write: false,
+            execute: true,
+            shared: false
+        }
+    );
+    assert_eq!(regions[1].permissions.to_string(), "r-xp");
+    // anonymous memory has no path, and what the kernel made has a name in brackets
+    assert_eq!(regions[2].path, None);
+    assert!(!regions[2].is_pseudo());
+    assert!(regions[3].is_pseudo());
+    assert_eq!(regions[4].path.as_deref(), Some("/dev/shm/with spaces"));
+    assert_eq!(regions[4].permissions.to_string(), "rw-s");
+
+    assert_eq!(
+        procfs::region_containing(&regions, 0x7ffff7fc9000)
+            .unwrap()
+            .path
+            .as_deref(),
+        Some("[vdso]")
+    );
+    assert!(procfs::region_containing(&regions, 0x1000).is_none());
+    assert!(procfs::parse_maps("not a memory map").is_err());
+}
+
+#[test]
+pub fn auxiliary_vectors_are_parsed() {
+    let words: [u64; 8] = [
+        AT_ENTRY,
+        0x401020,
+        AT_BASE,
+        0,
+        AT_SYSINFO_EHDR,
+        0x7ffff7fc8000,
+        0,
+        0,
+    ];
+    let data: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+    let auxv = AuxiliaryVector::parse(&data);
+    assert_eq!(auxv.entries.len(), 3);
+    assert_eq!(auxv.entry(), Some(0x401020));
+    assert_eq!(auxv.vdso(), Some(0x7ffff7fc8000));
+    // statically linked programs have no interpreter
+    assert_eq!(auxv.interpreter_base(), None);
+    assert_eq!(auxv.program_headers(), None);
+}
+
+#[test]
+pub fn the_inferior_is_where_it_says_it_is() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let mut elf = ParsedELF::parse_elf(&object).unwrap();
+    let (target, _status) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(SUBJECT, vec![]).unwrap()).unwrap();
+    let auxv = target.auxiliary_vector().unwrap();
+    elf.relocate(elf.load_bias_from_auxv(&auxv).unwrap());
+    assert_eq!(auxv.entry(), Some(elf.entry_point()));
+
+    // the program's code, the dynamic linker and the vDSO are mapped where the auxiliary vector says
+    let regions = target.memory_map().unwrap();
+    let code = procfs::region_containing(&regions, elf.entry_point()).unwrap();
+    assert!(code.permissions.execute);
+    assert!(code
+        .path
+        .as_ref()
+        .is_some_and(|path| path.ends_with("stepping_pie")));
+    let interpreter = procfs::region_containing(&regions, auxv.interpreter_base().unwrap()).unwrap();
+    assert!(interpreter
+        .path
+        .as_ref()
+        .is_some_and(|path| path.contains("ld-linux")));
+    let vdso = procfs::region_containing(&regions, auxv.vdso().unwrap()).unwrap();
+    assert_eq!(vdso.path.as_deref(), Some("[vdso]"));
+
+    // reading memory that isn't mapped says so
+    let unmapped = target.read_memory(midas::types::Address(8), 8).unwrap_err();
+    assert_eq!(unmapped, "Cannot access memory at address 0x8");
+}
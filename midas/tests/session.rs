@@ -0,0 +1,262 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    commands::Command,
+    elf::ParsedELF,
+    session::{Event, Session, StopReason},
+    software_breakpoint::BreakpointRequest,
+    target::{self, linux::LinuxTarget, Target},
+};
+use nixwrap::WaitStatus;
+use std::{
+    process,
+    sync::{
+        mpsc::{self, Receiver},
+        Once,
+    },
+};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/stepping");
+
+// lines of stepping.c
+const ADD_BODY: u32 = 9;
+const ADD_TOTAL: u32 = 10;
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = process::Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .arg("stepping")
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+fn launch(elf: ParsedELF) -> (Session, Receiver<Event>) {
+    let (target, _status) = LinuxTarget::launch(&mut target::make_command(SUBJECT, vec![]).unwrap()).unwrap();
+    let (sender, events) = mpsc::channel();
+    (Session::new(target, elf, true, sender), events)
+}
+
+fn function(name: &str) -> BreakpointRequest {
+    BreakpointRequest::Function {
+        name: name.into(),
+        file: None,
+    }
+}
+
+fn breakpoint(location: BreakpointRequest) -> Command {
+    Command::SetBreakpoint {
+        location,
+        hardware: false,
+        temporary: false,
+        condition: None,
+    }
+}
+
+// the event of a stop, that has to be the last one there is
+fn stopped(events: &Receiver<Event>) -> (StopReason, Option<u32>, Option<String>) {
+    match events.try_iter().last() {
+        Some(Event::Stopped {
+            reason, location, ..
+        }) => (reason, location.line, location.function),
+        event => panic!("the inferior didn't stop: {:?}", event),
+    }
+}
+
+#[test]
+pub fn commands_are_told_about_with_events() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let (mut session, events) = launch(elf);
+    assert!(events
+        .try_iter()
+        .all(|event| !matches!(event, Event::Warning(_))));
+
+    session.handle(breakpoint(function("add"))).unwrap();
+    let number = match events.try_recv() {
+        Ok(Event::BreakpointCreated(created)) => {
+            assert!(!created.is_pending());
+            created.number
+        }
+        event => panic!("no breakpoint was created: {:?}", event),
+    };
+
+    session.handle(Command::Continue).unwrap();
+    match stopped(&events) {
+        (StopReason::Breakpoint { breakpoints, .. }, line, function) => {
+            assert_eq!(breakpoints[0].number, number);
+            assert_eq!(line, Some(ADD_BODY));
+            assert_eq!(function.as_deref(), Some("add"));
+        }
+        stop => panic!("didn't stop at the breakpoint: {:?}", stop),
+    }
+
+    session.handle(Command::Next { count: 1 }).unwrap();
+    let (reason, line, _) = stopped(&events);
+    assert!(matches!(reason, StopReason::EndSteppingRange));
+    assert_eq!(line, Some(ADD_TOTAL));
+
+    session.handle(Command::Finish).unwrap();
+    let (reason, _, function) = stopped(&events);
+    assert!(matches!(
+        reason,
+        StopReason::FunctionFinished { value: Some(_) }
+    ));
+    assert_eq!(function.as_deref(), Some("main"));
+
+    session.handle(Command::Disable(vec![number])).unwrap();
+    match events.try_recv() {
+        Ok(Event::BreakpointModified(modified)) => assert!(!modified.enabled),
+        event => panic!("the breakpoint wasn't modified: {:?}", event),
+    }
+    session.handle(Command::Continue).unwrap();
+    let exits: Vec<Event> = events.try_iter().collect();
+    assert!(matches!(
+        exits.as_slice(),
+        [Event::Exited(WaitStatus::ExitedNormally(_, 0))]
+    ));
+}
+
+#[test]
+pub fn failing_commands_are_errors() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let (mut session, events) = launch(elf);
+    let _ = events.try_iter().count();
+
+    let line = BreakpointRequest::Line {
+        number: 100_000,
+        file: "stepping.c".into(),
+    };
+    assert_eq!(
+        session.handle(breakpoint(line)),
+        Err("No line 100000 in file \"stepping.c\".".into())
+    );
+    assert!(session
+        .handle(Command::Advance(function("no_such_function")))
+        .is_err());
+    assert!(session.handle(Command::Delete(vec![1])).is_err());
+    assert!(session
+        .handle(Command::Watch {
+            expression: String::new(),
+            access: midas::breakpoints::WatchAccess::Write,
+        })
+        .is_err());
+    // nothing happened, so there's nothing to tell
+    assert_eq!(events.try_iter().count(), 0);
+
+    // a function that isn't loaded yet makes a pending breakpoint
+    session
+        .handle(breakpoint(function("no_such_function")))
+        .unwrap();
+    match events.try_recv() {
+        Ok(Event::BreakpointCreated(created)) => assert!(created.is_pending()),
+        event => panic!("no breakpoint was created: {:?}", event),
+    }
+
+    session.handle(Command::Kill).unwrap();
+    assert!(matches!(
+        events.try_recv(),
+        Ok(Event::Exited(WaitStatus::Killed(..)))
+    ));
+    assert!(session.target().read_registers().is_err());
+}
+
+#[test]
+pub fn detaching_lets_the_inferior_run_on() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let (mut session, events) = launch(elf);
+    session.handle(breakpoint(function("add"))).unwrap();
+    session.handle(Command::Continue).unwrap();
+    let _ = events.try_iter().count();
+
+    session.handle(Command::Detach).unwrap();
+    assert!(events
+        .try_iter()
+        .all(|event| matches!(event, Event::BreakpointDeleted(_))));
+    assert!(session.handle(Command::Continue).is_err());
+    // it's still our child, if no longer traced; without the breakpoint it runs to the end
+    let status = nixwrap::waitpid(*session.target().process_id(), 0).unwrap();
+    assert!(
+        matches!(status, WaitStatus::ExitedNormally(_, 0)),
+        "{:?}",
+        status
+    );
+}
+
+#[test]
+pub fn queued_signals_are_delivered_on_resuming() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let (mut session, events) = launch(elf);
+    let _ = events.try_iter().count();
+
+    session
+        .handle(Command::QueueSignal(nixwrap::signals::Signal::Termination))
+        .unwrap();
+    session.handle(Command::Continue).unwrap();
+    assert!(matches!(
+        events.try_recv(),
+        Ok(Event::Exited(WaitStatus::Killed(
+            _,
+            nixwrap::signals::Signal::Termination
+        )))
+    ));
+}
+
+#[test]
+pub fn locals_are_watched_with_the_size_of_their_type() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let (mut session, events) = launch(elf);
+    session.handle(breakpoint(function("sum"))).unwrap();
+    session.handle(Command::Continue).unwrap();
+    let _ = events.try_iter().count();
+
+    session
+        .handle(Command::Watch {
+            expression: "s".into(),
+            access: midas::breakpoints::WatchAccess::Write,
+        })
+        .unwrap();
+    match events.try_recv() {
+        Ok(Event::BreakpointCreated(created)) => assert!(matches!(
+            created.kind,
+            midas::breakpoints::BreakpointKind::Watchpoint { len: 4, .. }
+        )),
+        event => panic!("no watchpoint was created: {:?}", event),
+    }
+    // s is 0, then 0 + 1 + 2 + 3
+    let mut values = vec![];
+    while values.last() != Some(&6) {
+        session.handle(Command::Continue).unwrap();
+        match stopped(&events) {
+            (StopReason::Watchpoint { new_value, .. }, _, function) => {
+                assert_eq!(function.as_deref(), Some("sum"));
+                values.push(new_value.unwrap());
+            }
+            stop => panic!("the watchpoint didn't trigger: {:?}", stop),
+        }
+    }
+    assert_eq!(values.first(), Some(&0));
+    session.handle(Command::Kill).unwrap();
+}
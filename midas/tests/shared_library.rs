@@ -0,0 +1,214 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    breakpoints::{BreakpointKind, BreakpointTable},
+    elf::ParsedELF,
+    expression::Expression,
+    software_breakpoint::BreakpointRequest,
+    target::{self, Target},
+    types::Address,
+};
+use nixwrap::{MidasSysResultDynamic, WaitStatus};
+use std::{process::Command, sync::Once};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/solib");
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .arg("solib")
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// no conditions are set in these tests
+fn evaluate(_target: &dyn Target, _condition: &Expression) -> MidasSysResultDynamic<u64> {
+    Err("no conditions here".into())
+}
+
+fn library_names(breakpoints: &BreakpointTable) -> Vec<String> {
+    breakpoints
+        .shared_libraries()
+        .iter()
+        .map(|library| {
+            std::path::Path::new(&library.path)
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned()
+        })
+        .collect()
+}
+
+fn break_at(breakpoints: &mut BreakpointTable, target: &mut dyn Target, address: usize) -> usize {
+    breakpoints
+        .insert(
+            target,
+            BreakpointKind::Software,
+            BreakpointRequest::Address(Address(address)),
+            vec![Address(address)],
+        )
+        .unwrap()
+        .number
+}
+
+fn pc(target: &dyn Target) -> usize {
+    target.read_registers().unwrap().pc() as usize
+}
+
+#[test]
+pub fn libraries_are_tracked_as_they_are_loaded_and_unloaded() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let function = |name: &str| {
+        elf.symbol_table
+            .get_function_symbol(name)
+            .unwrap()
+            .value
+            .unwrap()
+            .get()
+    };
+    let (mut target, _status) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(SUBJECT, vec![]).unwrap()).unwrap();
+    let mut breakpoints = BreakpointTable::default();
+    breakpoints
+        .track_shared_libraries(target.as_mut(), &elf)
+        .unwrap();
+    // nothing is loaded before the dynamic linker has run
+    assert!(library_names(&breakpoints).is_empty());
+
+    let main = break_at(&mut breakpoints, target.as_mut(), function("main"));
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints[0].number, main);
+    let names = library_names(&breakpoints);
+    assert!(names.contains(&"libsolib_library.so".to_owned()));
+    assert!(names.contains(&"libc.so.6".to_owned()));
+    assert!(!names.contains(&"libsolib_plugin.so".to_owned()));
+
+    // the library's functions can be broken on, where they're loaded
+    let library = breakpoints
+        .shared_libraries()
+        .iter()
+        .find(|library| library.path.ends_with("libsolib_library.so"))
+        .unwrap();
+    assert!(library.debug_info);
+    let library_add = library.function_address("library_add").unwrap();
+    assert!(library.text.as_ref().unwrap().contains(&library_add));
+    assert_eq!(
+        breakpoints.shared_libraries().symbolize(library_add + 4),
+        Some(("library_add".to_owned(), 4))
+    );
+    let number = break_at(&mut breakpoints, target.as_mut(), library_add);
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints[0].number, number);
+    assert_eq!(pc(target.as_ref()), library_add);
+
+    // dlopen loads the plugin, and dlclose unloads it
+    break_at(&mut breakpoints, target.as_mut(), function("after_dlopen"));
+    breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(library_names(&breakpoints).contains(&"libsolib_plugin.so".to_owned()));
+    let plugin_twice = breakpoints
+        .shared_libraries()
+        .iter()
+        .find_map(|library| library.function_address("plugin_twice"))
+        .unwrap();
+    let number = break_at(&mut breakpoints, target.as_mut(), plugin_twice);
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints[0].number, number);
+    breakpoints.delete(target.as_mut(), number).unwrap();
+
+    break_at(&mut breakpoints, target.as_mut(), function("after_dlclose"));
+    breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(!library_names(&breakpoints).contains(&"libsolib_plugin.so".to_owned()));
+
+    // the dynamic linker's breakpoint is never reported
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(matches!(stop.status, WaitStatus::ExitedNormally(_, 0)));
+}
+
+#[test]
+pub fn pending_breakpoints_resolve_when_their_library_is_loaded() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let (mut target, _status) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(SUBJECT, vec![]).unwrap()).unwrap();
+    let mut breakpoints = BreakpointTable::default();
+    breakpoints
+        .track_shared_libraries(target.as_mut(), &elf)
+        .unwrap();
+    let pending = |name: &str| BreakpointRequest::Function {
+        name: name.to_owned(),
+        file: None,
+    };
+    let library_add = breakpoints
+        .insert_pending(BreakpointKind::Software, pending("library_add"))
+        .unwrap()
+        .number;
+    let plugin_twice = breakpoints
+        .insert_pending(BreakpointKind::Hardware, pending("plugin_twice"))
+        .unwrap()
+        .number;
+    assert!(breakpoints.get(library_add).unwrap().is_pending());
+    assert!(breakpoints
+        .insert_pending(
+            BreakpointKind::Software,
+            BreakpointRequest::Address(Address(0x1000))
+        )
+        .is_err());
+
+    // the library the program is linked with is loaded before main
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints[0].number, library_add);
+    assert!(!breakpoints.get(library_add).unwrap().is_pending());
+    assert!(breakpoints.get(plugin_twice).unwrap().is_pending());
+
+    // the plugin is loaded by dlopen
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints[0].number, plugin_twice);
+    let location = breakpoints.get(plugin_twice).unwrap().locations[0];
+    assert!(breakpoints
+        .shared_libraries()
+        .containing(location.value())
+        .is_some_and(|library| library.path.ends_with("libsolib_plugin.so")));
+
+    // and unloaded by dlclose, which leaves the breakpoint pending again
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(matches!(stop.status, WaitStatus::ExitedNormally(_, 0)));
+    assert!(breakpoints.get(plugin_twice).unwrap().is_pending());
+    assert!(breakpoints.get(plugin_twice).unwrap().enabled);
+}
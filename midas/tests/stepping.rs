@@ -0,0 +1,299 @@
+use linuxwrapper as nixwrap;
+use midas::{
+    self,
+    breakpoints::{BreakpointKind, BreakpointTable},
+    dwarf::{linenumber::LineTable, Section},
+    elf::ParsedELF,
+    expression::Expression,
+    frame::Frame,
+    software_breakpoint::BreakpointRequest,
+    stepping::{self, ReturnValue},
+    target::{self, Target},
+    types::Address,
+};
+use nixwrap::{signals::Signal, MidasSysResultDynamic, WaitStatus};
+use std::{process::Command, sync::Once};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/stepping");
+
+// lines of stepping.c
+const ADD_BODY: u32 = 9;
+const FACT_RECURSES: u32 = 17;
+const FACT_END: u32 = 18;
+const SUM_LOOP: u32 = 31;
+const SUM_LOOP_BODY: u32 = 32;
+const SUM_RETURN: u32 = 33;
+const MAIN_BODY: u32 = 37;
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .arg("stepping")
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// no conditions are set in these tests
+fn evaluate(_target: &dyn Target, _condition: &Expression) -> MidasSysResultDynamic<u64> {
+    Err("no conditions here".into())
+}
+
+fn line_of(target: &dyn Target, lines: &LineTable) -> u32 {
+    let pc = target.read_registers().unwrap().pc() as usize;
+    lines.row_at(pc).unwrap().line()
+}
+
+fn function_of(target: &dyn Target, elf: &ParsedELF) -> String {
+    Frame::current(target, elf).unwrap().function.unwrap().name
+}
+
+// the first line of the body of `function`
+fn body_of(elf: &ParsedELF, lines: &LineTable, function: &str) -> usize {
+    let symbol = elf.symbol_table.get_function_symbol(function).unwrap();
+    let start = symbol.value.unwrap().get();
+    lines
+        .address_after_prologue(start..start + symbol.size)
+        .unwrap()
+}
+
+// launches the subject and runs it to after the prologue of `function`
+fn run_to_function(elf: &ParsedELF, function: &str) -> (Box<dyn Target>, BreakpointTable) {
+    let (mut target, _status) =
+        midas::target::linux::LinuxTarget::launch(&mut target::make_command(SUBJECT, vec![]).unwrap()).unwrap();
+    let lines = LineTable::new(8, elf.get_dwarf_section(Section::DebugLine).unwrap());
+    let body = Address(body_of(elf, &lines, function));
+    let mut breakpoints = BreakpointTable::default();
+    let number = breakpoints
+        .insert(
+            target.as_mut(),
+            BreakpointKind::Software,
+            BreakpointRequest::Address(body),
+            vec![body],
+        )
+        .unwrap()
+        .number;
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert_eq!(stop.breakpoints[0].number, number);
+    breakpoints.delete(target.as_mut(), number).unwrap();
+    (target, breakpoints)
+}
+
+fn assert_finished(stop: &midas::breakpoints::Stop) {
+    assert!(!stop.by_breakpoint());
+    assert!(matches!(stop.status, WaitStatus::Stopped(_, Signal::Trap)));
+}
+
+#[test]
+pub fn next_steps_over_calls() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let lines = LineTable::new(8, elf.get_dwarf_section(Section::DebugLine).unwrap());
+    let (mut target, mut breakpoints) = run_to_function(&elf, "main");
+    assert_eq!(line_of(target.as_ref(), &lines), MAIN_BODY);
+    for line in MAIN_BODY + 1..MAIN_BODY + 6 {
+        let stop = stepping::next(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+        assert_finished(&stop);
+        assert_eq!(line_of(target.as_ref(), &lines), line);
+        assert_eq!(function_of(target.as_ref(), &elf), "main");
+    }
+}
+
+#[test]
+pub fn step_enters_functions_after_their_prologue() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let lines = LineTable::new(8, elf.get_dwarf_section(Section::DebugLine).unwrap());
+    let (mut target, mut breakpoints) = run_to_function(&elf, "main");
+    let stop = stepping::step(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_finished(&stop);
+    assert_eq!(function_of(target.as_ref(), &elf), "add");
+    assert_eq!(line_of(target.as_ref(), &lines), ADD_BODY);
+    // the parameter has been stored where the debug information says it is
+    let frame = Frame::current(target.as_ref(), &elf).unwrap();
+    let x = frame.variable(&elf, "x").unwrap();
+    assert_eq!(frame.value_of(target.as_ref(), &x).unwrap(), 1);
+
+    // stepping off the end of the function continues to the caller's next line
+    for _ in 0..4 {
+        stepping::step(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    }
+    assert_eq!(function_of(target.as_ref(), &elf), "main");
+    assert_eq!(line_of(target.as_ref(), &lines), MAIN_BODY + 1);
+}
+
+#[test]
+pub fn nexti_steps_over_calls_that_stepi_enters() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let lines = LineTable::new(8, elf.get_dwarf_section(Section::DebugLine).unwrap());
+    let (mut target, mut breakpoints) = run_to_function(&elf, "main");
+    let pc = |target: &dyn Target| target.read_registers().unwrap().pc();
+
+    // the argument of add(1) is moved to edi, then add is called
+    let stop = stepping::nexti(target.as_mut(), &mut breakpoints, &evaluate).unwrap();
+    assert_finished(&stop);
+    let call = pc(target.as_ref());
+    let stop = stepping::nexti(target.as_mut(), &mut breakpoints, &evaluate).unwrap();
+    assert_finished(&stop);
+    assert_eq!(pc(target.as_ref()), call + 5);
+    assert_eq!(function_of(target.as_ref(), &elf), "main");
+    assert_eq!(line_of(target.as_ref(), &lines), MAIN_BODY + 1);
+
+    // the same for add(2)
+    stepping::stepi(target.as_mut(), &mut breakpoints, &evaluate).unwrap();
+    let stop = stepping::stepi(target.as_mut(), &mut breakpoints, &evaluate).unwrap();
+    assert_finished(&stop);
+    let add = elf.symbol_table.get_function_symbol("add").unwrap();
+    assert_eq!(pc(target.as_ref()), add.value.unwrap().get() as u64);
+    assert_eq!(function_of(target.as_ref(), &elf), "add");
+}
+
+#[test]
+pub fn next_stays_in_the_frame_of_a_recursive_function() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let lines = LineTable::new(8, elf.get_dwarf_section(Section::DebugLine).unwrap());
+    let (mut target, mut breakpoints) = run_to_function(&elf, "fact");
+    let cfa = Frame::current(target.as_ref(), &elf).unwrap().cfa;
+    stepping::next(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_eq!(line_of(target.as_ref(), &lines), FACT_RECURSES);
+    let stop = stepping::next(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_finished(&stop);
+    assert_eq!(line_of(target.as_ref(), &lines), FACT_END);
+    assert_eq!(Frame::current(target.as_ref(), &elf).unwrap().cfa, cfa);
+}
+
+#[test]
+pub fn finish_returns_the_value() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let (mut target, mut breakpoints) = run_to_function(&elf, "fact");
+    let finish = stepping::finish(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_finished(&finish.stop);
+    assert_eq!(finish.function.unwrap().name, "fact");
+    assert_eq!(
+        finish.value,
+        Some(ReturnValue::Integer {
+            value: 24,
+            signed: true
+        })
+    );
+    assert_eq!(function_of(target.as_ref(), &elf), "main");
+
+    // a struct of a long and a double comes back in rax and xmm0
+    stepping::next(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    stepping::step(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_eq!(function_of(target.as_ref(), &elf), "make_pair");
+    let finish = stepping::finish(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    let mut pair = 7u64.to_le_bytes().to_vec();
+    pair.extend(3.5f64.to_le_bytes());
+    assert_eq!(finish.value, Some(ReturnValue::Bytes(pair)));
+
+    stepping::next(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    stepping::step(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_eq!(function_of(target.as_ref(), &elf), "half");
+    let finish = stepping::finish(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_eq!(finish.value, Some(ReturnValue::Float(2.5)));
+
+    let stop = breakpoints
+        .continue_execution(target.as_mut(), &evaluate)
+        .unwrap();
+    assert!(matches!(stop.status, WaitStatus::ExitedNormally(_, 0)));
+    assert!(stepping::next(target.as_mut(), &elf, &mut breakpoints, &evaluate).is_err());
+}
+
+#[test]
+pub fn until_runs_to_the_end_of_a_loop() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let lines = LineTable::new(8, elf.get_dwarf_section(Section::DebugLine).unwrap());
+    let (mut target, mut breakpoints) = run_to_function(&elf, "sum");
+    for line in [SUM_LOOP, SUM_LOOP_BODY, SUM_LOOP] {
+        stepping::next(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+        assert_eq!(line_of(target.as_ref(), &lines), line);
+    }
+    // next goes around the loop again, until leaves it
+    let stop = stepping::until(target.as_mut(), &elf, &mut breakpoints, &evaluate).unwrap();
+    assert_finished(&stop);
+    assert_eq!(line_of(target.as_ref(), &lines), SUM_RETURN);
+    let frame = Frame::current(target.as_ref(), &elf).unwrap();
+    let s = frame.variable(&elf, "s").unwrap();
+    assert_eq!(frame.value_of(target.as_ref(), &s).unwrap(), 6);
+}
+
+#[test]
+pub fn advance_stops_at_the_location_or_when_the_frame_returns() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let lines = LineTable::new(8, elf.get_dwarf_section(Section::DebugLine).unwrap());
+    let fact = body_of(&elf, &lines, "fact") as u64;
+    let (mut target, mut breakpoints) = run_to_function(&elf, "main");
+    let n = |target: &dyn Target| {
+        let frame = Frame::current(target, &elf).unwrap();
+        let n = frame.variable(&elf, "n").unwrap();
+        frame.value_of(target, &n).unwrap()
+    };
+
+    let stop = stepping::advance(
+        target.as_mut(),
+        &elf,
+        &mut breakpoints,
+        &evaluate,
+        fact,
+        false,
+    )
+    .unwrap();
+    assert_finished(&stop);
+    assert_eq!(function_of(target.as_ref(), &elf), "fact");
+    assert_eq!(n(target.as_ref()), 4);
+
+    // advance stops at the location in the recursive call
+    stepping::advance(
+        target.as_mut(),
+        &elf,
+        &mut breakpoints,
+        &evaluate,
+        fact,
+        false,
+    )
+    .unwrap();
+    assert_eq!(n(target.as_ref()), 3);
+
+    // until doesn't, so it runs until the frame returns
+    let stop = stepping::advance(
+        target.as_mut(),
+        &elf,
+        &mut breakpoints,
+        &evaluate,
+        fact,
+        true,
+    )
+    .unwrap();
+    assert_finished(&stop);
+    assert_eq!(function_of(target.as_ref(), &elf), "fact");
+    assert_eq!(n(target.as_ref()), 4);
+    assert!(breakpoints.iter().next().is_none());
+}
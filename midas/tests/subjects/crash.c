@@ -0,0 +1,21 @@
+// a program that crashes, for debugging it's core dump
+#include <stddef.h>
+
+int crashes = 0;
+
+void crash(int *pointer, int depth)
+{
+    if (depth > 0) {
+        crash(pointer, depth - 1);
+        return;
+    }
+    crashes += 1;
+    *pointer = crashes;
+}
+
+int main(int argc, char **argv)
+{
+    int local = 7 * argc;
+    crash(NULL, 2);
+    return local;
+}
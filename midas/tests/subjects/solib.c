@@ -0,0 +1,21 @@
+#include <dlfcn.h>
+
+int library_add(int x);
+
+int after_dlopen(int (*twice)(int)) {
+    return twice(2);
+}
+
+int after_dlclose(int sum) {
+    return sum == 6 ? 0 : 2;
+}
+
+int main(void) {
+    int sum = library_add(1);
+    void *plugin = dlopen("$ORIGIN/libsolib_plugin.so", RTLD_NOW);
+    if (!plugin)
+        return 1;
+    sum += after_dlopen((int (*)(int))dlsym(plugin, "plugin_twice"));
+    dlclose(plugin);
+    return after_dlclose(sum);
+}
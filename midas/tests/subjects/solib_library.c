@@ -0,0 +1,4 @@
+int library_add(int x) {
+    int added = x + 1;
+    return added;
+}
@@ -0,0 +1,4 @@
+int plugin_twice(int x) {
+    int twice = x * 2;
+    return twice;
+}
@@ -0,0 +1,44 @@
+struct pair {
+    long a;
+    double b;
+};
+
+int total = 0;
+
+int add(int x) {
+    int doubled = x * 2;
+    total += doubled;
+    return total;
+}
+
+int fact(int n) {
+    if (n <= 1)
+        return 1;
+    return n * fact(n - 1);
+}
+
+struct pair make_pair(long a) {
+    struct pair p = { a, a / 2.0 };
+    return p;
+}
+
+double half(double x) {
+    return x / 2;
+}
+
+int sum(int n) {
+    int s = 0;
+    for (int i = 0; i < n; i++)
+        s += i;
+    return s;
+}
+
+int main(void) {
+    add(1);
+    add(2);
+    int f = fact(4);
+    struct pair p = make_pair(7);
+    double h = half(5.0);
+    int s = sum(4);
+    return f + p.a + (int)h + s - 39;
+}
@@ -0,0 +1,29 @@
+// a program whose threads wait, while the main thread stops itself with a SIGTRAP
+#include <pthread.h>
+#include <signal.h>
+#include <unistd.h>
+
+#define THREADS 2
+
+static pthread_barrier_t started;
+
+void *wait_forever(void *argument)
+{
+    pthread_barrier_wait(&started);
+    for (;;) {
+        pause();
+    }
+    return argument;
+}
+
+int main(void)
+{
+    pthread_t threads[THREADS];
+    pthread_barrier_init(&started, NULL, THREADS + 1);
+    for (int i = 0; i < THREADS; i++) {
+        pthread_create(&threads[i], NULL, wait_forever, NULL);
+    }
+    pthread_barrier_wait(&started);
+    raise(SIGTRAP);
+    return 0;
+}
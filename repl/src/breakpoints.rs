@@ -0,0 +1,243 @@
+//! Displaying breakpoints; `info breakpoints` and what's printed when the inferior stops at one
+use midas::{
+    breakpoints::{BreakpointKind, BreakpointTable, UserBreakpoint, WatchAccess},
+    elf::ParsedELF,
+    shared_library::SharedLibraries,
+    software_breakpoint::BreakpointRequest,
+};
+
+use crate::examine::{label, symbol_offset};
+
+fn type_name(breakpoint: &UserBreakpoint) -> &'static str {
+    match &breakpoint.kind {
+        BreakpointKind::Software => "breakpoint",
+        BreakpointKind::Hardware => "hw breakpoint",
+        BreakpointKind::Watchpoint { access, .. } => match access {
+            WatchAccess::Write => "hw watchpoint",
+            WatchAccess::Read => "read watchpoint",
+            WatchAccess::Access => "acc watchpoint",
+        },
+    }
+}
+
+pub fn watch_description(access: WatchAccess) -> &'static str {
+    match access {
+        WatchAccess::Write => "Hardware watchpoint",
+        WatchAccess::Read => "Hardware read watchpoint",
+        WatchAccess::Access => "Hardware access (read/write) watchpoint",
+    }
+}
+
+fn description(breakpoint: &UserBreakpoint) -> &'static str {
+    match breakpoint.kind {
+        BreakpointKind::Hardware => "Hardware assisted breakpoint",
+        _ if breakpoint.temporary => "Temporary breakpoint",
+        _ => "Breakpoint",
+    }
+}
+
+/// What's said when a breakpoint or watchpoint is created
+pub fn describe_created(breakpoint: &UserBreakpoint) -> Vec<String> {
+    match &breakpoint.kind {
+        BreakpointKind::Watchpoint {
+            access, expression, ..
+        } => vec![format!(
+            "{} {}: {}",
+            watch_description(*access),
+            breakpoint.number,
+            expression
+        )],
+        _ if breakpoint.is_pending() => vec![
+            format!(
+                "Function \"{}\" not defined.",
+                describe_spec(&breakpoint.spec)
+            ),
+            format!(
+                "{} {} ({}) pending.",
+                description(breakpoint),
+                breakpoint.number,
+                describe_spec(&breakpoint.spec)
+            ),
+        ],
+        _ => vec![format!(
+            "{} {} set @ {:X?}",
+            description(breakpoint),
+            breakpoint.number,
+            breakpoint.locations[0]
+        )],
+    }
+}
+
+/// What's said when breakpoints stop the inferior at `pc`; the breakpoint, and the conditions that couldn't be
+/// evaluated
+pub fn describe_hit(
+    breakpoints: &[UserBreakpoint],
+    condition_errors: &[(usize, String)],
+    libraries: &SharedLibraries,
+    elf: &ParsedELF,
+    pc: usize,
+) -> Vec<String> {
+    let mut lines: Vec<String> = condition_errors
+        .iter()
+        .map(|(number, err)| {
+            format!(
+                "Error in testing condition for breakpoint {}:\n{}",
+                number, err
+            )
+        })
+        .collect();
+    if let Some(breakpoint) = breakpoints.first() {
+        lines.push(format!(
+            "{} {}, {}",
+            if breakpoint.temporary {
+                "Temporary breakpoint"
+            } else {
+                "Breakpoint"
+            },
+            breakpoint.number,
+            match libraries.symbolize(pc) {
+                Some((name, 0)) => format!("0x{:x} <{}>", pc, name),
+                Some((name, offset)) => format!("0x{:x} <{}+{}>", pc, name, offset),
+                None => label(elf, pc),
+            }
+        ));
+    }
+    lines
+}
+
+fn display_value(value: Option<i64>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "<unreadable>".into(),
+    }
+}
+
+/// What's said when a watchpoint stops the inferior at `pc`; the old and new value of the watched expression
+pub fn describe_watchpoint(
+    watchpoint: &UserBreakpoint,
+    old_value: Option<i64>,
+    new_value: Option<i64>,
+    elf: &ParsedELF,
+    pc: usize,
+) -> Vec<String> {
+    let (access, expression) = match &watchpoint.kind {
+        BreakpointKind::Watchpoint {
+            access, expression, ..
+        } => (*access, expression),
+        _ => return vec![],
+    };
+    let mut lines = vec![
+        format!(
+            "{} {}: {}",
+            watch_description(access),
+            watchpoint.number,
+            expression
+        ),
+        String::new(),
+    ];
+    if access == WatchAccess::Read || new_value == old_value {
+        lines.push(format!("Value = {}", display_value(new_value)));
+    } else {
+        lines.push(format!("Old value = {}", display_value(old_value)));
+        lines.push(format!("New value = {}", display_value(new_value)));
+    }
+    lines.push(format!("stopped at {}", label(elf, pc)));
+    lines
+}
+
+/// How the user asked for the breakpoint
+pub fn describe_spec(spec: &BreakpointRequest) -> String {
+    match spec {
+        BreakpointRequest::Address(address) => format!("*0x{:x}", address.value()),
+        BreakpointRequest::Line { number, file } => format!("{}:{}", file, number),
+        BreakpointRequest::Function { name, file: None } => name.clone(),
+        BreakpointRequest::Function {
+            name,
+            file: Some(file),
+        } => format!("{}:{}", file, name),
+    }
+}
+
+// the What column of a breakpoint with a single location; where it is, and if that's not just an address, what
+// the user asked for
+fn what(elf: &ParsedELF, breakpoint: &UserBreakpoint) -> String {
+    let location = symbol_offset(elf, breakpoint.locations[0].value());
+    match (&breakpoint.spec, location) {
+        (BreakpointRequest::Address(_), Some(location)) => location,
+        (BreakpointRequest::Address(address), None) => describe_spec(&BreakpointRequest::Address(*address)),
+        (spec, Some(location)) => format!("{} {}", describe_spec(spec), location),
+        (spec, None) => describe_spec(spec),
+    }
+}
+
+/// The lines of `info breakpoints`
+pub fn info_breakpoints(breakpoints: &BreakpointTable, elf: &ParsedELF) -> Vec<String> {
+    if breakpoints.iter().next().is_none() {
+        return vec!["No breakpoints or watchpoints.".into()];
+    }
+    let mut lines = vec![format!(
+        "{:<7} {:<14} {:<4} {:<3} {:<18} {}",
+        "Num", "Type", "Disp", "Enb", "Address", "What"
+    )];
+    for breakpoint in breakpoints.iter() {
+        let enabled = if breakpoint.enabled { "y" } else { "n" };
+        let header = format!(
+            "{:<7} {:<14} {:<4} {:<3}",
+            breakpoint.number,
+            type_name(breakpoint),
+            if breakpoint.temporary { "del" } else { "keep" },
+            enabled
+        );
+        match &breakpoint.kind {
+            BreakpointKind::Watchpoint { expression, .. } => {
+                lines.push(format!("{} {:<18} {}", header, "", expression))
+            }
+            _ if breakpoint.is_pending() => lines.push(format!(
+                "{} {:<18} {}",
+                header,
+                "<PENDING>",
+                describe_spec(&breakpoint.spec)
+            )),
+            _ if breakpoint.locations.len() == 1 => lines.push(format!(
+                "{} 0x{:016x} {}",
+                header,
+                breakpoint.locations[0].value(),
+                what(elf, breakpoint)
+            )),
+            _ => {
+                lines.push(format!(
+                    "{} {:<18} {}",
+                    header,
+                    "<MULTIPLE>",
+                    describe_spec(&breakpoint.spec)
+                ));
+                for (index, location) in breakpoint.locations.iter().enumerate() {
+                    lines.push(format!(
+                        "{:<7} {:<14} {:<4} {:<3} 0x{:016x} {}",
+                        format!("{}.{}", breakpoint.number, index + 1),
+                        "",
+                        "",
+                        enabled,
+                        location.value(),
+                        symbol_offset(elf, location.value()).unwrap_or_default()
+                    ));
+                }
+            }
+        }
+        if let Some(condition) = &breakpoint.condition {
+            lines.push(format!("\tstop only if {}", condition));
+        }
+        match breakpoint.hit_count {
+            0 => {}
+            1 => lines.push("\tbreakpoint already hit 1 time".into()),
+            hits => lines.push(format!("\tbreakpoint already hit {} times", hits)),
+        }
+        if breakpoint.ignore_count > 0 {
+            lines.push(format!(
+                "\tWill ignore next {} crossings of breakpoint.",
+                breakpoint.ignore_count
+            ));
+        }
+    }
+    lines
+}
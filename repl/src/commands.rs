@@ -1,8 +1,972 @@
-use midas::commands::Command;
-#[allow(dead_code)]
-pub fn parse_input(input: String) -> Command {
-    match &input[..] {
-        "info" | "i" => Command::Info,
-        _ => Command::Unknown,
+//! The commands of the repl, and how they're written. Every command is in a table, with it's aliases, how it's used
+//! and what it does; that's what the input is matched against, and what `help` shows. Commands can be abbreviated to
+//! any prefix that's unique, `disas` for `disassemble`, and the aliases gdb users expect, like `b` and `bt`, always
+//! win over abbreviations.
+//!
+//! Arguments are separated by whitespace, and can be quoted, `b "my file.c:10"`. Expressions, like the ones of
+//! `print` and `watch`, are everything after the command, as it was written. Numbers can be written in any base;
+//! `0x1f`, `0o17`, `0b101` or `31`.
+use midas::{
+    breakpoints::WatchAccess, commands::Command, disassemble::Syntax, software_breakpoint::BreakpointRequest,
+    types::Address,
+};
+use std::ops::Range;
+
+/// What the user asked for; either something for the session to do, or something the repl does itself, like
+/// printing values or showing help
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplCommand {
+    /// `delete`, `enable` and `disable` without numbers have an empty list, that means all breakpoints
+    Session(Command),
+    Examine {
+        format: Option<String>,
+        expression: String,
+    },
+    Disassemble {
+        modifiers: String,
+        arguments: String,
+    },
+    Print {
+        hex: bool,
+        expression: String,
+    },
+    Backtrace,
+    /// Writes a core dump, to the file if one is given
+    GenerateCoreFile(Option<String>),
+    SetDisassemblyFlavor(Syntax),
+    ShowDisassemblyFlavor,
+    Info(Info),
+    /// The lines to show
+    Help(Vec<String>),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Info {
+    Auxv,
+    Breakpoints,
+    Proc,
+    ProcMappings,
+    SharedLibraries,
+}
+
+// a command as it's written, and what it's arguments make of it
+struct CommandSpec {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    // the arguments, as `help` shows them
+    usage: &'static str,
+    summary: &'static str,
+    // whether it takes a format glued to it's name, like x/16xb
+    takes_format: bool,
+    subcommands: &'static [CommandSpec],
+    parse: fn(&Arguments) -> Result<ReplCommand, String>,
+}
+
+const fn spec(
+    name: &'static str,
+    aliases: &'static [&'static str],
+    usage: &'static str,
+    summary: &'static str,
+    parse: fn(&Arguments) -> Result<ReplCommand, String>,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        aliases,
+        usage,
+        summary,
+        takes_format: false,
+        subcommands: &[],
+        parse,
+    }
+}
+
+const fn with_format(spec: CommandSpec) -> CommandSpec {
+    CommandSpec {
+        takes_format: true,
+        ..spec
+    }
+}
+
+const fn with_subcommands(spec: CommandSpec, subcommands: &'static [CommandSpec]) -> CommandSpec {
+    CommandSpec {
+        subcommands,
+        ..spec
+    }
+}
+
+const LOCATIONS: &str = "A location is a function, FILE:LINE, FILE:FUNCTION, or *ADDRESS.";
+
+// sorted by name, as help lists them
+const COMMANDS: &[CommandSpec] = &[
+    spec(
+        "advance",
+        &[],
+        "LOCATION",
+        "Run until LOCATION is reached, in any frame, or the current function returns.",
+        |arguments| {
+            let location = arguments
+                .location(0)?
+                .ok_or("Argument required (a location).")?;
+            Ok(ReplCommand::Session(Command::Advance(location)))
+        },
+    ),
+    spec(
+        "awatch",
+        &[],
+        "EXPRESSION",
+        "Set a watchpoint that stops when EXPRESSION is read or written.",
+        |arguments| watch(arguments, WatchAccess::Access),
+    ),
+    spec(
+        "backtrace",
+        &["bt", "where"],
+        "",
+        "Print the frames of the stack, innermost first.",
+        |arguments| {
+            arguments.at_most(0)?;
+            Ok(ReplCommand::Backtrace)
+        },
+    ),
+    spec(
+        "break",
+        &["b", "breakpoint"],
+        "LOCATION [if CONDITION]",
+        "Set a breakpoint at LOCATION, that only stops when CONDITION holds, if one is given.",
+        |arguments| breakpoint(arguments, false, false),
+    ),
+    spec(
+        "condition",
+        &[],
+        "NUMBER [CONDITION]",
+        "Make breakpoint NUMBER stop only when CONDITION holds; without one, it always stops.",
+        |arguments| {
+            let number = arguments
+                .breakpoint_number(0)?
+                .ok_or("Argument required (breakpoint number).")?;
+            let condition = Some(arguments.rest(1).to_owned()).filter(|condition| !condition.is_empty());
+            Ok(ReplCommand::Session(Command::Condition {
+                number,
+                condition,
+            }))
+        },
+    ),
+    spec(
+        "delete",
+        &["d"],
+        "[NUMBER...]",
+        "Delete the breakpoints with the numbers given, or all of them.",
+        |arguments| {
+            Ok(ReplCommand::Session(Command::Delete(
+                arguments.breakpoint_numbers()?,
+            )))
+        },
+    ),
+    spec(
+        "disable",
+        &[],
+        "[NUMBER...]",
+        "Disable the breakpoints with the numbers given, or all of them.",
+        |arguments| {
+            Ok(ReplCommand::Session(Command::Disable(
+                arguments.breakpoint_numbers()?,
+            )))
+        },
+    ),
+    with_format(spec(
+        "disassemble",
+        &["disas"],
+        "[/MODIFIERS] [FUNCTION | ADDRESS | START,END | START,+LENGTH]",
+        "Disassemble a function, a range of addresses, or the function that's executing. /r shows the raw bytes.",
+        |arguments| {
+            // modifiers can be glued to the command, disassemble/r, or come after it, disassemble /r
+            let mut modifiers = arguments.format.unwrap_or_default().to_owned();
+            let mut index = 0;
+            while let Some(modifier) = arguments
+                .word(index)
+                .and_then(|word| word.strip_prefix('/'))
+            {
+                modifiers += modifier;
+                index += 1;
+            }
+            Ok(ReplCommand::Disassemble {
+                modifiers,
+                arguments: arguments.rest(index).to_owned(),
+            })
+        },
+    )),
+    spec(
+        "enable",
+        &[],
+        "[NUMBER...]",
+        "Enable the breakpoints with the numbers given, or all of them.",
+        |arguments| {
+            Ok(ReplCommand::Session(Command::Enable(
+                arguments.breakpoint_numbers()?,
+            )))
+        },
+    ),
+    with_format(spec(
+        "examine",
+        &["x"],
+        "[/FMT] [EXPRESSION]",
+        "Examine memory at the address EXPRESSION evaluates to. FMT is a count, a format letter and a size letter.",
+        |arguments| {
+            Ok(ReplCommand::Examine {
+                format: arguments.format.map(str::to_owned),
+                expression: arguments.rest(0).to_owned(),
+            })
+        },
+    )),
+    spec(
+        "finish",
+        &["fin"],
+        "",
+        "Run until the current function returns, and print the value it returns.",
+        |arguments| {
+            arguments.at_most(0)?;
+            Ok(ReplCommand::Session(Command::Finish))
+        },
+    ),
+    spec(
+        "generate-core-file",
+        &["gcore"],
+        "[FILE]",
+        "Save a core dump of the program, to FILE or core.PID.",
+        |arguments| {
+            arguments.at_most(1)?;
+            Ok(ReplCommand::GenerateCoreFile(
+                arguments.word(0).map(str::to_owned),
+            ))
+        },
+    ),
+    spec(
+        "hbreak",
+        &[],
+        "LOCATION [if CONDITION]",
+        "Set a hardware breakpoint at LOCATION, in one of the debug registers.",
+        |arguments| breakpoint(arguments, true, false),
+    ),
+    spec(
+        "help",
+        &["h"],
+        "[COMMAND [SUBCOMMAND]]",
+        "Print the list of commands, or what a command does.",
+        |arguments| {
+            let topic: Vec<&str> = (0..).map_while(|index| arguments.word(index)).collect();
+            help(&topic).map(ReplCommand::Help)
+        },
+    ),
+    spec(
+        "ignore",
+        &[],
+        "NUMBER COUNT",
+        "Don't stop at breakpoint NUMBER the next COUNT times it's reached.",
+        |arguments| {
+            let number = arguments
+                .breakpoint_number(0)?
+                .ok_or("Argument required (a breakpoint number).")?;
+            let count = arguments
+                .number(1)?
+                .ok_or("Second argument (specified ignore-count) is missing.")?;
+            arguments.at_most(2)?;
+            Ok(ReplCommand::Session(Command::Ignore { number, count }))
+        },
+    ),
+    with_subcommands(
+        spec(
+            "info",
+            &["i"],
+            "SUBCOMMAND",
+            "Print what's known about the program being debugged.",
+            |_| help(&["info"]).map(ReplCommand::Help),
+        ),
+        &[
+            spec(
+                "auxv",
+                &[],
+                "",
+                "The auxiliary vector of the program.",
+                |arguments| info(arguments, Info::Auxv),
+            ),
+            spec(
+                "breakpoints",
+                &["b", "watchpoints"],
+                "",
+                "The breakpoints and watchpoints, and how often they've been hit.",
+                |arguments| info(arguments, Info::Breakpoints),
+            ),
+            spec(
+                "proc",
+                &[],
+                "[mappings]",
+                "The process of the program, or the memory it has mapped.",
+                |arguments| match arguments.word(0) {
+                    None => Ok(ReplCommand::Info(Info::Proc)),
+                    Some("mappings") => info(&arguments.after(1), Info::ProcMappings),
+                    Some(what) => Err(format!("Undefined info proc command: \"{}\".", what)),
+                },
+            ),
+            spec(
+                "sharedlibrary",
+                &["dll"],
+                "",
+                "The shared libraries that are loaded, and where.",
+                |arguments| info(arguments, Info::SharedLibraries),
+            ),
+        ],
+    ),
+    spec(
+        "next",
+        &["n"],
+        "[COUNT]",
+        "Step COUNT lines, over the functions called.",
+        |arguments| {
+            Ok(ReplCommand::Session(Command::Next {
+                count: count(arguments)?,
+            }))
+        },
+    ),
+    spec(
+        "nexti",
+        &["ni"],
+        "[COUNT]",
+        "Step COUNT instructions, over calls.",
+        |arguments| {
+            Ok(ReplCommand::Session(Command::NextInstruction {
+                count: count(arguments)?,
+            }))
+        },
+    ),
+    with_format(spec(
+        "print",
+        &["p"],
+        "[/x] EXPRESSION",
+        "Print the value of EXPRESSION; in hex with /x.",
+        |arguments| {
+            let hex = match arguments.format {
+                None => false,
+                Some("x") => true,
+                Some(format) => return Err(format!("Undefined output format \"{}\".", format)),
+            };
+            Ok(ReplCommand::Print {
+                hex,
+                expression: arguments.rest(0).to_owned(),
+            })
+        },
+    )),
+    spec("quit", &["q"], "", "Exit the debugger.", |_| {
+        Ok(ReplCommand::Quit)
+    }),
+    spec(
+        "run",
+        &["r"],
+        "",
+        "Run the program until it stops at a breakpoint, is signaled or exits.",
+        |arguments| {
+            arguments.at_most(0)?;
+            Ok(ReplCommand::Session(Command::Continue))
+        },
+    ),
+    spec(
+        "rwatch",
+        &[],
+        "EXPRESSION",
+        "Set a watchpoint that stops when EXPRESSION is read.",
+        |arguments| watch(arguments, WatchAccess::Read),
+    ),
+    with_subcommands(
+        spec(
+            "set",
+            &[],
+            "SETTING VALUE",
+            "Change a setting of the debugger.",
+            |arguments| match arguments.word(0) {
+                Some(variable) => Err(format!("No symbol \"{}\" in current context.", variable)),
+                None => Err("Argument required (expression to compute).".into()),
+            },
+        ),
+        &[spec(
+            "disassembly-flavor",
+            &[],
+            "att | intel",
+            "The syntax instructions are disassembled in.",
+            |arguments| {
+                arguments.at_most(1)?;
+                match arguments.word(0) {
+                    Some("att") => Ok(ReplCommand::SetDisassemblyFlavor(Syntax::Att)),
+                    Some("intel") => Ok(ReplCommand::SetDisassemblyFlavor(Syntax::Intel)),
+                    Some(value) => Err(format!("Undefined item: \"{}\".", value)),
+                    None => Err("Requires an argument. Valid arguments are att, intel.".into()),
+                }
+            },
+        )],
+    ),
+    with_subcommands(
+        spec(
+            "show",
+            &[],
+            "SETTING",
+            "Print a setting of the debugger.",
+            |_| help(&["show"]).map(ReplCommand::Help),
+        ),
+        &[spec(
+            "disassembly-flavor",
+            &[],
+            "",
+            "The syntax instructions are disassembled in.",
+            |arguments| {
+                arguments.at_most(0)?;
+                Ok(ReplCommand::ShowDisassemblyFlavor)
+            },
+        )],
+    ),
+    spec(
+        "step",
+        &["s"],
+        "[COUNT]",
+        "Step COUNT lines, into the functions called.",
+        |arguments| {
+            Ok(ReplCommand::Session(Command::Step {
+                count: count(arguments)?,
+            }))
+        },
+    ),
+    spec(
+        "stepi",
+        &["si"],
+        "[COUNT]",
+        "Step COUNT instructions, into calls.",
+        |arguments| {
+            Ok(ReplCommand::Session(Command::StepInstruction {
+                count: count(arguments)?,
+            }))
+        },
+    ),
+    spec(
+        "tbreak",
+        &[],
+        "LOCATION [if CONDITION]",
+        "Set a breakpoint at LOCATION, that's deleted once it stops the program.",
+        |arguments| breakpoint(arguments, false, true),
+    ),
+    spec(
+        "until",
+        &["u"],
+        "[LOCATION]",
+        "Run to the next line, without stopping in loops, or until LOCATION is reached in the current frame.",
+        |arguments| Ok(ReplCommand::Session(Command::Until(arguments.location(0)?))),
+    ),
+    spec(
+        "watch",
+        &[],
+        "EXPRESSION",
+        "Set a watchpoint that stops when EXPRESSION is written; a variable, *ADDRESS or -l ADDRESS.",
+        |arguments| watch(arguments, WatchAccess::Write),
+    ),
+];
+
+// an argument, and where in the input it starts
+struct Word {
+    text: String,
+    start: usize,
+}
+
+/// Splits `input` into words. Quotes group words, `"my file.c"` or `'my file.c'`, and in double quotes, a backslash
+/// escapes the character after it.
+fn split_words(input: &str) -> Result<Vec<Word>, String> {
+    let mut words = vec![];
+    let mut characters = input.char_indices().peekable();
+    loop {
+        while characters.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let Some(&(start, _)) = characters.peek() else {
+            return Ok(words);
+        };
+        let mut text = String::new();
+        while let Some((_, c)) = characters.next_if(|(_, c)| !c.is_whitespace()) {
+            match c {
+                '"' | '\'' => loop {
+                    match characters.next() {
+                        Some((_, close)) if close == c => break,
+                        Some((_, '\\')) if c == '"' => match characters.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err("Unterminated quoted string.".into()),
+                        },
+                        Some((_, inside)) => text.push(inside),
+                        None => return Err("Unterminated quoted string.".into()),
+                    }
+                },
+                c => text.push(c),
+            }
+        }
+        words.push(Word { text, start });
+    }
+}
+
+// the arguments of a command
+struct Arguments<'input> {
+    name: &'static str,
+    format: Option<&'input str>,
+    input: &'input str,
+    words: &'input [Word],
+}
+
+impl<'input> Arguments<'input> {
+    fn word(&self, index: usize) -> Option<&'input str> {
+        self.words.get(index).map(|word| word.text.as_str())
+    }
+
+    /// Everything from the word at `index` on, as it was written
+    fn rest(&self, index: usize) -> &'input str {
+        self.words
+            .get(index)
+            .map_or("", |word| self.input[word.start..].trim_end())
+    }
+
+    // the arguments from the word at `index` on, like a subcommand gets them
+    fn after(&self, index: usize) -> Arguments<'input> {
+        Arguments {
+            words: self.words.get(index..).unwrap_or_default(),
+            ..*self
+        }
+    }
+
+    // the error for the words from `index` on, that the command has no use for
+    fn junk(&self, index: usize) -> String {
+        format!(
+            "Junk at end of arguments to \"{}\": \"{}\".",
+            self.name,
+            self.rest(index)
+        )
+    }
+
+    fn at_most(&self, count: usize) -> Result<(), String> {
+        match self.words.len() > count {
+            true => Err(self.junk(count)),
+            false => Ok(()),
+        }
+    }
+
+    fn number(&self, index: usize) -> Result<Option<usize>, String> {
+        self.word(index).map(parse_number).transpose()
+    }
+
+    fn breakpoint_number(&self, index: usize) -> Result<Option<usize>, String> {
+        self.word(index)
+            .map(|number| parse_number(number).map_err(|_| format!("Bad breakpoint argument: '{}'", number)))
+            .transpose()
+    }
+
+    fn breakpoint_numbers(&self) -> Result<Vec<usize>, String> {
+        (0..self.words.len())
+            .filter_map(|index| self.breakpoint_number(index).transpose())
+            .collect()
+    }
+
+    fn location(&self, index: usize) -> Result<Option<BreakpointRequest>, String> {
+        let location = self.word(index).map(parse_location).transpose()?;
+        self.at_most(index + 1)?;
+        Ok(location)
+    }
+}
+
+/// Parses a number in any base; hex with 0x, octal with 0o, binary with 0b, and decimal without a prefix. The
+/// prefixes can be upper case too, like 0X1F.
+pub fn parse_number(text: &str) -> Result<usize, String> {
+    let invalid = || format!("Invalid number \"{}\".", text);
+    let prefixed = |prefix: &str| {
+        text.get(..2)
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    };
+    let (digits, radix) = if prefixed("0x") {
+        (&text[2..], 16)
+    } else if prefixed("0o") {
+        (&text[2..], 8)
+    } else if prefixed("0b") {
+        (&text[2..], 2)
+    } else {
+        (text, 10)
+    };
+    match digits.starts_with(['+', '-']) {
+        true => Err(invalid()),
+        false => usize::from_str_radix(digits, radix).map_err(|_| invalid()),
+    }
+}
+
+/// Parses a location; `*ADDRESS`, `FILE:LINE`, `FILE:FUNCTION` or `FUNCTION`. A bare hex number, 0x401000, is an
+/// address too.
+pub fn parse_location(location: &str) -> Result<BreakpointRequest, String> {
+    if let Some(address) = location.strip_prefix('*') {
+        return parse_number(address.trim()).map(|address| BreakpointRequest::Address(Address(address)));
+    }
+    if location.starts_with(|c: char| c.is_ascii_digit()) {
+        return match parse_number(location)? {
+            address if location.len() > 2 && location[..2].eq_ignore_ascii_case("0x") => {
+                Ok(BreakpointRequest::Address(Address(address)))
+            }
+            line => Err(format!(
+                "No default source file; give line {} as FILE:{}.",
+                line, line
+            )),
+        };
+    }
+    // C++ names have colons in them, ns::function, that don't separate a file
+    match location.rsplit_once(':') {
+        Some((file, _)) if file.is_empty() || file.ends_with(':') => Ok(BreakpointRequest::Function {
+            name: location.to_owned(),
+            file: None,
+        }),
+        Some((file, line)) if line.starts_with(|c: char| c.is_ascii_digit()) => Ok(BreakpointRequest::Line {
+            number: parse_number(line)?,
+            file: file.to_owned(),
+        }),
+        Some((_, "")) => Err(format!("Malformed location \"{}\".", location)),
+        Some((file, name)) => Ok(BreakpointRequest::Function {
+            name: name.to_owned(),
+            file: Some(file.to_owned()),
+        }),
+        None => Ok(BreakpointRequest::Function {
+            name: location.to_owned(),
+            file: None,
+        }),
+    }
+}
+
+fn breakpoint(arguments: &Arguments, hardware: bool, temporary: bool) -> Result<ReplCommand, String> {
+    let Some(location) = arguments.word(0) else {
+        return Err(format!(
+            "{} command requires parameters: <address | function | symbol | source location> [if condition]",
+            arguments.name
+        ));
+    };
+    let condition = match arguments.word(1) {
+        None => None,
+        Some("if") if arguments.word(2).is_some() => Some(arguments.rest(2).to_owned()),
+        Some("if") => return Err("Argument required (boolean expression).".into()),
+        Some(_) => return Err(arguments.junk(1)),
+    };
+    Ok(ReplCommand::Session(Command::SetBreakpoint {
+        location: parse_location(location)?,
+        hardware,
+        temporary,
+        condition,
+    }))
+}
+
+fn watch(arguments: &Arguments, access: WatchAccess) -> Result<ReplCommand, String> {
+    Ok(ReplCommand::Session(Command::Watch {
+        expression: arguments.rest(0).to_owned(),
+        access,
+    }))
+}
+
+fn count(arguments: &Arguments) -> Result<usize, String> {
+    arguments.at_most(1)?;
+    Ok(arguments.number(0)?.unwrap_or(1))
+}
+
+fn info(arguments: &Arguments, info: Info) -> Result<ReplCommand, String> {
+    arguments.at_most(0)?;
+    Ok(ReplCommand::Info(info))
+}
+
+// the command `name` is, as it's written or abbreviated; `of` is the command it's a subcommand of
+fn find<'table>(table: &'table [CommandSpec], name: &str, of: Option<&str>) -> Result<&'table CommandSpec, String> {
+    if let Some(spec) = table
+        .iter()
+        .find(|spec| spec.name == name || spec.aliases.contains(&name))
+    {
+        return Ok(spec);
+    }
+    let (kind, help) = match of {
+        Some(command) => (format!("{} command", command), format!("help {}", command)),
+        None => ("command".to_owned(), "help".to_owned()),
+    };
+    let matches: Vec<&CommandSpec> = table
+        .iter()
+        .filter(|spec| spec.name.starts_with(name))
+        .collect();
+    match matches[..] {
+        [spec] => Ok(spec),
+        [] => Err(format!(
+            "Undefined {}: \"{}\".  Try \"{}\".",
+            kind, name, help
+        )),
+        _ => Err(format!(
+            "Ambiguous {} \"{}\": {}.",
+            kind,
+            name,
+            matches
+                .iter()
+                .map(|spec| spec.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Parses a line of input into the command it is; None when there's nothing on it
+pub fn parse(input: &str) -> Result<Option<ReplCommand>, String> {
+    let words = split_words(input)?;
+    let Some(first) = words.first() else {
+        return Ok(None);
+    };
+    // commands like x take their format glued to the command name; x/16xb
+    let (name, format) = match first.text.split_once('/') {
+        Some((name, format)) => (name, Some(format)),
+        None => (first.text.as_str(), None),
+    };
+    let mut spec = find(COMMANDS, name, None)?;
+    if format.is_some() && !spec.takes_format {
+        return Err(format!(
+            "Undefined command: \"{}\".  Try \"help\".",
+            first.text
+        ));
+    }
+    let mut arguments = Arguments {
+        name: spec.name,
+        format,
+        input,
+        words: &words[1..],
+    };
+    if let (false, Some(subcommand)) = (spec.subcommands.is_empty(), arguments.word(0)) {
+        let command = spec.name;
+        spec = find(spec.subcommands, subcommand, Some(command))?;
+        arguments = arguments.after(1);
+        arguments.name = spec.name;
+    }
+    (spec.parse)(&arguments).map(Some)
+}
+
+// the first line of the help of a command; how it's used
+fn usage(prefix: &str, spec: &CommandSpec) -> String {
+    format!("{}{} {}", prefix, spec.name, spec.usage)
+        .trim_end()
+        .to_owned()
+}
+
+/// The help for `topic`; the list of commands if it's empty, otherwise what the command it names does, and the
+/// subcommands it has
+pub fn help(topic: &[&str]) -> Result<Vec<String>, String> {
+    let Some((&first, rest)) = topic.split_first() else {
+        let mut lines = vec!["List of commands:".to_owned(), String::new()];
+        lines.extend(
+            COMMANDS
+                .iter()
+                .map(|spec| format!("{} -- {}", spec.name, spec.summary)),
+        );
+        lines.push(String::new());
+        lines.push("Type \"help\" followed by command name for full documentation.".into());
+        lines.push("Command name abbreviations are allowed if unambiguous.".into());
+        return Ok(lines);
+    };
+    let command = find(COMMANDS, first, None)?;
+    let (prefix, spec) = match rest {
+        [] => (String::new(), command),
+        [subcommand] => (
+            format!("{} ", command.name),
+            find(command.subcommands, subcommand, Some(command.name))?,
+        ),
+        _ => {
+            return Err(format!(
+                "Junk at end of arguments to \"help\": \"{}\".",
+                rest[1]
+            ))
+        }
+    };
+    let mut lines = vec![usage(&prefix, spec), spec.summary.to_owned()];
+    if spec.usage.contains("LOCATION") {
+        lines.push(LOCATIONS.into());
+    }
+    if !spec.aliases.is_empty() {
+        lines.push(format!("Aliases: {}.", spec.aliases.join(", ")));
+    }
+    if !spec.subcommands.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("List of {} subcommands:", spec.name));
+        lines.push(String::new());
+        lines.extend(spec.subcommands.iter().map(|subcommand| {
+            format!(
+                "{} -- {}",
+                usage(&format!("{} ", spec.name), subcommand),
+                subcommand.summary
+            )
+        }));
+    }
+    Ok(lines)
+}
+
+/// What the word being written can be, as the usage of the command says
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completion {
+    /// One of these words; command names, subcommands and keywords
+    Words(Vec<String>),
+    Symbols,
+    /// A function, or a source file and a line in it
+    Locations,
+    BreakpointNumbers,
+    Files,
+    Nothing,
+}
+
+fn names(table: &[CommandSpec]) -> impl Iterator<Item = String> + '_ {
+    table.iter().map(|spec| spec.name.to_owned())
+}
+
+// what an argument written as `placeholder` in a usage can be; `of` is the argument before it
+fn completion_of(placeholder: &str, of: Option<&str>) -> Completion {
+    let mut words = vec![];
+    for alternative in placeholder.trim_end_matches("...").split('|') {
+        match alternative {
+            "LOCATION" => return Completion::Locations,
+            "FUNCTION" | "EXPRESSION" | "CONDITION" => return Completion::Symbols,
+            "NUMBER" => return Completion::BreakpointNumbers,
+            "FILE" => return Completion::Files,
+            "COMMAND" => words.extend(names(COMMANDS)),
+            "SUBCOMMAND" => match of.and_then(|command| find(COMMANDS, command, None).ok()) {
+                Some(command) => words.extend(names(command.subcommands)),
+                None => return Completion::Nothing,
+            },
+            keyword if keyword.chars().all(|c| c.is_ascii_lowercase()) => words.push(keyword.to_owned()),
+            _ => {}
+        }
+    }
+    match words.is_empty() {
+        true => Completion::Nothing,
+        false => Completion::Words(words),
+    }
+}
+
+/// What the word at the end of `line` can be. The first word is a command, then a subcommand if the command has
+/// them, and then the arguments it's usage has. Formats, like the /x of `x/x` or `disassemble /r`, aren't arguments.
+pub fn completion(line: &str) -> Completion {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    if !line.ends_with(char::is_whitespace) {
+        // the one being written
+        words.pop();
+    }
+    let Some((first, mut arguments)) = words.split_first() else {
+        return Completion::Words(names(COMMANDS).collect());
+    };
+    let name = first.split('/').next().unwrap_or_default();
+    let Ok(mut spec) = find(COMMANDS, name, None) else {
+        return Completion::Nothing;
+    };
+    if !spec.subcommands.is_empty() {
+        let Some((subcommand, rest)) = arguments.split_first() else {
+            return Completion::Words(names(spec.subcommands).collect());
+        };
+        match find(spec.subcommands, subcommand, Some(spec.name)) {
+            Ok(subcommand) => spec = subcommand,
+            Err(_) => return Completion::Nothing,
+        }
+        arguments = rest;
+    }
+    let arguments: Vec<&str> = arguments
+        .iter()
+        .filter(|argument| !argument.starts_with('/'))
+        .copied()
+        .collect();
+    // alternatives are one placeholder; FUNCTION | ADDRESS
+    let usage = spec.usage.replace(" | ", "|");
+    let placeholders: Vec<&str> = usage
+        .split_whitespace()
+        .map(|placeholder| placeholder.trim_matches(['[', ']']))
+        .filter(|placeholder| !placeholder.starts_with('/'))
+        .collect();
+    let placeholder = match placeholders.get(arguments.len()) {
+        Some(placeholder) => placeholder,
+        // lists, and expressions that have spaces in them, go on to the end of the line
+        None => match placeholders.last() {
+            Some(last) if last.ends_with("...") || matches!(*last, "EXPRESSION" | "CONDITION") => last,
+            _ => return Completion::Nothing,
+        },
+    };
+    completion_of(placeholder, arguments.first().copied())
+}
+
+/// What a word of the input is, as the console colors it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    /// A command or subcommand, as it's written or abbreviated
+    Command,
+    /// A word where a command goes that no command starts with
+    Unknown,
+    Number,
+    /// A hex number, or one after a `*`; how addresses are written
+    Address,
+}
+
+// the bytes of `line` that each of it's words span
+fn word_ranges(line: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut start = None;
+    for (index, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(index),
+            (Some(first), true) => {
+                words.push(first..index);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+// whether `name` is a command of `table`, or none; None while it's the start of several
+fn command_token(table: &[CommandSpec], name: &str) -> Option<Token> {
+    match find(table, name, None) {
+        Ok(_) => Some(Token::Command),
+        Err(_) if table.iter().any(|spec| spec.name.starts_with(name)) => None,
+        Err(_) => Some(Token::Unknown),
+    }
+}
+
+/// The words of `line` that mean something, and the bytes they span; the command, and the subcommand if it has them,
+/// and the numbers and addresses after. Unlike `parse`, this takes lines that are still being written, so a command
+/// that's the start of several is neither a command nor unknown yet.
+pub fn tokens(line: &str) -> Vec<(Range<usize>, Token)> {
+    let mut tokens = vec![];
+    let mut words = word_ranges(line).into_iter();
+    let Some(first) = words.next() else {
+        return tokens;
+    };
+    // the format glued to a command isn't part of it's name; x/16xb
+    let name = line[first.clone()].split('/').next().unwrap_or_default();
+    match command_token(COMMANDS, name) {
+        Some(Token::Unknown) => {
+            tokens.push((first, Token::Unknown));
+            return tokens;
+        }
+        Some(token) => tokens.push((first.start..first.start + name.len(), token)),
+        None => {}
+    }
+    if let Some(spec) = find(COMMANDS, name, None)
+        .ok()
+        .filter(|spec| !spec.subcommands.is_empty())
+    {
+        if let Some(subcommand) = words.next() {
+            if let Some(token) = command_token(spec.subcommands, &line[subcommand.clone()]) {
+                tokens.push((subcommand, token));
+            }
+        }
+    }
+    for word in words {
+        let text = &line[word.clone()];
+        let address = text.strip_prefix('*').unwrap_or(text);
+        let token = match parse_number(address) {
+            Ok(_)
+                if address
+                    .get(..2)
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case("0x")) =>
+            {
+                Token::Address
+            }
+            Ok(_) if address.len() == text.len() => Token::Number,
+            _ => continue,
+        };
+        tokens.push((word, token));
     }
+    tokens
 }
@@ -0,0 +1,195 @@
+//! Tab completion at the console. The command table says what an argument is; this knows what there is of each kind,
+//! the symbols and source files of the program, the breakpoints that are set and the files on disk.
+use std::rc::Rc;
+
+use midas::{dwarf::linenumber::LineTable, elf::ParsedELF};
+
+use crate::commands::{self, Completion};
+
+#[derive(Clone)]
+pub struct ReplCompleter {
+    // sorted, and without duplicates; they're the same for the whole session, so they're shared between the
+    // completers of each command
+    symbols: Rc<Vec<String>>,
+    source_files: Rc<Vec<String>>,
+    breakpoints: Vec<usize>,
+}
+
+fn sorted(names: impl Iterator<Item = String>) -> Rc<Vec<String>> {
+    let mut names: Vec<String> = names.collect();
+    names.sort();
+    names.dedup();
+    Rc::new(names)
+}
+
+// the start of the word that ends at the end of `line`, where words end where `ends_word` says
+fn word_start(line: &str, ends_word: impl Fn(char) -> bool) -> usize {
+    line.char_indices()
+        .rev()
+        .find(|(_, c)| ends_word(*c))
+        .map_or(0, |(index, c)| index + c.len_utf8())
+}
+
+fn matching<'a>(candidates: impl Iterator<Item = &'a String>, word: &str) -> Vec<String> {
+    candidates
+        .filter(|candidate| candidate.starts_with(word))
+        .cloned()
+        .collect()
+}
+
+// the files and directories that `word` is the start of the path of; directories end in a slash
+fn paths(word: &str) -> Vec<String> {
+    let (directory, name) = match word.rfind('/') {
+        Some(slash) => word.split_at(slash + 1),
+        None => ("", word),
+    };
+    let Ok(entries) = std::fs::read_dir(if directory.is_empty() { "." } else { directory }) else {
+        return vec![];
+    };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            // hidden files, only if they're asked for
+            if !file_name.starts_with(name) || (file_name.starts_with('.') && !name.starts_with('.')) {
+                return None;
+            }
+            let slash = match entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                true => "/",
+                false => "",
+            };
+            Some(format!("{}{}{}", directory, file_name, slash))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+impl ReplCompleter {
+    pub fn new(elf: &ParsedELF, lines: Option<&LineTable>) -> ReplCompleter {
+        let files = lines.into_iter().flat_map(|lines| lines.files());
+        ReplCompleter {
+            symbols: sorted(elf.symbol_table.names().map(|name| name.to_owned())),
+            // locations are written with the name of the file; stepping.c:9
+            source_files: sorted(files.filter_map(|file| {
+                let name = std::path::Path::new(file).file_name()?;
+                Some(name.to_string_lossy().into_owned())
+            })),
+            breakpoints: vec![],
+        }
+    }
+
+    /// The completer, for when the breakpoints with `numbers` are the ones that are set
+    pub fn with_breakpoints(&self, numbers: Vec<usize>) -> ReplCompleter {
+        ReplCompleter {
+            breakpoints: numbers,
+            ..self.clone()
+        }
+    }
+}
+
+impl cli::completion::Completer for ReplCompleter {
+    fn complete(&self, line: &str, position: usize) -> (usize, Vec<String>) {
+        let line = &line[..position];
+        let start = word_start(line, char::is_whitespace);
+        let word = &line[start..];
+        match commands::completion(line) {
+            Completion::Words(words) => (start, matching(words.iter(), word)),
+            Completion::Symbols => {
+                // symbols are parts of expressions; the a in *a+1
+                let start = word_start(line, |c| !(c.is_alphanumeric() || c == '_'));
+                (start, matching(self.symbols.iter(), &line[start..]))
+            }
+            // addresses, and the lines of files, aren't completed
+            Completion::Locations if word.starts_with('*') || word.contains(':') => (start, vec![]),
+            Completion::Locations => {
+                let mut candidates = matching(self.symbols.iter(), word);
+                candidates.extend(
+                    matching(self.source_files.iter(), word)
+                        .into_iter()
+                        .map(|file| format!("{}:", file)),
+                );
+                candidates.sort();
+                (start, candidates)
+            }
+            Completion::BreakpointNumbers => {
+                // the ones that are already given aren't again
+                let given: Vec<&str> = line[..start].split_whitespace().collect();
+                let numbers: Vec<String> = self
+                    .breakpoints
+                    .iter()
+                    .map(|number| number.to_string())
+                    .filter(|number| !given.contains(&number.as_str()))
+                    .collect();
+                (start, matching(numbers.iter(), word))
+            }
+            Completion::Files => (start, paths(word)),
+            Completion::Nothing => (start, vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli::completion::Completer;
+
+    fn completer(breakpoints: Vec<usize>) -> ReplCompleter {
+        ReplCompleter {
+            symbols: Rc::new(vec!["add".into(), "main".into()]),
+            source_files: Rc::new(vec!["stepping.c".into()]),
+            breakpoints,
+        }
+    }
+
+    #[test]
+    fn candidates_replace_the_word_at_the_cursor() {
+        let completer = completer(vec![1, 2, 12]);
+        let (start, commands) = completer.complete("disa", 4);
+        assert_eq!(start, 0);
+        assert!(commands.contains(&"disable".to_owned()) && commands.contains(&"disassemble".to_owned()));
+        assert!(commands.iter().all(|command| command.starts_with("disa")));
+
+        assert_eq!(completer.complete("b ma", 4), (2, vec!["main".to_owned()]));
+        assert_eq!(
+            completer.complete("b st", 4),
+            (2, vec!["stepping.c:".to_owned()])
+        );
+        assert_eq!(
+            completer.complete("p 1+ma", 6),
+            (4, vec!["main".to_owned()])
+        );
+
+        // what comes after the cursor isn't part of the word
+        assert_eq!(
+            completer.complete("delete 1 2", 8),
+            (7, vec!["1".to_owned(), "12".to_owned()])
+        );
+        // nor are the numbers that were given already
+        assert_eq!(
+            completer.complete("delete 1 ", 9),
+            (9, vec!["2".to_owned(), "12".to_owned()])
+        );
+    }
+
+    #[test]
+    fn file_paths_are_completed() {
+        let directory = std::env::temp_dir().join(format!("completion-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("cores")).unwrap();
+        std::fs::write(directory.join("core.1"), b"").unwrap();
+        std::fs::write(directory.join(".hidden"), b"").unwrap();
+        let directory = directory.to_str().unwrap().to_owned();
+
+        let line = format!("gcore {}/co", directory);
+        let (start, paths) = completer(vec![]).complete(&line, line.len());
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(start, "gcore ".len());
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}/core.1", directory),
+                format!("{}/cores/", directory)
+            ]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Showing machine code, gdb style; `disassemble [/rs] [function | address | start,end | start,+length]`, and the
+//! instructions of `x/i`
+use midas::{
+    disassemble::{self, DecodeError, Syntax},
+    dwarf::linenumber::LineTable,
+    elf::ParsedELF,
+    expression::Expression,
+    target::Target,
+    types::Address,
+};
+
+use crate::examine::InferiorContext;
+
+/// No instruction is longer than this
+const MAX_INSTRUCTION_LENGTH: usize = 15;
+
+/// An instruction as it's shown
+pub struct Decoded {
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+/// The symbol `address` is in, for the targets of branches and rip relative operands; `main+4`
+pub fn symbolize(elf: &ParsedELF, address: u64) -> Option<String> {
+    match elf.symbol_table.symbolize(address as usize)? {
+        (name, 0) => Some(name.to_owned()),
+        (name, offset) => Some(format!("{}+{}", name, offset)),
+    }
+}
+
+/// Where the pc is, instructions are marked with an arrow
+pub fn pc_marker(pc: Option<u64>, address: u64) -> &'static str {
+    if pc == Some(address) {
+        "=> "
+    } else {
+        "   "
+    }
+}
+
+/// Decodes the instruction at `address`. A byte that doesn't start an instruction is shown as (bad), like gdb does,
+/// so that decoding can go on from the next one. Errors with the first address that can't be read.
+pub fn decode_at(target: &dyn Target, elf: &ParsedELF, syntax: Syntax, address: u64) -> Result<Decoded, u64> {
+    let read = target
+        .read_memory(Address(address as usize), MAX_INSTRUCTION_LENGTH)
+        .map_err(|_| address)?;
+    match disassemble::decode(&read.data, address) {
+        Ok(instruction) => Ok(Decoded {
+            bytes: read.data[..instruction.length].to_vec(),
+            text: instruction.format(syntax, &|target| symbolize(elf, target)),
+        }),
+        Err(DecodeError::Truncated) => Err(address + read.data.len() as u64),
+        Err(DecodeError::Invalid) => Ok(Decoded {
+            bytes: read.data[..1].to_vec(),
+            text: "(bad)".to_owned(),
+        }),
+    }
+}
+
+// an address the user typed; the name of a function, or an expression
+fn address_of(target: &dyn Target, elf: &ParsedELF, text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    if let Some(address) = elf
+        .symbol_table
+        .get_function_symbol(text)
+        .and_then(|symbol| symbol.value)
+    {
+        return Ok(address.get() as u64);
+    }
+    Expression::parse(text)?.evaluate(&InferiorContext::new(target, elf))
+}
+
+// the name and the addresses of the function that `address` is in
+fn function_containing(elf: &ParsedELF, address: u64) -> Result<(String, u64, u64), String> {
+    let (name, _) = elf
+        .symbol_table
+        .symbolize(address as usize)
+        .ok_or("No function contains specified address.")?;
+    let symbol = elf
+        .symbol_table
+        .get_function_symbol(name)
+        .filter(|symbol| symbol.size > 0)
+        .ok_or("No function contains specified address.")?;
+    let start = symbol.value.map_or(0, |value| value.get()) as u64;
+    Ok((name.to_owned(), start, start + symbol.size as u64))
+}
+
+/// The `disassemble` command. `modifiers` are r, to show the bytes of the instructions, and s, to show the source
+/// lines they're from.
+pub fn disassemble(
+    target: &dyn Target,
+    elf: &ParsedELF,
+    lines: Option<&LineTable>,
+    syntax: Syntax,
+    modifiers: &str,
+    argument: &str,
+) -> Result<Vec<String>, String> {
+    let mut raw = false;
+    let mut with_source = false;
+    for modifier in modifiers.chars() {
+        match modifier {
+            'r' => raw = true,
+            's' | 'm' => with_source = true,
+            _ => return Err("Invalid disassembly modifier.".to_owned()),
+        }
+    }
+    let pc = target.read_registers().ok().map(|registers| registers.pc());
+    // a range shows whole labels, a function the offsets into it
+    let (header, function, start, end) = match argument.split_once(',') {
+        Some((start, end)) => {
+            let start = address_of(target, elf, start)?;
+            let end = match end.trim().strip_prefix('+') {
+                Some(length) => start + address_of(target, elf, length)?,
+                None => address_of(target, elf, end)?,
+            };
+            (
+                format!("Dump of assembler code from 0x{:x} to 0x{:x}:", start, end),
+                None,
+                start,
+                end,
+            )
+        }
+        None => {
+            let address = match argument.trim() {
+                "" => pc.ok_or("No frame selected.")?,
+                argument => address_of(target, elf, argument)?,
+            };
+            let (name, start, end) = function_containing(elf, address)?;
+            (
+                format!("Dump of assembler code for function {}:", name),
+                Some(start),
+                start,
+                end,
+            )
+        }
+    };
+
+    let mut output = vec![header];
+    let mut previous_line: Option<(String, u32)> = None;
+    let mut address = start;
+    while address < end {
+        let decoded = match decode_at(target, elf, syntax, address) {
+            Ok(decoded) => decoded,
+            Err(unreadable) => {
+                return Err(format!(
+                    "Cannot access memory at address 0x{:x}",
+                    unreadable
+                ))
+            }
+        };
+        if with_source {
+            if let Some((file, line)) = lines.and_then(|lines| {
+                let row = lines.row_at(address as usize)?;
+                Some((lines.file_name(row)?.to_owned(), row.line()))
+            }) {
+                if previous_line.as_ref() != Some(&(file.clone(), line)) {
+                    if previous_line.is_some() {
+                        output.push(String::new());
+                    }
+                    if previous_line.as_ref().map(|(previous, _)| previous) != Some(&file) {
+                        output.push(format!("{}:", file));
+                    }
+                    output.push(match crate::source::source_line(elf, &file, line) {
+                        Some(text) => format!("{}\t{}", line, text),
+                        None => format!("{}\tin {}", line, file),
+                    });
+                    previous_line = Some((file, line));
+                }
+            }
+        }
+        let label = match function {
+            Some(function) => format!(" <+{}>", address - function),
+            None => elf
+                .symbol_table
+                .symbolize(address as usize)
+                .map(|(name, offset)| format!(" <{}+{}>", name, offset))
+                .unwrap_or_default(),
+        };
+        let bytes = if raw {
+            let hex: Vec<String> = decoded
+                .bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            format!("{}\t", hex.join(" "))
+        } else {
+            String::new()
+        };
+        output.push(format!(
+            "{}0x{:016x}{}:\t{}{}",
+            pc_marker(pc, address),
+            address,
+            label,
+            bytes,
+            decoded.text
+        ));
+        address += decoded.bytes.len() as u64;
+    }
+    output.push("End of assembler dump.".to_owned());
+    Ok(output)
+}
@@ -0,0 +1,359 @@
+//! The `x` command; examine memory, gdb style. `x/[count][format][size] [address expression]`
+use midas::{disassemble::Syntax, elf::ParsedELF, expression::Expression, target::Target, types::Address};
+
+pub use midas::frame::{format_float, format_variable, InferiorContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Hex,
+    Decimal,
+    Unsigned,
+    Octal,
+    Binary,
+    Char,
+    String,
+    Float,
+    Address,
+    Instruction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    Byte = 1,
+    HalfWord = 2,
+    Word = 4,
+    Giant = 8,
+}
+
+impl Size {
+    fn bytes(&self) -> usize {
+        *self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExamineFormat {
+    pub count: usize,
+    pub format: Format,
+    pub size: Size,
+}
+
+impl Default for ExamineFormat {
+    fn default() -> Self {
+        ExamineFormat {
+            count: 1,
+            format: Format::Hex,
+            size: Size::Word,
+        }
+    }
+}
+
+impl ExamineFormat {
+    /// Parses the part after the /, i.e. the "16xb" of x/16xb. Format and size that aren't given, are taken from
+    /// `previous`, like gdb does; the count always defaults to 1.
+    pub fn parse(spec: &str, previous: &ExamineFormat) -> Result<ExamineFormat, String> {
+        let digits = spec.chars().take_while(|c| c.is_ascii_digit()).count();
+        let count = if digits == 0 {
+            1
+        } else {
+            spec[..digits]
+                .parse::<usize>()
+                .map_err(|_| format!("invalid count '{}'", &spec[..digits]))?
+        };
+        let mut format = None;
+        let mut size = None;
+        for c in spec[digits..].chars() {
+            match c {
+                'x' => format = Some(Format::Hex),
+                'd' => format = Some(Format::Decimal),
+                'u' => format = Some(Format::Unsigned),
+                'o' => format = Some(Format::Octal),
+                't' => format = Some(Format::Binary),
+                'c' => format = Some(Format::Char),
+                's' => format = Some(Format::String),
+                'f' => format = Some(Format::Float),
+                'a' => format = Some(Format::Address),
+                'i' => format = Some(Format::Instruction),
+                'b' => size = Some(Size::Byte),
+                'h' => size = Some(Size::HalfWord),
+                'w' => size = Some(Size::Word),
+                'g' => size = Some(Size::Giant),
+                _ => return Err(format!("invalid format letter '{}'", c)),
+            }
+        }
+        let format = format.unwrap_or(previous.format);
+        // some formats only make sense with certain sizes
+        let size = match format {
+            Format::Address => Size::Giant,
+            Format::String | Format::Instruction => Size::Byte,
+            Format::Char => size.unwrap_or(Size::Byte),
+            Format::Float => match size.unwrap_or(previous.size) {
+                Size::Word => Size::Word,
+                _ => Size::Giant,
+            },
+            _ => size.unwrap_or(previous.size),
+        };
+        Ok(ExamineFormat {
+            count,
+            format,
+            size,
+        })
+    }
+
+    fn items_per_line(&self) -> usize {
+        match (self.format, self.size) {
+            (Format::Binary, Size::Giant) => 1,
+            (Format::Binary, _) => 2,
+            (_, Size::Byte) | (_, Size::HalfWord) => 8,
+            (_, Size::Word) => 4,
+            (_, Size::Giant) => 2,
+        }
+    }
+}
+
+/// Keeps the state between invocations of `x`; the last format used, and where the last examine ended, so that a
+/// plain `x` continues where the previous one left off.
+#[derive(Default)]
+pub struct Examiner {
+    last_format: ExamineFormat,
+    next_address: Option<usize>,
+}
+
+/// The symbol `address` is in, as <symbol+offset>
+pub fn symbol_offset(elf: &ParsedELF, address: usize) -> Option<String> {
+    match elf.symbol_table.symbolize(address)? {
+        (name, 0) => Some(format!("<{}>", name)),
+        (name, offset) => Some(format!("<{}+{}>", name, offset)),
+    }
+}
+
+pub fn label(elf: &ParsedELF, address: usize) -> String {
+    match symbol_offset(elf, address) {
+        Some(symbol) => format!("0x{:x} {}", address, symbol),
+        None => format!("0x{:x}", address),
+    }
+}
+
+fn escape(byte: u8) -> String {
+    match byte {
+        b'\n' => "\\n".into(),
+        b'\t' => "\\t".into(),
+        b'\r' => "\\r".into(),
+        0 => "\\000".into(),
+        b'\\' => "\\\\".into(),
+        b'"' => "\\\"".into(),
+        b'\'' => "\\'".into(),
+        0x20..=0x7e => (byte as char).to_string(),
+        _ => format!("\\{:03o}", byte),
+    }
+}
+
+/// The value of `expression`, as `print` shows it; in hex if `hex`. A variable on it's own is shown as it's type
+/// says, anything else is a C long.
+pub fn print(target: &dyn Target, elf: &ParsedELF, expression: &str, hex: bool) -> Result<String, String> {
+    if expression.trim().is_empty() {
+        return Err("Argument required (expression to compute).".into());
+    }
+    let parsed = Expression::parse(expression)?;
+    let context = InferiorContext::new(target, elf);
+    let variable = match (&parsed, &context.frame) {
+        (Expression::Name(name), Some(frame)) => frame.variable(elf, name).map(|variable| (frame, variable)),
+        _ => None,
+    };
+    if let Some((frame, variable)) = variable {
+        let value = frame.value_of(target, &variable)?;
+        return Ok(if hex {
+            format!("0x{:x}", value)
+        } else {
+            format_variable(&variable, value)
+        });
+    }
+    let value = parsed.evaluate(&context)?;
+    Ok(if hex {
+        format!("0x{:x}", value)
+    } else {
+        (value as i64).to_string()
+    })
+}
+
+fn format_value(bytes: &[u8], format: Format, elf: &ParsedELF) -> String {
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let unsigned = u64::from_le_bytes(buf);
+    let bits = bytes.len() * 8;
+    // sign extend from the item size
+    let signed = ((unsigned << (64 - bits)) as i64) >> (64 - bits);
+    match format {
+        Format::Hex => format!("0x{:0width$x}", unsigned, width = bytes.len() * 2),
+        Format::Decimal => format!("{}", signed),
+        Format::Unsigned => format!("{}", unsigned),
+        Format::Octal => format!("0{:o}", unsigned),
+        Format::Binary => format!("{:0width$b}", unsigned, width = bits),
+        Format::Char => format!("{} '{}'", signed, escape(bytes[0])),
+        Format::Float if bytes.len() == 4 => format_float(f32::from_bits(unsigned as u32) as f64),
+        Format::Float => format_float(f64::from_bits(unsigned)),
+        Format::Address => label(elf, unsigned as usize),
+        Format::String | Format::Instruction => unreachable!("strings and instructions aren't fixed size values"),
+    }
+}
+
+impl Examiner {
+    /// Runs `x/<spec> <expression>` and returns the lines to display
+    pub fn examine(
+        &mut self,
+        target: &dyn Target,
+        elf: &ParsedELF,
+        spec: Option<&str>,
+        expression: &str,
+        syntax: Syntax,
+    ) -> Result<Vec<String>, String> {
+        let format = match spec {
+            Some(spec) => ExamineFormat::parse(spec, &self.last_format)?,
+            None => ExamineFormat {
+                count: 1,
+                ..self.last_format
+            },
+        };
+        let address = if expression.trim().is_empty() {
+            self.next_address
+                .ok_or("argument required (starting display address)")?
+        } else {
+            Expression::parse(expression)?.evaluate(&InferiorContext::new(target, elf))? as usize
+        };
+        self.last_format = format;
+        match format.format {
+            Format::String => self.examine_strings(target, elf, address, format.count),
+            Format::Instruction => Ok(self.examine_instructions(target, elf, address, format.count, syntax)),
+            _ => self.examine_values(target, elf, address, format),
+        }
+    }
+
+    fn examine_values(
+        &mut self,
+        target: &dyn Target,
+        elf: &ParsedELF,
+        address: usize,
+        format: ExamineFormat,
+    ) -> Result<Vec<String>, String> {
+        let size = format.size.bytes();
+        let read = target
+            .read_memory(Address(address), format.count * size)
+            .map_err(|_| format!("Cannot access memory at address 0x{:x}", address))?;
+        let mut lines = vec![];
+        for (line_index, line_items) in read.data.chunks(size * format.items_per_line()).enumerate() {
+            let line_address = address + line_index * size * format.items_per_line();
+            let values: Vec<String> = line_items
+                .chunks_exact(size)
+                .map(|item| format_value(item, format.format, elf))
+                .collect();
+            if !values.is_empty() {
+                lines.push(format!(
+                    "{}:\t{}",
+                    label(elf, line_address),
+                    values.join("\t")
+                ));
+            }
+        }
+        let examined = read.data.len() - read.data.len() % size;
+        self.next_address = Some(address + examined);
+        if read.is_partial() {
+            lines.push(format!(
+                "Cannot access memory at address 0x{:x}",
+                address + examined
+            ));
+        }
+        Ok(lines)
+    }
+
+    fn examine_instructions(
+        &mut self,
+        target: &dyn Target,
+        elf: &ParsedELF,
+        address: usize,
+        count: usize,
+        syntax: Syntax,
+    ) -> Vec<String> {
+        let pc = target.read_registers().ok().map(|registers| registers.pc());
+        let mut address = address as u64;
+        let mut lines = vec![];
+        for _ in 0..count {
+            match crate::disassemble::decode_at(target, elf, syntax, address) {
+                Ok(decoded) => {
+                    lines.push(format!(
+                        "{}{}:\t{}",
+                        crate::disassemble::pc_marker(pc, address),
+                        label(elf, address as usize),
+                        decoded.text
+                    ));
+                    address += decoded.bytes.len() as u64;
+                }
+                Err(unreadable) => {
+                    lines.push(format!(
+                        "Cannot access memory at address 0x{:x}",
+                        unreadable
+                    ));
+                    break;
+                }
+            }
+        }
+        self.next_address = Some(address as usize);
+        lines
+    }
+
+    fn examine_strings(
+        &mut self,
+        target: &dyn Target,
+        elf: &ParsedELF,
+        mut address: usize,
+        count: usize,
+    ) -> Result<Vec<String>, String> {
+        // strings longer than this get cut off, like gdb's "print elements" limit
+        const MAX_STRING_LENGTH: usize = 200;
+        const CHUNK: usize = 64;
+        let mut lines = vec![];
+        for _ in 0..count {
+            let mut string = vec![];
+            let mut terminated = false;
+            let mut unreadable = false;
+            while !terminated && string.len() < MAX_STRING_LENGTH {
+                let read = match target.read_memory(Address(address + string.len()), CHUNK) {
+                    Ok(read) => read,
+                    Err(_) => {
+                        unreadable = true;
+                        break;
+                    }
+                };
+                match read.data.iter().position(|b| *b == 0) {
+                    Some(end) => {
+                        string.extend_from_slice(&read.data[..end]);
+                        terminated = true;
+                    }
+                    None => string.extend_from_slice(&read.data),
+                }
+                if read.is_partial() && !terminated {
+                    unreadable = true;
+                    break;
+                }
+            }
+            string.truncate(MAX_STRING_LENGTH);
+            if unreadable && string.is_empty() {
+                lines.push(format!(
+                    "{}:\t<error: Cannot access memory at address 0x{:x}>",
+                    label(elf, address),
+                    address
+                ));
+                break;
+            }
+            let escaped: String = string.iter().map(|b| escape(*b)).collect();
+            lines.push(format!(
+                "{}:\t\"{}\"{}",
+                label(elf, address),
+                escaped,
+                if terminated { "" } else { "..." }
+            ));
+            address += string.len() + terminated as usize;
+        }
+        self.next_address = Some(address);
+        Ok(lines)
+    }
+}
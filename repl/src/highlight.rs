@@ -0,0 +1,30 @@
+//! Colors at the console. Commands are bold, what isn't one is red, and numbers and addresses have colors of their
+//! own. A command that was entered before is hinted at as it's typed again.
+use std::ops::Range;
+
+use cli::highlight::{self, Color, Highlighter, Style};
+
+use crate::commands::{self, Token};
+
+pub struct ReplHighlighter;
+
+impl Highlighter for ReplHighlighter {
+    fn highlight(&self, line: &str) -> Vec<(Range<usize>, Style)> {
+        commands::tokens(line)
+            .into_iter()
+            .map(|(range, token)| {
+                let style = match token {
+                    Token::Command => Style::color(Color::Green).bold(),
+                    Token::Unknown => Style::color(Color::Red),
+                    Token::Number => Style::color(Color::Cyan),
+                    Token::Address => Style::color(Color::Yellow),
+                };
+                (range, style)
+            })
+            .collect()
+    }
+
+    fn hint(&self, line: &str, history: &[String]) -> Option<String> {
+        highlight::history_hint(line, history)
+    }
+}
@@ -0,0 +1,371 @@
+//! The interpreters the repl talks to it's user through. The console is for a person at a terminal; the JSON
+//! interpreter is for scripts and test harnesses, and reads one command and writes one object per line, so that
+//! nothing has to scrape the text that's meant for people.
+use midas::{
+    breakpoints::{BreakpointKind, UserBreakpoint},
+    json::Value,
+    session::{Event, Location, StopReason},
+};
+use nixwrap::WaitStatus;
+use std::io::{BufRead, Write};
+
+use crate::{breakpoints::describe_spec, source::format_return_value};
+
+pub trait Interpreter {
+    /// The next command to run; None once there are no more
+    fn read_command(&mut self) -> Option<String>;
+    fn display_output(&mut self, output: &str);
+    /// What a command says when it fails
+    fn display_error(&mut self, error: &str) {
+        self.display_output(error)
+    }
+    /// Something that happened to the inferior, with what there is to know about it. The console has already shown
+    /// it as text, so only interpreters for programs report these.
+    fn report_event(&mut self, _event: &str, _body: Value) {}
+    /// What completes commands as they're typed; only people type them
+    fn set_completer(&mut self, _completer: Box<dyn cli::completion::Completer>) {}
+}
+
+impl Interpreter for cli::Prompt {
+    fn read_command(&mut self) -> Option<String> {
+        Some(self.read_input())
+    }
+
+    fn display_output(&mut self, output: &str) {
+        cli::Prompt::display_output(self, output)
+    }
+
+    fn set_completer(&mut self, completer: Box<dyn cli::completion::Completer>) {
+        cli::Prompt::set_completer(self, completer)
+    }
+}
+
+// a command the JSON interpreter is running, and what it's said so far
+struct Running {
+    id: Value,
+    command: String,
+    output: Vec<String>,
+    errors: Vec<String>,
+}
+
+/// Reads commands from stdin, either as they'd be typed, `b main`, or as an object with an id that the response
+/// and the events the command causes carry, `{"id": 1, "command": "b main"}`. Writes to stdout:
+/// - `{"type":"response","id":1,"command":"b main","success":true,"output":[...]}` once a command is done, with
+///   `"error"` too when it failed
+/// - `{"type":"event","event":"breakpoint-hit","id":2,...}` for every stop of the inferior, and for it's exit
+/// - `{"type":"event","event":"output","stream":"stdout","output":"..."}` for what the inferior writes, and for what
+///   we say outside of a command, with `"stream":"console"`
+#[derive(Default)]
+pub struct JsonInterpreter {
+    running: Option<Running>,
+}
+
+// objects are written whole, one per line, as the inferior's output is written from other threads
+fn write_line(value: &Value) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", value);
+    let _ = stdout.flush();
+}
+
+fn output_event(stream: &str, output: &str) -> Value {
+    Value::object(vec![
+        ("type", "event".into()),
+        ("event", "output".into()),
+        ("stream", stream.into()),
+        ("output", output.into()),
+    ])
+}
+
+impl JsonInterpreter {
+    // responds to the command that's running, if there is one
+    fn finish(&mut self) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        let mut response = Value::object(vec![
+            ("type", "response".into()),
+            ("id", running.id),
+            ("command", running.command.into()),
+            ("success", running.errors.is_empty().into()),
+            (
+                "output",
+                Value::Array(running.output.into_iter().map(Value::from).collect()),
+            ),
+        ]);
+        if !running.errors.is_empty() {
+            response.insert("error", running.errors.join("\n").into());
+        }
+        write_line(&response);
+    }
+}
+
+// the id and command of a request object
+fn parse_request(line: &str) -> Result<(Value, String), (Value, String)> {
+    let request = Value::parse(line).map_err(|err| (Value::Null, err))?;
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    match request.get("command").and_then(Value::as_str) {
+        Some(command) => Ok((id, command.to_owned())),
+        None => Err((id, "the request has no command".into())),
+    }
+}
+
+impl Interpreter for JsonInterpreter {
+    fn read_command(&mut self) -> Option<String> {
+        self.finish();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if std::io::stdin().lock().read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            let text = line.trim();
+            if text.is_empty() {
+                continue;
+            }
+            let (id, command) = if text.starts_with('{') {
+                match parse_request(text) {
+                    Ok(request) => request,
+                    Err((id, err)) => {
+                        self.running = Some(Running {
+                            id,
+                            command: String::new(),
+                            output: vec![],
+                            errors: vec![err],
+                        });
+                        self.finish();
+                        continue;
+                    }
+                }
+            } else {
+                (Value::Null, text.to_owned())
+            };
+            self.running = Some(Running {
+                id,
+                command: command.clone(),
+                output: vec![],
+                errors: vec![],
+            });
+            return Some(command);
+        }
+    }
+
+    fn display_output(&mut self, output: &str) {
+        match &mut self.running {
+            Some(running) => running.output.push(output.to_owned()),
+            None => write_line(&output_event("console", output)),
+        }
+    }
+
+    fn display_error(&mut self, error: &str) {
+        match &mut self.running {
+            Some(running) => running.errors.push(error.to_owned()),
+            None => write_line(&output_event("console", error)),
+        }
+    }
+
+    fn report_event(&mut self, event: &str, body: Value) {
+        let id = self
+            .running
+            .as_ref()
+            .map_or(Value::Null, |running| running.id.clone());
+        let mut message = Value::object(vec![
+            ("type", "event".into()),
+            ("event", event.into()),
+            ("id", id),
+        ]);
+        if let Value::Object(members) = body {
+            for (key, value) in members {
+                message.insert(&key, value);
+            }
+        }
+        write_line(&message);
+    }
+}
+
+// the last command is answered when there won't be another, after `quit`
+impl Drop for JsonInterpreter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Makes the inferior of `command` read nothing and have what it writes reported as output events, so that it
+/// neither takes the commands meant for us nor writes in between our objects
+pub fn capture_output(command: &mut std::process::Command) -> Result<(), String> {
+    command.stdin(std::process::Stdio::null());
+    for stream in ["stdout", "stderr"] {
+        let (reader, writer) = std::io::pipe().map_err(|err| err.to_string())?;
+        match stream {
+            "stdout" => command.stdout(writer),
+            _ => command.stderr(writer),
+        };
+        std::thread::spawn(move || {
+            let mut reader = std::io::BufReader::new(reader);
+            let mut line = vec![];
+            while reader
+                .read_until(b'\n', &mut line)
+                .is_ok_and(|read| read > 0)
+            {
+                let text = String::from_utf8_lossy(&line);
+                write_line(&output_event(stream, text.trim_end_matches('\n')));
+                line.clear();
+            }
+        });
+    }
+    Ok(())
+}
+
+fn location(location: &Location) -> Value {
+    Value::object(vec![
+        ("pc", location.pc.into()),
+        ("function", location.function.clone().into()),
+        ("file", location.file.clone().into()),
+        ("line", location.line.into()),
+    ])
+}
+
+fn breakpoint(breakpoint: &UserBreakpoint) -> Value {
+    let (kind, expression) = match &breakpoint.kind {
+        BreakpointKind::Software => ("breakpoint", None),
+        BreakpointKind::Hardware => ("hw breakpoint", None),
+        BreakpointKind::Watchpoint { expression, .. } => ("watchpoint", Some(expression.as_str())),
+    };
+    Value::object(vec![
+        ("number", breakpoint.number.into()),
+        ("type", kind.into()),
+        ("location", describe_spec(&breakpoint.spec).into()),
+        ("expression", expression.into()),
+        (
+            "addresses",
+            Value::Array(
+                breakpoint
+                    .locations
+                    .iter()
+                    .map(|address| address.value().into())
+                    .collect(),
+            ),
+        ),
+        ("pending", breakpoint.is_pending().into()),
+        ("enabled", breakpoint.enabled.into()),
+        ("temporary", breakpoint.temporary.into()),
+        ("condition", breakpoint.condition.clone().into()),
+        ("ignore_count", breakpoint.ignore_count.into()),
+        ("hit_count", breakpoint.hit_count.into()),
+    ])
+}
+
+/// The event of the session as interpreters for programs report it, and what there is to know about it; None for
+/// the ones that are only text. Stops at breakpoints and watchpoints are `breakpoint-hit` events, and ones by a
+/// signal are `signal` events. Steps, `until` and `finish` getting where they were going are `stopped` events, that
+/// give the reason. When the inferior is gone, it's an `exited` event.
+pub fn event(event: &Event) -> Option<(&'static str, Value)> {
+    let (reason, location_of_stop) = match event {
+        Event::Warning(_) => return None,
+        Event::BreakpointCreated(created) => {
+            return Some((
+                "breakpoint-created",
+                Value::object(vec![("breakpoint", breakpoint(created))]),
+            ))
+        }
+        Event::BreakpointModified(modified) => {
+            return Some((
+                "breakpoint-modified",
+                Value::object(vec![("breakpoint", breakpoint(modified))]),
+            ))
+        }
+        Event::BreakpointDeleted(number) => {
+            return Some((
+                "breakpoint-deleted",
+                Value::object(vec![("number", (*number).into())]),
+            ))
+        }
+        Event::Exited(WaitStatus::ExitedNormally(_, code)) => {
+            return Some((
+                "exited",
+                Value::object(vec![("exit_code", (*code as i64).into())]),
+            ))
+        }
+        Event::Exited(WaitStatus::Killed(_, signal)) => {
+            return Some((
+                "exited",
+                Value::object(vec![
+                    ("signal", signal.name().into()),
+                    ("description", signal.description().into()),
+                ]),
+            ))
+        }
+        Event::Exited(_) => return Some(("exited", Value::object(vec![("core_dumped", true.into())]))),
+        Event::Stopped {
+            reason, location, ..
+        } => (reason, location),
+    };
+    let location = ("location", location(location_of_stop));
+    Some(match reason {
+        StopReason::Breakpoint {
+            breakpoints,
+            condition_errors,
+        } => {
+            let numbers = breakpoints
+                .iter()
+                .map(|breakpoint| breakpoint.number)
+                .chain(condition_errors.iter().map(|(number, _)| *number));
+            let mut body = Value::object(vec![
+                (
+                    "breakpoints",
+                    Value::Array(numbers.map(Value::from).collect()),
+                ),
+                location,
+            ]);
+            if !condition_errors.is_empty() {
+                let errors = condition_errors
+                    .iter()
+                    .map(|(number, err)| {
+                        Value::object(vec![
+                            ("breakpoint", (*number).into()),
+                            ("error", err.as_str().into()),
+                        ])
+                    })
+                    .collect();
+                body.insert("condition_errors", Value::Array(errors));
+            }
+            ("breakpoint-hit", body)
+        }
+        StopReason::Watchpoint {
+            watchpoint,
+            old_value,
+            new_value,
+        } => (
+            "breakpoint-hit",
+            Value::object(vec![
+                ("breakpoints", Value::Array(vec![watchpoint.number.into()])),
+                ("old_value", (*old_value).into()),
+                ("new_value", (*new_value).into()),
+                location,
+            ]),
+        ),
+        StopReason::Signal(signal) => (
+            "signal",
+            Value::object(vec![
+                ("signal", signal.name().into()),
+                ("description", signal.description().into()),
+                location,
+            ]),
+        ),
+        StopReason::EndSteppingRange => (
+            "stopped",
+            Value::object(vec![("reason", "end-stepping-range".into()), location]),
+        ),
+        StopReason::LocationReached => (
+            "stopped",
+            Value::object(vec![("reason", "location-reached".into()), location]),
+        ),
+        StopReason::FunctionFinished { value } => (
+            "stopped",
+            Value::object(vec![
+                ("reason", "function-finished".into()),
+                location,
+                ("value", value.as_ref().and_then(format_return_value).into()),
+            ]),
+        ),
+    })
+}
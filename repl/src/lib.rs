@@ -0,0 +1,421 @@
+extern crate cli;
+extern crate linuxwrapper as nixwrap;
+extern crate midas;
+use midas::{
+    breakpoints::BreakpointTable,
+    commands::Command,
+    session::{Event, Session, StopReason},
+    shared_library::SharedLibraries,
+    target::{
+        self,
+        coredump::{self, CoreTarget},
+        Target,
+    },
+};
+use std::sync::mpsc::Receiver;
+mod breakpoints;
+mod commands;
+mod completion;
+mod disassemble;
+mod examine;
+mod highlight;
+mod interpreter;
+mod process;
+mod source;
+
+use commands::{Info, ReplCommand};
+
+/// Has the session do `command`, and shows what came of it. Returns whether it succeeded.
+fn run(
+    p: &mut dyn interpreter::Interpreter,
+    session: &mut Session,
+    events: &Receiver<Event>,
+    command: Command,
+) -> bool {
+    let result = session.handle(command);
+    report_events(p, session, events);
+    if let Err(err) = &result {
+        p.display_error(err);
+    }
+    result.is_ok()
+}
+
+/// Shows the events the session has sent; as text, and to interpreters for programs, as they are
+fn report_events(p: &mut dyn interpreter::Interpreter, session: &Session, events: &Receiver<Event>) {
+    for event in events.try_iter() {
+        for line in describe_event(&event, session) {
+            p.display_output(&line);
+        }
+        if let Some((name, body)) = interpreter::event(&event) {
+            p.report_event(name, body);
+        }
+    }
+}
+
+// the numbers of `delete`, `enable` and `disable`; no numbers means all breakpoints
+fn all_if_none(numbers: Vec<usize>, breakpoints: &BreakpointTable) -> Vec<usize> {
+    match numbers.is_empty() {
+        true => breakpoints.iter().map(|bp| bp.number).collect(),
+        false => numbers,
+    }
+}
+
+// the most commands the history keeps
+const HISTORY_SIZE: usize = 1000;
+
+/// Where the commands typed at the console are kept between sessions; $MIDAS_HISTORY, or ~/.midas_history
+fn history_file() -> Option<std::path::PathBuf> {
+    match std::env::var_os("MIDAS_HISTORY") {
+        Some(file) => Some(file.into()),
+        None => Some(std::path::Path::new(&std::env::var_os("HOME")?).join(".midas_history")),
+    }
+}
+
+/// Takes the value of option `--NAME=VALUE` out of `args`, if it's given before the inferior's arguments. Options
+/// aren't files, so they're taken out before the arguments are looked at.
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let prefix = format!("--{}=", name);
+    let options = args
+        .iter()
+        .position(|item| item == "--")
+        .unwrap_or(args.len());
+    let index = args[..options]
+        .iter()
+        .position(|arg| arg.starts_with(&prefix))?;
+    Some(args.remove(index)[prefix.len()..].to_owned())
+}
+
+/// Debugs the program of the command line `args`, as the repl does when it's run with them; `args[0]` is the name
+/// it's run as. The server runs it for `server --interpreter=json`.
+pub fn start(mut args: Vec<String>) -> Result<(), String> {
+    // like gdb, --interpreter=NAME chooses how we talk to the user
+    let interpreter = take_option(&mut args, "interpreter").unwrap_or_else(|| "console".to_owned());
+    let json = match &interpreter[..] {
+        "console" => false,
+        "json" => true,
+        name => return Err(format!("Interpreter `{}' unrecognized", name)),
+    };
+    // and like gdb, the program is started where it was the last time, unless --disable-randomization=off
+    let disable_randomization = match take_option(&mut args, "disable-randomization").as_deref() {
+        None | Some("on") => true,
+        Some("off") => false,
+        Some(value) => return Err(format!("\"on\" or \"off\" expected, not `{}'", value)),
+    };
+    let separator = args.iter().position(|item| item == "--");
+    let inferiors_args: Vec<&str> = if let Some(pos) = separator {
+        args.iter().skip(pos).map(|s| s.as_str()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let program_path = args
+        .get(1)
+        .ok_or("You did not provide a binary".to_owned())?;
+    // like gdb, a second file is the core dump of the program
+    let core_path = args.get(2).filter(|arg| *arg != "--");
+    let mut p: Box<dyn interpreter::Interpreter> = if json {
+        Box::new(interpreter::JsonInterpreter::default())
+    } else {
+        let mut prompt = cli::Prompt::new("midas> ")?;
+        prompt.set_highlighter(Box::new(highlight::ReplHighlighter));
+        if let Some(file) = history_file() {
+            if let Err(err) = prompt.set_history_file(&file, HISTORY_SIZE) {
+                prompt.display_output(&format!(
+                    "Can't read the history in {}: {}",
+                    file.display(),
+                    err
+                ));
+            }
+        }
+        Box::new(prompt)
+    };
+    let object = std::rc::Rc::new(midas::elf::load_object(std::path::Path::new(program_path))?);
+    let _elf = midas::elf::ParsedELF::parse_elf(&object).map_err(|e| e.description())?;
+
+    let target_: Box<dyn Target> = match core_path {
+        Some(core_path) => {
+            let core = CoreTarget::open(std::path::Path::new(core_path))?;
+            for line in process::core_summary(&core) {
+                p.display_output(&line);
+            }
+            Box::new(core)
+        }
+        None => {
+            let mut command = target::make_command_with(program_path, inferiors_args, disable_randomization)?;
+            if json {
+                interpreter::capture_output(&mut command)?;
+            }
+            let (target_, _waitstatus) =
+                midas::target::linux::LinuxTarget::launch(&mut command).map_err(|e| e.to_string())?;
+            p.display_output(&format!("spawned {}", *target_.process_id()));
+            p.report_event(
+                "started",
+                midas::json::Value::object(vec![("pid", (*target_.process_id() as i64).into())]),
+            );
+            target_
+        }
+    };
+    let (sender, events) = std::sync::mpsc::channel();
+    // the process of a core dump has all the libraries it will ever have
+    let mut session = Session::new(target_, _elf, core_path.is_none(), sender);
+    report_events(p.as_mut(), &session, &events);
+    let mut examiner = examine::Examiner::default();
+    if core_path.is_some() {
+        let lines = session.lines();
+        let frames = source::backtrace(
+            session.target(),
+            session.elf(),
+            session.breakpoints().shared_libraries(),
+            lines,
+        );
+        p.display_output(&frames.first().cloned().unwrap_or_default());
+        if let Some(line) = source::describe_line(session.target(), session.elf(), lines, false) {
+            p.display_output(&line);
+        }
+    }
+    let mut flavor = midas::disassemble::Syntax::Att;
+    // the values `print` has shown; gdb numbers them $1, $2, ...
+    let mut values_printed = 0;
+    let completer = completion::ReplCompleter::new(session.elf(), session.lines());
+    loop {
+        let numbers = session.breakpoints().iter().map(|bp| bp.number).collect();
+        p.set_completer(Box::new(completer.with_breakpoints(numbers)));
+        let Some(input) = p.read_command() else {
+            return Ok(());
+        };
+        let command = match commands::parse(&input) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(err) => {
+                p.display_error(&err);
+                continue;
+            }
+        };
+        let lines = match command {
+            ReplCommand::Examine { format, expression } => examiner.examine(
+                session.target(),
+                session.elf(),
+                format.as_deref(),
+                &expression,
+                flavor,
+            ),
+            ReplCommand::Disassemble {
+                modifiers,
+                arguments,
+            } => disassemble::disassemble(
+                session.target(),
+                session.elf(),
+                session.lines(),
+                flavor,
+                &modifiers,
+                &arguments,
+            ),
+            ReplCommand::SetDisassemblyFlavor(syntax) => {
+                flavor = syntax;
+                Ok(vec![])
+            }
+            ReplCommand::ShowDisassemblyFlavor => Ok(vec![format!(
+                "The disassembly flavor is \"{}\".",
+                match flavor {
+                    midas::disassemble::Syntax::Att => "att",
+                    midas::disassemble::Syntax::Intel => "intel",
+                }
+            )]),
+            ReplCommand::Help(lines) => Ok(lines),
+            ReplCommand::Quit => {
+                p.display_output("quitting");
+                return Ok(());
+            }
+            ReplCommand::Backtrace => Ok(source::backtrace(
+                session.target(),
+                session.elf(),
+                session.breakpoints().shared_libraries(),
+                session.lines(),
+            )),
+            ReplCommand::Print { hex, expression } => examine::print(session.target(), session.elf(), &expression, hex)
+                .map(|value| {
+                    values_printed += 1;
+                    vec![format!("${} = {}", values_printed, value)]
+                }),
+            ReplCommand::GenerateCoreFile(_) if core_path.is_some() => Err("The program is not being run.".into()),
+            ReplCommand::GenerateCoreFile(file) => {
+                let file = file.unwrap_or_else(|| format!("core.{}", *session.target().process_id()));
+                coredump::write_core(session.target(), std::path::Path::new(&file))
+                    .map(|_| vec![format!("Saved corefile {}", file)])
+                    .map_err(|err| format!("Can't create a corefile: {}", err))
+            }
+            ReplCommand::Info(Info::Breakpoints) => Ok(breakpoints::info_breakpoints(
+                session.breakpoints(),
+                session.elf(),
+            )),
+            ReplCommand::Info(Info::SharedLibraries) => Ok(info_shared_libraries(
+                session.breakpoints().shared_libraries(),
+            )),
+            ReplCommand::Info(Info::Proc) => Ok(process::info_proc(session.target())),
+            ReplCommand::Info(Info::ProcMappings) => process::info_proc_mappings(session.target()),
+            ReplCommand::Info(Info::Auxv) => process::info_auxv(session.target()),
+            ReplCommand::Session(command) => {
+                let command = match command {
+                    Command::Delete(numbers) => Command::Delete(all_if_none(numbers, session.breakpoints())),
+                    Command::Enable(numbers) => Command::Enable(all_if_none(numbers, session.breakpoints())),
+                    Command::Disable(numbers) => Command::Disable(all_if_none(numbers, session.breakpoints())),
+                    Command::Finish if session.target().read_registers().is_ok() => {
+                        p.display_output(&format!(
+                            "Run till exit from {}",
+                            source::describe_frame(session.target(), session.elf(), session.lines())
+                        ));
+                        Command::Finish
+                    }
+                    command => command,
+                };
+                // what's said once the breakpoint is changed
+                let said = match &command {
+                    Command::Condition {
+                        number,
+                        condition: None,
+                    } => Some(format!("Breakpoint {} now unconditional.", number)),
+                    Command::Ignore { number, count } => Some(match count {
+                        0 => format!("Will stop next time breakpoint {} is reached.", number),
+                        1 => format!("Will ignore next crossing of breakpoint {}.", number),
+                        count => format!(
+                            "Will ignore next {} crossings of breakpoint {}.",
+                            count, number
+                        ),
+                    }),
+                    _ => None,
+                };
+                match run(p.as_mut(), &mut session, &events, command) {
+                    true => Ok(said.into_iter().collect()),
+                    false => Ok(vec![]),
+                }
+            }
+        };
+        match lines {
+            Ok(lines) => {
+                for line in lines {
+                    p.display_output(&line);
+                }
+            }
+            Err(err) => p.display_error(&err),
+        }
+    }
+}
+/// The lines of `info sharedlibrary`; where the code of each library is, and whether we could read it's symbols
+fn info_shared_libraries(libraries: &SharedLibraries) -> Vec<String> {
+    if libraries.iter().next().is_none() {
+        return vec!["No shared libraries loaded at this time.".into()];
+    }
+    let mut lines = vec![format!(
+        "{:<19} {:<19} {:<11} {}",
+        "From", "To", "Syms Read", "Shared Object Library"
+    )];
+    for library in libraries.iter() {
+        let (from, to) = match &library.text {
+            Some(text) => (
+                format!("0x{:016x}", text.start),
+                format!("0x{:016x}", text.end),
+            ),
+            None => (String::new(), String::new()),
+        };
+        let read = match (library.symbols_read(), library.debug_info) {
+            (false, _) => "No",
+            (true, false) => "Yes (*)",
+            (true, true) => "Yes",
+        };
+        lines.push(format!(
+            "{:<19} {:<19} {:<11} {}",
+            from, to, read, library.path
+        ));
+    }
+    if libraries
+        .iter()
+        .any(|library| library.symbols_read() && !library.debug_info)
+    {
+        lines.push("(*): Shared library is missing debugging information.".into());
+    }
+    lines
+}
+
+/// What to tell the user about an event of the session
+fn describe_event(event: &Event, session: &Session) -> Vec<String> {
+    let (target, elf, lines) = (session.target(), session.elf(), session.lines());
+    let (reason, location, new_frame) = match event {
+        Event::Warning(warning) => return vec![warning.clone()],
+        Event::BreakpointCreated(breakpoint) => return breakpoints::describe_created(breakpoint),
+        Event::BreakpointModified(_) | Event::BreakpointDeleted(_) => return vec![],
+        Event::Exited(status) => {
+            return prepare_waitstatus_display_message(*status, target)
+                .into_iter()
+                .collect()
+        }
+        Event::Stopped {
+            reason,
+            location,
+            new_frame,
+        } => (reason, location, *new_frame),
+    };
+    match reason {
+        StopReason::Breakpoint {
+            breakpoints,
+            condition_errors,
+        } => breakpoints::describe_hit(
+            breakpoints,
+            condition_errors,
+            session.breakpoints().shared_libraries(),
+            elf,
+            location.pc,
+        ),
+        StopReason::Watchpoint {
+            watchpoint,
+            old_value,
+            new_value,
+        } => breakpoints::describe_watchpoint(watchpoint, *old_value, *new_value, elf, location.pc),
+        StopReason::Signal(signal) => prepare_waitstatus_display_message(
+            nixwrap::WaitStatus::Stopped(target.process_id(), *signal),
+            target,
+        )
+        .into_iter()
+        .collect(),
+        StopReason::EndSteppingRange => {
+            let mut description = source::describe_step(target, elf, lines, new_frame);
+            // instruction steps can stop where there's no line information; in the plt, say
+            if description.is_empty() {
+                description.push(source::describe_frame(target, elf, lines));
+            }
+            description
+        }
+        StopReason::LocationReached => source::describe_step(target, elf, lines, new_frame),
+        StopReason::FunctionFinished { value } => {
+            let mut description = vec![source::describe_frame(target, elf, lines)];
+            description.extend(source::describe_line(target, elf, lines, false));
+            if let Some(value) = value.as_ref().and_then(source::format_return_value) {
+                description.push(format!("Value returned is {}", value));
+            }
+            description
+        }
+    }
+}
+
+#[allow(unused)]
+fn prepare_waitstatus_display_message(status: nixwrap::WaitStatus, target: &dyn Target) -> Option<String> {
+    match status {
+        nixwrap::WaitStatus::Continued(_) => Some("Inferior continued".to_string()),
+        nixwrap::WaitStatus::ExitedNormally(_, exit_code) => Some(format!(
+            "Inferior exited normally with exit code {}",
+            exit_code
+        )),
+        nixwrap::WaitStatus::Stopped(_, signal) => match signal {
+            nixwrap::signals::Signal::Trap => match target.stopped_at_breakpoint() {
+                Some(addr) => Some(format!("Hit breakpoint @ {:X?}", addr)),
+                None => Some("Caught trap signal".to_string()),
+            },
+            signal => Some(format!(
+                "Program received signal {}, {}.",
+                signal.name(),
+                signal.description()
+            )),
+        },
+        nixwrap::WaitStatus::Killed(_, signal) => Some(format!("Inferior killed with signal {:?}", signal)),
+        nixwrap::WaitStatus::CoreDumped(_) => Some("Core dumped".to_string()),
+    }
+}
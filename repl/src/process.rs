@@ -0,0 +1,150 @@
+//! What's known about the inferior's process; `info proc`, `info proc mappings` and `info auxv`, and what a core dump
+//! says about the process that dumped it
+use midas::{
+    procfs,
+    target::{coredump::CoreTarget, Target},
+    types::Address,
+};
+
+/// What gdb says when it opens a core dump; the command that dumped it and the signal that killed it
+pub fn core_summary(core: &CoreTarget) -> Vec<String> {
+    let mut lines = vec![];
+    if let Some(command) = core.arguments().or(core.program()) {
+        lines.push(format!("Core was generated by `{}'.", command));
+    }
+    lines.push(match nixwrap::signals::Signal::from_raw(core.signal()) {
+        Ok(signal) => format!(
+            "Program terminated with signal {}, {}.",
+            signal.name(),
+            signal.description()
+        ),
+        Err(0) => "Program terminated.".to_owned(),
+        Err(number) => format!("Program terminated with signal {}.", number),
+    });
+    lines
+}
+
+/// The lines of `info proc`; the process, and what it's running
+pub fn info_proc(target: &dyn Target) -> Vec<String> {
+    let pid = target.process_id();
+    let mut lines = vec![format!("process {}", *pid)];
+    if let Ok(command_line) = procfs::command_line(pid) {
+        lines.push(format!("cmdline = '{}'", command_line.join(" ")));
+    }
+    if let Ok(directory) = procfs::working_directory(pid) {
+        lines.push(format!("cwd = '{}'", directory));
+    }
+    if let Ok(executable) = procfs::executable(pid) {
+        lines.push(format!("exe = '{}'", executable));
+    }
+    lines
+}
+
+/// The lines of `info proc mappings`; the regions of memory the inferior has mapped
+pub fn info_proc_mappings(target: &dyn Target) -> Result<Vec<String>, String> {
+    let regions = target.memory_map()?;
+    let mut lines = vec![
+        format!("process {}", *target.process_id()),
+        "Mapped address spaces:".to_owned(),
+        String::new(),
+        format!(
+            "{:>18} {:>18} {:>10} {:>10}  {:<5}  {}",
+            "Start Addr", "End Addr", "Size", "Offset", "Perms", "objfile"
+        ),
+    ];
+    for region in regions {
+        lines.push(format!(
+            "{:>18} {:>18} {:>10} {:>10}  {:<5}  {}",
+            format!("0x{:x}", region.range.start),
+            format!("0x{:x}", region.range.end),
+            format!("0x{:x}", region.range.len()),
+            format!("0x{:x}", region.offset),
+            region.permissions.to_string(),
+            region.path.unwrap_or_default()
+        ));
+    }
+    Ok(lines)
+}
+
+// how the value of an auxiliary vector entry is shown
+enum Shown {
+    Decimal,
+    Hex,
+    // the address of a nul terminated string, which is shown too
+    String,
+}
+
+// the name, description and how the value is shown, of the entries of the auxiliary vector of x86-64 linux
+fn describe_entry(key: u64) -> Option<(&'static str, &'static str, Shown)> {
+    Some(match key {
+        0 => ("AT_NULL", "End of vector", Shown::Hex),
+        3 => ("AT_PHDR", "Program headers for program", Shown::Hex),
+        4 => ("AT_PHENT", "Size of program header entry", Shown::Decimal),
+        5 => ("AT_PHNUM", "Number of program headers", Shown::Decimal),
+        6 => ("AT_PAGESZ", "System page size", Shown::Decimal),
+        7 => ("AT_BASE", "Base address of interpreter", Shown::Hex),
+        8 => ("AT_FLAGS", "Flags", Shown::Hex),
+        9 => ("AT_ENTRY", "Entry point of program", Shown::Hex),
+        11 => ("AT_UID", "Real user ID", Shown::Decimal),
+        12 => ("AT_EUID", "Effective user ID", Shown::Decimal),
+        13 => ("AT_GID", "Real group ID", Shown::Decimal),
+        14 => ("AT_EGID", "Effective group ID", Shown::Decimal),
+        15 => ("AT_PLATFORM", "String identifying platform", Shown::String),
+        16 => (
+            "AT_HWCAP",
+            "Machine-dependent CPU capability hints",
+            Shown::Hex,
+        ),
+        17 => ("AT_CLKTCK", "Frequency of times()", Shown::Decimal),
+        23 => (
+            "AT_SECURE",
+            "Boolean, was exec setuid-like?",
+            Shown::Decimal,
+        ),
+        25 => ("AT_RANDOM", "Address of 16 random bytes", Shown::Hex),
+        26 => ("AT_HWCAP2", "Extension of AT_HWCAP", Shown::Hex),
+        31 => ("AT_EXECFN", "File name of executable", Shown::String),
+        33 => (
+            "AT_SYSINFO_EHDR",
+            "System-supplied DSO's ELF header",
+            Shown::Hex,
+        ),
+        51 => (
+            "AT_MINSIGSTKSZ",
+            "Minimal stack size for signal delivery",
+            Shown::Hex,
+        ),
+        _ => return None,
+    })
+}
+
+// the nul terminated string at `address`, if it can be read
+fn read_string(target: &dyn Target, address: u64) -> Option<String> {
+    let read = target.read_memory(Address(address as usize), 256).ok()?;
+    let end = read.data.iter().position(|&byte| byte == 0)?;
+    Some(String::from_utf8_lossy(&read.data[..end]).into_owned())
+}
+
+/// The lines of `info auxv`; the auxiliary vector the kernel started the inferior with
+pub fn info_auxv(target: &dyn Target) -> Result<Vec<String>, String> {
+    let auxv = target.auxiliary_vector()?;
+    if auxv.entries.is_empty() {
+        return Err("No auxiliary vector found, or failed reading it.".to_owned());
+    }
+    // the vector ends with AT_NULL, which isn't kept
+    let entries = auxv.entries.iter().copied().chain(std::iter::once((0, 0)));
+    Ok(entries
+        .map(|(key, value)| {
+            let (name, description, shown) = describe_entry(key).unwrap_or(("???", "", Shown::Hex));
+            let value = match shown {
+                Shown::Decimal => value.to_string(),
+                Shown::Hex => format!("0x{:x}", value),
+                Shown::String => match read_string(target, value) {
+                    Some(text) => format!("0x{:x} \"{}\"", value, text),
+                    None => format!("0x{:x}", value),
+                },
+            };
+            format!("{:<4} {:<20} {:<30} {}", key, name, description, value)
+        })
+        .collect())
+}
@@ -0,0 +1,168 @@
+//! Where in the source the inferior is; the function, file and line, for reporting where stepping stopped.
+use midas::{
+    dwarf::linenumber::LineTable, elf::ParsedELF, frame::Frame, shared_library::SharedLibraries, stepping::ReturnValue,
+    target::Target,
+};
+
+/// The text of line `line` of `file`. File names without a directory are relative to the directory the program was
+/// compiled in.
+pub fn source_line(elf: &ParsedELF, file: &str, line: u32) -> Option<String> {
+    let source = std::fs::read_to_string(elf.source_path(file)?).ok()?;
+    source
+        .lines()
+        .nth(line.checked_sub(1)? as usize)
+        .map(|text| text.to_owned())
+}
+
+/// The function of `frame` with the values of it's arguments; `add (x=7)`
+pub fn function_with_arguments(target: &dyn Target, frame: &Frame) -> Option<String> {
+    let function = frame.function.as_ref()?;
+    let arguments: Vec<String> = function
+        .variables
+        .iter()
+        .filter(|variable| variable.parameter)
+        .map(|variable| match frame.value_of(target, variable) {
+            Ok(value) => format!(
+                "{}={}",
+                variable.name,
+                crate::examine::format_variable(variable, value)
+            ),
+            Err(_) => format!("{}=<optimized out>", variable.name),
+        })
+        .collect();
+    Some(format!("{} ({})", function.name, arguments.join(", ")))
+}
+
+// where `frame` is in the source, like gdb describes it; `add (x=7) at c.c:4`, with the address first unless it's
+// the innermost frame at the start of a line. None if there's no debug information for where it is.
+fn frame_in_source(target: &dyn Target, lines: Option<&LineTable>, frame: &Frame) -> Option<String> {
+    let function = function_with_arguments(target, frame)?;
+    let lines = lines?;
+    let row = lines.row_at(frame.lookup_pc())?;
+    let file = lines.file_name(row).unwrap_or("??");
+    let file = std::path::Path::new(file)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(file);
+    let address = if frame.level == 0 && lines.is_statement_start(frame.pc) {
+        String::new()
+    } else {
+        format!("0x{:016x} in ", frame.pc)
+    };
+    Some(format!(
+        "{}{} at {}:{}",
+        address,
+        function,
+        file,
+        row.line()
+    ))
+}
+
+/// The frame the inferior is in, like gdb describes it; `add (x=7) at c.c:4`
+pub fn describe_frame(target: &dyn Target, elf: &ParsedELF, lines: Option<&LineTable>) -> String {
+    let frame = match Frame::current(target, elf) {
+        Ok(frame) => frame,
+        Err(err) => return err,
+    };
+    frame_in_source(target, lines, &frame).unwrap_or_else(|| crate::examine::label(elf, frame.pc))
+}
+
+/// The lines of `bt`; the frames of the stopped inferior, like gdb shows them.
+/// `#1  0x0000000000401156 in main () at c.c:12`
+pub fn backtrace(
+    target: &dyn Target,
+    elf: &ParsedELF,
+    libraries: &SharedLibraries,
+    lines: Option<&LineTable>,
+) -> Vec<String> {
+    let frames = match midas::frame::backtrace(target, elf, libraries) {
+        Ok(frames) => frames,
+        Err(_) => return vec!["No stack.".to_owned()],
+    };
+    frames
+        .iter()
+        .map(|frame| {
+            let library = libraries.containing(frame.lookup_pc());
+            // the lines of a library are in it's own debug information
+            let lines = match library {
+                Some(library) => library.line_table(),
+                None => lines,
+            };
+            let description = frame_in_source(target, lines, frame).unwrap_or_else(|| {
+                let symbol = match library {
+                    Some(library) => library.symbolize(frame.lookup_pc()),
+                    None => elf
+                        .symbol_table
+                        .symbolize(frame.lookup_pc())
+                        .map(|(name, offset)| (name.to_owned(), offset)),
+                };
+                let name = symbol.map_or("??".to_owned(), |(name, _)| name);
+                match library {
+                    Some(library) => format!("0x{:016x} in {} () from {}", frame.pc, name, library.path),
+                    None => format!("0x{:016x} in {} ()", frame.pc, name),
+                }
+            });
+            format!("#{:<3}{}", frame.level, description)
+        })
+        .collect()
+}
+
+/// The line the inferior is at, `4\t    int doubled = x * 2;`, with the address first if it's not at the start of
+/// it and `with_address`
+pub fn describe_line(
+    target: &dyn Target,
+    elf: &ParsedELF,
+    lines: Option<&LineTable>,
+    with_address: bool,
+) -> Option<String> {
+    let pc = target.read_registers().ok()?.pc() as usize;
+    let lines = lines?;
+    let row = lines.row_at(pc)?;
+    let file = lines.file_name(row)?;
+    let address = if with_address && !lines.is_statement_start(pc) {
+        format!("0x{:016x}\t", pc)
+    } else {
+        String::new()
+    };
+    Some(match source_line(elf, file, row.line()) {
+        Some(text) => format!("{}{}\t{}", address, row.line(), text),
+        None => format!(
+            "{}{}\t{}: No such file or directory.",
+            address,
+            row.line(),
+            file
+        ),
+    })
+}
+
+/// Where a step stopped; the frame, if it's a `new_frame`, and the line
+pub fn describe_step(target: &dyn Target, elf: &ParsedELF, lines: Option<&LineTable>, new_frame: bool) -> Vec<String> {
+    let mut description = vec![];
+    if new_frame {
+        description.push(describe_frame(target, elf, lines));
+    }
+    description.extend(describe_line(target, elf, lines, !new_frame));
+    description
+}
+
+/// How `finish` shows a return value; None for functions that return nothing
+pub fn format_return_value(value: &ReturnValue) -> Option<String> {
+    match value {
+        ReturnValue::Void => None,
+        ReturnValue::Integer {
+            value,
+            signed: true,
+        } => Some((*value as i64).to_string()),
+        ReturnValue::Integer { value, .. } => Some(value.to_string()),
+        ReturnValue::Float(value) => Some(crate::examine::format_float(*value)),
+        ReturnValue::Bytes(bytes) => Some(format!(
+            "{{{}}}",
+            bytes
+                .iter()
+                .map(|byte| format!("0x{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        ReturnValue::Unknown => Some("<the type of the value is not supported>".into()),
+    }
+}
@@ -0,0 +1,181 @@
+use midas::json::Value;
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::Once,
+};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../midas/tests/subjects")
+    };
+}
+
+const STEPPING: &str = concat!(tests_dir!(), "/executables/stepping");
+const REPL: &str = env!("CARGO_BIN_EXE_repl");
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .arg("stepping")
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// the repl, driven through it's JSON interpreter so that what each command said can be told apart
+struct Repl {
+    repl: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+}
+
+impl Repl {
+    fn start() -> Repl {
+        let mut repl = Command::new(REPL)
+            .args(["--interpreter=json", STEPPING])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        Repl {
+            input: repl.stdin.take().unwrap(),
+            output: BufReader::new(repl.stdout.take().unwrap()),
+            repl,
+        }
+    }
+
+    // the output of `command`, or what was wrong with it
+    fn send(&mut self, command: &str) -> Result<Vec<String>, String> {
+        writeln!(self.input, "{}", command).unwrap();
+        loop {
+            let mut line = String::new();
+            assert!(
+                self.output.read_line(&mut line).unwrap() > 0,
+                "the repl quit"
+            );
+            let message = Value::parse(&line).unwrap();
+            if message.get("type").and_then(Value::as_str) != Some("response") {
+                continue;
+            }
+            return match message.get("error").and_then(Value::as_str) {
+                Some(error) => Err(error.to_owned()),
+                None => Ok(message
+                    .get("output")
+                    .and_then(Value::as_array)
+                    .unwrap()
+                    .iter()
+                    .map(|line| line.as_str().unwrap().to_owned())
+                    .collect()),
+            };
+        }
+    }
+
+    fn quit(mut self) {
+        assert_eq!(self.send("quit"), Ok(vec!["quitting".to_owned()]));
+        assert!(self.repl.wait().unwrap().success());
+    }
+}
+
+#[test]
+pub fn commands_can_be_abbreviated() {
+    compile_subject();
+    let mut repl = Repl::start();
+    let help = repl.send("help").unwrap();
+    assert_eq!(help[0], "List of commands:");
+    assert!(help.iter().any(|line| line.starts_with("disassemble -- ")));
+
+    assert_eq!(
+        repl.send("di add"),
+        Err("Ambiguous command \"di\": disable, disassemble.".into())
+    );
+    assert_eq!(
+        repl.send("frobnicate"),
+        Err("Undefined command: \"frobnicate\".  Try \"help\".".into())
+    );
+    let disassembly = repl.send("disas add").unwrap();
+    assert_eq!(disassembly[0], "Dump of assembler code for function add:");
+    // aliases win over abbreviations; b is break, not backtrace
+    assert!(repl.send("b add").unwrap()[0].starts_with("Breakpoint 1 set"));
+    assert_eq!(
+        repl.send("help b").unwrap()[0],
+        "break LOCATION [if CONDITION]"
+    );
+    assert!(repl.send("i b").unwrap()[1].contains("<add+"));
+    assert!(repl
+        .send("help info")
+        .unwrap()
+        .contains(&"List of info subcommands:".to_owned()));
+    assert_eq!(
+        repl.send("info frobnicate"),
+        Err("Undefined info command: \"frobnicate\".  Try \"help info\".".into())
+    );
+    assert_eq!(
+        repl.send("step/x"),
+        Err("Undefined command: \"step/x\".  Try \"help\".".into())
+    );
+    repl.quit();
+}
+
+#[test]
+pub fn arguments_are_quoted_numbers_and_locations() {
+    compile_subject();
+    let mut repl = Repl::start();
+    assert_eq!(repl.send("p/x 0o17"), Ok(vec!["$1 = 0xf".to_owned()]));
+    assert_eq!(repl.send("p/x 0X1F"), Ok(vec!["$2 = 0x1f".to_owned()]));
+    assert!(repl.send("b stepping.c:9").unwrap()[0].starts_with("Breakpoint 1 set"));
+    assert!(repl.send(r#"b "stepping.c:10" if 1"#).unwrap()[0].starts_with("Breakpoint 2 set"));
+    assert!(repl.send("tbreak *0x401000").unwrap()[0].starts_with("Temporary breakpoint 3 set @ Address(0x401000)"));
+    assert_eq!(
+        repl.send("b \"stepping.c:9"),
+        Err("Unterminated quoted string.".into())
+    );
+    assert_eq!(
+        repl.send("b add now"),
+        Err("Junk at end of arguments to \"break\": \"now\".".into())
+    );
+    assert!(repl.send("b 9").unwrap_err().contains("FILE:9"));
+    assert_eq!(repl.send("step 2z"), Err("Invalid number \"2z\".".into()));
+    assert_eq!(
+        repl.send("step 0X+2"),
+        Err("Invalid number \"0X+2\".".into())
+    );
+    assert_eq!(
+        repl.send("d 3 x"),
+        Err("Bad breakpoint argument: 'x'".into())
+    );
+    // _init, at 0x401000, runs before main
+    assert_eq!(repl.send("d 0b11"), Ok(vec![]));
+
+    assert!(repl.send("run").unwrap()[0].starts_with("Breakpoint 1, "));
+    assert_eq!(
+        repl.send("ignore 1 0x2"),
+        Ok(vec![
+            "Will ignore next 2 crossings of breakpoint 1.".to_owned()
+        ])
+    );
+    assert_eq!(
+        repl.send("ignore 1 0X3"),
+        Ok(vec![
+            "Will ignore next 3 crossings of breakpoint 1.".to_owned()
+        ])
+    );
+    assert_eq!(
+        repl.send("ignore 1 0B0"),
+        Ok(vec![
+            "Will stop next time breakpoint 1 is reached.".to_owned()
+        ])
+    );
+    assert_eq!(
+        repl.send("condition 2"),
+        Ok(vec!["Breakpoint 2 now unconditional.".to_owned()])
+    );
+    repl.quit();
+}
@@ -0,0 +1,289 @@
+use midas::json::Value;
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::Once,
+};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../midas/tests/subjects")
+    };
+}
+
+const STEPPING: &str = concat!(tests_dir!(), "/executables/stepping");
+const STEPPING_PIE: &str = concat!(tests_dir!(), "/executables/stepping_pie");
+const CRASH: &str = concat!(tests_dir!(), "/executables/crash");
+const REPL: &str = env!("CARGO_BIN_EXE_repl");
+
+// lines of stepping.c
+const ADD_BODY_LINE: i64 = 9;
+const ADD_TOTAL_LINE: i64 = 10;
+
+fn compile_subjects() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .args(["stepping", "stepping_pie", "crash"])
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// a script, driving the repl
+struct Script {
+    repl: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    // the events that arrived, that haven't been looked at yet
+    events: Vec<Value>,
+}
+
+impl Script {
+    fn start(args: &[&str]) -> Script {
+        let mut repl = Command::new(REPL)
+            .arg("--interpreter=json")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        Script {
+            input: repl.stdin.take().unwrap(),
+            output: BufReader::new(repl.stdout.take().unwrap()),
+            repl,
+            events: vec![],
+        }
+    }
+
+    fn read(&mut self) -> Option<Value> {
+        let mut line = String::new();
+        if self.output.read_line(&mut line).unwrap() == 0 {
+            return None;
+        }
+        Some(Value::parse(&line).unwrap_or_else(|err| panic!("{:?} isn't JSON: {}", line, err)))
+    }
+
+    // sends `line` and returns the response to it, keeping the events that come before it
+    fn send(&mut self, line: &str) -> Value {
+        writeln!(self.input, "{}", line).unwrap();
+        loop {
+            let message = self.read().expect("the repl quit");
+            match message.get("type").and_then(Value::as_str) {
+                Some("response") => return message,
+                _ => self.events.push(message),
+            }
+        }
+    }
+
+    fn command(&mut self, id: i64, command: &str) -> Value {
+        let response = self.send(&Value::object(vec![("id", id.into()), ("command", command.into())]).to_string());
+        assert_eq!(response.get("id"), Some(&Value::Integer(id)));
+        assert_eq!(
+            response.get("command").and_then(Value::as_str),
+            Some(command)
+        );
+        response
+    }
+
+    // the events named `name` that have arrived
+    fn take_events(&mut self, name: &str) -> Vec<Value> {
+        let (events, rest) = std::mem::take(&mut self.events)
+            .into_iter()
+            .partition(|event| event.get("event").and_then(Value::as_str) == Some(name));
+        self.events = rest;
+        events
+    }
+
+    fn quit(mut self) {
+        let response = self.send("quit");
+        assert_eq!(response.get("success"), Some(&Value::Bool(true)));
+        assert!(self.repl.wait().unwrap().success());
+    }
+}
+
+fn line_of(event: &Value) -> Option<i64> {
+    event.get("location")?.get("line")?.as_i64()
+}
+
+fn function_of(event: &Value) -> Option<&str> {
+    event.get("location")?.get("function")?.as_str()
+}
+
+#[test]
+pub fn commands_are_answered_with_objects() {
+    compile_subjects();
+    let mut script = Script::start(&[STEPPING]);
+    let started = script.read().unwrap();
+    assert_eq!(
+        started.get("stream").and_then(Value::as_str),
+        Some("console")
+    );
+    assert!(started
+        .get("output")
+        .and_then(Value::as_str)
+        .unwrap()
+        .starts_with("spawned "));
+
+    let response = script.command(1, "b add");
+    assert_eq!(response.get("success"), Some(&Value::Bool(true)));
+    let output = response.get("output").and_then(Value::as_array).unwrap();
+    assert!(
+        output[0].as_str().unwrap().starts_with("Breakpoint 1 set"),
+        "{}",
+        response
+    );
+
+    // the events a command causes carry it's id, and come before it's response
+    let response = script.command(2, "run");
+    assert_eq!(response.get("success"), Some(&Value::Bool(true)));
+    let hits = script.take_events("breakpoint-hit");
+    assert_eq!(hits.len(), 1, "{:?}", script.events);
+    assert_eq!(hits[0].get("id"), Some(&Value::Integer(2)));
+    assert_eq!(
+        hits[0].get("breakpoints"),
+        Some(&Value::Array(vec![Value::Integer(1)]))
+    );
+    assert_eq!(function_of(&hits[0]), Some("add"));
+    assert_eq!(line_of(&hits[0]), Some(ADD_BODY_LINE));
+
+    script.command(3, "next");
+    let stops = script.take_events("stopped");
+    assert_eq!(stops.len(), 1);
+    assert_eq!(
+        stops[0].get("reason").and_then(Value::as_str),
+        Some("end-stepping-range")
+    );
+    assert_eq!(line_of(&stops[0]), Some(ADD_TOTAL_LINE));
+
+    script.command(4, "finish");
+    let stops = script.take_events("stopped");
+    assert_eq!(
+        stops[0].get("reason").and_then(Value::as_str),
+        Some("function-finished")
+    );
+    assert_eq!(stops[0].get("value").and_then(Value::as_str), Some("2"));
+    assert_eq!(function_of(&stops[0]), Some("main"));
+
+    script.command(5, "delete");
+    script.command(6, "run");
+    let exits = script.take_events("exited");
+    assert_eq!(exits.len(), 1);
+    assert_eq!(exits[0].get("id"), Some(&Value::Integer(6)));
+    assert_eq!(exits[0].get("exit_code"), Some(&Value::Integer(0)));
+    script.quit();
+}
+
+// where `program` stops at a breakpoint on add, started with `options`
+fn add_address(options: &[&str], program: &str) -> i64 {
+    let mut script = Script::start(&[options, &[program]].concat());
+    script.command(1, "b add");
+    script.command(2, "run");
+    let hits = script.take_events("breakpoint-hit");
+    assert_eq!(hits.len(), 1, "{:?}", script.events);
+    assert_eq!(function_of(&hits[0]), Some("add"));
+    script.command(3, "kill");
+    script.quit();
+    hits[0]
+        .get("location")
+        .and_then(|location| location.get("pc"))
+        .and_then(Value::as_i64)
+        .unwrap()
+}
+
+#[test]
+pub fn randomization_is_disabled_unless_asked() {
+    compile_subjects();
+    // a position independent program is loaded at the same address every run, like gdb does it
+    assert_eq!(
+        add_address(&[], STEPPING_PIE),
+        add_address(&[], STEPPING_PIE)
+    );
+    assert_eq!(
+        add_address(&["--disable-randomization=on"], STEPPING_PIE),
+        add_address(&[], STEPPING_PIE)
+    );
+    // and wherever the kernel puts it otherwise, where it's breakpoints are found all the same
+    add_address(&["--disable-randomization=off"], STEPPING_PIE);
+
+    let repl = Command::new(REPL)
+        .args(["--disable-randomization=maybe", STEPPING])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(!repl.success());
+}
+
+#[test]
+pub fn failures_are_responses_too() {
+    compile_subjects();
+    let mut script = Script::start(&[STEPPING]);
+    let response = script.command(1, "b");
+    assert_eq!(response.get("success"), Some(&Value::Bool(false)));
+    assert!(response
+        .get("error")
+        .and_then(Value::as_str)
+        .unwrap()
+        .contains("requires parameters"));
+
+    // without an id, the response has none
+    let response = script.send("print 1 +");
+    assert_eq!(response.get("id"), Some(&Value::Null));
+    assert_eq!(response.get("success"), Some(&Value::Bool(false)));
+
+    let response = script.send(r#"{"id": "a", "command": 7}"#);
+    assert_eq!(response.get("id").and_then(Value::as_str), Some("a"));
+    assert_eq!(response.get("success"), Some(&Value::Bool(false)));
+    let response = script.send("{");
+    assert_eq!(response.get("success"), Some(&Value::Bool(false)));
+    script.quit();
+
+    let status = Command::new(REPL)
+        .args(["--interpreter=mi", STEPPING])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+pub fn signals_and_output_are_events() {
+    compile_subjects();
+    let mut script = Script::start(&[CRASH]);
+    script.command(1, "run");
+    let signals = script.take_events("signal");
+    assert_eq!(signals.len(), 1, "{:?}", script.events);
+    assert_eq!(
+        signals[0].get("signal").and_then(Value::as_str),
+        Some("SIGSEGV")
+    );
+    assert_eq!(function_of(&signals[0]), Some("crash"));
+    script.quit();
+
+    // what the inferior writes doesn't get in between the objects
+    let mut script = Script::start(&["/bin/echo", "--", "hello"]);
+    script.command(1, "run");
+    // it's output is forwarded as it arrives, which may be after the response
+    let said_hello = |event: &Value| {
+        event.get("stream").and_then(Value::as_str) == Some("stdout")
+            && event
+                .get("output")
+                .and_then(Value::as_str)
+                .is_some_and(|output| output.ends_with("hello"))
+    };
+    while !script.events.iter().any(said_hello) {
+        let event = script.read().unwrap();
+        script.events.push(event);
+    }
+    assert_eq!(script.take_events("exited").len(), 1);
+    script.quit();
+}
@@ -0,0 +1,817 @@
+//! A server of the Debug Adapter Protocol, that editors like VS Code speak to debuggers. Messages are JSON, each
+//! preceded by a `Content-Length` header, and are requests from the editor, our responses to them and events we
+//! send when something happens, like the inferior stopping. It's over stdin and stdout, so the inferior gets neither;
+//! what it writes is sent to the editor as `output` events.
+//!
+//! Like the GDB stub, the inferior only runs while we wait for it to stop, so it can't be paused.
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+};
+
+use midas::{
+    commands::Command,
+    elf::ParsedELF,
+    expression::Expression,
+    frame::{self, format_variable, Frame, InferiorContext},
+    json::Value,
+    session::{Event, Session as Debugger, StopReason},
+    software_breakpoint::BreakpointRequest,
+    target::{self, linux::LinuxTarget, Target},
+};
+use nixwrap::{MidasSysResultDynamic, Pid, WaitStatus};
+
+const NOT_RUNNING: &str = "The program is not being run.";
+
+/// The half of the connection that messages are sent on. It's shared with the threads that forward what the
+/// inferior writes, and numbers the messages in the order they're sent.
+pub struct Channel<W: Write> {
+    writer: W,
+    sequence: i64,
+}
+
+type SharedChannel<W> = Arc<Mutex<Channel<W>>>;
+
+impl<W: Write> Channel<W> {
+    pub fn new(writer: W) -> Channel<W> {
+        Channel {
+            writer,
+            sequence: 0,
+        }
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<(), String> {
+        self.sequence += 1;
+        message.insert("seq", self.sequence.into());
+        let body = message.to_string();
+        write!(
+            self.writer,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .and_then(|_| self.writer.flush())
+        .map_err(|err| format!("Can't send to the client: {}", err))
+    }
+}
+
+fn send<W: Write>(channel: &SharedChannel<W>, message: Value) -> Result<(), String> {
+    channel.lock().map_err(|err| err.to_string())?.send(message)
+}
+
+/// Reads the next message; None once the client hangs up
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input
+            .read_line(&mut header)
+            .map_err(|err| err.to_string())?
+            == 0
+        {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("bad Content-Length: {}", value.trim()))?,
+                );
+            }
+        }
+    }
+    let length = length.ok_or("a message without a Content-Length")?;
+    let mut body = vec![0u8; length];
+    input.read_exact(&mut body).map_err(|err| err.to_string())?;
+    Value::parse(&String::from_utf8_lossy(&body)).map(Some)
+}
+
+fn response(request: &Value, result: Result<Value, String>) -> Value {
+    let mut response = Value::object(vec![
+        ("type", "response".into()),
+        (
+            "request_seq",
+            request.get("seq").cloned().unwrap_or(Value::Null),
+        ),
+        (
+            "command",
+            request.get("command").cloned().unwrap_or(Value::Null),
+        ),
+        ("success", result.is_ok().into()),
+    ]);
+    match result {
+        Ok(body) => response.insert("body", body),
+        Err(message) => {
+            response.insert("message", message.clone().into());
+            response.insert(
+                "body",
+                Value::object(vec![(
+                    "error",
+                    Value::object(vec![("id", 1i64.into()), ("format", message.into())]),
+                )]),
+            );
+        }
+    }
+    response
+}
+
+fn event(name: &str, body: Value) -> Value {
+    Value::object(vec![
+        ("type", "event".into()),
+        ("event", name.into()),
+        ("body", body),
+    ])
+}
+
+fn arguments(request: &Value) -> Value {
+    request
+        .get("arguments")
+        .cloned()
+        .unwrap_or(Value::Object(vec![]))
+}
+
+// the features of the protocol we support, beyond the basics
+fn capabilities() -> Value {
+    Value::object(vec![
+        ("supportsConfigurationDoneRequest", true.into()),
+        ("supportsConditionalBreakpoints", true.into()),
+        ("supportsEvaluateForHovers", true.into()),
+        ("supportsTerminateRequest", true.into()),
+        ("supportTerminateDebuggee", true.into()),
+    ])
+}
+
+// sends what the inferior writes to `output`, as output events of `category`, until it closes it
+fn forward_output<W: Write + Send + 'static>(
+    output: std::io::PipeReader,
+    category: &'static str,
+    channel: SharedChannel<W>,
+) {
+    std::thread::spawn(move || {
+        let mut output = std::io::BufReader::new(output);
+        let mut line = vec![];
+        while output
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            let body = Value::object(vec![
+                ("category", category.into()),
+                ("output", String::from_utf8_lossy(&line).into_owned().into()),
+            ]);
+            if send(&channel, event("output", body)).is_err() {
+                return;
+            }
+            line.clear();
+        }
+    });
+}
+
+// starts the inferior of a launch request, and returns it, with the path of it's program
+fn launch<W: Write + Send + 'static>(
+    arguments: &Value,
+    channel: &SharedChannel<W>,
+) -> MidasSysResultDynamic<(Box<dyn Target>, WaitStatus, String)> {
+    let program = arguments
+        .get("program")
+        .and_then(Value::as_str)
+        .ok_or("launch needs the program to debug")?;
+    let args: Vec<&str> = arguments
+        .get("args")
+        .and_then(Value::as_array)
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut command = target::make_command(program, args)?;
+    if let Some(cwd) = arguments.get("cwd").and_then(Value::as_str) {
+        command.current_dir(cwd);
+    }
+    // stdin and stdout are the client's
+    let (stdout, stdout_writer) = std::io::pipe().map_err(|err| err.to_string())?;
+    let (stderr, stderr_writer) = std::io::pipe().map_err(|err| err.to_string())?;
+    command
+        .stdin(std::process::Stdio::null())
+        .stdout(stdout_writer)
+        .stderr(stderr_writer);
+    let (target_, status) = LinuxTarget::launch(&mut command)?;
+    // our copies of the write ends are closed, so that the output ends once the inferior is gone
+    drop(command);
+    forward_output(stdout, "stdout", channel.clone());
+    forward_output(stderr, "stderr", channel.clone());
+    Ok((target_, status, program.to_owned()))
+}
+
+fn attach(arguments: &Value) -> MidasSysResultDynamic<(Box<dyn Target>, WaitStatus, String)> {
+    let pid = arguments
+        .get("pid")
+        .or_else(|| arguments.get("processId"))
+        .and_then(Value::as_i64)
+        .ok_or("attach needs the pid of the process to debug")?;
+    let (target_, status) = LinuxTarget::attach(Pid(pid as _))?;
+    let program = std::fs::read_link(format!("/proc/{}/exe", pid))
+        .map_err(|err| err.to_string())?
+        .to_string_lossy()
+        .into_owned();
+    Ok((target_, status, program))
+}
+
+/// Answers the requests that arrive on `input` until the client disconnects or hangs up, sending responses and
+/// events on `channel`. A session debugs the one program that a launch or attach request starts it with.
+pub fn serve<R: BufRead, W: Write + Send + 'static>(mut input: R, channel: SharedChannel<W>) -> Result<(), String> {
+    loop {
+        let request = match read_message(&mut input)? {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        let command = request.get("command").and_then(Value::as_str).unwrap_or("");
+        let started = match command {
+            "initialize" => {
+                send(&channel, response(&request, Ok(capabilities())))?;
+                continue;
+            }
+            "launch" => launch(&arguments(&request), &channel).map(|started| (started, true)),
+            "attach" => attach(&arguments(&request)).map(|started| (started, false)),
+            "disconnect" => return send(&channel, response(&request, Ok(Value::Null))),
+            _ => Err(format!("{} before launch or attach", command)),
+        };
+        let ((target_, status, program), launched) = match started {
+            Ok(started) => started,
+            Err(err) => {
+                send(&channel, response(&request, Err(err)))?;
+                continue;
+            }
+        };
+        let object = std::rc::Rc::new(midas::elf::load_object(std::path::Path::new(&program))?);
+        let elf = ParsedELF::parse_elf(&object).map_err(|err| err.description())?;
+        let mut session = Session::new(target_, status, elf, program, launched);
+        session.stop_on_entry = arguments(&request)
+            .get("stopOnEntry")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        send(&channel, response(&request, Ok(Value::Null)))?;
+        send(&channel, event("initialized", Value::Null))?;
+        return session.serve(&mut input, &channel);
+    }
+}
+
+// what to do once a request has been handled
+enum Action {
+    Respond(Result<Value, String>),
+    /// Respond, then let the inferior run, and tell the client where it stopped
+    Resume(Resume),
+    /// Respond, and end the session; killing the inferior if it's true, leaving it to run if not
+    Disconnect(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    /// Don't run it at all; it's stopped at the entry point, and the client is told so
+    Entry,
+    Continue,
+    Next,
+    StepIn,
+    StepOut,
+}
+
+struct Session<'elf> {
+    debugger: Debugger<'elf>,
+    // what the debugger reports about the commands we give it
+    events: Receiver<Event>,
+    program: String,
+    // the numbers of the breakpoints in each source file, as the last setBreakpoints for it set them
+    source_breakpoints: HashMap<String, Vec<usize>>,
+    // why the inferior last stopped
+    status: WaitStatus,
+    // whether we started the inferior, rather than attached to it
+    launched: bool,
+    stop_on_entry: bool,
+}
+
+// the variables references of the scopes of the frame at `level`; they have to be non zero
+fn arguments_reference(level: usize) -> usize {
+    2 * level + 1
+}
+
+fn locals_reference(level: usize) -> usize {
+    2 * level + 2
+}
+
+impl<'elf> Session<'elf> {
+    fn new(
+        target: Box<dyn Target>,
+        status: WaitStatus,
+        elf: ParsedELF<'elf>,
+        program: String,
+        launched: bool,
+    ) -> Session<'elf> {
+        let (sender, events) = mpsc::channel();
+        // a process that's attached to has loaded it's libraries already; libraries it loads later aren't seen
+        let debugger = Debugger::new(target, elf, launched, sender);
+        Session {
+            debugger,
+            events,
+            program,
+            source_breakpoints: HashMap::new(),
+            status,
+            launched,
+            stop_on_entry: false,
+        }
+    }
+
+    fn alive(&self) -> bool {
+        matches!(self.status, WaitStatus::Stopped(..))
+    }
+
+    fn ensure_alive(&self) -> Result<(), String> {
+        if self.alive() {
+            Ok(())
+        } else {
+            Err(NOT_RUNNING.into())
+        }
+    }
+
+    fn serve<R: BufRead, W: Write>(&mut self, input: &mut R, channel: &SharedChannel<W>) -> Result<(), String> {
+        for message in self.take_events() {
+            send(channel, message)?;
+        }
+        while let Some(request) = read_message(input)? {
+            match self.handle(&request) {
+                Action::Respond(result) => send(channel, response(&request, result))?,
+                Action::Resume(resume) => {
+                    let body = match resume {
+                        Resume::Continue => Value::object(vec![("allThreadsContinued", true.into())]),
+                        _ => Value::Null,
+                    };
+                    send(channel, response(&request, Ok(body)))?;
+                    for event in self.resume(resume) {
+                        send(channel, event)?;
+                    }
+                }
+                Action::Disconnect(terminate) => {
+                    let result = self.disconnect(terminate);
+                    send(channel, response(&request, result.map(|_| Value::Null)))?;
+                    return Ok(());
+                }
+            }
+        }
+        // a client that's gone without disconnecting takes a launched inferior with it
+        self.disconnect(self.launched)
+    }
+
+    fn handle(&mut self, request: &Value) -> Action {
+        let arguments = arguments(request);
+        let command = request.get("command").and_then(Value::as_str).unwrap_or("");
+        let running = self.ensure_alive();
+        let resume = |resume| match &running {
+            Ok(_) => Action::Resume(resume),
+            Err(err) => Action::Respond(Err(err.clone())),
+        };
+        match command {
+            "initialize" => Action::Respond(Ok(capabilities())),
+            "setBreakpoints" => Action::Respond(self.set_breakpoints(&arguments)),
+            "setExceptionBreakpoints" => Action::Respond(Ok(Value::object(vec![(
+                "breakpoints",
+                Value::Array(vec![]),
+            )]))),
+            "configurationDone" if self.stop_on_entry => resume(Resume::Entry),
+            "configurationDone" => resume(Resume::Continue),
+            "threads" => Action::Respond(Ok(self.threads())),
+            "stackTrace" => Action::Respond(running.and_then(|_| self.stack_trace(&arguments))),
+            "scopes" => Action::Respond(running.and_then(|_| self.scopes(&arguments))),
+            "variables" => Action::Respond(running.and_then(|_| self.variables(&arguments))),
+            "evaluate" => Action::Respond(running.and_then(|_| self.evaluate(&arguments))),
+            "continue" => resume(Resume::Continue),
+            "next" => resume(Resume::Next),
+            "stepIn" => resume(Resume::StepIn),
+            "stepOut" => resume(Resume::StepOut),
+            "terminate" => Action::Disconnect(true),
+            "disconnect" => Action::Disconnect(
+                arguments
+                    .get("terminateDebuggee")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(self.launched),
+            ),
+            _ => Action::Respond(Err(format!("{} is not supported", command))),
+        }
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let source = arguments
+            .get("source")
+            .ok_or("setBreakpoints needs a source")?;
+        let path = source
+            .get("path")
+            .or_else(|| source.get("name"))
+            .and_then(Value::as_str)
+            .ok_or("setBreakpoints needs the path of the source")?;
+        // the breakpoints of the file are replaced with the ones of the request
+        let numbers = self.source_breakpoints.remove(path).unwrap_or_default();
+        let _ = self.debugger.handle(Command::Delete(numbers));
+        let mut set = vec![];
+        let mut numbers = vec![];
+        for requested in arguments
+            .get("breakpoints")
+            .and_then(Value::as_array)
+            .unwrap_or(&[])
+        {
+            let line = requested.get("line").and_then(Value::as_u64).unwrap_or(0) as u32;
+            let condition = requested
+                .get("condition")
+                .and_then(Value::as_str)
+                .filter(|condition| !condition.trim().is_empty());
+            let inserted = self.insert_line_breakpoint(path, line, condition);
+            set.push(match inserted {
+                Ok((number, line)) => {
+                    numbers.push(number);
+                    Value::object(vec![
+                        ("id", number.into()),
+                        ("verified", true.into()),
+                        ("line", line.into()),
+                        ("source", source.clone()),
+                    ])
+                }
+                Err(message) => Value::object(vec![
+                    ("verified", false.into()),
+                    ("line", line.into()),
+                    ("message", message.into()),
+                ]),
+            });
+        }
+        self.source_breakpoints.insert(path.to_owned(), numbers);
+        Ok(Value::object(vec![("breakpoints", Value::Array(set))]))
+    }
+
+    // inserts a breakpoint on `line` of `file`, or the first line after it with code, and returns it's number and the
+    // line it's on
+    fn insert_line_breakpoint(
+        &mut self,
+        file: &str,
+        line: u32,
+        condition: Option<&str>,
+    ) -> Result<(usize, u32), String> {
+        self.debugger.handle(Command::SetBreakpoint {
+            location: BreakpointRequest::Line {
+                number: line as usize,
+                file: file.to_owned(),
+            },
+            hardware: false,
+            temporary: false,
+            condition: condition.map(str::to_owned),
+        })?;
+        self.events
+            .try_iter()
+            .find_map(|event| match event {
+                Event::BreakpointCreated(breakpoint) => match breakpoint.spec {
+                    BreakpointRequest::Line { number, .. } => Some((breakpoint.number, number as u32)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .ok_or_else(|| format!("No line {} in file \"{}\".", line, file))
+    }
+
+    fn threads(&self) -> Value {
+        let threads = if self.alive() {
+            let name = std::path::Path::new(&self.program)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            vec![Value::object(vec![
+                ("id", self.thread_id().into()),
+                ("name", name.into()),
+            ])]
+        } else {
+            vec![]
+        };
+        Value::object(vec![("threads", Value::Array(threads))])
+    }
+
+    fn backtrace(&self) -> Result<Vec<Frame>, String> {
+        frame::backtrace(
+            self.debugger.target(),
+            self.debugger.elf(),
+            self.debugger.breakpoints().shared_libraries(),
+        )
+    }
+
+    // the frame at `level`, counting out from the innermost
+    fn frame(&self, level: usize) -> Result<Frame, String> {
+        self.backtrace()?
+            .into_iter()
+            .nth(level)
+            .ok_or(format!("No frame at level {}.", level))
+    }
+
+    fn stack_trace(&self, arguments: &Value) -> Result<Value, String> {
+        let frames = self.backtrace()?;
+        let total = frames.len();
+        let start = arguments
+            .get("startFrame")
+            .and_then(Value::as_u64)
+            .unwrap_or(0) as usize;
+        let levels = match arguments.get("levels").and_then(Value::as_u64).unwrap_or(0) as usize {
+            0 => total,
+            levels => levels,
+        };
+        let stack_frames = frames
+            .iter()
+            .skip(start)
+            .take(levels)
+            .map(|frame| self.stack_frame(frame))
+            .collect();
+        Ok(Value::object(vec![
+            ("stackFrames", Value::Array(stack_frames)),
+            ("totalFrames", total.into()),
+        ]))
+    }
+
+    fn stack_frame(&self, frame: &Frame) -> Value {
+        let name = match &frame.function {
+            Some(function) => function.name.clone(),
+            None => self
+                .debugger
+                .elf()
+                .symbol_table
+                .symbolize(frame.pc)
+                .map(|(name, _)| name.to_owned())
+                .unwrap_or_else(|| "??".into()),
+        };
+        // frame ids are the frame's level, plus one so that they're never 0
+        let mut stack_frame = Value::object(vec![
+            ("id", (frame.level + 1).into()),
+            ("name", name.into()),
+            ("line", 0u32.into()),
+            ("column", 0u32.into()),
+            (
+                "instructionPointerReference",
+                format!("0x{:x}", frame.pc).into(),
+            ),
+        ]);
+        let lines = self.debugger.lines();
+        let row = lines.and_then(|lines| lines.row_at(frame.lookup_pc()));
+        let file = row.and_then(|row| lines?.file_name(row));
+        if let (Some(row), Some(file)) = (row, file) {
+            let path = self
+                .debugger
+                .elf()
+                .source_path(file)
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|| file.to_owned());
+            let name = std::path::Path::new(file)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| file.to_owned());
+            stack_frame.insert(
+                "source",
+                Value::object(vec![("name", name.into()), ("path", path.into())]),
+            );
+            stack_frame.insert("line", row.line().into());
+            stack_frame.insert("column", 1u32.into());
+        } else {
+            stack_frame.insert("presentationHint", "subtle".into());
+        }
+        stack_frame
+    }
+
+    // the level of the frame of the request's frameId; the innermost frame if there's none
+    fn level(arguments: &Value) -> usize {
+        arguments
+            .get("frameId")
+            .and_then(Value::as_u64)
+            .map(|id| (id as usize).saturating_sub(1))
+            .unwrap_or(0)
+    }
+
+    fn scopes(&self, arguments: &Value) -> Result<Value, String> {
+        let level = Self::level(arguments);
+        let frame = self.frame(level)?;
+        let count = |parameter: bool| {
+            frame.function.as_ref().map_or(0, |function| {
+                function
+                    .variables
+                    .iter()
+                    .filter(|variable| variable.parameter == parameter)
+                    .count()
+            })
+        };
+        let scope = |name: &str, hint: &str, reference: usize, count: usize| {
+            Value::object(vec![
+                ("name", name.into()),
+                ("presentationHint", hint.into()),
+                ("variablesReference", reference.into()),
+                ("namedVariables", count.into()),
+                ("expensive", false.into()),
+            ])
+        };
+        Ok(Value::object(vec![(
+            "scopes",
+            Value::Array(vec![
+                scope(
+                    "Arguments",
+                    "arguments",
+                    arguments_reference(level),
+                    count(true),
+                ),
+                scope("Locals", "locals", locals_reference(level), count(false)),
+            ]),
+        )]))
+    }
+
+    fn variables(&self, arguments: &Value) -> Result<Value, String> {
+        let reference = arguments
+            .get("variablesReference")
+            .and_then(Value::as_u64)
+            .filter(|reference| *reference != 0)
+            .ok_or("variables needs a variablesReference")? as usize;
+        let level = (reference - 1) / 2;
+        let parameters = reference == arguments_reference(level);
+        let frame = self.frame(level)?;
+        let variables = frame
+            .function
+            .iter()
+            .flat_map(|function| function.variables.iter())
+            .filter(|variable| variable.parameter == parameters)
+            .map(|variable| {
+                let value = match frame.value_of(self.debugger.target(), variable) {
+                    Ok(value) => format_variable(variable, value),
+                    Err(_) if variable.location.is_none() => "<optimized out>".into(),
+                    Err(err) => err,
+                };
+                Value::object(vec![
+                    ("name", variable.name.as_str().into()),
+                    ("value", value.into()),
+                    ("variablesReference", 0u32.into()),
+                ])
+            })
+            .collect();
+        Ok(Value::object(vec![("variables", Value::Array(variables))]))
+    }
+
+    // the value of an expression, like print shows it; a variable as it's type says, anything else as a C long
+    fn evaluate(&self, arguments: &Value) -> Result<Value, String> {
+        let expression = arguments
+            .get("expression")
+            .and_then(Value::as_str)
+            .ok_or("evaluate needs an expression")?;
+        let parsed = Expression::parse(expression)?;
+        let frame = self.frame(Self::level(arguments))?;
+        let variable = match &parsed {
+            Expression::Name(name) => frame.variable(self.debugger.elf(), name),
+            _ => None,
+        };
+        let result = match variable {
+            Some(variable) => format_variable(
+                &variable,
+                frame.value_of(self.debugger.target(), &variable)?,
+            ),
+            None => {
+                let context = InferiorContext::in_frame(self.debugger.target(), self.debugger.elf(), frame);
+                (parsed.evaluate(&context)? as i64).to_string()
+            }
+        };
+        Ok(Value::object(vec![
+            ("result", result.into()),
+            ("variablesReference", 0u32.into()),
+        ]))
+    }
+
+    fn thread_id(&self) -> i64 {
+        *self.debugger.target().process_id() as i64
+    }
+
+    // the body of a stopped event, for `reason`
+    fn stopped(&self, reason: &str) -> Value {
+        Value::object(vec![
+            ("reason", reason.into()),
+            ("threadId", self.thread_id().into()),
+            ("allThreadsStopped", true.into()),
+        ])
+    }
+
+    // runs the inferior as `resume` says, and returns the events that tell the client where it stopped
+    fn resume(&mut self, resume: Resume) -> Vec<Value> {
+        let command = match resume {
+            Resume::Entry => return vec![event("stopped", self.stopped("entry"))],
+            Resume::Continue => Command::Continue,
+            Resume::Next => Command::Next { count: 1 },
+            Resume::StepIn => Command::Step { count: 1 },
+            Resume::StepOut => Command::Finish,
+        };
+        let result = self.debugger.handle(command);
+        let mut messages = self.take_events();
+        if let Err(err) = result {
+            let body = Value::object(vec![
+                ("category", "stderr".into()),
+                ("output", format!("{}\n", err).into()),
+            ]);
+            messages.push(event("output", body));
+        }
+        messages
+    }
+
+    // the events of the debugger, as the client is told them
+    fn take_events(&mut self) -> Vec<Value> {
+        let events: Vec<Event> = self.events.try_iter().collect();
+        events
+            .into_iter()
+            .flat_map(|event| self.client_events(event))
+            .collect()
+    }
+
+    fn client_events(&mut self, debugger_event: Event) -> Vec<Value> {
+        let reason = match debugger_event {
+            Event::Warning(warning) => {
+                let body = Value::object(vec![
+                    ("category", "console".into()),
+                    ("output", format!("{}\n", warning).into()),
+                ]);
+                return vec![event("output", body)];
+            }
+            Event::Stopped { reason, .. } => reason,
+            Event::Exited(status) => {
+                self.status = status;
+                let exit_code = match status {
+                    WaitStatus::ExitedNormally(_, code) => code,
+                    // like a shell reports them
+                    WaitStatus::Killed(_, signal) => 128 + signal as i32,
+                    _ => 128,
+                };
+                return vec![
+                    event(
+                        "exited",
+                        Value::object(vec![("exitCode", (exit_code as i64).into())]),
+                    ),
+                    event("terminated", Value::Null),
+                ];
+            }
+            _ => return vec![],
+        };
+        let mut body = self.stopped("step");
+        match reason {
+            StopReason::Breakpoint {
+                breakpoints,
+                condition_errors,
+            } => {
+                let hit: Vec<Value> = breakpoints
+                    .iter()
+                    .map(|breakpoint| breakpoint.number.into())
+                    .chain(condition_errors.iter().map(|(number, _)| (*number).into()))
+                    .collect();
+                body.insert("reason", "breakpoint".into());
+                body.insert("hitBreakpointIds", Value::Array(hit));
+                if let Some((number, err)) = condition_errors.first() {
+                    body.insert(
+                        "text",
+                        format!(
+                            "Error in testing condition for breakpoint {}: {}",
+                            number, err
+                        )
+                        .into(),
+                    );
+                }
+            }
+            StopReason::Watchpoint { watchpoint, .. } => {
+                body.insert("reason", "breakpoint".into());
+                body.insert(
+                    "hitBreakpointIds",
+                    Value::Array(vec![watchpoint.number.into()]),
+                );
+            }
+            StopReason::Signal(signal) => {
+                body.insert("reason", "exception".into());
+                body.insert(
+                    "description",
+                    format!("{}, {}", signal.name(), signal.description()).into(),
+                );
+            }
+            StopReason::EndSteppingRange | StopReason::LocationReached | StopReason::FunctionFinished { .. } => {}
+        }
+        vec![event("stopped", body)]
+    }
+
+    // ends the session; the inferior is killed if `terminate`, otherwise we detach from it, and it runs on without
+    // the breakpoints
+    fn disconnect(&mut self, terminate: bool) -> Result<(), String> {
+        if !self.alive() {
+            return Ok(());
+        }
+        let command = if terminate {
+            Command::Kill
+        } else {
+            Command::Detach
+        };
+        let result = self.debugger.handle(command);
+        self.take_events();
+        result
+    }
+}
@@ -0,0 +1,496 @@
+//! A stub of the GDB remote serial protocol, so that GDB, LLDB and anything else that speaks it can drive a
+//! `Session`, like the repl and the DAP server do. It's all-stop, with the one thread midas traces, and the inferior
+//! only runs while the stub waits for it to stop; an interrupt can't stop it before that.
+use std::{
+    io::{Read, Write},
+    sync::mpsc::Receiver,
+};
+
+use midas::{
+    commands::Command,
+    session::{Event, Session, StopReason},
+    software_breakpoint::BreakpointRequest,
+    types::Address,
+};
+use nixwrap::{signals::Signal, MidasSysResultDynamic, WaitStatus};
+
+use crate::{
+    packet::{Connection, Incoming},
+    registers::{self, REGISTERS},
+};
+
+const OK: &[u8] = b"OK";
+const ERROR: &[u8] = b"E01";
+// what the reply to an unsupported packet is
+const UNSUPPORTED: &[u8] = b"";
+
+const SUPPORTED: &str = "PacketSize=4000;QStartNoAckMode+;qXfer:features:read+;qXfer:auxv:read+;\
+                         qXfer:exec-file:read+;swbreak+;vContSupported+";
+
+// what to do once a packet has been handled
+enum Action {
+    Reply(Vec<u8>),
+    /// Reply, then stop sending acknowledgements
+    StartNoAckMode,
+    /// Reply, and end the session
+    ReplyAndClose(Vec<u8>),
+    Close,
+}
+
+/// The signal numbers of the protocol are GDB's own, which only agree with Linux' for some of the signals
+fn gdb_signal(signal: Signal) -> u8 {
+    match signal {
+        Signal::BusError => 10,
+        Signal::UserDefined1 => 30,
+        Signal::UserDefined2 => 31,
+        Signal::ChildStopped => 20,
+        Signal::Continued => 19,
+        Signal::Stopped => 17,
+        Signal::SignalTerminalStop => 18,
+        Signal::UrgentOutOfBand => 16,
+        Signal::InputOutputPoll => 23,
+        Signal::PowerFailure => 32,
+        Signal::BadSystemCallArgument => 12,
+        // GDB_SIGNAL_UNKNOWN; gdb has no SIGSTKFLT
+        Signal::StackFault => 143,
+        signal => signal as u8,
+    }
+}
+
+/// The signal of GDB's number `number`; None if Linux has no such signal
+fn from_gdb_signal(number: u8) -> Option<Signal> {
+    (1..32)
+        .filter_map(|raw| Signal::from_raw(raw).ok())
+        .find(|signal| gdb_signal(*signal) == number)
+}
+
+// the signal of a C, S or vCont action, in hex; 0 is none
+fn resume_signal(number: &str) -> Result<Option<Signal>, ()> {
+    match u8::from_str_radix(number, 16).map_err(|_| ())? {
+        0 => Ok(None),
+        number => from_gdb_signal(number).map(Some).ok_or(()),
+    }
+}
+
+fn hex(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|byte| format!("{:02x}", byte).into_bytes())
+        .collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+// "a,b" of hex numbers, like the address and length of memory packets
+fn hex_pair(text: &str) -> Option<(usize, usize)> {
+    let (first, second) = text.split_once(',')?;
+    Some((
+        usize::from_str_radix(first, 16).ok()?,
+        usize::from_str_radix(second, 16).ok()?,
+    ))
+}
+
+// the part of `data` a qXfer read of "offset,length" asks for; prefixed with 'l' if it's the last of it
+fn transfer(data: &[u8], offset_length: &str) -> Vec<u8> {
+    let (offset, length) = match hex_pair(offset_length) {
+        Some(pair) => pair,
+        None => return ERROR.to_vec(),
+    };
+    let rest = data.get(offset..).unwrap_or(&[]);
+    let chunk = &rest[..length.min(rest.len())];
+    let mut reply = vec![if chunk.len() == rest.len() {
+        b'l'
+    } else {
+        b'm'
+    }];
+    reply.extend_from_slice(chunk);
+    reply
+}
+
+pub struct GdbStub<'elf> {
+    session: Session<'elf>,
+    // what the session reports about the commands we give it
+    events: Receiver<Event>,
+    // why the inferior last stopped
+    status: WaitStatus,
+    // whether the last stop was at one of the breakpoints, and not a step that landed on one
+    at_breakpoint: bool,
+    // whether the frontend understands swbreak in stop replies
+    swbreak: bool,
+    // whether the frontend detached, leaving the inferior to run once we let go of it
+    detached: bool,
+}
+
+impl<'elf> GdbStub<'elf> {
+    /// A stub for `session`, the inferior of which stopped with `status`; `events` are what the session sends
+    pub fn new(session: Session<'elf>, events: Receiver<Event>, status: WaitStatus) -> GdbStub<'elf> {
+        GdbStub {
+            session,
+            events,
+            status,
+            at_breakpoint: false,
+            swbreak: false,
+            detached: false,
+        }
+    }
+
+    /// Whether the inferior is still around to be debugged
+    pub fn alive(&self) -> bool {
+        matches!(self.status, WaitStatus::Stopped(..))
+    }
+
+    /// Whether the session ended with the frontend detaching
+    pub fn detached(&self) -> bool {
+        self.detached
+    }
+
+    /// Kills the inferior, unless it's already gone
+    pub fn kill(&mut self) -> Result<(), String> {
+        if self.alive() {
+            self.command(Command::Kill)?;
+        }
+        Ok(())
+    }
+
+    // has the session do `command`, and keeps what it reported of the inferior stopping or exiting
+    fn command(&mut self, command: Command) -> MidasSysResultDynamic<()> {
+        let result = self.session.handle(command);
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Stopped { reason, .. } => {
+                    let signal = match reason {
+                        StopReason::Signal(signal) => signal,
+                        _ => Signal::Trap,
+                    };
+                    self.status = WaitStatus::Stopped(self.session.target().process_id(), signal);
+                    self.at_breakpoint = matches!(reason, StopReason::Breakpoint { .. });
+                }
+                Event::Exited(status) => self.status = status,
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Answers the packets that arrive on `connection`, until the session ends or the frontend hangs up
+    pub fn serve<S: Read + Write>(&mut self, connection: &mut Connection<S>) -> std::io::Result<()> {
+        while let Some(incoming) = connection.read()? {
+            let packet = match incoming {
+                Incoming::Packet(packet) => packet,
+                // by the time it's read the inferior has stopped, and the frontend has been told so
+                Incoming::Interrupt => continue,
+            };
+            match self.handle(&packet) {
+                Action::Reply(reply) => connection.write(&reply)?,
+                Action::StartNoAckMode => {
+                    connection.write(OK)?;
+                    connection.disable_acknowledgements();
+                }
+                Action::ReplyAndClose(reply) => {
+                    connection.write(&reply)?;
+                    return Ok(());
+                }
+                Action::Close => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    /// The stop reply packet, telling why the inferior last stopped
+    pub fn stop_reply(&self) -> Vec<u8> {
+        match self.status {
+            WaitStatus::Stopped(pid, signal) => {
+                let mut reply = format!("T{:02x}thread:{:x};", gdb_signal(signal), *pid);
+                if self.swbreak && self.at_breakpoint {
+                    reply.push_str("swbreak:;");
+                }
+                reply.into_bytes()
+            }
+            WaitStatus::ExitedNormally(_, code) => format!("W{:02x}", code as u8).into_bytes(),
+            WaitStatus::Killed(_, signal) => format!("X{:02x}", gdb_signal(signal)).into_bytes(),
+            // the signal that made it dump core isn't kept
+            WaitStatus::CoreDumped(_) => b"X00".to_vec(),
+            WaitStatus::Continued(pid) => format!("T00thread:{:x};", *pid).into_bytes(),
+        }
+    }
+
+    // continues or single steps the inferior, from `address` if there is one, delivering `signal` if there is one
+    fn resume(&mut self, step: bool, signal: Option<Signal>, address: Option<&str>) -> Action {
+        if let Some(address) = address.filter(|address| !address.is_empty()) {
+            let moved = usize::from_str_radix(address, 16)
+                .map_err(|err| err.to_string())
+                .and_then(|address| {
+                    let target = self.session.target_mut();
+                    let mut registers = target.read_registers()?;
+                    registers.rip = address as u64;
+                    target.write_registers(registers)
+                });
+            if moved.is_err() {
+                return Action::Reply(ERROR.to_vec());
+            }
+        }
+        if let Some(signal) = signal {
+            if self.command(Command::QueueSignal(signal)).is_err() {
+                return Action::Reply(ERROR.to_vec());
+            }
+        }
+        let command = match step {
+            true => Command::StepInstruction { count: 1 },
+            false => Command::Continue,
+        };
+        match self.command(command) {
+            Ok(()) => Action::Reply(self.stop_reply()),
+            Err(_) => Action::Reply(ERROR.to_vec()),
+        }
+    }
+
+    fn read_memory(&self, address_length: &str) -> Vec<u8> {
+        match hex_pair(address_length)
+            .map(|(address, length)| self.session.target().read_memory(Address(address), length))
+        {
+            Some(Ok(read)) => hex(&read.data),
+            _ => ERROR.to_vec(),
+        }
+    }
+
+    fn write_memory(&mut self, address_length: &[u8], data: Option<Vec<u8>>) -> Vec<u8> {
+        let address_length = String::from_utf8_lossy(address_length);
+        match (hex_pair(&address_length), data) {
+            (Some((address, length)), Some(data)) if data.len() == length => {
+                match self
+                    .session
+                    .target_mut()
+                    .write_memory(Address(address), &data)
+                {
+                    Ok(written) if written == length => OK.to_vec(),
+                    _ => ERROR.to_vec(),
+                }
+            }
+            _ => ERROR.to_vec(),
+        }
+    }
+
+    // Z0 and z0; sets or deletes a breakpoint of the session at an address. The other kinds aren't supported.
+    fn breakpoint(&mut self, insert: bool, arguments: &str) -> Vec<u8> {
+        let mut arguments = arguments.split(',');
+        let (kind, address) = (arguments.next(), arguments.next());
+        if kind != Some("0") {
+            return UNSUPPORTED.to_vec();
+        }
+        let address = match address.and_then(|address| usize::from_str_radix(address, 16).ok()) {
+            Some(address) => Address(address),
+            None => return ERROR.to_vec(),
+        };
+        let location = BreakpointRequest::Address(address);
+        let result = if insert {
+            self.command(Command::SetBreakpoint {
+                location,
+                hardware: false,
+                temporary: false,
+                condition: None,
+            })
+        } else {
+            let number = self
+                .session
+                .breakpoints()
+                .iter()
+                .find(|breakpoint| breakpoint.spec == location)
+                .map(|breakpoint| breakpoint.number);
+            match number {
+                Some(number) => self.command(Command::Delete(vec![number])),
+                None => Err(format!("No breakpoint at {:#x}", address)),
+            }
+        };
+        match result {
+            Ok(()) => OK.to_vec(),
+            Err(_) => ERROR.to_vec(),
+        }
+    }
+
+    fn read_register(&self, number: &str) -> Vec<u8> {
+        let register = match usize::from_str_radix(number, 16)
+            .ok()
+            .and_then(|number| REGISTERS.get(number))
+        {
+            Some(register) => register,
+            None => return ERROR.to_vec(),
+        };
+        let target = self.session.target();
+        match target.read_registers() {
+            Ok(values) => registers::encode(register, &values, target.read_xmm_registers().ok().as_ref()).into_bytes(),
+            Err(_) => ERROR.to_vec(),
+        }
+    }
+
+    fn write_register(&mut self, assignment: &str) -> Vec<u8> {
+        let (number, value) = match assignment.split_once('=') {
+            Some(assignment) => assignment,
+            None => return ERROR.to_vec(),
+        };
+        let register = match usize::from_str_radix(number, 16)
+            .ok()
+            .and_then(|number| REGISTERS.get(number))
+        {
+            Some(register) => register,
+            None => return ERROR.to_vec(),
+        };
+        let target = self.session.target_mut();
+        let written = target.read_registers().and_then(|mut values| {
+            registers::decode(register, &mut values, value)?;
+            target.write_registers(values)
+        });
+        match written {
+            Ok(()) => OK.to_vec(),
+            Err(_) => ERROR.to_vec(),
+        }
+    }
+
+    // vCont;action[:thread][;action[:thread]]...; there's only the one thread, that the first action is for
+    fn resume_with_actions(&mut self, actions: &str) -> Action {
+        let action = actions
+            .split(';')
+            .nth(1)
+            .map(|action| action.split(':').next().unwrap_or_default())
+            .unwrap_or_default();
+        match action.split_at(action.len().min(1)) {
+            ("c", "") => self.resume(false, None, None),
+            ("s", "") => self.resume(true, None, None),
+            ("C", signal) => self.resume_with_signal(false, signal),
+            ("S", signal) => self.resume_with_signal(true, signal),
+            _ => Action::Reply(ERROR.to_vec()),
+        }
+    }
+
+    // C sig[;addr] and S sig[;addr]; the signal is delivered as the inferior resumes
+    fn resume_with_signal(&mut self, step: bool, arguments: &str) -> Action {
+        let (signal, address) = match arguments.split_once(';') {
+            Some((signal, address)) => (signal, Some(address)),
+            None => (arguments, None),
+        };
+        match resume_signal(signal) {
+            Ok(signal) => self.resume(step, signal, address),
+            Err(()) => Action::Reply(ERROR.to_vec()),
+        }
+    }
+
+    fn query(&mut self, query: &str) -> Action {
+        let pid = *self.session.target().process_id();
+        let reply = if let Some(features) = query.strip_prefix("qSupported") {
+            self.swbreak = features
+                .split([':', ';'])
+                .any(|feature| feature == "swbreak+");
+            SUPPORTED.as_bytes().to_vec()
+        } else if query == "QStartNoAckMode" {
+            return Action::StartNoAckMode;
+        } else if let Some(offset_length) = query.strip_prefix("qXfer:features:read:target.xml:") {
+            transfer(registers::target_description().as_bytes(), offset_length)
+        } else if let Some(offset_length) = query.strip_prefix("qXfer:auxv:read::") {
+            match self.session.target().auxiliary_vector() {
+                Ok(auxv) => transfer(&auxv.to_bytes(), offset_length),
+                Err(_) => ERROR.to_vec(),
+            }
+        } else if let Some(annex) = query.strip_prefix("qXfer:exec-file:read:") {
+            let offset_length = annex.rsplit(':').next().unwrap_or_default();
+            match midas::procfs::executable(self.session.target().process_id()) {
+                Ok(path) => transfer(path.as_bytes(), offset_length),
+                Err(_) => ERROR.to_vec(),
+            }
+        } else if query.starts_with("qXfer:") {
+            ERROR.to_vec()
+        } else {
+            match query {
+                // the inferior was started by us, and is killed, not detached, when the frontend quits
+                "qAttached" => b"0".to_vec(),
+                "qC" => format!("QC{:x}", pid).into_bytes(),
+                "qfThreadInfo" => format!("m{:x}", pid).into_bytes(),
+                "qsThreadInfo" => b"l".to_vec(),
+                "qSymbol::" => OK.to_vec(),
+                // what lldb asks, before it reads the target description
+                "qHostInfo" => format!(
+                    "triple:{};ptrsize:8;endian:little;",
+                    String::from_utf8(hex(b"x86_64-pc-linux-gnu")).unwrap()
+                )
+                .into_bytes(),
+                "qProcessInfo" => format!(
+                    "pid:{:x};triple:{};ptrsize:8;endian:little;ostype:linux;",
+                    pid,
+                    String::from_utf8(hex(b"x86_64-pc-linux-gnu")).unwrap()
+                )
+                .into_bytes(),
+                _ => UNSUPPORTED.to_vec(),
+            }
+        };
+        Action::Reply(reply)
+    }
+
+    fn handle(&mut self, packet: &[u8]) -> Action {
+        // X is the only packet with binary data; the rest are text
+        if let Some(packet) = packet.strip_prefix(b"X") {
+            let reply = match packet.iter().position(|&byte| byte == b':') {
+                Some(colon) => self.write_memory(&packet[..colon], Some(packet[colon + 1..].to_vec())),
+                None => ERROR.to_vec(),
+            };
+            return Action::Reply(reply);
+        }
+        let packet = String::from_utf8_lossy(packet);
+        let (command, arguments) = match packet.char_indices().nth(1) {
+            Some((index, _)) => packet.split_at(index),
+            None => (&packet[..], ""),
+        };
+        let reply = match command {
+            "?" => self.stop_reply(),
+            "g" => match registers::read_all(self.session.target()) {
+                Ok(registers) => registers.into_bytes(),
+                Err(_) => ERROR.to_vec(),
+            },
+            "G" => match registers::write_all(self.session.target_mut(), arguments) {
+                Ok(()) => OK.to_vec(),
+                Err(_) => ERROR.to_vec(),
+            },
+            "p" => self.read_register(arguments),
+            "P" => self.write_register(arguments),
+            "m" => self.read_memory(arguments),
+            "M" => match arguments.split_once(':') {
+                Some((address_length, data)) => self.write_memory(address_length.as_bytes(), from_hex(data)),
+                None => ERROR.to_vec(),
+            },
+            "Z" => self.breakpoint(true, arguments),
+            "z" => self.breakpoint(false, arguments),
+            "c" => return self.resume(false, None, Some(arguments)),
+            "s" => return self.resume(true, None, Some(arguments)),
+            "C" => return self.resume_with_signal(false, arguments),
+            "S" => return self.resume_with_signal(true, arguments),
+            "v" if packet == "vCont?" => b"vCont;c;C;s;S".to_vec(),
+            "v" if packet.starts_with("vCont;") => return self.resume_with_actions(&packet),
+            "v" if packet.starts_with("vKill") => match self.kill() {
+                Ok(()) => OK.to_vec(),
+                Err(_) => ERROR.to_vec(),
+            },
+            // there's only one thread to select
+            "H" | "T" => OK.to_vec(),
+            // the inferior runs on without us, with the breakpoints taken out
+            "D" => match self.command(Command::Detach) {
+                Ok(()) => {
+                    self.detached = true;
+                    return Action::ReplyAndClose(OK.to_vec());
+                }
+                Err(_) => ERROR.to_vec(),
+            },
+            "k" => {
+                let _ = self.kill();
+                return Action::Close;
+            }
+            "q" | "Q" => return self.query(&packet),
+            _ => UNSUPPORTED.to_vec(),
+        };
+        Action::Reply(reply)
+    }
+}
@@ -1,3 +1,103 @@
-fn main() {
-    println!("Hello, world!");
+extern crate linuxwrapper as nixwrap;
+extern crate midas;
+use midas::target::{self, Target};
+use std::{net::TcpListener, os::unix::net::UnixListener, path::Path};
+mod dap;
+mod gdbstub;
+mod packet;
+mod registers;
+
+const USAGE: &str = "Usage: server [--disable-randomization=on|off] COMM PROG [ARGS ...]
+       server --dap
+       server --interpreter=json PROG [-- ARGS ...]
+
+Starts PROG and waits for a GDB frontend to connect, to debug it over the remote serial protocol. COMM is
+HOST:PORT or :PORT to listen on a TCP port, or the path of a Unix socket to listen on. PROG is started with
+address space randomization disabled, like gdbserver does, unless --disable-randomization=off.
+
+With --dap, speaks the Debug Adapter Protocol over stdin and stdout instead, for editors to launch or attach to
+the program to debug.
+
+With --interpreter=json, takes the repl's commands over stdin and writes what comes of them to stdout, one JSON
+object per line, like `repl --interpreter=json` does.";
+
+fn main() -> Result<(), String> {
+    let mut args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == "--dap") {
+        let channel = std::sync::Arc::new(std::sync::Mutex::new(dap::Channel::new(std::io::stdout())));
+        return dap::serve(std::io::stdin().lock(), channel);
+    }
+    // the commands of the repl, and it's interpreters; the options are it's own
+    if args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg.starts_with("--interpreter="))
+    {
+        return repl::start(args);
+    }
+    let option = args
+        .get(1)
+        .and_then(|arg| arg.strip_prefix("--disable-randomization="))
+        .map(str::to_owned);
+    let disable_randomization = match option.as_deref() {
+        None | Some("on") => true,
+        Some("off") => false,
+        Some(_) => return Err(USAGE.into()),
+    };
+    if option.is_some() {
+        args.remove(1);
+    }
+    let (comm, program) = match (args.get(1), args.get(2)) {
+        (Some(comm), Some(program)) => (comm, program),
+        _ => return Err(USAGE.into()),
+    };
+    let arguments = args[3..].iter().map(String::as_str).collect();
+    let (target_, status) = midas::target::linux::LinuxTarget::launch(&mut target::make_command_with(
+        program,
+        arguments,
+        disable_randomization,
+    )?)?;
+    println!(
+        "Process {} created; pid = {}",
+        program,
+        *target_.process_id()
+    );
+    let object = std::rc::Rc::new(midas::elf::load_object(Path::new(program))?);
+    let elf = midas::elf::ParsedELF::parse_elf(&object).map_err(|err| err.description())?;
+    let (sender, events) = std::sync::mpsc::channel();
+    let session = midas::session::Session::new(target_, elf, true, sender);
+    let mut stub = gdbstub::GdbStub::new(session, events, status);
+
+    let served = match comm.rsplit_once(':') {
+        Some((host, port)) => {
+            let host = if host.is_empty() { "0.0.0.0" } else { host };
+            let listener = TcpListener::bind((host, port.parse::<u16>().map_err(|_| USAGE.to_owned())?))
+                .map_err(|err| format!("Can't bind address {}: {}", comm, err))?;
+            let port = listener.local_addr().map_err(|err| err.to_string())?.port();
+            println!("Listening on port {}", port);
+            let (stream, address) = listener.accept().map_err(|err| err.to_string())?;
+            println!(
+                "Remote debugging from host {}, port {}",
+                address.ip(),
+                address.port()
+            );
+            stream.set_nodelay(true).map_err(|err| err.to_string())?;
+            stub.serve(&mut packet::Connection::new(stream))
+        }
+        None => {
+            let path = Path::new(comm);
+            let listener =
+                UnixListener::bind(path).map_err(|err| format!("Can't bind socket {}: {}", path.display(), err))?;
+            println!("Listening on {}", path.display());
+            let accepted = listener.accept().map_err(|err| err.to_string());
+            let _ = std::fs::remove_file(path);
+            let (stream, _) = accepted?;
+            println!("Remote debugging using {}", path.display());
+            stub.serve(&mut packet::Connection::new(stream))
+        }
+    };
+    // a frontend that's gone without detaching takes the inferior with it. One that detached left it running
+    let killed = if stub.detached() { Ok(()) } else { stub.kill() };
+    served.map_err(|err| format!("Remote connection closed: {}", err))?;
+    killed
 }
@@ -0,0 +1,143 @@
+//! The framing of the GDB remote serial protocol. A packet is `$payload#cc`, where `cc` is the sum of the payload's
+//! bytes modulo 256 in hex, and every packet is acknowledged with a `+` (or a `-`, asking for it again) until both
+//! sides agree to stop doing so. In the payload `}` escapes the next byte, which is then xor'ed with 0x20.
+use std::io::{Read, Write};
+
+const INTERRUPT: u8 = 0x03;
+const ESCAPE: u8 = b'}';
+
+/// What the client sent
+#[derive(Debug, PartialEq, Eq)]
+pub enum Incoming {
+    Packet(Vec<u8>),
+    /// Ctrl-C; a request to stop the inferior, sent outside of any packet
+    Interrupt,
+}
+
+/// A connection to a GDB frontend, over a socket or anything else that can be read and written
+pub struct Connection<S: Read + Write> {
+    stream: S,
+    buffer: Vec<u8>,
+    position: usize,
+    acknowledge: bool,
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+// the bytes that mean something in a packet can only be sent escaped
+fn escape(payload: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(payload.len());
+    for &byte in payload {
+        if matches!(byte, b'$' | b'#' | b'*' | ESCAPE) {
+            escaped.extend_from_slice(&[ESCAPE, byte ^ 0x20]);
+        } else {
+            escaped.push(byte);
+        }
+    }
+    escaped
+}
+
+fn unescape(payload: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(payload.len());
+    let mut bytes = payload.iter();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            ESCAPE => unescaped.extend(bytes.next().map(|byte| byte ^ 0x20)),
+            _ => unescaped.push(byte),
+        }
+    }
+    unescaped
+}
+
+impl<S: Read + Write> Connection<S> {
+    pub fn new(stream: S) -> Connection<S> {
+        Connection {
+            stream,
+            buffer: vec![],
+            position: 0,
+            acknowledge: true,
+        }
+    }
+
+    /// Stops sending and expecting acknowledgements; after the reply to QStartNoAckMode has been sent
+    pub fn disable_acknowledgements(&mut self) {
+        self.acknowledge = false;
+    }
+
+    // the next byte the client sent; None once it hangs up
+    fn next_byte(&mut self) -> std::io::Result<Option<u8>> {
+        if self.position == self.buffer.len() {
+            self.buffer.resize(4096, 0);
+            let read = self.stream.read(&mut self.buffer)?;
+            self.buffer.truncate(read);
+            self.position = 0;
+            if read == 0 {
+                return Ok(None);
+            }
+        }
+        self.position += 1;
+        Ok(Some(self.buffer[self.position - 1]))
+    }
+
+    /// Reads the next packet, or interrupt. Packets that arrive garbled are asked for again. None once the client
+    /// hangs up.
+    pub fn read(&mut self) -> std::io::Result<Option<Incoming>> {
+        loop {
+            // acknowledgements and anything else between packets is skipped
+            match self.next_byte()? {
+                None => return Ok(None),
+                Some(INTERRUPT) => return Ok(Some(Incoming::Interrupt)),
+                Some(b'$') => {}
+                Some(_) => continue,
+            }
+            let mut payload = vec![];
+            loop {
+                match self.next_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => payload.push(byte),
+                }
+            }
+            let (high, low) = match (self.next_byte()?, self.next_byte()?) {
+                (Some(high), Some(low)) => (high, low),
+                _ => return Ok(None),
+            };
+            let sent = std::str::from_utf8(&[high, low])
+                .ok()
+                .and_then(|sum| u8::from_str_radix(sum, 16).ok());
+            if self.acknowledge {
+                if sent != Some(checksum(&payload)) {
+                    self.stream.write_all(b"-")?;
+                    continue;
+                }
+                self.stream.write_all(b"+")?;
+            }
+            return Ok(Some(Incoming::Packet(unescape(&payload))));
+        }
+    }
+
+    /// Sends a packet, and again for as long as the client asks for it to be
+    pub fn write(&mut self, payload: &[u8]) -> std::io::Result<()> {
+        let escaped = escape(payload);
+        let mut packet = Vec::with_capacity(escaped.len() + 4);
+        packet.push(b'$');
+        packet.extend_from_slice(&escaped);
+        packet.extend_from_slice(format!("#{:02x}", checksum(&escaped)).as_bytes());
+        loop {
+            self.stream.write_all(&packet)?;
+            self.stream.flush()?;
+            if !self.acknowledge {
+                return Ok(());
+            }
+            loop {
+                match self.next_byte()? {
+                    Some(b'+') | None => return Ok(()),
+                    Some(b'-') => break,
+                    Some(_) => continue,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! The registers of an x86-64 inferior as GDB numbers them, and the target description that tells the frontend so.
+//! The `g` packet is every register in this order, each in target byte order; those we can't read are sent as `xx`.
+use midas::target::Target;
+use nixwrap::ptrace::UserRegisters;
+
+/// Where the value of a register comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// One of the general purpose registers, by it's name in `UserRegisters`
+    General(&'static str),
+    Xmm(usize),
+    /// The x87 registers and mxcsr, which `Target` doesn't read
+    Unavailable,
+}
+
+pub struct Register {
+    pub name: &'static str,
+    pub bits: usize,
+    pub kind: &'static str,
+    pub group: Option<&'static str>,
+    pub feature: &'static str,
+    pub source: Source,
+}
+
+const CORE: &str = "org.gnu.gdb.i386.core";
+const SSE: &str = "org.gnu.gdb.i386.sse";
+const LINUX: &str = "org.gnu.gdb.i386.linux";
+const SEGMENTS: &str = "org.gnu.gdb.i386.segments";
+
+const fn general(name: &'static str, bits: usize, kind: &'static str, feature: &'static str) -> Register {
+    Register {
+        name,
+        bits,
+        kind,
+        group: None,
+        feature,
+        source: Source::General(name),
+    }
+}
+
+const fn x87(name: &'static str, bits: usize, kind: &'static str) -> Register {
+    Register {
+        name,
+        bits,
+        kind,
+        group: Some("float"),
+        feature: CORE,
+        source: Source::Unavailable,
+    }
+}
+
+const fn xmm(name: &'static str, index: usize) -> Register {
+    Register {
+        name,
+        bits: 128,
+        kind: "vec128",
+        group: Some("vector"),
+        feature: SSE,
+        source: Source::Xmm(index),
+    }
+}
+
+/// The registers, in the order of their numbers
+pub const REGISTERS: [Register; 60] = [
+    general("rax", 64, "int64", CORE),
+    general("rbx", 64, "int64", CORE),
+    general("rcx", 64, "int64", CORE),
+    general("rdx", 64, "int64", CORE),
+    general("rsi", 64, "int64", CORE),
+    general("rdi", 64, "int64", CORE),
+    general("rbp", 64, "data_ptr", CORE),
+    general("rsp", 64, "data_ptr", CORE),
+    general("r8", 64, "int64", CORE),
+    general("r9", 64, "int64", CORE),
+    general("r10", 64, "int64", CORE),
+    general("r11", 64, "int64", CORE),
+    general("r12", 64, "int64", CORE),
+    general("r13", 64, "int64", CORE),
+    general("r14", 64, "int64", CORE),
+    general("r15", 64, "int64", CORE),
+    general("rip", 64, "code_ptr", CORE),
+    general("eflags", 32, "int32", CORE),
+    general("cs", 32, "int32", CORE),
+    general("ss", 32, "int32", CORE),
+    general("ds", 32, "int32", CORE),
+    general("es", 32, "int32", CORE),
+    general("fs", 32, "int32", CORE),
+    general("gs", 32, "int32", CORE),
+    x87("st0", 80, "i387_ext"),
+    x87("st1", 80, "i387_ext"),
+    x87("st2", 80, "i387_ext"),
+    x87("st3", 80, "i387_ext"),
+    x87("st4", 80, "i387_ext"),
+    x87("st5", 80, "i387_ext"),
+    x87("st6", 80, "i387_ext"),
+    x87("st7", 80, "i387_ext"),
+    x87("fctrl", 32, "int"),
+    x87("fstat", 32, "int"),
+    x87("ftag", 32, "int"),
+    x87("fiseg", 32, "int"),
+    x87("fioff", 32, "int"),
+    x87("foseg", 32, "int"),
+    x87("fooff", 32, "int"),
+    x87("fop", 32, "int"),
+    xmm("xmm0", 0),
+    xmm("xmm1", 1),
+    xmm("xmm2", 2),
+    xmm("xmm3", 3),
+    xmm("xmm4", 4),
+    xmm("xmm5", 5),
+    xmm("xmm6", 6),
+    xmm("xmm7", 7),
+    xmm("xmm8", 8),
+    xmm("xmm9", 9),
+    xmm("xmm10", 10),
+    xmm("xmm11", 11),
+    xmm("xmm12", 12),
+    xmm("xmm13", 13),
+    xmm("xmm14", 14),
+    xmm("xmm15", 15),
+    Register {
+        name: "mxcsr",
+        bits: 32,
+        kind: "int",
+        group: Some("vector"),
+        feature: SSE,
+        source: Source::Unavailable,
+    },
+    general("orig_rax", 64, "int", LINUX),
+    general("fs_base", 64, "int", SEGMENTS),
+    general("gs_base", 64, "int", SEGMENTS),
+];
+
+// the type of the xmm registers, which the sse feature has to define, as gdb's own description of it does
+const VEC128: &str = r#"    <vector id="v4f" type="ieee_single" count="4"/>
+    <vector id="v2d" type="ieee_double" count="2"/>
+    <vector id="v16i8" type="int8" count="16"/>
+    <vector id="v8i16" type="int16" count="8"/>
+    <vector id="v4i32" type="int32" count="4"/>
+    <vector id="v2i64" type="int64" count="2"/>
+    <union id="vec128">
+      <field name="v4_float" type="v4f"/>
+      <field name="v2_double" type="v2d"/>
+      <field name="v16_int8" type="v16i8"/>
+      <field name="v8_int16" type="v8i16"/>
+      <field name="v4_int32" type="v4i32"/>
+      <field name="v2_int64" type="v2i64"/>
+      <field name="uint128" type="uint128"/>
+    </union>
+"#;
+
+/// The target description the frontend reads with `qXfer:features:read:target.xml`
+pub fn target_description() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n<target version=\"1.0\">\n  \
+         <architecture>i386:x86-64</architecture>\n  <osabi>GNU/Linux</osabi>\n",
+    );
+    let mut feature = None;
+    for (number, register) in REGISTERS.iter().enumerate() {
+        if feature != Some(register.feature) {
+            if feature.is_some() {
+                xml.push_str("  </feature>\n");
+            }
+            feature = Some(register.feature);
+            xml.push_str(&format!("  <feature name=\"{}\">\n", register.feature));
+            if register.feature == SSE {
+                xml.push_str(VEC128);
+            }
+        }
+        xml.push_str(&format!(
+            "    <reg name=\"{}\" bitsize=\"{}\" type=\"{}\" regnum=\"{}\"",
+            register.name, register.bits, register.kind, number
+        ));
+        if let Some(group) = register.group {
+            xml.push_str(&format!(" group=\"{}\"", group));
+        }
+        xml.push_str("/>\n");
+    }
+    xml.push_str("  </feature>\n</target>\n");
+    xml
+}
+
+/// The value of `register` in hex, as the `g` and `p` packets send it
+pub fn encode(register: &Register, registers: &UserRegisters, xmm: Option<&[u128; 16]>) -> String {
+    let bytes = register.bits / 8;
+    let value = match register.source {
+        Source::General(name) => registers.get_by_name(name).map(|value| value as u128),
+        Source::Xmm(index) => xmm.map(|xmm| xmm[index]),
+        Source::Unavailable => None,
+    };
+    match value {
+        Some(value) => value.to_le_bytes()[..bytes]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+        None => "xx".repeat(bytes),
+    }
+}
+
+/// Sets `register` in `registers` to the hex value `hex`. Only the general purpose registers can be written; the
+/// rest are left as they are.
+pub fn decode(register: &Register, registers: &mut UserRegisters, hex: &str) -> Result<(), String> {
+    let name = match register.source {
+        Source::General(name) => name,
+        _ => return Err(format!("{} can't be written", register.name)),
+    };
+    let mut value = 0u64;
+    for (index, byte) in hex.as_bytes().chunks(2).enumerate().take(8) {
+        let byte = std::str::from_utf8(byte)
+            .ok()
+            .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            .ok_or_else(|| format!("{} is not a value for {}", hex, register.name))?;
+        value |= (byte as u64) << (index * 8);
+    }
+    registers.set_by_name(name, value);
+    Ok(())
+}
+
+/// Every register of `target`, as the `g` packet sends them
+pub fn read_all(target: &dyn Target) -> Result<String, String> {
+    let registers = target.read_registers()?;
+    let xmm = target.read_xmm_registers().ok();
+    Ok(REGISTERS
+        .iter()
+        .map(|register| encode(register, &registers, xmm.as_ref()))
+        .collect())
+}
+
+/// Writes the registers of a `G` packet to `target`
+pub fn write_all(target: &mut dyn Target, hex: &str) -> Result<(), String> {
+    let mut registers = target.read_registers()?;
+    let mut offset = 0;
+    for register in REGISTERS.iter() {
+        let digits = register.bits / 4;
+        let value = hex
+            .get(offset..offset + digits)
+            .ok_or("the G packet is too short")?;
+        offset += digits;
+        if matches!(register.source, Source::General(_)) && !value.starts_with('x') {
+            decode(register, &mut registers, value)?;
+        }
+    }
+    target.write_registers(registers)
+}
@@ -0,0 +1,447 @@
+use midas::json::Value;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::Once,
+};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../midas/tests/subjects")
+    };
+}
+
+const STEPPING: &str = concat!(tests_dir!(), "/executables/stepping");
+const CRASH: &str = concat!(tests_dir!(), "/executables/crash");
+const SOURCE: &str = concat!(tests_dir!(), "/stepping.c");
+const SERVER: &str = env!("CARGO_BIN_EXE_server");
+
+// lines of stepping.c
+const ADD_BODY_LINE: u64 = 9;
+const ADD_TOTAL_LINE: u64 = 10;
+const SECOND_ADD_LINE: u64 = 38;
+const FACT_LINE: u64 = 39;
+const BLANK_LINE: u64 = 35;
+const MAIN_LINE: u64 = 36;
+
+fn compile_subjects() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .args(["stepping", "crash"])
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// the editor's side of the protocol
+struct Client {
+    server: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    sequence: i64,
+    // the events that arrived, that haven't been waited for yet
+    events: Vec<Value>,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut server = Command::new(SERVER)
+            .arg("--dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        Client {
+            input: server.stdin.take().unwrap(),
+            output: BufReader::new(server.stdout.take().unwrap()),
+            server,
+            sequence: 0,
+            events: vec![],
+        }
+    }
+
+    fn read(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            assert_ne!(
+                self.output.read_line(&mut header).unwrap(),
+                0,
+                "the server quit"
+            );
+            match header.trim_end().split_once(": ") {
+                Some(("Content-Length", value)) => length = value.parse().unwrap(),
+                None if header.trim_end().is_empty() => break,
+                _ => panic!("unexpected header {}", header),
+            }
+        }
+        let mut body = vec![0u8; length];
+        self.output.read_exact(&mut body).unwrap();
+        Value::parse(std::str::from_utf8(&body).unwrap()).unwrap()
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.sequence += 1;
+        let request = Value::object(vec![
+            ("seq", self.sequence.into()),
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", arguments),
+        ])
+        .to_string();
+        write!(
+            self.input,
+            "Content-Length: {}\r\n\r\n{}",
+            request.len(),
+            request
+        )
+        .unwrap();
+        self.input.flush().unwrap();
+        loop {
+            let message = self.read();
+            match message.get("type").and_then(Value::as_str) {
+                Some("event") => self.events.push(message),
+                Some("response") => {
+                    assert_eq!(
+                        message.get("request_seq").and_then(Value::as_i64),
+                        Some(self.sequence)
+                    );
+                    assert_eq!(
+                        message.get("command").and_then(Value::as_str),
+                        Some(command)
+                    );
+                    return message;
+                }
+                _ => panic!("unexpected message {}", message),
+            }
+        }
+    }
+
+    // the body of a request that has to succeed
+    fn body(&mut self, command: &str, arguments: Value) -> Value {
+        let response = self.request(command, arguments);
+        assert_eq!(
+            response.get("success"),
+            Some(&Value::Bool(true)),
+            "{}",
+            response
+        );
+        response.get("body").cloned().unwrap_or(Value::Null)
+    }
+
+    fn event(&mut self, name: &str) -> Value {
+        loop {
+            if let Some(index) = self
+                .events
+                .iter()
+                .position(|event| event.get("event").and_then(Value::as_str) == Some(name))
+            {
+                let event = self.events.remove(index);
+                return event.get("body").cloned().unwrap_or(Value::Null);
+            }
+            let message = self.read();
+            assert_eq!(message.get("type").and_then(Value::as_str), Some("event"));
+            self.events.push(message);
+        }
+    }
+
+    fn start_session(&mut self, command: &str, arguments: Value) {
+        let capabilities = self.body(
+            "initialize",
+            Value::object(vec![("adapterID", "midas".into())]),
+        );
+        assert_eq!(
+            capabilities.get("supportsConfigurationDoneRequest"),
+            Some(&Value::Bool(true))
+        );
+        self.body(command, arguments);
+        self.event("initialized");
+    }
+
+    fn top_frame(&mut self, thread: i64) -> Value {
+        let trace = self.body(
+            "stackTrace",
+            Value::object(vec![("threadId", thread.into()), ("levels", 1usize.into())]),
+        );
+        trace.get("stackFrames").and_then(Value::as_array).unwrap()[0].clone()
+    }
+
+    fn evaluate(&mut self, expression: &str, frame: &Value) -> String {
+        let body = self.body(
+            "evaluate",
+            Value::object(vec![
+                ("expression", expression.into()),
+                ("frameId", frame.get("id").cloned().unwrap()),
+            ]),
+        );
+        body.get("result")
+            .and_then(Value::as_str)
+            .unwrap()
+            .to_owned()
+    }
+}
+
+fn get<'a>(value: &'a Value, path: &[&str]) -> &'a Value {
+    path.iter().fold(value, |value, key| {
+        value
+            .get(key)
+            .unwrap_or_else(|| panic!("no {} in {}", key, value))
+    })
+}
+
+fn line_breakpoints(lines: &[(u64, Option<&str>)]) -> Value {
+    let breakpoints = lines
+        .iter()
+        .map(|(line, condition)| {
+            let mut breakpoint = Value::object(vec![("line", (*line).into())]);
+            if let Some(condition) = condition {
+                breakpoint.insert("condition", (*condition).into());
+            }
+            breakpoint
+        })
+        .collect();
+    Value::object(vec![
+        ("source", Value::object(vec![("path", SOURCE.into())])),
+        ("breakpoints", Value::Array(breakpoints)),
+    ])
+}
+
+#[test]
+pub fn editors_debug_launched_programs() {
+    compile_subjects();
+    let mut client = Client::start();
+    // nothing can be done before there's a program
+    assert_eq!(
+        client.request("threads", Value::Null).get("success"),
+        Some(&Value::Bool(false))
+    );
+    client.start_session("launch", Value::object(vec![("program", STEPPING.into())]));
+
+    let set = client.body(
+        "setBreakpoints",
+        line_breakpoints(&[
+            (ADD_BODY_LINE, Some("x == 2")),
+            (BLANK_LINE, None),
+            (1000, None),
+        ]),
+    );
+    let set = set
+        .get("breakpoints")
+        .and_then(Value::as_array)
+        .unwrap()
+        .to_vec();
+    assert_eq!(set[0].get("verified"), Some(&Value::Bool(true)));
+    assert_eq!(
+        set[0].get("line").and_then(Value::as_u64),
+        Some(ADD_BODY_LINE)
+    );
+    // a line without code moves to the next one that has some
+    assert_eq!(set[1].get("line").and_then(Value::as_u64), Some(MAIN_LINE));
+    assert_eq!(set[2].get("verified"), Some(&Value::Bool(false)));
+    // main's breakpoint is replaced by the next request for the file, and never hit
+    let set = client.body(
+        "setBreakpoints",
+        line_breakpoints(&[(ADD_BODY_LINE, Some("x == 2"))]),
+    );
+    let add_breakpoint = get(&set, &["breakpoints"]).as_array().unwrap()[0]
+        .get("id")
+        .cloned()
+        .unwrap();
+
+    client.body("configurationDone", Value::Null);
+    let stopped = client.event("stopped");
+    assert_eq!(get(&stopped, &["reason"]).as_str(), Some("breakpoint"));
+    assert_eq!(
+        get(&stopped, &["hitBreakpointIds"]).as_array(),
+        Some(&[add_breakpoint][..])
+    );
+    let thread = get(&stopped, &["threadId"]).as_i64().unwrap();
+    let threads = client.body("threads", Value::Null);
+    assert_eq!(
+        get(&threads, &["threads"]).as_array().unwrap()[0].get("id"),
+        Some(&Value::Integer(thread))
+    );
+
+    // the condition skipped the first call
+    let trace = client.body(
+        "stackTrace",
+        Value::object(vec![("threadId", thread.into())]),
+    );
+    let frames = get(&trace, &["stackFrames"]).as_array().unwrap().to_vec();
+    assert_eq!(get(&trace, &["totalFrames"]).as_u64(), Some(2));
+    assert_eq!(get(&frames[0], &["name"]).as_str(), Some("add"));
+    assert_eq!(get(&frames[0], &["line"]).as_u64(), Some(ADD_BODY_LINE));
+    assert!(get(&frames[0], &["source", "path"])
+        .as_str()
+        .unwrap()
+        .ends_with("stepping.c"));
+    assert_eq!(get(&frames[1], &["name"]).as_str(), Some("main"));
+    assert_eq!(get(&frames[1], &["line"]).as_u64(), Some(SECOND_ADD_LINE));
+
+    let scopes = client.body(
+        "scopes",
+        Value::object(vec![("frameId", frames[0].get("id").cloned().unwrap())]),
+    );
+    let scopes = get(&scopes, &["scopes"]).as_array().unwrap().to_vec();
+    assert_eq!(get(&scopes[0], &["name"]).as_str(), Some("Arguments"));
+    let arguments = client.body(
+        "variables",
+        Value::object(vec![(
+            "variablesReference",
+            scopes[0].get("variablesReference").cloned().unwrap(),
+        )]),
+    );
+    let arguments = get(&arguments, &["variables"]).as_array().unwrap().to_vec();
+    assert_eq!(arguments.len(), 1);
+    assert_eq!(get(&arguments[0], &["name"]).as_str(), Some("x"));
+    assert_eq!(get(&arguments[0], &["value"]).as_str(), Some("2"));
+    let locals = client.body(
+        "variables",
+        Value::object(vec![(
+            "variablesReference",
+            scopes[1].get("variablesReference").cloned().unwrap(),
+        )]),
+    );
+    assert_eq!(
+        get(&locals, &["variables"]).as_array().unwrap()[0].get("name"),
+        Some(&Value::String("doubled".into()))
+    );
+
+    // expressions are evaluated in the frame they're asked about
+    assert_eq!(client.evaluate("x * 10", &frames[0]), "20");
+    assert_eq!(client.evaluate("total", &frames[0]), "2");
+    assert_ne!(
+        client.evaluate("$rip", &frames[1]),
+        client.evaluate("$rip", &frames[0])
+    );
+    let error = client.request(
+        "evaluate",
+        Value::object(vec![("expression", "nothing_by_this_name".into())]),
+    );
+    assert_eq!(error.get("success"), Some(&Value::Bool(false)));
+
+    client.body("next", Value::object(vec![("threadId", thread.into())]));
+    let stopped = client.event("stopped");
+    assert_eq!(get(&stopped, &["reason"]).as_str(), Some("step"));
+    assert_eq!(
+        get(&client.top_frame(thread), &["line"]).as_u64(),
+        Some(ADD_TOTAL_LINE)
+    );
+    // the value of add(2) isn't used, so what follows the call is the next line
+    client.body("stepOut", Value::object(vec![("threadId", thread.into())]));
+    client.event("stopped");
+    let frame = client.top_frame(thread);
+    assert_eq!(get(&frame, &["name"]).as_str(), Some("main"));
+    assert_eq!(get(&frame, &["line"]).as_u64(), Some(FACT_LINE));
+    client.body("stepIn", Value::object(vec![("threadId", thread.into())]));
+    client.event("stopped");
+    assert_eq!(
+        get(&client.top_frame(thread), &["name"]).as_str(),
+        Some("fact")
+    );
+
+    // without breakpoints, it runs to the end; main returns 0
+    client.body("setBreakpoints", line_breakpoints(&[]));
+    client.body("continue", Value::object(vec![("threadId", thread.into())]));
+    let exited = client.event("exited");
+    assert_eq!(get(&exited, &["exitCode"]).as_i64(), Some(0));
+    client.event("terminated");
+    assert_eq!(
+        client.request("stackTrace", Value::Null).get("success"),
+        Some(&Value::Bool(false))
+    );
+    client.body("disconnect", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+}
+
+#[test]
+pub fn crashes_and_output_are_reported() {
+    compile_subjects();
+    let mut client = Client::start();
+    client.start_session(
+        "launch",
+        Value::object(vec![
+            ("program", CRASH.into()),
+            ("stopOnEntry", true.into()),
+        ]),
+    );
+    client.body("configurationDone", Value::Null);
+    let stopped = client.event("stopped");
+    assert_eq!(get(&stopped, &["reason"]).as_str(), Some("entry"));
+    let thread = get(&stopped, &["threadId"]).as_i64().unwrap();
+    client.body("continue", Value::object(vec![("threadId", thread.into())]));
+    let stopped = client.event("stopped");
+    assert_eq!(get(&stopped, &["reason"]).as_str(), Some("exception"));
+    assert!(get(&stopped, &["description"])
+        .as_str()
+        .unwrap()
+        .starts_with("SIGSEGV"));
+    assert_eq!(
+        get(&client.top_frame(thread), &["name"]).as_str(),
+        Some("crash")
+    );
+    // a launched program ends with the session
+    client.body("disconnect", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+    assert!(!std::path::Path::new(&format!("/proc/{}", thread)).exists());
+
+    // what the inferior writes doesn't get mixed up with the protocol
+    let mut client = Client::start();
+    let arguments = vec![Value::from("hello,"), Value::from("editor")];
+    client.start_session(
+        "launch",
+        Value::object(vec![
+            ("program", "/bin/echo".into()),
+            ("args", Value::Array(arguments)),
+        ]),
+    );
+    client.body("configurationDone", Value::Null);
+    let output = client.event("output");
+    assert_eq!(get(&output, &["category"]).as_str(), Some("stdout"));
+    assert_eq!(get(&output, &["output"]).as_str(), Some("hello, editor\n"));
+    assert_eq!(
+        get(&client.event("exited"), &["exitCode"]).as_i64(),
+        Some(0)
+    );
+    drop(client.input);
+    assert!(client.server.wait().unwrap().success());
+}
+
+#[test]
+pub fn editors_attach_to_running_processes() {
+    let mut sleeper = Command::new("/bin/sleep").arg("30").spawn().unwrap();
+    let mut client = Client::start();
+    client.start_session(
+        "attach",
+        Value::object(vec![
+            ("pid", (sleeper.id() as i64).into()),
+            ("stopOnEntry", true.into()),
+        ]),
+    );
+    client.body("configurationDone", Value::Null);
+    let stopped = client.event("stopped");
+    assert_eq!(get(&stopped, &["reason"]).as_str(), Some("entry"));
+    assert_eq!(
+        get(&stopped, &["threadId"]).as_i64(),
+        Some(sleeper.id() as i64)
+    );
+    let trace = client.body(
+        "stackTrace",
+        Value::object(vec![("threadId", (sleeper.id() as i64).into())]),
+    );
+    assert!(!get(&trace, &["stackFrames"]).as_array().unwrap().is_empty());
+    // a process that was attached to is left running
+    client.body("disconnect", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(sleeper.try_wait().unwrap(), None);
+    sleeper.kill().unwrap();
+    sleeper.wait().unwrap();
+}
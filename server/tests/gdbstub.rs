@@ -0,0 +1,376 @@
+use midas::elf::ParsedELF;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    os::unix::net::UnixStream,
+    process::{Child, Command, Stdio},
+    sync::Once,
+};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../midas/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/stepping");
+const CRASH: &str = concat!(tests_dir!(), "/executables/crash");
+const SERVER: &str = env!("CARGO_BIN_EXE_server");
+
+// the size of the registers of a `g` packet, in hex digits
+const REGISTERS_SIZE: usize = 2 * (16 * 8 + 8 + 7 * 4 + 8 * 10 + 8 * 4 + 16 * 16 + 4 + 3 * 8);
+// where rip is in it, and it's number
+const RIP_OFFSET: usize = 16 * 16;
+const RIP: usize = 16;
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .args(["stepping", "crash"])
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+// starts the server on `program`, and waits for it to say where it's listening
+fn start_server(comm: &str, program: &str) -> (Child, String) {
+    let mut server = Command::new(SERVER)
+        .args([comm, program])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut output = BufReader::new(server.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.starts_with("Listening on") {
+        line.clear();
+        assert_ne!(output.read_line(&mut line).unwrap(), 0, "the server quit");
+    }
+    // the rest of what it says is read, so that it can keep saying it
+    std::thread::spawn(move || std::io::copy(&mut output, &mut std::io::sink()));
+    (server, line.trim_end().to_owned())
+}
+
+// the frontend's side of the protocol
+struct Client<S: Read + Write> {
+    stream: S,
+    acknowledge: bool,
+}
+
+impl<S: Read + Write> Client<S> {
+    fn new(stream: S) -> Client<S> {
+        Client {
+            stream,
+            acknowledge: true,
+        }
+    }
+
+    fn byte(&mut self) -> u8 {
+        let mut byte = [0u8];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    fn write_packet(&mut self, payload: &[u8]) {
+        let checksum = payload
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        self.stream.write_all(b"$").unwrap();
+        self.stream.write_all(payload).unwrap();
+        self.stream
+            .write_all(format!("#{:02x}", checksum).as_bytes())
+            .unwrap();
+    }
+
+    fn read_packet(&mut self) -> Vec<u8> {
+        while self.byte() != b'$' {}
+        let mut payload = vec![];
+        loop {
+            match self.byte() {
+                b'#' => break,
+                b'}' => {
+                    let byte = self.byte();
+                    payload.push(byte ^ 0x20)
+                }
+                byte => payload.push(byte),
+            }
+        }
+        self.byte();
+        self.byte();
+        if self.acknowledge {
+            self.stream.write_all(b"+").unwrap();
+        }
+        payload
+    }
+
+    fn send_bytes(&mut self, payload: &[u8]) -> String {
+        self.write_packet(payload);
+        if self.acknowledge {
+            assert_eq!(self.byte(), b'+');
+        }
+        String::from_utf8_lossy(&self.read_packet()).into_owned()
+    }
+
+    fn send(&mut self, payload: &str) -> String {
+        self.send_bytes(payload.as_bytes())
+    }
+
+    fn no_acknowledgements(&mut self) {
+        assert_eq!(self.send("QStartNoAckMode"), "OK");
+        self.acknowledge = false;
+    }
+
+    fn read_all(&mut self, object: &str) -> String {
+        let mut data = String::new();
+        loop {
+            let reply = self.send(&format!("qXfer:{}:{:x},400", object, data.len()));
+            data.push_str(&reply[1..]);
+            if reply.starts_with('l') {
+                return data;
+            }
+            assert!(reply.starts_with('m'));
+        }
+    }
+}
+
+fn address_of(elf: &ParsedELF, function: &str) -> usize {
+    elf.symbol_table
+        .get_function_symbol(function)
+        .and_then(|symbol| symbol.value)
+        .unwrap()
+        .get()
+}
+
+fn little_endian(value: usize) -> String {
+    value
+        .to_le_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+pub fn frontends_debug_over_tcp() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let add = address_of(&elf, "add");
+    let text = elf.get_section_data(".text").unwrap();
+    let add_code = &text[add - elf.get_section_address(".text").unwrap()..][..8];
+
+    let (mut server, listening) = start_server("127.0.0.1:0", SUBJECT);
+    let port = listening.rsplit(' ').next().unwrap();
+    let mut client = Client::new(TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap());
+
+    let supported = client.send("qSupported:multiprocess+;swbreak+;hwbreak+");
+    assert!(supported.contains("qXfer:features:read+"));
+    assert!(supported.contains("swbreak+"));
+    client.no_acknowledgements();
+    let description = client.read_all("features:read:target.xml");
+    assert!(description.contains("<architecture>i386:x86-64</architecture>"));
+    assert_eq!(description.matches("<reg ").count(), 60);
+    assert!(description.contains("name=\"rip\" bitsize=\"64\" type=\"code_ptr\" regnum=\"16\""));
+    assert_eq!(client.send("qXfer:features:read:target.xml:ffff,10"), "l");
+    assert!(!client.read_all("auxv:read:").is_empty());
+    assert!(client
+        .read_all("exec-file:read:")
+        .ends_with("executables/stepping"));
+
+    // it's stopped where exec stopped it, before the first instruction
+    assert!(client.send("?").starts_with("T05thread:"));
+    let registers = client.send("g");
+    assert_eq!(registers.len(), REGISTERS_SIZE);
+    // x87 registers can't be read
+    assert!(registers.contains(&"xx".repeat(10)));
+
+    // breakpoints don't show in memory, and are where the inferior stops
+    assert_eq!(client.send(&format!("m{:x},8", add)), hex(add_code));
+    assert_eq!(client.send(&format!("Z0,{:x},1", add)), "OK");
+    assert_eq!(client.send(&format!("m{:x},8", add)), hex(add_code));
+    assert_eq!(client.send("vCont?"), "vCont;c;C;s;S");
+    let stop = client.send("vCont;c");
+    assert!(stop.starts_with("T05thread:") && stop.ends_with("swbreak:;"));
+    assert_eq!(client.send(&format!("p{:x}", RIP)), little_endian(add));
+    let registers = client.send("g");
+    assert_eq!(registers[RIP_OFFSET..RIP_OFFSET + 16], little_endian(add));
+    let stop = client.send("s");
+    assert!(stop.starts_with("T05") && !stop.contains("swbreak"));
+    assert_ne!(client.send(&format!("p{:x}", RIP)), little_endian(add));
+
+    // registers are written one at a time, or all at once
+    let all = client.send("g");
+    assert_eq!(client.send("P0=3412000000000000"), "OK");
+    assert_eq!(client.send("p0"), "3412000000000000");
+    assert_eq!(client.send(&format!("G{}", all)), "OK");
+    assert_eq!(client.send("g"), all);
+
+    // memory is written in hex, or in binary
+    let stack_pointer = u64::from_le_bytes(
+        (0..8)
+            .map(|index| u8::from_str_radix(&all[7 * 16 + index * 2..][..2], 16).unwrap())
+            .collect::<Vec<u8>>()
+            .try_into()
+            .unwrap(),
+    );
+    let saved = client.send(&format!("m{:x},4", stack_pointer));
+    assert_eq!(
+        client.send(&format!("M{:x},4:deadbeef", stack_pointer)),
+        "OK"
+    );
+    assert_eq!(client.send(&format!("m{:x},4", stack_pointer)), "deadbeef");
+    let mut restore = format!("X{:x},4:", stack_pointer).into_bytes();
+    for index in 0..4 {
+        let byte = u8::from_str_radix(&saved[index * 2..][..2], 16).unwrap();
+        // the bytes that mean something in a packet are escaped
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            restore.extend_from_slice(&[b'}', byte ^ 0x20]);
+        } else {
+            restore.push(byte);
+        }
+    }
+    assert_eq!(client.send_bytes(&restore), "OK");
+    assert_eq!(client.send(&format!("m{:x},4", stack_pointer)), saved);
+    assert!(client.send("m0,8").starts_with('E'));
+
+    // without the breakpoint, it runs to the end; main returns 0
+    assert_eq!(client.send(&format!("z0,{:x},1", add)), "OK");
+    assert_eq!(client.send("c"), "W00");
+    drop(client);
+    assert!(server.wait().unwrap().success());
+}
+
+#[test]
+pub fn frontends_debug_over_unix_sockets() {
+    compile_subject();
+    let socket = std::env::temp_dir().join(format!("midas-gdbstub-{}", std::process::id()));
+    let (mut server, listening) = start_server(socket.to_str().unwrap(), SUBJECT);
+    assert_eq!(listening, format!("Listening on {}", socket.display()));
+    let mut client = Client::new(UnixStream::connect(&socket).unwrap());
+
+    // a packet that arrives garbled is asked for again
+    client.stream.write_all(b"$?#00").unwrap();
+    assert_eq!(client.byte(), b'-');
+    let stop = client.send("?");
+    assert!(stop.starts_with("T05thread:"));
+    let pid = stop.trim_start_matches("T05thread:").trim_end_matches(';');
+    assert_eq!(client.send("qC"), format!("QC{}", pid));
+    assert_eq!(client.send("qfThreadInfo"), format!("m{}", pid));
+    assert_eq!(client.send("qsThreadInfo"), "l");
+    // what isn't supported gets an empty reply
+    assert_eq!(client.send("qThisIsNotAPacket"), "");
+    assert_eq!(client.send("Z2,1000,4"), "");
+
+    // killing it ends the session
+    client.write_packet(b"k");
+    assert_eq!(client.byte(), b'+');
+    assert!(server.wait().unwrap().success());
+    let pid = i32::from_str_radix(pid, 16).unwrap();
+    assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+    assert!(!socket.exists());
+}
+
+#[test]
+pub fn signals_are_delivered_on_resuming() {
+    compile_subject();
+    let (mut server, listening) = start_server("127.0.0.1:0", CRASH);
+    let port = listening.rsplit(' ').next().unwrap();
+    let mut client = Client::new(TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap());
+
+    // it stops at the fault, and continuing without the signal only faults again
+    assert!(client.send("c").starts_with("T0bthread:"));
+    assert!(client.send("vCont;c").starts_with("T0bthread:"));
+    // signals gdb has no number for can't be delivered
+    assert!(client.send("C99").starts_with('E'));
+    assert!(client.send("vCont;Cxx").starts_with('E'));
+    // and delivered, the signal kills it
+    assert_eq!(client.send("vCont;C0b"), "X0b");
+    drop(client);
+    assert!(server.wait().unwrap().success());
+}
+
+#[test]
+pub fn detaching_leaves_the_inferior_running() {
+    compile_subject();
+    let object = midas::elf::load_object(std::path::Path::new(SUBJECT)).unwrap();
+    let elf = ParsedELF::parse_elf(&object).unwrap();
+    let add = address_of(&elf, "add");
+    let (mut server, listening) = start_server("127.0.0.1:0", SUBJECT);
+    let port = listening.rsplit(' ').next().unwrap();
+    let mut client = Client::new(TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap());
+
+    let stop = client.send("?");
+    let pid = i32::from_str_radix(
+        stop.trim_start_matches("T05thread:").trim_end_matches(';'),
+        16,
+    )
+    .unwrap();
+    assert_eq!(client.send(&format!("Z0,{:x},1", add)), "OK");
+    assert_eq!(client.send("D"), "OK");
+    // the breakpoint was taken out, and it's no longer traced, even before the server exits
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+    assert!(
+        !status.contains(&format!("TracerPid:\t{}", server.id())),
+        "{}",
+        status
+    );
+    assert!(server.wait().unwrap().success());
+}
+
+// whether gdb is installed, to run it against the stub
+fn gdb_installed() -> bool {
+    Command::new("gdb")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[test]
+pub fn gdb_debugs_through_the_stub() {
+    if !gdb_installed() {
+        eprintln!("gdb isn't installed, skipping");
+        return;
+    }
+    compile_subject();
+    let (mut server, listening) = start_server("127.0.0.1:0", SUBJECT);
+    let port = listening.rsplit(' ').next().unwrap();
+    let output = Command::new("gdb")
+        .args(["-batch", "-nx"])
+        .args(["-ex", &format!("target remote 127.0.0.1:{}", port)])
+        .args(["-ex", "break add", "-ex", "continue", "-ex", "print x"])
+        .args([
+            "-ex",
+            "stepi",
+            "-ex",
+            "info registers rip",
+            "-ex",
+            "backtrace",
+        ])
+        .args(["-ex", "delete", "-ex", "continue"])
+        .arg(SUBJECT)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Breakpoint 1, add (x=1)"), "{}", stdout);
+    assert!(stdout.contains("$1 = 1"), "{}", stdout);
+    assert!(stdout.contains("rip "), "{}", stdout);
+    assert!(
+        stdout.contains("in main ()") || stdout.contains(" main () at "),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("exited normally"), "{}", stdout);
+    assert!(server.wait().unwrap().success());
+}
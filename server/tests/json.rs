@@ -0,0 +1,75 @@
+use midas::json::Value;
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+    sync::Once,
+};
+
+static BUILT_TEST_DEBUGGEE: Once = Once::new();
+
+macro_rules! tests_dir {
+    () => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../midas/tests/subjects")
+    };
+}
+
+const SUBJECT: &str = concat!(tests_dir!(), "/executables/stepping");
+const SERVER: &str = env!("CARGO_BIN_EXE_server");
+
+fn compile_subject() {
+    BUILT_TEST_DEBUGGEE.call_once(|| {
+        let status = Command::new("make")
+            .stdout(std::process::Stdio::null())
+            .arg("stepping")
+            .current_dir(tests_dir!())
+            .spawn()
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success())
+    });
+}
+
+#[test]
+pub fn scripts_drive_the_server_like_the_repl() {
+    compile_subject();
+    let mut server = Command::new(SERVER)
+        .args(["--interpreter=json", SUBJECT])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut input = server.stdin.take().unwrap();
+    for request in [
+        r#"{"id": 1, "command": "b add"}"#,
+        r#"{"id": 2, "command": "run"}"#,
+        r#"{"id": 3, "command": "quit"}"#,
+    ] {
+        writeln!(input, "{}", request).unwrap();
+    }
+    let messages: Vec<Value> = BufReader::new(server.stdout.take().unwrap())
+        .lines()
+        .map(|line| Value::parse(&line.unwrap()).unwrap())
+        .collect();
+    assert!(server.wait().unwrap().success());
+
+    let responses: Vec<&Value> = messages
+        .iter()
+        .filter(|message| message.get("type").and_then(Value::as_str) == Some("response"))
+        .collect();
+    assert_eq!(responses.len(), 3, "{:?}", messages);
+    assert!(responses
+        .iter()
+        .all(|response| response.get("success") == Some(&Value::Bool(true))));
+    let hit = messages
+        .iter()
+        .find(|message| message.get("event").and_then(Value::as_str) == Some("breakpoint-hit"))
+        .unwrap();
+    assert_eq!(hit.get("id"), Some(&Value::Integer(2)));
+    assert_eq!(
+        hit.get("location")
+            .and_then(|location| location.get("function"))
+            .and_then(Value::as_str),
+        Some("add")
+    );
+}